@@ -0,0 +1,48 @@
+//! Measures what builtins pay for reaching state through `ScriptWorld` rather than
+//! the fields of `RuntimeGlobals`. Run with `cargo bench --bench script_world`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use rustyjsonserver::rjscript::{
+    evaluator::runtime::{runtime_globals::RuntimeGlobals, value::RJSValue, world::ScriptWorld},
+    testing::{eval_script_str_with_world, TestWorld},
+};
+
+const ITERS: u32 = 2_000_000;
+
+fn time(label: &str, iters: u32, mut f: impl FnMut()) -> Duration {
+    // warm up caches and the branch predictor before timing
+    for _ in 0..iters / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..iters {
+        f();
+    }
+    let per_iter = start.elapsed() / iters;
+    println!("{:<28} {:>8?}/iter", label, per_iter);
+    per_iter
+}
+
+fn main() {
+    let globals = RuntimeGlobals::isolated(None);
+    globals.cache.set("k".into(), RJSValue::Number(1.0));
+
+    let direct = time("cache get, field", ITERS, || {
+        black_box(black_box(&globals).cache.get(black_box("k")));
+    });
+    let world = time("cache get, ScriptWorld", ITERS, || {
+        black_box(ScriptWorld::cache(black_box(&globals)).get(black_box("k")));
+    });
+    println!("ScriptWorld / field: {:.3}", world.as_secs_f64() / direct.as_secs_f64());
+
+    let test_world = TestWorld::new();
+    let src = "for (let i: num = 0; i < 100; i = i + 1) { cacheSet(\"k\", i); cacheGet(\"k\"); }\n\
+               return 200, cacheGet(\"k\");";
+    time("script, 200 cache builtins", 2_000, || {
+        black_box(eval_script_str_with_world(src, serde_json::Value::Null, &test_world).unwrap());
+    });
+}
@@ -1,7 +1,7 @@
-use std::{error::Error, io, path::PathBuf, sync::Arc};
+use std::{error::Error, io, path::{Path, PathBuf}, sync::Arc};
 use clap::Args;
 use rustyjsonserver::{
-    config::{manager::ConfigManager, resolver::get_config_path_cwd}, filewatcher::watcher, http::server, rjscript::evaluator::runtime::runtime_globals::RuntimeGlobals, rjsdb::{TableDb, db::JsonTableDb}
+    config::{manager::ConfigManager, resolver::get_config_path_cwd}, filewatcher::watcher, http::server, rjscript::evaluator::{engine::driver::run_startup_script, runtime::{files::FileSandbox, runtime_globals::{EnvAllowlist, RuntimeGlobals, RuntimeOptions}}}, rjsdb::{TableDb, db::{JsonTableDb, SyncMode}, mem::MemTableDb}
 };
 use tracing::info;
 
@@ -15,31 +15,133 @@ pub struct ServeArgs {
     /// Disable file-watching
     #[arg(long)]
     pub no_watch: bool,
+
+    /// Let scripts read environment variables starting with PREFIX through env() (repeatable)
+    #[arg(long = "allow-env", value_name = "PREFIX")]
+    pub allow_env: Vec<String>,
+
+    /// Let scripts make outbound HTTP requests with httpFetch()
+    #[arg(long)]
+    pub allow_net: bool,
+
+    /// Directory readFile() and readJson() may read fixtures from
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Listen on a Unix domain socket at PATH instead of the configured TCP port
+    #[arg(long, value_name = "PATH")]
+    pub unix: Option<PathBuf>,
 }
 
 pub async fn run(args: ServeArgs) -> Result<(), Box<dyn Error>> {
     let cfg = get_config_path_cwd(&args.config.to_string_lossy());
     info!(%cfg, watch_enabled = !args.no_watch, "serving configuration");
 
-    // init persistence
-    let path = std::env::var("RJS_DB_DIR").unwrap_or_else(|_| "./data".into());
-    let db = JsonTableDb::open(path)?;
-    let db_arc: Arc<dyn TableDb> = Arc::new(db);
-    RuntimeGlobals::init_with_db(Some(db_arc));
-
     // Initialize manager, mapping String→io::Error
     let manager = ConfigManager::new(cfg.clone())
         .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("ConfigManager::new failed: {}", e)))?;
 
+    // init persistence, unless the config opts out of it
+    let db = if manager.db_disabled() {
+        info!("persistent DB disabled by config");
+        None
+    } else if std::env::var_os("RJS_DB_DIR").is_none() && std::env::var("RJS_DB_INMEM").is_ok_and(|v| v.trim() == "1") {
+        info!("persistent DB kept in memory only");
+        let db: Arc<dyn TableDb> = Arc::new(MemTableDb::new());
+        Some(db)
+    } else {
+        let path = std::env::var("RJS_DB_DIR").unwrap_or_else(|_| "./data".into());
+        let db: Arc<dyn TableDb> = Arc::new(JsonTableDb::open_with_sync(path, SyncMode::from_env()?)?);
+        Some(db)
+    };
+    // comma-separated list of the variables scripts may read with env(), extended
+    // by the --allow-env prefixes; without either no variable is readable
+    let env_names = std::env::var("RJS_ENV_ALLOWLIST")
+        .map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    let env_allowlist = EnvAllowlist { names: env_names, prefixes: args.allow_env.clone() };
+    let files = args
+        .data_dir
+        .as_ref()
+        .map(|dir| {
+            FileSandbox::new(dir)
+                .map_err(|e| io::Error::new(e.kind(), format!("--data-dir {}: {}", dir.display(), e)))
+        })
+        .transpose()?;
+    RuntimeGlobals::init_with_db(db.clone(), RuntimeOptions { env_allowlist, allow_net: args.allow_net, files });
+
+    // One-time setup (seeding, cache warm-up) before any request is served
+    if let Some(script) = manager.startup_script() {
+        let status = run_startup_script(script, RuntimeGlobals::get())?;
+        info!(status, "startup script finished");
+    }
+
     // Spawn file-watcher if requested
     if !args.no_watch {
         watcher::spawn_watcher(manager.clone());
     }
 
+    let served = tokio::select! {
+        served = listen(&args, &manager) => served,
+        _ = shutdown_signal() => {
+            info!("shutting down");
+            Ok(())
+        }
+    };
+    // the globals live on in a static, so a buffered DB is never dropped
+    if let Some(db) = &db {
+        db.flush()?;
+    }
+    served
+}
+
+async fn listen(args: &ServeArgs, manager: &ConfigManager) -> Result<(), Box<dyn Error>> {
+    if let Some(socket) = &args.unix {
+        return serve_unix(socket, manager).await;
+    }
+
     let addr = format!("0.0.0.0:{}", manager.port());
     info!(%addr, "starting HTTP server");
-    
-    server::run(&addr, manager.routes_handle()).await?;
 
-    Ok(())
+    server::run(&addr, manager.routes_handle()).await
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM where there is one.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[cfg(unix)]
+async fn serve_unix(socket: &Path, manager: &ConfigManager) -> Result<(), Box<dyn Error>> {
+    info!(socket = %socket.display(), "starting HTTP server");
+    server::run_unix(socket, manager.routes_handle()).await
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(_: &Path, _: &ConfigManager) -> Result<(), Box<dyn Error>> {
+    Err("--unix is only supported on Unix platforms".into())
 }
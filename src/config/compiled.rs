@@ -1,15 +1,32 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use serde_json::Value;
 use tracing::{debug};
 
 use crate::rjscript::{
     self,
-    ast::{block::Block, position::Position},
+    ast::{
+        block::Block,
+        position::Position,
+        stmt::{Stmt, StmtKind},
+    },
     parser::parser,
+    preprocess::lints::util::collect_db_calls,
 };
 
-use super::resolved::{ResolvedConfig, ResolvedMethodResponse, ResolvedResource};
+use crate::http::status::check_status_code;
+
+use super::{
+    raw::{RawDelay, RawFailure, RawKeepAlive},
+    resolved::{ResolvedConfig, ResolvedMethodResponse, ResolvedResource, ResolvedScript},
+    resolver::resolve_path,
+};
 
 #[derive(Clone, Debug)]
 pub enum CompiledMethodResponse {
@@ -17,10 +34,51 @@ pub enum CompiledMethodResponse {
     Response { status: u16, body: Value },
 }
 
+/// Latency injected before the response is written, picked in `min..=max` ms.
+#[derive(Clone, Copy, Debug)]
+pub struct CompiledDelay {
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl CompiledDelay {
+    /// Picks a duration in the configured range using `seed` as the source of randomness.
+    pub fn pick(&self, seed: u64) -> Duration {
+        let span = self.max_ms - self.min_ms;
+        // `0..=u64::MAX` is every value a seed can take
+        let ms = match span.checked_add(1) {
+            Some(values) => self.min_ms + seed % values,
+            None => seed,
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+/// Injected failure: answers with `status`/`body` for a `probability` share of requests.
+#[derive(Clone, Debug)]
+pub struct CompiledFailure {
+    pub probability: f64,
+    pub status: u16,
+    pub body: Value,
+}
+
+/// Limits for persistent connections.
+#[derive(Clone, Copy, Debug)]
+pub struct CompiledKeepAlive {
+    /// How long to wait for the next request before closing the connection
+    pub idle_timeout: Duration,
+    /// Requests served on one connection before it is closed
+    pub max_requests: u32,
+}
+
 #[derive(Clone, Debug)]
 pub struct CompiledMethodDefinition {
     pub method: String,
     pub response: CompiledMethodResponse,
+    pub delay: Option<CompiledDelay>,
+    pub failure: Option<CompiledFailure>,
+    /// `after` script of the nearest resource (or the config) that sets one
+    pub after: Option<Arc<Block>>,
 }
 
 #[derive(Clone, Debug)]
@@ -28,13 +86,15 @@ pub struct CompiledResource {
     path: String,
     children: Vec<CompiledResource>,
     methods: Vec<CompiledMethodDefinition>,
+    /// Only the script this resource sets itself, the methods carry the inherited one
+    after: Option<Arc<Block>>,
 }
 
 impl CompiledResource {
-    pub fn methods_map(&self) -> HashMap<String, CompiledMethodResponse> {
+    pub fn methods_map(&self) -> HashMap<String, CompiledMethodDefinition> {
         self.methods
             .iter()
-            .map(|def| (def.method.clone(), def.response.clone()))
+            .map(|def| (def.method.clone(), def.clone()))
             .collect()
     }
 
@@ -49,36 +109,138 @@ impl CompiledResource {
 
 pub struct CompiledConfig {
     pub port: u16,
+    pub status_reasons: HashMap<u16, String>,
+    pub keep_alive: Option<CompiledKeepAlive>,
+    /// Run once at startup, see `ConfigManager::startup_script`
+    pub startup: Option<Block>,
+    /// Script applied to the responses of resources without an `after` of their own
+    pub after: Option<Arc<Block>>,
+    /// No persistent DB is opened, see `check_no_db_calls`
+    pub disable_db: bool,
     pub resources: Vec<CompiledResource>,
 }
 
-fn compile_method_response(
-    response: ResolvedMethodResponse,
-) -> Result<CompiledMethodResponse, String> {
-    debug!("Compiling method response: {response:?}");
-    match response {
-        ResolvedMethodResponse::Script { script } => {
-            match parser::parse_script(&script) {
-                Ok(block) => {
-                    // Run lints + transforms
-                    let prep = rjscript::preprocess::preprocess(block.stmts);
-
-                    if !prep.errors.is_empty() {
-                        for e in &prep.errors {
-                            eprintln!("{e}");
-                        }
-                        return Err("lint errors".into());
-                    }
+fn compile_status_reasons(raw: HashMap<String, String>) -> Result<HashMap<u16, String>, String> {
+    let mut reasons = HashMap::with_capacity(raw.len());
+    for (key, phrase) in raw {
+        let code = key
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|c| (100..=599).contains(c))
+            .ok_or_else(|| format!("statusReasons key '{}' must be a status code in 100..=599", key))?;
+        reasons.insert(code, phrase);
+    }
+    Ok(reasons)
+}
+
+/// Replaces each `include "path";` in `stmts` with the functions declared in that file.
+/// Paths are resolved against `base_dir`; `stack` holds the files being included
+/// (outermost first) so cycles can be reported.
+fn expand_includes(
+    stmts: Vec<Stmt>,
+    base_dir: &Path,
+    current: &str,
+    stack: &mut Vec<String>,
+) -> Result<Vec<Stmt>, String> {
+    let mut out = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+        let StmtKind::Include(path) = &stmt.kind else {
+            out.push(stmt);
+            continue;
+        };
+        let file = resolve_path(path, base_dir);
+        // Canonical paths, so `lib/a.rjs` and `lib/../lib/a.rjs` count as the same file
+        let file = fs::canonicalize(&file)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or(file);
+        if stack.contains(&file) {
+            return Err(format!(
+                "Include cycle: {} includes {}, which is already being included",
+                current, file
+            ));
+        }
+        let src = fs::read_to_string(&file)
+            .map_err(|e| format!("Error reading included file {} (from {}): {}", file, current, e))?;
+        let block = parser::parse_script(&src)
+            .map_err(|e| format!("Failed to parse included file {}: {}", file, e))?;
+
+        let dir = Path::new(&file).parent().unwrap_or(base_dir).to_path_buf();
+        stack.push(file.clone());
+        let included = expand_includes(block.stmts, &dir, &file, stack)?;
+        stack.pop();
+
+        if let Some(other) = included
+            .iter()
+            .find(|s| !matches!(s.kind, StmtKind::FunctionDecl { .. }))
+        {
+            return Err(format!(
+                "Included file {} may only declare functions, found a statement at {}:{}",
+                file, other.pos.line, other.pos.column
+            ));
+        }
+
+        let prep = rjscript::preprocess::preprocess_include(included);
+        if !prep.errors.is_empty() {
+            for e in &prep.errors {
+                eprintln!("{file}:{e}");
+            }
+            return Err(format!("lint errors in included file {}", file));
+        }
+        out.extend(prep.stmts);
+    }
+    Ok(out)
+}
 
-                    let processed_block = Block::new(prep.stmts, Position::UNKNOWN);
+/// Parses a script, expands its includes and runs the lints. `prelude` statements are
+/// linted as if they opened the script, then left out of the compiled block.
+fn compile_script(
+    script: &str,
+    source: Option<String>,
+    base_dir: Option<String>,
+    prelude: Vec<Stmt>,
+) -> Result<Block, String> {
+    match parser::parse_script(script) {
+        Ok(block) => {
+            let name = source.as_deref().unwrap_or("inline script");
+            let base_dir = PathBuf::from(base_dir.as_deref().unwrap_or("."));
+            let mut stack: Vec<String> = source
+                .iter()
+                .map(|s| fs::canonicalize(s).map_or_else(|_| s.clone(), |p| p.to_string_lossy().into_owned()))
+                .collect();
+            let prelude_len = prelude.len();
+            let mut stmts = prelude;
+            stmts.extend(expand_includes(block.stmts, &base_dir, name, &mut stack)?);
 
-                    Ok(CompiledMethodResponse::Script {
-                        script: processed_block,
-                    })
+            // Run lints + transforms
+            let mut prep = rjscript::preprocess::preprocess(stmts);
+
+            if !prep.errors.is_empty() {
+                for e in &prep.errors {
+                    eprintln!("{e}");
                 }
-                Err(err) => Err(format!("Failed to parse script: {}", err)),
+                return Err("lint errors".into());
             }
+
+            prep.stmts.drain(..prelude_len);
+            Ok(Block::new(prep.stmts, Position::UNKNOWN))
         }
+        Err(err) => Err(format!("Failed to parse script: {}", err)),
+    }
+}
+
+fn compile_method_response(
+    response: ResolvedMethodResponse,
+) -> Result<CompiledMethodResponse, String> {
+    debug!("Compiling method response: {response:?}");
+    match response {
+        ResolvedMethodResponse::Script {
+            script,
+            source,
+            base_dir,
+        } => Ok(CompiledMethodResponse::Script {
+            script: compile_script(&script, source, base_dir, Vec::new())?,
+        }),
         ResolvedMethodResponse::Response { response } => {
             match response {
                 Value::Object(mut map) => {
@@ -88,9 +250,10 @@ fn compile_method_response(
 
                     let status = match map.remove("status") {
                         Some(Value::Number(n)) => n
-                            .as_u64()
-                            .and_then(|v| u16::try_from(v).ok())
-                            .ok_or_else(|| "response.status must be a valid u16".to_string())?,
+                            .as_f64()
+                            .ok_or_else(|| "response.status must be a valid number".to_string())
+                            .and_then(check_status_code)
+                            .map_err(|e| format!("response.status: {}", e))?,
                         Some(_) => {
                             return Err("response.status must be a number".to_string());
                         }
@@ -107,22 +270,118 @@ fn compile_method_response(
     }
 }
 
-fn compile_resource(resource: ResolvedResource) -> Result<CompiledResource, String> {
+fn compile_delay(delay: Option<RawDelay>) -> Result<Option<CompiledDelay>, String> {
+    match delay {
+        None => Ok(None),
+        Some(RawDelay::Fixed(ms)) => Ok(Some(CompiledDelay {
+            min_ms: ms,
+            max_ms: ms,
+        })),
+        Some(RawDelay::Range { min, max }) => {
+            if min > max {
+                return Err(format!(
+                    "delay.min ({}) must not be greater than delay.max ({})",
+                    min, max
+                ));
+            }
+            Ok(Some(CompiledDelay {
+                min_ms: min,
+                max_ms: max,
+            }))
+        }
+    }
+}
+
+fn compile_failure(failure: Option<RawFailure>) -> Result<Option<CompiledFailure>, String> {
+    let Some(failure) = failure else {
+        return Ok(None);
+    };
+    if !(0.0..=1.0).contains(&failure.probability) {
+        return Err(format!(
+            "failure.probability must be between 0 and 1, got {}",
+            failure.probability
+        ));
+    }
+    let status = check_status_code(failure.status as f64)
+        .map_err(|e| format!("failure.status: {}", e))?;
+    let body = failure
+        .body
+        .unwrap_or_else(|| serde_json::json!({ "error": "Injected failure" }));
+    Ok(Some(CompiledFailure {
+        probability: failure.probability,
+        status,
+        body,
+    }))
+}
+
+fn compile_keep_alive(keep_alive: Option<RawKeepAlive>) -> Result<Option<CompiledKeepAlive>, String> {
+    let Some(keep_alive) = keep_alive else {
+        return Ok(None);
+    };
+    if keep_alive.idle_timeout_ms == 0 {
+        return Err("keepAlive.idleTimeoutMs must be greater than 0".to_string());
+    }
+    if keep_alive.max_requests == 0 {
+        return Err("keepAlive.maxRequests must be greater than 0".to_string());
+    }
+    Ok(Some(CompiledKeepAlive {
+        idle_timeout: Duration::from_millis(keep_alive.idle_timeout_ms),
+        max_requests: keep_alive.max_requests,
+    }))
+}
+
+/// Declares the `response` constant an `after` script reads, for the lints only: the
+/// evaluator defines it at run time.
+const AFTER_PRELUDE: &str = "const response: obj = { status: 200, body: null };";
+
+/// Compiles an `after` script, linted with `AFTER_PRELUDE` in front of it.
+fn compile_after(after: Option<ResolvedScript>) -> Result<Option<Arc<Block>>, String> {
+    let Some(ResolvedScript { script, source, base_dir }) = after else {
+        return Ok(None);
+    };
+    let prelude = parser::parse_script(AFTER_PRELUDE)
+        .map_err(|e| format!("after: cannot parse the `response` prelude: {}", e))?
+        .stmts;
+    let block = compile_script(&script, source, base_dir, prelude).map_err(|e| format!("after: {}", e))?;
+    Ok(Some(Arc::new(block)))
+}
+
+/// Compiles the `startup` script.
+fn compile_startup(startup: Option<ResolvedScript>) -> Result<Option<Block>, String> {
+    let Some(ResolvedScript { script, source, base_dir }) = startup else {
+        return Ok(None);
+    };
+    let block = compile_script(&script, source, base_dir, Vec::new()).map_err(|e| format!("startup: {}", e))?;
+    Ok(Some(block))
+}
+
+fn compile_resource(
+    resource: ResolvedResource,
+    inherited_after: Option<&Arc<Block>>,
+) -> Result<CompiledResource, String> {
     debug!(path = %resource.path, "Compiling resource");
+    let own_after = compile_after(resource.after).map_err(|e| format!("{}: {}", resource.path, e))?;
+    let after = own_after.as_ref().or(inherited_after);
+
     // Compile child resources recursively.
     let compiled_children = resource
         .children
         .into_iter()
-        .map(compile_resource)
+        .map(|child| compile_resource(child, after))
         .collect::<Result<Vec<_>, String>>()?;
 
     // Compile each method in the resource.
     let mut compiled_methods = Vec::with_capacity(resource.methods.len());
     for method in resource.methods {
         let compiled_resp = compile_method_response(method.response)?;
+        let delay = compile_delay(method.delay)?;
+        let failure = compile_failure(method.failure)?;
         compiled_methods.push(CompiledMethodDefinition {
             method: method.method,
             response: compiled_resp,
+            delay,
+            failure,
+            after: after.cloned(),
         });
     }
 
@@ -130,18 +389,289 @@ fn compile_resource(resource: ResolvedResource) -> Result<CompiledResource, Stri
         path: resource.path,
         children: compiled_children,
         methods: compiled_methods,
+        after: own_after,
     })
 }
 
+/// Appends `"<label> (<builtin> at <line>:<col>, ...)"` to `out` when `script` calls DB builtins.
+fn note_db_calls(label: &str, script: &Block, out: &mut Vec<String>) {
+    let calls = collect_db_calls(script);
+    if calls.is_empty() {
+        return;
+    }
+    let calls: Vec<String> = calls
+        .iter()
+        .map(|(name, pos)| format!("{} at {}:{}", name, pos.line, pos.column))
+        .collect();
+    out.push(format!("{} ({})", label, calls.join(", ")));
+}
+
+fn collect_resource_db_calls(resource: &CompiledResource, parent_path: &str, out: &mut Vec<String>) {
+    let path = if parent_path.is_empty() {
+        resource.path.clone()
+    } else {
+        format!("{}/{}", parent_path, resource.path)
+    };
+    for def in &resource.methods {
+        if let CompiledMethodResponse::Script { script } = &def.response {
+            note_db_calls(&format!("{} {}", def.method, path), script, out);
+        }
+    }
+    if let Some(script) = &resource.after {
+        note_db_calls(&format!("after {}", path), script, out);
+    }
+    for child in &resource.children {
+        collect_resource_db_calls(child, &path, out);
+    }
+}
+
+/// With `disableDb` set, any script calling a `db*` builtin would only fail once it
+/// runs, so such scripts are reported when the config is loaded instead.
+fn check_no_db_calls(
+    startup: Option<&Block>,
+    after: Option<&Block>,
+    resources: &[CompiledResource],
+) -> Result<(), String> {
+    let mut offending = Vec::new();
+    if let Some(script) = startup {
+        note_db_calls("startup", script, &mut offending);
+    }
+    if let Some(script) = after {
+        note_db_calls("after", script, &mut offending);
+    }
+    for resource in resources {
+        collect_resource_db_calls(resource, "", &mut offending);
+    }
+    if offending.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "disableDb is set but these scripts use the persistent DB: {}",
+            offending.join("; ")
+        ))
+    }
+}
+
 pub fn compile_config(resolved: ResolvedConfig) -> Result<CompiledConfig, String> {
+    let after = compile_after(resolved.after)?;
     let compiled_resources = resolved
         .resources
         .into_iter()
-        .map(compile_resource)
+        .map(|resource| compile_resource(resource, after.as_ref()))
         .collect::<Result<Vec<_>, String>>()?;
 
+    let status_reasons = compile_status_reasons(resolved.status_reasons)?;
+    let keep_alive = compile_keep_alive(resolved.keep_alive)?;
+    let startup = compile_startup(resolved.startup)?;
+    if resolved.disable_db {
+        check_no_db_calls(startup.as_ref(), after.as_deref(), &compiled_resources)?;
+    }
+
     Ok(CompiledConfig {
         port: resolved.port,
+        status_reasons,
+        keep_alive,
+        startup,
+        after,
+        disable_db: resolved.disable_db,
         resources: compiled_resources,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        http::request::Request,
+        rjscript::{
+            evaluator::{
+                engine::driver::{eval_script_with_globals, run_startup_script},
+                runtime::{value::RJSValue, world::ScriptWorld},
+            },
+            semantics::methods::BUILTINS_TBL,
+            testing::TestWorld,
+        },
+    };
+
+    fn compile(config: Value) -> Result<CompiledConfig, String> {
+        compile_config(serde_json::from_value(config).unwrap())
+    }
+
+    fn inline(script: &str) -> Value {
+        json!({ "script": script })
+    }
+
+    #[test]
+    fn after_prelude_parses() {
+        assert!(parser::parse_script(AFTER_PRELUDE).is_ok());
+    }
+
+    #[test]
+    fn after_scripts_are_inherited_until_overridden() {
+        let config = compile(json!({
+            "port": 8080,
+            "after": inline("return response.status, response.body;"),
+            "resources": [
+                { "path": "/a", "methods": [{ "method": "GET", "response": { "body": 1 } }] },
+                {
+                    "path": "/b",
+                    "after": inline("return 201, response.body;"),
+                    "methods": [{ "method": "GET", "response": { "body": 2 } }]
+                }
+            ]
+        }))
+        .unwrap();
+
+        let global = config.after.as_ref().expect("config after");
+        let a = &config.resources[0];
+        assert!(a.after.is_none());
+        assert!(Arc::ptr_eq(a.methods_map()["GET"].after.as_ref().unwrap(), global));
+        let b = &config.resources[1];
+        let own = b.after.as_ref().expect("resource after");
+        assert!(Arc::ptr_eq(b.methods_map()["GET"].after.as_ref().unwrap(), own));
+    }
+
+    #[test]
+    fn script_errors_name_the_hook() {
+        let err = compile(json!({ "port": 8080, "startup": inline("return 200,"), "resources": [] }))
+            .err()
+            .unwrap();
+        assert!(err.starts_with("startup: "), "{}", err);
+
+        let err = compile(json!({ "port": 8080, "after": inline("return ("), "resources": [] }))
+            .err()
+            .unwrap();
+        assert!(err.starts_with("after: "), "{}", err);
+    }
+
+    #[test]
+    fn delays_pick_within_their_range() {
+        let ms = |min_ms, max_ms, seed| CompiledDelay { min_ms, max_ms }.pick(seed).as_millis() as u64;
+        assert_eq!(ms(250, 250, 12345), 250);
+        assert_eq!(ms(100, 500, 0), 100);
+        assert_eq!(ms(100, 500, 400), 500);
+        assert_eq!(ms(100, 500, 401), 100);
+        assert!((100..=500).contains(&ms(100, 500, u64::MAX)));
+        assert_eq!(ms(0, u64::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn delay_config_forms() {
+        let delay = |delay: Value| {
+            let config = compile(json!({
+                "port": 8080,
+                "resources": [{ "path": "/a", "methods": [{ "method": "GET", "delay": delay, "response": { "body": 1 } }] }]
+            }))?;
+            let d = config.resources[0].methods_map()["GET"].delay.unwrap();
+            Ok::<_, String>((d.min_ms, d.max_ms))
+        };
+        assert_eq!(delay(json!(200)), Ok((200, 200)));
+        assert_eq!(delay(json!({ "min": 100, "max": 500 })), Ok((100, 500)));
+        assert_eq!(delay(json!({ "min": 7, "max": 7 })), Ok((7, 7)));
+        let err = delay(json!({ "min": 500, "max": 100 })).unwrap_err();
+        assert!(err.contains("delay.min (500) must not be greater than delay.max (100)"), "{}", err);
+    }
+
+    #[test]
+    fn startup_script_seeds_the_db_before_requests() {
+        let config = compile(json!({
+            "port": 8080,
+            "startup": inline(
+                "dbCreateTable(\"users\");\n\
+                 dbCreateEntry(\"users\", { name: \"Ann\" });\n\
+                 cacheSet(\"seeded\", true);\n\
+                 return 200, true;"
+            ),
+            "resources": [{
+                "path": "/users",
+                "methods": [{ "method": "GET", "script": "return 200, dbGetAll(\"users\").length();" }]
+            }]
+        }))
+        .unwrap();
+        let world = TestWorld::with_mem_db();
+
+        let startup = config.startup.as_ref().unwrap();
+        assert_eq!(run_startup_script(startup, Arc::clone(world.globals())), Ok(200));
+        assert_eq!(world.db_dump().unwrap()["users"].len(), 1);
+        assert_eq!(world.cache().get("seeded"), Some(RJSValue::Bool(true)));
+
+        let CompiledMethodResponse::Script { script } = &config.resources[0].methods_map()["GET"].response else {
+            panic!("GET /users is a script");
+        };
+        let req = Request::new(Value::Null, HashMap::new(), HashMap::new(), HashMap::new());
+        let (status, count) = eval_script_with_globals(script, &req, Arc::clone(world.globals())).unwrap();
+        assert_eq!((status, count), (200, RJSValue::Number(1.0)));
+    }
+
+    #[test]
+    fn failing_startup_scripts_are_errors() {
+        for (src, expected) in [
+            ("return 503, \"not ready\";", "failed with status 503: \"not ready\""),
+            ("throw \"boom\";", "failed with status 500: \"boom\""),
+            ("dbCreateTable(\"t\"); return 200, true;", "startup script failed: "),
+        ] {
+            let config = compile(json!({ "port": 8080, "startup": inline(src), "resources": [] })).unwrap();
+            let err = run_startup_script(config.startup.as_ref().unwrap(), Arc::clone(TestWorld::new().globals()))
+                .unwrap_err();
+            assert!(err.contains(expected), "{}: {}", src, err);
+        }
+    }
+
+    #[test]
+    fn startup_compiles_to_its_block() {
+        let config = compile(json!({
+            "port": 8080,
+            "startup": inline("cacheSet(\"ready\", true); return 200, true;"),
+            "resources": []
+        }))
+        .unwrap();
+        assert_eq!(config.startup.map(|b| b.stmts.len()), Some(2));
+        assert!(config.after.is_none());
+    }
+
+    #[test]
+    fn disable_db_rejects_scripts_calling_db_builtins() {
+        let config = |disable_db: bool| {
+            json!({
+                "port": 8080,
+                "disableDb": disable_db,
+                "startup": inline("let ok: bool = true;\ndbCreateTable(\"users\");\nreturn 200, ok;"),
+                "resources": [{
+                    "path": "users",
+                    "methods": [
+                        { "method": "GET", "script": "return 200, cacheGet(\"users\");" },
+                        { "method": "DELETE", "script": "if (true) {\n  dbDrop();\n}\nreturn 204, null;" }
+                    ],
+                    "children": [{
+                        "path": ":id",
+                        "methods": [{ "method": "GET", "script": "return 200, dbGetById(\"users\", \"1\");" }]
+                    }]
+                }]
+            })
+        };
+        let err = compile(config(true)).err().unwrap();
+        assert_eq!(
+            err,
+            "disableDb is set but these scripts use the persistent DB: startup (dbCreateTable at 2:1); \
+             DELETE users (dbDrop at 2:3); GET users/:id (dbGetById at 1:13)"
+        );
+        assert!(!compile(config(false)).unwrap().disable_db);
+
+        let config = compile(json!({
+            "port": 8080,
+            "disableDb": true,
+            "resources": [{ "path": "a", "methods": [{ "method": "GET", "script": "return 200, now();" }] }]
+        }))
+        .unwrap();
+        assert!(config.disable_db);
+    }
+
+    #[test]
+    fn every_db_builtin_is_flagged() {
+        for (builtin, name) in BUILTINS_TBL {
+            assert_eq!(builtin.is_db(), name.starts_with("db"), "{}", name);
+        }
+    }
+}
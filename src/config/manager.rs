@@ -2,6 +2,7 @@ use std::{path::{Path, PathBuf}, sync::{Arc, RwLock}};
 use super::resolver::{load_config, resolve_config_references};
 use super::compiled::compile_config;
 use crate::http::router::{get_routes_from_config, RoutesData};
+use crate::rjscript::ast::block::Block;
 
 #[derive(Clone)]
 pub struct ConfigManager {
@@ -9,6 +10,8 @@ pub struct ConfigManager {
     root_folder: PathBuf,
     routes: Arc<RwLock<Option<RoutesData>>>,
     port: u16,
+    startup: Option<Arc<Block>>,
+    disable_db: bool,
 }
 
 impl ConfigManager {
@@ -25,9 +28,11 @@ impl ConfigManager {
 
         let initial_routes = get_routes_from_config(&compiled, &root_folder);
         let port = compiled.port;
+        let startup = compiled.startup.map(Arc::new);
+        let disable_db = compiled.disable_db;
         let routes = Arc::new(RwLock::new(Some(initial_routes)));
 
-        Ok(ConfigManager { config_path, root_folder, routes, port })
+        Ok(ConfigManager { config_path, root_folder, routes, port, startup, disable_db })
     }
 
     /// Reload on file change
@@ -49,6 +54,16 @@ impl ConfigManager {
         self.port
     }
 
+    /// The `startup` script from the initial load; reloads do not run it again.
+    pub fn startup_script(&self) -> Option<&Block> {
+        self.startup.as_deref()
+    }
+
+    /// Whether the config opts out of the persistent DB (`disableDb`), from the initial load.
+    pub fn db_disabled(&self) -> bool {
+        self.disable_db
+    }
+
     pub fn root_folder(&self) -> &PathBuf {
         &self.root_folder
     }
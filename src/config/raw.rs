@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -19,9 +21,37 @@ pub enum RawMethodResponse {
     Response { response: Value },
 }
 
+/// Artificial latency for a method, in milliseconds: either a fixed
+/// value (`"delay": 200`) or a random range (`"delay": {"min": 100, "max": 500}`).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum RawDelay {
+    Fixed(u64),
+    Range { min: u64, max: u64 },
+}
+
+pub fn default_failure_status() -> u16 {
+    503
+}
+
+/// Random failure injection for a method: with the given `probability` (0..=1)
+/// the request is answered with `status`/`body` instead of the real response.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RawFailure {
+    pub probability: f64,
+    #[serde(default = "default_failure_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RawMethodDefinition {
     pub method: String,
+    #[serde(default)]
+    pub delay: Option<RawDelay>,
+    #[serde(default)]
+    pub failure: Option<RawFailure>,
     #[serde(flatten)]
     pub response: RawMethodResponse,
 }
@@ -32,6 +62,8 @@ pub struct RawPartialResource {
     pub children: Vec<RawResource>,
     #[serde(default)]
     pub methods: Vec<RawMethodDefinition>,
+    #[serde(default)]
+    pub after: Option<RawScript>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -50,12 +82,48 @@ pub enum RawResource {
         children: Vec<RawResource>,
         #[serde(default)]
         methods: Vec<RawMethodDefinition>,
+        /// Script reshaping the responses of this resource and its children
+        #[serde(default)]
+        after: Option<RawScript>,
     },
 }
 
+pub fn default_keep_alive_idle_timeout_ms() -> u64 {
+    5000
+}
+
+pub fn default_keep_alive_max_requests() -> u32 {
+    100
+}
+
+/// Persistent connections: an idle connection is closed after `idleTimeoutMs`,
+/// and every connection is closed once it has served `maxRequests` requests.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RawKeepAlive {
+    #[serde(default = "default_keep_alive_idle_timeout_ms", rename = "idleTimeoutMs")]
+    pub idle_timeout_ms: u64,
+    #[serde(default = "default_keep_alive_max_requests", rename = "maxRequests")]
+    pub max_requests: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RawConfig {
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Extra or overridden reason phrases, keyed by status code (e.g. `"499"`).
+    #[serde(default, rename = "statusReasons")]
+    pub status_reasons: HashMap<String, String>,
+    /// Without it every connection serves a single request.
+    #[serde(default, rename = "keepAlive")]
+    pub keep_alive: Option<RawKeepAlive>,
+    /// Script run once when the server starts, before it binds its listener.
+    #[serde(default)]
+    pub startup: Option<RawScript>,
+    /// Script reshaping every response, unless a resource sets its own `after`.
+    #[serde(default)]
+    pub after: Option<RawScript>,
+    /// Run without the persistent DB; scripts calling `db*` builtins are rejected at load.
+    #[serde(default, rename = "disableDb")]
+    pub disable_db: bool,
     pub resources: Vec<RawResource>,
 }
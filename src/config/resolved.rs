@@ -1,16 +1,44 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::raw::{RawDelay, RawFailure, RawKeepAlive};
+
+/// A standalone script field (`startup`, `after`), read from its file if it was a reference.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResolvedScript {
+    pub script: String,
+    /// File the script was read from, `None` for scripts inline in the config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Directory `include` paths in the script are resolved against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_dir: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum ResolvedMethodResponse {
-    Script { script: String },
+    Script {
+        script: String,
+        /// File the script was read from, `None` for scripts inline in the config
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        /// Directory `include` paths in the script are resolved against
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base_dir: Option<String>,
+    },
     Response { response: Value },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ResolvedMethodDefinition {
     pub method: String,
+    #[serde(default)]
+    pub delay: Option<RawDelay>,
+    #[serde(default)]
+    pub failure: Option<RawFailure>,
     #[serde(flatten)]
     pub response: ResolvedMethodResponse,
 }
@@ -22,10 +50,22 @@ pub struct ResolvedResource {
     pub children: Vec<ResolvedResource>,
     #[serde(default)]
     pub methods: Vec<ResolvedMethodDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<ResolvedScript>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResolvedConfig {
     pub port: u16,
+    #[serde(default, rename = "statusReasons", skip_serializing_if = "HashMap::is_empty")]
+    pub status_reasons: HashMap<String, String>,
+    #[serde(default, rename = "keepAlive", skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<RawKeepAlive>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup: Option<ResolvedScript>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<ResolvedScript>,
+    #[serde(default, rename = "disableDb", skip_serializing_if = "std::ops::Not::not")]
+    pub disable_db: bool,
     pub resources: Vec<ResolvedResource>,
 }
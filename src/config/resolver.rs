@@ -1,7 +1,7 @@
 use serde_json;
 use std::{env, fs, io::ErrorKind, path::Path};
 
-use super::{raw::{RawConfig, RawMethodResponse, RawPartialResource, RawResource, RawScript}, resolved::{ResolvedConfig, ResolvedMethodDefinition, ResolvedMethodResponse, ResolvedResource}};
+use super::{raw::{RawConfig, RawMethodResponse, RawPartialResource, RawResource, RawScript}, resolved::{ResolvedConfig, ResolvedMethodDefinition, ResolvedMethodResponse, ResolvedResource, ResolvedScript}};
 
 
 pub fn get_config_path_cwd(config_arg: &str) -> String {
@@ -39,23 +39,32 @@ pub fn load_script_from_ref(script_ref: &str, root_folder: &Path) -> Result<Stri
         .map_err(|e| format!("Error reading script file {}: {}", script_path, e))
 }
 
-fn resolve_method_response(mut raw: RawMethodResponse, root_folder: &Path) -> Result<ResolvedMethodResponse, String> {
-    // If it's a script response and we have a reference, inline it.
-    if let RawMethodResponse::Script { ref mut script } = raw {
-        if let RawScript::Ref { ref fref } = script {
-            let script_content = load_script_from_ref(&fref, root_folder)?;
-            *script = RawScript::Inline(script_content);
+/// Reads a referenced script from its file; inline scripts are kept as they are.
+fn resolve_script(script: RawScript, root_folder: &Path) -> Result<ResolvedScript, String> {
+    let (script, source) = match script {
+        RawScript::Inline(script) => (script, None),
+        RawScript::Ref { fref } => {
+            let script = load_script_from_ref(&fref, root_folder)?;
+            (script, Some(resolve_path(&fref, root_folder)))
         }
-    }
-    
-    // Now that we assume the script is inline, extract it.
+    };
+    // Includes are relative to the script file, or to the config defining an inline script
+    let base_dir = match &source {
+        Some(path) => Path::new(path).parent().unwrap_or(root_folder),
+        None => root_folder,
+    };
+    Ok(ResolvedScript {
+        script,
+        base_dir: Some(base_dir.to_string_lossy().into_owned()),
+        source,
+    })
+}
+
+fn resolve_method_response(raw: RawMethodResponse, root_folder: &Path) -> Result<ResolvedMethodResponse, String> {
     match raw {
         RawMethodResponse::Script { script } => {
-            if let RawScript::Inline(s) = script {
-                Ok(ResolvedMethodResponse::Script { script: s })
-            } else {
-                unreachable!("All script references should have been inlined")
-            }
+            let ResolvedScript { script, source, base_dir } = resolve_script(script, root_folder)?;
+            Ok(ResolvedMethodResponse::Script { script, source, base_dir })
         }
         RawMethodResponse::Response { response } => {
             Ok(ResolvedMethodResponse::Response { response })
@@ -65,7 +74,7 @@ fn resolve_method_response(mut raw: RawMethodResponse, root_folder: &Path) -> Re
 
 fn inline_resource(resource: RawResource, root_folder: &Path) -> Result<ResolvedResource, String> {
     match resource {
-        RawResource::Inline { path, children, methods } => {
+        RawResource::Inline { path, children, methods, after } => {
             // Process children: inline and convert each child.
             let resolved_children = children.into_iter()
                 .map(|child| inline_resource(child, root_folder))
@@ -76,6 +85,8 @@ fn inline_resource(resource: RawResource, root_folder: &Path) -> Result<Resolved
             for method in methods {
                 let resolved_method = ResolvedMethodDefinition {
                     method: method.method,
+                    delay: method.delay,
+                    failure: method.failure,
                     response: resolve_method_response(method.response, root_folder)?,
                 };
                 resolved_methods.push(resolved_method);
@@ -85,6 +96,7 @@ fn inline_resource(resource: RawResource, root_folder: &Path) -> Result<Resolved
                 path,
                 children: resolved_children,
                 methods: resolved_methods,
+                after: after.map(|after| resolve_script(after, root_folder)).transpose()?,
             })
         }
         RawResource::Reference { fref, path: override_path } => {
@@ -105,6 +117,7 @@ fn inline_resource(resource: RawResource, root_folder: &Path) -> Result<Resolved
                 path: override_path,
                 children: partial.children,
                 methods: partial.methods,
+                after: partial.after,
             };
             // Use the external file's directory as the new base for resolving further references.
             let new_root = external_path.parent().unwrap_or(root_folder);
@@ -119,8 +132,15 @@ pub fn resolve_config_references(config: RawConfig, root_folder: &Path) -> Resul
     let resolved_resources = config.resources.into_iter()
         .map(|resource| inline_resource(resource, root_folder))
         .collect::<Result<Vec<_>, String>>()?;
+    let startup = config.startup.map(|startup| resolve_script(startup, root_folder)).transpose()?;
+    let after = config.after.map(|after| resolve_script(after, root_folder)).transpose()?;
     Ok(ResolvedConfig {
         port: config.port,
+        status_reasons: config.status_reasons,
+        keep_alive: config.keep_alive,
+        startup,
+        after,
+        disable_db: config.disable_db,
         resources: resolved_resources,
     })
 }
@@ -1,190 +1,293 @@
-use crate::config::compiled::CompiledMethodResponse;
+use crate::config::compiled::{CompiledDelay, CompiledFailure, CompiledMethodDefinition, CompiledMethodResponse};
 use crate::http::router::RoutesData;
-use crate::rjscript;
+use crate::random;
+use crate::rjscript::ast::position::Position;
+use crate::rjscript::evaluator::engine::driver;
+use crate::rjscript::evaluator::errors::EvalError;
 use crate::rjscript::evaluator::runtime::value::RJSValue;
 use serde_json;
-use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
+use std::sync::{Arc, RwLock};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tracing::error;
 
+use super::range::{self, RangeOutcome};
 use super::request::{parse_http_request, Request};
 use super::router::find_route;
 
-const NOT_FOUND: &str = "HTTP/1.1 404 NOT FOUND\r\n\r\n";
-const INTERNAL_SERVER_ERROR: &str = "HTTP/1.1 500 INTERNAL SERVER ERROR\r\n\r\n";
-const SERVICE_UNAVAILABLE: &str = "HTTP/1.1 503 SERVICE UNAVAILABLE\r\n\r\n";
-
-fn reason_phrase(status: u16) -> &'static str {
-    match status {
-        100 => "Continue",
-        101 => "Switching Protocols",
-        102 => "Processing",
-        103 => "Early Hints",
-        200 => "OK",
-        201 => "Created",
-        202 => "Accepted",
-        203 => "Non-Authoritative Information",
-        204 => "No Content",
-        205 => "Reset Content",
-        206 => "Partial Content",
-        207 => "Multi-Status",
-        208 => "Already Reported",
-        226 => "IM Used",
-        300 => "Multiple Choices",
-        301 => "Moved Permanently",
-        302 => "Found",
-        303 => "See Other",
-        304 => "Not Modified",
-        305 => "Use Proxy",
-        307 => "Temporary Redirect",
-        308 => "Permanent Redirect",
-        400 => "Bad Request",
-        401 => "Unauthorized",
-        402 => "Payment Required",
-        403 => "Forbidden",
-        404 => "Not Found",
-        405 => "Method Not Allowed",
-        406 => "Not Acceptable",
-        407 => "Proxy Authentication Required",
-        408 => "Request Timeout",
-        409 => "Conflict",
-        410 => "Gone",
-        411 => "Length Required",
-        412 => "Precondition Failed",
-        413 => "Payload Too Large",
-        414 => "URI Too Long",
-        415 => "Unsupported Media Type",
-        416 => "Range Not Satisfiable",
-        417 => "Expectation Failed",
-        418 => "I'm a teapot",
-        421 => "Misdirected Request",
-        422 => "Unprocessable Entity",
-        423 => "Locked",
-        424 => "Failed Dependency",
-        425 => "Too Early",
-        426 => "Upgrade Required",
-        428 => "Precondition Required",
-        429 => "Too Many Requests",
-        431 => "Request Header Fields Too Large",
-        451 => "Unavailable For Legal Reasons",
-        500 => "Internal Server Error",
-        501 => "Not Implemented",
-        502 => "Bad Gateway",
-        503 => "Service Unavailable",
-        504 => "Gateway Timeout",
-        505 => "HTTP Version Not Supported",
-        506 => "Variant Also Negotiates",
-        507 => "Insufficient Storage",
-        508 => "Loop Detected",
-        510 => "Not Extended",
-        511 => "Network Authentication Required",
-        _ => "Unknown",
+const NOT_FOUND: &str = "HTTP/1.1 404 NOT FOUND\r\n";
+const INTERNAL_SERVER_ERROR: &str = "HTTP/1.1 500 INTERNAL SERVER ERROR\r\n";
+const SERVICE_UNAVAILABLE: &str = "HTTP/1.1 503 SERVICE UNAVAILABLE\r\n";
+
+/// Sleeps for the route's configured latency without blocking the worker.
+async fn apply_delay(delay: &CompiledDelay) {
+    tokio::time::sleep(delay.pick(random::next_u64())).await;
+}
+
+/// Applies the route's failure injection to `roll`, a draw in `[0, 1)`; `Some`
+/// means the request must fail.
+fn roll_failure(failure: &CompiledFailure, roll: f64) -> Option<(u16, serde_json::Value)> {
+    if roll < failure.probability {
+        Some((failure.status, failure.body.clone()))
+    } else {
+        None
     }
 }
 
+/// Runs the handler, then the route's `after` script on whatever it answered.
+/// A handler that fails to evaluate is a `500` the `after` script never sees.
 fn handle_method_response(
-    response: &CompiledMethodResponse,
+    definition: &CompiledMethodDefinition,
     req: &Request,
 ) -> Result<(u16, serde_json::Value), ()> {
-    match response {
-        CompiledMethodResponse::Response { status, body } => Ok((*status, body.clone())),
+    let response = match &definition.response {
+        CompiledMethodResponse::Response { status, body } => {
+            let Some(after) = &definition.after else {
+                return Ok((*status, body.clone()));
+            };
+            RJSValue::json_to_rjs(body, Position::UNKNOWN)
+                .and_then(|body| driver::eval_after_script(after, req, *status, body))
+        }
         CompiledMethodResponse::Script { script } => {
-            match rjscript::evaluator::engine::driver::eval_script(&script, req) {
-                Ok((code, val)) => Ok((code, RJSValue::rjs_to_json(&val))),
-                Err(err) => {
-                    error!("Evaluation error: {}", err);
-                    Err(())
+            let result = driver::eval_script(script, req);
+            match (&definition.after, result) {
+                (Some(after), Ok((code, val))) => driver::eval_after_script(after, req, code, val),
+                (Some(after), Err(EvalError::Http { status, body, .. })) => {
+                    driver::eval_after_script(after, req, status, body)
                 }
+                (None, result) => result,
+                (_, Err(err)) => Err(err),
             }
         }
+    };
+    match response {
+        Ok((code, val)) => Ok((code, RJSValue::rjs_to_json(&val))),
+        // an intended error response, not a script bug
+        Err(EvalError::Http { status, body, .. }) => Ok((status, RJSValue::rjs_to_json(&body))),
+        Err(err) => {
+            error!("Evaluation error: {}", err);
+            Err(())
+        }
     }
 }
 
-async fn read_http_request(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
-    let mut data = Vec::new();
+/// Reads the next request from `stream`. `pending` holds bytes already read from the
+/// connection that follow the previous request; bytes read past this request are
+/// left in it for the next call. Returns an empty request once the client closed
+/// the connection without sending another one.
+async fn read_http_request<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    pending: &mut Vec<u8>,
+) -> io::Result<Vec<u8>> {
     let mut buf = [0u8; 1024];
 
     // Read until we find the header terminator.
-    loop {
+    let header_end = loop {
+        if let Some(end) = pending.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
         let n = stream.read(&mut buf).await?;
         if n == 0 {
-            break;
-        }
-        data.extend_from_slice(&buf[..n]);
-        if data.windows(4).any(|window| window == b"\r\n\r\n") {
-            break;
-        }
-    }
-
-    // Determine if there is a Content-Length header to know if the body is complete.
-    let request_str = String::from_utf8_lossy(&data).to_string();
-    if let Some(header_end) = request_str.find("\r\n\r\n") {
-        // Parse the header section to extract Content-Length.
-        let headers_section = &request_str[..header_end];
-        let mut content_length = 0;
-        for line in headers_section.lines().skip(1) {
-            // Skip the request line.
-            if let Some(idx) = line.find(':') {
-                let key = line[..idx].trim();
-                let value = line[idx + 1..].trim();
-                if key.eq_ignore_ascii_case("Content-Length") {
-                    content_length = value.parse::<usize>().unwrap_or(0);
-                }
+            // closed, possibly mid-request: hand over whatever arrived
+            return Ok(std::mem::take(pending));
+        }
+        pending.extend_from_slice(&buf[..n]);
+    };
+
+    // The Content-Length header tells where the body, and so the request, ends.
+    let headers_section = String::from_utf8_lossy(&pending[..header_end]);
+    let mut content_length = 0;
+    for line in headers_section.lines().skip(1) {
+        // Skip the request line.
+        if let Some(idx) = line.find(':') {
+            let key = line[..idx].trim();
+            let value = line[idx + 1..].trim();
+            if key.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.parse::<usize>().unwrap_or(0);
             }
         }
+    }
+    let request_len = header_end + 4 + content_length;
 
-        // Calculate how many body bytes have already been read.
-        let body_start = header_end + 4;
-        let current_body_len = data.len().saturating_sub(body_start);
-        if current_body_len < content_length {
-            // Read the remaining body bytes.
-            let remaining = content_length - current_body_len;
-            let mut body_buf = vec![0; remaining];
-            stream.read_exact(&mut body_buf).await?;
-            data.extend(body_buf);
+    // Read the rest of the body.
+    while pending.len() < request_len {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid-body"));
         }
+        pending.extend_from_slice(&buf[..n]);
     }
-    Ok(data)
+    let rest = pending.split_off(request_len);
+    Ok(std::mem::replace(pending, rest))
 }
 
-pub async fn handle_client(
-    mut stream: TcpStream,
-    routes: Option<RoutesData>,
+/// Whether the client asked for the connection to be closed after this request.
+fn wants_close(req: &Request) -> bool {
+    req.headers.iter().any(|(key, value)| {
+        key.eq_ignore_ascii_case("Connection") && value.eq_ignore_ascii_case("close")
+    })
+}
+
+/// Writes `head` (status line plus headers) followed by the framing headers and `body`.
+async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    head: &str,
+    body: &[u8],
+    keep_open: bool,
+) -> io::Result<()> {
+    let connection = if keep_open { "keep-alive" } else { "close" };
+    let mut resp = format!(
+        "{}Content-Length: {}\r\nConnection: {}\r\n\r\n",
+        head,
+        body.len(),
+        connection,
+    )
+    .into_bytes();
+    resp.extend_from_slice(body);
+    stream.write_all(&resp).await
+}
+
+/// Answers a `Range` request for a static `200` response with `206` and the requested
+/// bytes, or `416` when none of them exist. `head` holds the headers of the full response;
+/// its `200` status line is replaced when a range applies.
+async fn write_ranged_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    routes: &RoutesData,
+    req: &Request,
+    head: String,
+    body: &[u8],
+    keep_open: bool,
+) -> io::Result<()> {
+    let etag = range::etag(body);
+    let outcome = range::evaluate(header(req, "Range"), header(req, "If-Range"), &etag, body.len());
+    let headers = head.split_once("\r\n").map_or("", |(_, rest)| rest);
+    let status_line = |code: u16| format!("HTTP/1.1 {} {}\r\n", code, routes.status_reasons.phrase(code));
+    match outcome {
+        RangeOutcome::Full => {
+            let head = format!("{}Accept-Ranges: bytes\r\nETag: {}\r\n", head, etag);
+            write_response(stream, &head, body, keep_open).await
+        }
+        RangeOutcome::Partial { start, end } => {
+            let head = format!(
+                "{}{}Accept-Ranges: bytes\r\nETag: {}\r\nContent-Range: bytes {}-{}/{}\r\n",
+                status_line(206),
+                headers,
+                etag,
+                start,
+                end,
+                body.len()
+            );
+            write_response(stream, &head, &body[start..=end], keep_open).await
+        }
+        RangeOutcome::Unsatisfiable => {
+            let head = format!(
+                "{}{}Accept-Ranges: bytes\r\nContent-Range: bytes */{}\r\n",
+                status_line(416),
+                headers,
+                body.len()
+            );
+            write_response(stream, &head, b"", keep_open).await
+        }
+    }
+}
+
+/// Value of the request header `name`, compared case-insensitively.
+fn header<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Serves requests on `stream` until the client closes it. Without a `keepAlive`
+/// config that is after the first response; with one, the connection is also
+/// closed when it stays idle too long or reaches its request limit. Each request
+/// is served with the routes current when it arrives.
+pub async fn handle_client<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    routes: Arc<RwLock<Option<RoutesData>>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let data = read_http_request(&mut stream).await?;
-    let (method, raw_path, mut req) = parse_http_request(&data);
+    let mut pending = Vec::new();
+    let mut served: u32 = 0;
+    loop {
+        let idle_keep_alive = routes.read().unwrap().as_ref().and_then(|r| r.keep_alive);
+        let data = match idle_keep_alive {
+            Some(ka) => {
+                match tokio::time::timeout(ka.idle_timeout, read_http_request(&mut stream, &mut pending)).await {
+                    Ok(read) => read?,
+                    // idle for too long
+                    Err(_) => return Ok(()),
+                }
+            }
+            None => read_http_request(&mut stream, &mut pending).await?,
+        };
+        if data.is_empty() {
+            // the client closed the connection
+            return Ok(());
+        }
+        served += 1;
+        let (method, raw_path, req) = parse_http_request(&data);
+        // a config reloaded while the connection was open applies from here on
+        let snapshot = routes.read().unwrap().clone();
+        let keep_alive = snapshot.as_ref().and_then(|r| r.keep_alive);
+        let keep_open = keep_alive.is_some_and(|ka| served < ka.max_requests) && !wants_close(&req);
+
+        handle_request(&mut stream, snapshot.as_ref(), &method, &raw_path, req, keep_open).await?;
+        if !keep_open {
+            return Ok(());
+        }
+    }
+}
 
+async fn handle_request<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    routes: Option<&RoutesData>,
+    method: &str,
+    raw_path: &str,
+    mut req: Request,
+    keep_open: bool,
+) -> io::Result<()> {
     // Handle CORS preflight requests with a very permissive policy for easier testing.
     if method.eq_ignore_ascii_case("OPTIONS") {
-        let cors_response = "HTTP/1.1 204 No Content\r\n\
+        let cors_head = "HTTP/1.1 204 No Content\r\n\
 Access-Control-Allow-Origin: *\r\n\
 Access-Control-Allow-Methods: GET, POST, PUT, PATCH, DELETE, OPTIONS\r\n\
 Access-Control-Allow-Headers: *\r\n\
 Access-Control-Allow-Credentials: true\r\n\
-Access-Control-Max-Age: 86400\r\n\r\n";
-        stream.write_all(cors_response.as_bytes()).await?;
-        return Ok(());
+Access-Control-Max-Age: 86400\r\n";
+        return write_response(stream, cors_head, b"", keep_open).await;
     }
 
-    if routes.is_none() {
-        stream.write_all(SERVICE_UNAVAILABLE.as_bytes()).await?;
-        return Ok(());
-    }
-    let routes = routes.unwrap();
+    let Some(routes) = routes else {
+        return write_response(stream, SERVICE_UNAVAILABLE, b"", keep_open).await;
+    };
 
-    if let Some((response, route_params)) = find_route(
+    if let Some((definition, route_params)) = find_route(
         &routes.static_routes,
         &routes.dynamic_root,
-        &raw_path,
-        &method,
+        raw_path,
+        method,
     ) {
         req.route_params = route_params;
 
-        let result = handle_method_response(&response, &req);
+        // An injected failure short-circuits the real handler.
+        let failed = definition.failure.as_ref().and_then(|f| roll_failure(f, random::next_f64()));
+        // only a static body is stable enough to be fetched in pieces
+        let ranged = failed.is_none()
+            && definition.after.is_none()
+            && method.eq_ignore_ascii_case("GET")
+            && matches!(definition.response, CompiledMethodResponse::Response { .. });
+        let result = match failed {
+            Some(failed) => Ok(failed),
+            None => handle_method_response(&definition, &req),
+        };
+
+        if let Some(delay) = &definition.delay {
+            apply_delay(delay).await;
+        }
 
         match result {
             Ok((response_code, response_value)) => {
-                let reason = reason_phrase(response_code);
+                let reason = routes.status_reasons.phrase(response_code);
                 let mut response_code_string = format!("HTTP/1.1 {} {}\r\n", response_code, reason);
 
                 response_code_string.push_str("Access-Control-Allow-Origin: *\r\n");
@@ -194,16 +297,488 @@ Access-Control-Max-Age: 86400\r\n\r\n";
                 response_code_string.push_str("Access-Control-Allow-Headers: *\r\n");
                 response_code_string.push_str("Access-Control-Allow-Credentials: true\r\n");
 
-                response_code_string.push_str("Content-Type: application/json\r\n\r\n");
-                let final_resp = format!("{}{}", response_code_string, response_value.to_string());
-                stream.write_all(final_resp.as_bytes()).await?;
-            }
-            Err(_) => {
-                stream.write_all(INTERNAL_SERVER_ERROR.as_bytes()).await?;
+                response_code_string.push_str("Content-Type: application/json\r\n");
+                let body = response_value.to_string();
+                if ranged && response_code == 200 {
+                    return write_ranged_response(stream, routes, &req, response_code_string, body.as_bytes(), keep_open).await;
+                }
+                write_response(stream, &response_code_string, body.as_bytes(), keep_open).await
             }
+            Err(_) => write_response(stream, INTERNAL_SERVER_ERROR, b"", keep_open).await,
         }
     } else {
-        stream.write_all(NOT_FOUND.as_bytes()).await?;
+        write_response(stream, NOT_FOUND, b"", keep_open).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::{json, Value};
+    use tokio::io::{duplex, DuplexStream};
+    use tokio::time::{Duration, Instant};
+
+    use super::*;
+    use crate::config::compiled::compile_config;
+    use crate::http::router::get_routes_from_config;
+
+    fn routes_for(config: Value) -> Option<RoutesData> {
+        let compiled = compile_config(serde_json::from_value(config).unwrap()).unwrap();
+        Some(get_routes_from_config(&compiled, Path::new(".")))
+    }
+
+    /// A config answering `GET /a` with `body`, keeping connections open for `max_requests`.
+    fn config(body: &str, max_requests: Option<u32>) -> Value {
+        let mut config = json!({
+            "port": 8080,
+            "resources": [{ "path": "/a", "methods": [{ "method": "GET", "response": { "body": body } }] }]
+        });
+        if let Some(max) = max_requests {
+            config["keepAlive"] = json!({ "idleTimeoutMs": 5000, "maxRequests": max });
+        }
+        config
+    }
+
+    fn shared(config: Value) -> Arc<RwLock<Option<RoutesData>>> {
+        Arc::new(RwLock::new(routes_for(config)))
+    }
+
+    /// The next response on `client` as text, `None` once the server closed the connection.
+    async fn response(client: &mut DuplexStream, pending: &mut Vec<u8>) -> Option<String> {
+        let data = read_http_request(client, pending).await.unwrap();
+        (!data.is_empty()).then(|| String::from_utf8(data).unwrap())
+    }
+
+    const GET_A: &str = "GET /a HTTP/1.1\r\nHost: x\r\n\r\n";
+
+    /// Serves `request` on a fresh connection and returns the response.
+    async fn exchange(routes: Arc<RwLock<Option<RoutesData>>>, request: &str) -> String {
+        let (mut client, server) = duplex(64 * 1024);
+        let (served, resp) = tokio::join!(handle_client(server, routes), async move {
+            client.write_all(request.as_bytes()).await.unwrap();
+            response(&mut client, &mut Vec::new()).await.unwrap()
+        });
+        served.unwrap();
+        resp
+    }
+
+    /// Splits a response into its status line, headers and body.
+    fn parts(resp: &str) -> (&str, Vec<&str>, &str) {
+        let (head, body) = resp.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.split("\r\n");
+        (lines.next().unwrap(), lines.collect(), body)
+    }
+
+    #[tokio::test]
+    async fn pipelined_requests_get_one_response_each() {
+        let (mut client, server) = duplex(64 * 1024);
+        let routes = shared(config("one", Some(10)));
+        let (served, ()) = tokio::join!(handle_client(server, routes), async move {
+            let mut pending = Vec::new();
+            client.write_all(format!("{GET_A}{GET_A}").as_bytes()).await.unwrap();
+            for _ in 0..2 {
+                let resp = response(&mut client, &mut pending).await.unwrap();
+                assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+                assert!(resp.contains("Connection: keep-alive"), "{}", resp);
+                assert!(resp.ends_with("\"one\""), "{}", resp);
+            }
+            assert!(pending.is_empty());
+        });
+        served.unwrap();
+    }
+
+    #[tokio::test]
+    async fn bodies_split_across_reads_keep_the_next_request() {
+        // a tiny pipe makes every read return a few bytes
+        let (mut client, mut server) = duplex(7);
+        let post = "POST /a HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world";
+        let ((), ()) = tokio::join!(
+            async move {
+                client.write_all(format!("{post}{GET_A}").as_bytes()).await.unwrap();
+            },
+            async move {
+                let mut pending = Vec::new();
+                let first = read_http_request(&mut server, &mut pending).await.unwrap();
+                assert_eq!(String::from_utf8(first).unwrap(), post);
+                let second = read_http_request(&mut server, &mut pending).await.unwrap();
+                assert_eq!(String::from_utf8(second).unwrap(), GET_A);
+                assert!(read_http_request(&mut server, &mut pending).await.unwrap().is_empty());
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn a_body_cut_short_is_an_error() {
+        let (mut client, mut server) = duplex(1024);
+        client.write_all(b"POST /a HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc").await.unwrap();
+        drop(client);
+        let err = read_http_request(&mut server, &mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn reloaded_routes_apply_to_the_next_request() {
+        let (mut client, server) = duplex(64 * 1024);
+        let routes = shared(config("old", Some(10)));
+        let reload = Arc::clone(&routes);
+        let (served, ()) = tokio::join!(handle_client(server, routes), async move {
+            let mut pending = Vec::new();
+            client.write_all(GET_A.as_bytes()).await.unwrap();
+            assert!(response(&mut client, &mut pending).await.unwrap().ends_with("\"old\""));
+
+            *reload.write().unwrap() = routes_for(config("new", Some(10)));
+            client.write_all(GET_A.as_bytes()).await.unwrap();
+            assert!(response(&mut client, &mut pending).await.unwrap().ends_with("\"new\""));
+        });
+        served.unwrap();
+    }
+
+    #[tokio::test]
+    async fn max_requests_closes_the_connection() {
+        let (mut client, server) = duplex(64 * 1024);
+        let routes = shared(config("one", Some(2)));
+        let (served, ()) = tokio::join!(handle_client(server, routes), async move {
+            let mut pending = Vec::new();
+            client.write_all(format!("{GET_A}{GET_A}{GET_A}").as_bytes()).await.unwrap();
+            let first = response(&mut client, &mut pending).await.unwrap();
+            assert!(first.contains("Connection: keep-alive"), "{}", first);
+            let second = response(&mut client, &mut pending).await.unwrap();
+            assert!(second.contains("Connection: close"), "{}", second);
+            assert_eq!(response(&mut client, &mut pending).await, None);
+        });
+        served.unwrap();
+    }
+
+    #[tokio::test]
+    async fn without_keep_alive_one_request_is_served() {
+        for (routes, request) in [
+            (shared(config("one", None)), GET_A.to_string()),
+            (shared(config("one", Some(10))), "GET /a HTTP/1.1\r\nConnection: close\r\n\r\n".to_string()),
+        ] {
+            let (mut client, server) = duplex(64 * 1024);
+            let (served, ()) = tokio::join!(handle_client(server, routes), async move {
+                let mut pending = Vec::new();
+                client.write_all(format!("{request}{GET_A}").as_bytes()).await.unwrap();
+                let resp = response(&mut client, &mut pending).await.unwrap();
+                assert!(resp.contains("Connection: close"), "{}", resp);
+                assert_eq!(response(&mut client, &mut pending).await, None);
+            });
+            served.unwrap();
+        }
+    }
+
+    #[test]
+    fn seeded_failures_repeat() {
+        let failure = CompiledFailure { probability: 0.3, status: 503, body: json!("down") };
+        let pattern = |seed| -> String {
+            let mut rng = random::SplitMix64::new(seed);
+            (0..20)
+                .map(|_| match roll_failure(&failure, rng.next_f64()) {
+                    Some((503, body)) if body == json!("down") => 'F',
+                    Some(other) => panic!("unexpected failure {:?}", other),
+                    None => '.',
+                })
+                .collect()
+        };
+        assert_eq!(pattern(42), ".FF.F.F...F....FF.F.");
+        assert_eq!(pattern(42), pattern(42));
+        assert_ne!(pattern(42), pattern(43));
+    }
+
+    #[test]
+    fn failure_probability_bounds() {
+        let never = CompiledFailure { probability: 0.0, status: 503, body: Value::Null };
+        let always = CompiledFailure { probability: 1.0, ..never.clone() };
+        for roll in [0.0, 0.5, 1.0 - f64::EPSILON] {
+            assert_eq!(roll_failure(&never, roll), None);
+            assert!(roll_failure(&always, roll).is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn ranges_of_static_bodies() {
+        // the JSON body is the string in quotes, 202 bytes
+        let text = "0123456789".repeat(20);
+        let body = format!("\"{}\"", text);
+        let routes = shared(config(&text, None));
+        let get = |range: &str| format!("GET /a HTTP/1.1\r\nRange: {}\r\n\r\n", range);
+
+        let resp = exchange(Arc::clone(&routes), &get("bytes=0-99")).await;
+        let (status, headers, sent) = parts(&resp);
+        assert_eq!(status, "HTTP/1.1 206 Partial Content");
+        assert!(headers.contains(&"Content-Range: bytes 0-99/202"), "{:?}", headers);
+        assert!(headers.contains(&"Content-Length: 100"), "{:?}", headers);
+        assert!(headers.contains(&"Content-Type: application/json"), "{:?}", headers);
+        assert_eq!(sent, &body[..100]);
+
+        let resp = exchange(Arc::clone(&routes), &get("bytes=10-")).await;
+        let (status, headers, sent) = parts(&resp);
+        assert_eq!(status, "HTTP/1.1 206 Partial Content");
+        assert!(headers.contains(&"Content-Range: bytes 10-201/202"), "{:?}", headers);
+        assert_eq!(sent, &body[10..]);
+
+        let resp = exchange(Arc::clone(&routes), &get("bytes=202-")).await;
+        let (status, headers, sent) = parts(&resp);
+        assert_eq!(status, "HTTP/1.1 416 Range Not Satisfiable");
+        assert!(headers.contains(&"Content-Range: bytes */202"), "{:?}", headers);
+        assert!(headers.contains(&"Content-Length: 0"), "{:?}", headers);
+        assert_eq!(sent, "");
+    }
+
+    #[tokio::test]
+    async fn if_range_with_a_stale_etag_gets_the_full_body() {
+        let routes = shared(config("hello", None));
+        let resp = exchange(Arc::clone(&routes), GET_A).await;
+        let (status, headers, body) = parts(&resp);
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(headers.contains(&"Accept-Ranges: bytes"), "{:?}", headers);
+        let etag = headers.iter().find_map(|h| h.strip_prefix("ETag: ")).unwrap();
+        assert_eq!(etag, range::etag(body.as_bytes()));
+
+        let fresh = format!("GET /a HTTP/1.1\r\nRange: bytes=1-2\r\nIf-Range: {}\r\n\r\n", etag);
+        let resp = exchange(Arc::clone(&routes), &fresh).await;
+        assert_eq!(parts(&resp).0, "HTTP/1.1 206 Partial Content");
+        assert_eq!(parts(&resp).2, "he");
+
+        let stale = "GET /a HTTP/1.1\r\nRange: bytes=1-2\r\nIf-Range: \"old\"\r\n\r\n";
+        let resp = exchange(routes, stale).await;
+        assert_eq!(parts(&resp).0, "HTTP/1.1 200 OK");
+        assert_eq!(parts(&resp).2, "\"hello\"");
+    }
+
+    #[tokio::test]
+    async fn ranges_are_ignored_for_scripts() {
+        let routes = shared(json!({
+            "port": 8080,
+            "resources": [{ "path": "/s", "methods": [{ "method": "GET", "script": "return 200, \"hello\";" }] }]
+        }));
+        let resp = exchange(routes, "GET /s HTTP/1.1\r\nRange: bytes=0-1\r\n\r\n").await;
+        let (status, headers, body) = parts(&resp);
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(!headers.iter().any(|h| h.starts_with("Content-Range")), "{:?}", headers);
+        assert_eq!(body, "\"hello\"");
+    }
+
+    /// Status code and parsed JSON body of a response with one.
+    fn status_and_json(resp: &str) -> (u16, Value) {
+        let (status, _, body) = parts(resp);
+        let code = status.split(' ').nth(1).unwrap().parse().unwrap();
+        (code, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    fn after_config() -> Value {
+        let method = |path: &str, method: Value| json!({ "path": path, "methods": [method] });
+        let script = |path: &str, src: &str| method(path, json!({ "method": "GET", "script": src }));
+        json!({
+            "port": 8080,
+            "after": { "script": "return response.status, { data: response.body, status: response.status };" },
+            "resources": [
+                method("/static", json!({ "method": "GET", "response": { "status": 201, "body": "s" } })),
+                script("/script", "return 202, [1, 2];"),
+                script("/plain", "return \"p\";"),
+                script("/empty", "return 204, null;"),
+                script("/error", "error(404, \"nope\");\nreturn 200, null;"),
+                script("/throw", "throw \"boom\";"),
+                script("/broken", "let xs: vec<num> = [];\nreturn 200, xs[3];"),
+                method("/flaky", json!({
+                    "method": "GET",
+                    "failure": { "probability": 1.0, "status": 503, "body": "down" },
+                    "response": { "body": "up" }
+                })),
+                {
+                    "path": "/own",
+                    "after": { "script": "return \"replaced\";" },
+                    "methods": [{ "method": "GET", "response": { "status": 201, "body": "s" } }],
+                    "children": [{ "path": "child", "methods": [{ "method": "GET", "script": "return 202, 1;" }] }]
+                }
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn after_script_wraps_every_kind_of_response() {
+        let routes = shared(after_config());
+        let get = |path: &str| format!("GET {} HTTP/1.1\r\n\r\n", path);
+        for (path, status, data) in [
+            ("/static", 201, json!("s")),
+            ("/script", 202, json!([1.0, 2.0])),
+            ("/plain", 200, json!("p")),
+            ("/empty", 204, Value::Null),
+            ("/error", 404, json!("nope")),
+            ("/throw", 500, json!("boom")),
+        ] {
+            let resp = exchange(Arc::clone(&routes), &get(path)).await;
+            assert_eq!(
+                status_and_json(&resp),
+                (status, json!({ "data": data, "status": status as f64 })),
+                "{}: {}",
+                path,
+                resp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn after_script_is_skipped_for_failures() {
+        let routes = shared(after_config());
+        // a script that fails to evaluate is an empty 500
+        let resp = exchange(Arc::clone(&routes), "GET /broken HTTP/1.1\r\n\r\n").await;
+        assert_eq!(parts(&resp).0, "HTTP/1.1 500 INTERNAL SERVER ERROR");
+        assert_eq!(parts(&resp).2, "");
+        // an injected failure is sent as configured
+        let resp = exchange(Arc::clone(&routes), "GET /flaky HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status_and_json(&resp), (503, json!("down")));
+        let resp = exchange(routes, "GET /missing HTTP/1.1\r\n\r\n").await;
+        assert_eq!(parts(&resp).0, "HTTP/1.1 404 NOT FOUND");
+    }
+
+    #[tokio::test]
+    async fn resource_after_script_replaces_the_inherited_one() {
+        let routes = shared(after_config());
+        // `return value;` keeps the status
+        let resp = exchange(Arc::clone(&routes), "GET /own HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status_and_json(&resp), (201, json!("replaced")));
+        let resp = exchange(routes, "GET /own/child HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status_and_json(&resp), (202, json!("replaced")));
+    }
+
+    #[tokio::test]
+    async fn after_script_can_rewrite_the_status() {
+        let routes = shared(json!({
+            "port": 8080,
+            "after": { "script": "if (response.status == 404) { return 200, { found: false }; }\nreturn response.status, response.body;" },
+            "resources": [
+                { "path": "/gone", "methods": [{ "method": "GET", "script": "error(404, \"x\");\nreturn 200, null;" }] },
+                { "path": "/ok", "methods": [{ "method": "GET", "response": { "body": "kept" } }] }
+            ]
+        }));
+        let resp = exchange(Arc::clone(&routes), "GET /gone HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status_and_json(&resp), (200, json!({ "found": false })));
+        let resp = exchange(routes, "GET /ok HTTP/1.1\r\nRange: bytes=0-1\r\n\r\n").await;
+        // reshaped static bodies ignore ranges
+        assert_eq!(status_and_json(&resp), (200, json!("kept")));
+    }
+
+    #[tokio::test]
+    async fn intended_errors_keep_their_status_and_script_bugs_are_500() {
+        let script = |path: &str, src: &str| json!({ "path": path, "methods": [{ "method": "GET", "script": src }] });
+        let routes = shared(json!({
+            "port": 8080,
+            "resources": [
+                script("/error", "error(404, { missing: \"user\" });\nreturn 200, null;"),
+                script("/error-in-try", "try {\n  error(409, \"taken\");\n} catch (e) {\n  return 200, \"caught\";\n}\nreturn 200, null;"),
+                script("/error-in-func", "func check(): bool {\n  error(422, [\"name\"]);\n  return true;\n}\nreturn 200, check();"),
+                script("/throw-status", "throw { status: 403, body: \"no\" };"),
+                script("/throw-value", "throw { reason: \"boom\" };"),
+                script("/throw-caught", "try {\n  throw 1;\n} catch (e) {\n  return 201, e;\n}\nreturn 200, null;"),
+                script("/bad-status", "error(700, \"x\");\nreturn 200, null;"),
+                script("/bug", "let xs: vec<num> = [];\nreturn 200, xs[3];")
+            ]
+        }));
+        let get = |path: &str| format!("GET {} HTTP/1.1\r\n\r\n", path);
+        for (path, status, body) in [
+            // `error(status, body)` is sent as is, even from a `try` or a function
+            ("/error", 404, json!({ "missing": "user" })),
+            ("/error-in-try", 409, json!("taken")),
+            ("/error-in-func", 422, json!(["name"])),
+            // an uncaught throw uses its `status` and `body`, or is a 500 with the value
+            ("/throw-status", 403, json!("no")),
+            ("/throw-value", 500, json!({ "reason": "boom" })),
+            ("/throw-caught", 201, json!(1.0)),
+        ] {
+            let resp = exchange(Arc::clone(&routes), &get(path)).await;
+            assert_eq!(status_and_json(&resp), (status, body), "{}: {}", path, resp);
+        }
+        // everything else is a script bug: an empty 500
+        for path in ["/bad-status", "/bug"] {
+            let resp = exchange(Arc::clone(&routes), &get(path)).await;
+            assert_eq!(parts(&resp).0, "HTTP/1.1 500 INTERNAL SERVER ERROR", "{}", path);
+            assert_eq!(parts(&resp).2, "", "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn idle_connection_is_closed_after_the_timeout() {
+        let mut config = config("one", None);
+        config["keepAlive"] = json!({ "idleTimeoutMs": 50, "maxRequests": 10 });
+        let routes = shared(config);
+
+        // idle after a request
+        let (mut client, server) = duplex(64 * 1024);
+        let (served, ()) = tokio::join!(handle_client(server, Arc::clone(&routes)), async move {
+            let mut pending = Vec::new();
+            client.write_all(GET_A.as_bytes()).await.unwrap();
+            let resp = response(&mut client, &mut pending).await.unwrap();
+            assert!(resp.contains("Connection: keep-alive"), "{}", resp);
+            let idle = Instant::now();
+            let closed = tokio::time::timeout(Duration::from_secs(5), response(&mut client, &mut pending));
+            assert_eq!(closed.await.expect("connection left open"), None);
+            assert!(idle.elapsed() >= Duration::from_millis(50));
+        });
+        served.unwrap();
+
+        // idle before the first request
+        let (mut client, server) = duplex(64 * 1024);
+        let (served, ()) = tokio::join!(handle_client(server, routes), async move {
+            let mut pending = Vec::new();
+            let closed = tokio::time::timeout(Duration::from_secs(5), response(&mut client, &mut pending));
+            assert_eq!(closed.await.expect("connection left open"), None);
+        });
+        served.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_request_in_time_resets_the_idle_timeout() {
+        let mut config = config("one", None);
+        config["keepAlive"] = json!({ "idleTimeoutMs": 200, "maxRequests": 10 });
+        let (mut client, server) = duplex(64 * 1024);
+        let (served, ()) = tokio::join!(handle_client(server, shared(config)), async move {
+            let mut pending = Vec::new();
+            for _ in 0..3 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                client.write_all(GET_A.as_bytes()).await.unwrap();
+                assert!(response(&mut client, &mut pending).await.is_some());
+            }
+        });
+        served.unwrap();
+    }
+
+    #[tokio::test]
+    async fn delayed_response_arrives_after_the_delay() {
+        let method = |path: &str, delay: Value| {
+            json!({ "path": path, "methods": [{ "method": "GET", "delay": delay, "response": { "body": path } }] })
+        };
+        let routes = shared(json!({
+            "port": 8080,
+            "resources": [method("/fixed", json!(150)), method("/range", json!({ "min": 80, "max": 120 }))]
+        }));
+        for (path, at_least) in [("/fixed", 150), ("/range", 80)] {
+            let started = Instant::now();
+            let resp = exchange(Arc::clone(&routes), &format!("GET {} HTTP/1.1\r\n\r\n", path)).await;
+            let took = started.elapsed();
+            assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+            assert!(took >= Duration::from_millis(at_least), "{} took {:?}", path, took);
+        }
+    }
+
+    #[tokio::test]
+    async fn delays_do_not_block_other_connections() {
+        let routes = shared(json!({
+            "port": 8080,
+            "resources": [
+                { "path": "/slow", "methods": [{ "method": "GET", "delay": 300, "response": { "body": 1 } }] },
+                { "path": "/fast", "methods": [{ "method": "GET", "response": { "body": 2 } }] }
+            ]
+        }));
+        let started = Instant::now();
+        let slow = exchange(Arc::clone(&routes), "GET /slow HTTP/1.1\r\n\r\n");
+        let fast = async {
+            let resp = exchange(Arc::clone(&routes), "GET /fast HTTP/1.1\r\n\r\n").await;
+            (resp, started.elapsed())
+        };
+        let (_, (resp, fast_took)) = tokio::join!(slow, fast);
+        assert!(resp.starts_with("HTTP/1.1 200"), "{}", resp);
+        assert!(fast_took < Duration::from_millis(300), "{:?}", fast_took);
     }
-    Ok(())
 }
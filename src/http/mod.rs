@@ -1,4 +1,6 @@
 pub mod handler;
+pub mod range;
 pub mod request;
 pub mod router;
-pub mod server;
\ No newline at end of file
+pub mod server;
+pub mod status;
\ No newline at end of file
@@ -0,0 +1,149 @@
+//! Byte ranges (`Range`/`If-Range`, RFC 9110 §14) for static responses.
+
+use crate::digest;
+
+/// What to send for a request that may carry a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeOutcome {
+    /// No usable range: send the whole body with `200`
+    Full,
+    /// Send bytes `start..=end` with `206`
+    Partial { start: usize, end: usize },
+    /// No byte of the range exists: `416`
+    Unsatisfiable,
+}
+
+/// Strong validator of a body, sent as `ETag` so clients can resume with `If-Range`.
+pub fn etag(body: &[u8]) -> String {
+    let hash = digest::sha256(body);
+    format!("\"{}\"", digest::to_hex(&hash[..8]))
+}
+
+/// Picks the part of a `len`-byte body to send. An `If-Range` that does not match
+/// `etag` means the client's copy is stale, so it gets the whole body. Multiple
+/// ranges and malformed headers are ignored, which the RFC allows.
+pub fn evaluate(range: Option<&str>, if_range: Option<&str>, etag: &str, len: usize) -> RangeOutcome {
+    let Some(range) = range else {
+        return RangeOutcome::Full;
+    };
+    if if_range.is_some_and(|v| v.trim() != etag) {
+        return RangeOutcome::Full;
+    }
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return RangeOutcome::Full;
+    };
+    if spec.contains(',') {
+        return RangeOutcome::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return RangeOutcome::Full;
+    };
+    let parse = |s: &str| -> Option<usize> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // a position past usize::MAX is past the end of any body
+        Some(s.parse().unwrap_or(usize::MAX))
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // `bytes=-N`: the last N bytes
+        return match parse(last) {
+            None => RangeOutcome::Full,
+            Some(0) => RangeOutcome::Unsatisfiable,
+            Some(_) if len == 0 => RangeOutcome::Unsatisfiable,
+            Some(suffix) => RangeOutcome::Partial { start: len.saturating_sub(suffix), end: len - 1 },
+        };
+    }
+    // `bytes=A-B`, or `bytes=A-` up to the end
+    let Some(start) = parse(first) else {
+        return RangeOutcome::Full;
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match parse(last) {
+            Some(end) if end >= start => Some(end),
+            _ => return RangeOutcome::Full,
+        }
+    };
+    if start >= len {
+        return RangeOutcome::Unsatisfiable;
+    }
+    RangeOutcome::Partial { start, end: end.map_or(len - 1, |end| end.min(len - 1)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG: &str = "\"abc\"";
+
+    fn eval(range: &str, len: usize) -> RangeOutcome {
+        evaluate(Some(range), None, TAG, len)
+    }
+
+    fn partial(start: usize, end: usize) -> RangeOutcome {
+        RangeOutcome::Partial { start, end }
+    }
+
+    #[test]
+    fn closed_ranges() {
+        assert_eq!(eval("bytes=0-99", 1000), partial(0, 99));
+        assert_eq!(eval("bytes=5-5", 10), partial(5, 5));
+        assert_eq!(eval(" bytes= 2 - 4 ", 10), partial(2, 4));
+        // an end past the body is clamped
+        assert_eq!(eval("bytes=0-99", 10), partial(0, 9));
+        assert_eq!(eval("bytes=9-99999999999999999999999", 10), partial(9, 9));
+    }
+
+    #[test]
+    fn open_ended_and_suffix_ranges() {
+        assert_eq!(eval("bytes=10-", 100), partial(10, 99));
+        assert_eq!(eval("bytes=99-", 100), partial(99, 99));
+        assert_eq!(eval("bytes=-10", 100), partial(90, 99));
+        assert_eq!(eval("bytes=-500", 100), partial(0, 99));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(eval("bytes=100-", 100), RangeOutcome::Unsatisfiable);
+        assert_eq!(eval("bytes=100-200", 100), RangeOutcome::Unsatisfiable);
+        assert_eq!(eval("bytes=-0", 100), RangeOutcome::Unsatisfiable);
+        assert_eq!(eval("bytes=0-", 0), RangeOutcome::Unsatisfiable);
+        assert_eq!(eval("bytes=-5", 0), RangeOutcome::Unsatisfiable);
+    }
+
+    #[test]
+    fn malformed_or_multiple_ranges_get_the_full_body() {
+        for range in ["bytes=5-2", "bytes=a-b", "bytes=-", "bytes=0-1,3-4", "items=0-1", "bytes=1", "bytes=+1-2"] {
+            assert_eq!(eval(range, 100), RangeOutcome::Full, "{}", range);
+        }
+        assert_eq!(evaluate(None, None, TAG, 100), RangeOutcome::Full);
+    }
+
+    #[test]
+    fn if_range_needs_the_current_etag() {
+        assert_eq!(evaluate(Some("bytes=0-1"), Some(TAG), TAG, 10), partial(0, 1));
+        assert_eq!(evaluate(Some("bytes=0-1"), Some(" \"abc\" "), TAG, 10), partial(0, 1));
+        assert_eq!(evaluate(Some("bytes=0-1"), Some("\"old\""), TAG, 10), RangeOutcome::Full);
+        // a weak or date validator never matches a strong etag
+        assert_eq!(evaluate(Some("bytes=0-1"), Some("W/\"abc\""), TAG, 10), RangeOutcome::Full);
+        assert_eq!(
+            evaluate(Some("bytes=0-1"), Some("Wed, 21 Oct 2015 07:28:00 GMT"), TAG, 10),
+            RangeOutcome::Full
+        );
+    }
+
+    #[test]
+    fn etag_follows_the_body() {
+        let tag = etag(b"hello");
+        assert_eq!(tag, etag(b"hello"));
+        assert_ne!(tag, etag(b"hello!"));
+        assert_eq!(tag.len(), 18);
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+        // the first 8 bytes of sha256("hello")
+        assert_eq!(tag, "\"2cf24dba5fb0a30e\"");
+    }
+}
@@ -8,6 +8,8 @@ pub struct Request {
     pub query_params: HashMap<String, String>,
     pub route_params: HashMap<String, String>,
     pub headers: HashMap<String, String>,
+    /// Path of the request without the query string, empty outside of a request
+    pub path: String,
 }
 
 impl Request {
@@ -22,6 +24,7 @@ impl Request {
             query_params,
             route_params,
             headers,
+            path: String::new(),
         }
     }
 }
@@ -79,6 +82,7 @@ pub fn parse_http_request(buffer: &[u8]) -> (String, String, Request) {
     debug!("Headers: {:?}", headers);
     debug!("Body: {}", body_json);
 
-    let request = Request::new(body_json, query_params, HashMap::new(), headers);
+    let mut request = Request::new(body_json, query_params, HashMap::new(), headers);
+    request.path = raw_path.clone();
     (method, raw_path, request)
 }
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::config::compiled::{CompiledConfig, CompiledMethodResponse, CompiledResource};
+use tracing::info;
+
+use crate::config::compiled::{CompiledConfig, CompiledKeepAlive, CompiledMethodDefinition, CompiledResource};
+use crate::http::status::StatusReasons;
 
 #[derive(Debug, Clone)]
 pub struct RouteNode {
-    pub methods: HashMap<String, CompiledMethodResponse>,
+    pub methods: HashMap<String, CompiledMethodDefinition>,
     pub static_children: HashMap<String, Box<RouteNode>>,
     pub dynamic_child: Option<(String, Box<RouteNode>)>,
 }
@@ -24,15 +28,17 @@ impl RouteNode {
 pub struct RoutesData {
     pub static_routes: StaticRoutes,
     pub dynamic_root: RouteNode,
+    pub status_reasons: Arc<StatusReasons>,
+    pub keep_alive: Option<CompiledKeepAlive>,
 }
 
-pub type StaticRoutes = HashMap<String, HashMap<String, CompiledMethodResponse>>;
+pub type StaticRoutes = HashMap<String, HashMap<String, CompiledMethodDefinition>>;
 
 fn match_static_route(
     static_routes: &StaticRoutes,
     path: &str,
     method: &str,
-) -> Option<CompiledMethodResponse> {
+) -> Option<CompiledMethodDefinition> {
     if let Some(methods) = static_routes.get(path) {
         if let Some(resp) = methods.get(method) {
             return Some(resp.clone());
@@ -45,7 +51,7 @@ fn match_dynamic_route(
     root: &RouteNode,
     path_segments: &[&str],
     method: &str,
-) -> Option<(CompiledMethodResponse, HashMap<String, String>)> {
+) -> Option<(CompiledMethodDefinition, HashMap<String, String>)> {
     let mut current = root;
     let mut route_params = HashMap::new();
 
@@ -67,17 +73,29 @@ fn match_dynamic_route(
     }
 }
 
+/// Non-empty segments of a path, so `/`, `` and `//` all have none and
+/// `/users/` is the same as `/users`.
+fn path_segments(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Key of a static route: the segments joined behind a single leading `/`.
+/// The root resource is `/`.
+fn normalize_path(segments: &[&str]) -> String {
+    format!("/{}", segments.join("/"))
+}
+
 pub fn find_route(
     static_routes: &StaticRoutes,
     dynamic_root: &RouteNode,
     raw_path: &str,
     method: &str,
-) -> Option<(CompiledMethodResponse, HashMap<String, String>)> {
-    if let Some(resp) = match_static_route(static_routes, raw_path, method) {
+) -> Option<(CompiledMethodDefinition, HashMap<String, String>)> {
+    let segments = path_segments(raw_path);
+    if let Some(resp) = match_static_route(static_routes, &normalize_path(&segments), method) {
         return Some((resp, HashMap::new()));
     }
 
-    let segments: Vec<&str> = raw_path.split('/').filter(|s| !s.is_empty()).collect();
     match match_dynamic_route(dynamic_root, &segments, method) {
         Some((resp, route_params)) => Some((resp, route_params)),
         None => None,
@@ -91,7 +109,7 @@ fn is_dynamic_segment(segment: &str) -> bool {
 fn insert_dynamic_path(
     root: &mut RouteNode,
     path_segments: &[&str],
-    methods: &std::collections::HashMap<String, CompiledMethodResponse>,
+    methods: &std::collections::HashMap<String, CompiledMethodDefinition>,
 ) {
     let mut current = root;
 
@@ -119,7 +137,7 @@ fn insert_dynamic_path(
 fn insert_static_path(
     static_routes: &mut StaticRoutes,
     full_path: &str,
-    methods: &std::collections::HashMap<String, CompiledMethodResponse>,
+    methods: &std::collections::HashMap<String, CompiledMethodDefinition>,
 ) {
     static_routes.insert(full_path.to_string(), methods.clone());
 }
@@ -136,16 +154,15 @@ fn process_route_insertion(
     static_routes: &mut StaticRoutes,
     dynamic_root: &mut RouteNode,
     full_path: &str,
-    methods_map: &std::collections::HashMap<String, CompiledMethodResponse>,
+    methods_map: &std::collections::HashMap<String, CompiledMethodDefinition>,
 ) {
-    let segments: Vec<&str> = full_path.split('/').filter(|s| !s.is_empty()).collect();
+    let segments = path_segments(full_path);
     let has_dynamic = segments.iter().any(|seg| seg.starts_with(':'));
 
     if has_dynamic {
         insert_dynamic_path(dynamic_root, &segments, methods_map);
     } else {
-        let path_with_slash = format!("/{}", segments.join("/"));
-        insert_static_path(static_routes, &path_with_slash, methods_map);
+        insert_static_path(static_routes, &normalize_path(&segments), methods_map);
     }
 }
 
@@ -185,8 +202,169 @@ pub fn get_routes_from_config(config: &CompiledConfig, root_folder: &Path) -> Ro
         );
     }
 
+    report_shadowed_routes(&static_routes, &dynamic_root);
+
     RoutesData {
         static_routes,
         dynamic_root,
+        status_reasons: Arc::new(StatusReasons::new(config.status_reasons.clone())),
+        keep_alive: config.keep_alive,
+    }
+}
+
+/// Logs every method of a static route that a dynamic route would otherwise serve,
+/// e.g. `GET /users/me` next to `GET /users/:id`. Static routes are tried first, so
+/// the dynamic one never sees those requests.
+fn report_shadowed_routes(static_routes: &StaticRoutes, dynamic_root: &RouteNode) {
+    let mut paths: Vec<&String> = static_routes.keys().collect();
+    paths.sort();
+    for path in paths {
+        let segments = path_segments(path);
+        let mut methods: Vec<&String> = static_routes[path].keys().collect();
+        methods.sort();
+        for method in methods {
+            if let Some(pattern) = dynamic_route_pattern(dynamic_root, &segments, method) {
+                info!(
+                    "{} {} is served by the static route, not by the dynamic route {}",
+                    method, path, pattern
+                );
+            }
+        }
+    }
+}
+
+/// Pattern (`/users/:id`) of the dynamic route that would serve `method` on `segments`,
+/// walking the tree the way `match_dynamic_route` does.
+fn dynamic_route_pattern(root: &RouteNode, segments: &[&str], method: &str) -> Option<String> {
+    let mut current = root;
+    let mut pattern = Vec::with_capacity(segments.len());
+
+    for seg in segments {
+        if let Some(child_node) = current.static_children.get(*seg) {
+            pattern.push(seg.to_string());
+            current = child_node;
+        } else if let Some((param_name, dynamic_node)) = &current.dynamic_child {
+            pattern.push(format!(":{}", param_name));
+            current = dynamic_node;
+        } else {
+            return None;
+        }
+    }
+
+    current
+        .methods
+        .contains_key(method)
+        .then(|| format!("/{}", pattern.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::config::compiled::{compile_config, CompiledMethodResponse};
+
+    fn routes_for(resources: Value) -> RoutesData {
+        let config = json!({ "port": 8080, "resources": resources });
+        let compiled = compile_config(serde_json::from_value(config).unwrap()).unwrap();
+        get_routes_from_config(&compiled, Path::new("."))
+    }
+
+    fn get(body: &str) -> Value {
+        json!({ "method": "GET", "response": { "body": body } })
+    }
+
+    /// Body of the static response `GET path` gets, with the route params.
+    fn route(routes: &RoutesData, path: &str) -> Option<(Value, HashMap<String, String>)> {
+        let (def, params) = find_route(&routes.static_routes, &routes.dynamic_root, path, "GET")?;
+        match def.response {
+            CompiledMethodResponse::Response { body, .. } => Some((body, params)),
+            CompiledMethodResponse::Script { .. } => panic!("expected a static response"),
+        }
+    }
+
+    fn body(routes: &RoutesData, path: &str) -> Option<Value> {
+        route(routes, path).map(|(body, _)| body)
+    }
+
+    #[test]
+    fn root_resource_is_reachable() {
+        for root in ["/", ""] {
+            let routes = routes_for(json!([{ "path": root, "methods": [get("root")] }]));
+            assert_eq!(routes.static_routes.keys().collect::<Vec<_>>(), ["/"], "{:?}", root);
+            for path in ["/", "", "//"] {
+                assert_eq!(body(&routes, path), Some(json!("root")), "{:?} at {:?}", root, path);
+            }
+            assert_eq!(body(&routes, "/users"), None);
+        }
+    }
+
+    #[test]
+    fn trailing_and_repeated_slashes_are_ignored() {
+        let routes = routes_for(json!([{ "path": "users", "methods": [get("users")] }]));
+        for path in ["/users", "/users/", "users", "//users//"] {
+            assert_eq!(body(&routes, path), Some(json!("users")), "{:?}", path);
+        }
+        assert_eq!(body(&routes, "/"), None);
+        assert_eq!(body(&routes, "/users/x"), None);
+    }
+
+    #[test]
+    fn children_of_the_root_resource() {
+        let routes = routes_for(json!([{
+            "path": "/",
+            "methods": [get("root")],
+            "children": [
+                { "path": "users/", "methods": [get("users")], "children": [
+                    { "path": ":id", "methods": [get("user")] }
+                ] }
+            ]
+        }]));
+        assert_eq!(body(&routes, "/"), Some(json!("root")));
+        assert_eq!(body(&routes, "/users/"), Some(json!("users")));
+
+        let (body, params) = route(&routes, "/users/7/").unwrap();
+        assert_eq!(body, json!("user"));
+        assert_eq!(params, HashMap::from([("id".to_string(), "7".to_string())]));
+    }
+
+    #[test]
+    fn static_routes_beat_dynamic_ones() {
+        let post = json!({ "method": "POST", "response": { "body": "created" } });
+        let routes = routes_for(json!([{
+            "path": "users",
+            "children": [
+                { "path": "me", "methods": [get("me")] },
+                { "path": ":id", "methods": [get("user"), post] }
+            ]
+        }]));
+        assert_eq!(route(&routes, "/users/me"), Some((json!("me"), HashMap::new())));
+        assert_eq!(
+            route(&routes, "/users/42"),
+            Some((json!("user"), HashMap::from([("id".to_string(), "42".to_string())])))
+        );
+        // per method: the static route has no POST, so the dynamic one serves it
+        let (def, params) = find_route(&routes.static_routes, &routes.dynamic_root, "/users/me", "POST").unwrap();
+        assert_eq!(def.method, "POST");
+        assert_eq!(params["id"], "me");
+    }
+
+    #[test]
+    fn shadowed_dynamic_routes_are_found() {
+        let routes = routes_for(json!([
+            { "path": "users/me", "methods": [get("me")] },
+            { "path": "users/:id", "methods": [get("user")] },
+            { "path": "users/:id/posts", "methods": [get("posts")] },
+            { "path": "users/me/posts", "methods": [{ "method": "DELETE", "response": { "body": "" } }] },
+            { "path": "teams/red", "methods": [get("red")] }
+        ]));
+        let shadowed = |path: &str, method: &str| {
+            dynamic_route_pattern(&routes.dynamic_root, &path_segments(path), method)
+        };
+        assert_eq!(shadowed("/users/me", "GET"), Some("/users/:id".to_string()));
+        // a different method or a path no dynamic route matches does not overlap
+        assert_eq!(shadowed("/users/me/posts", "DELETE"), None);
+        assert_eq!(shadowed("/users/me/posts", "GET"), Some("/users/:id/posts".to_string()));
+        assert_eq!(shadowed("/teams/red", "GET"), None);
     }
 }
@@ -1,4 +1,5 @@
 use std::sync::{Arc, RwLock};
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
 use tracing::{error, info};
 use super::{handler::handle_client, router::RoutesData};
@@ -12,15 +13,121 @@ pub async fn run(
 
     loop {
         let (stream, _) = listener.accept().await?;
-        let routes_clone = Arc::clone(&routes);
-        tokio::spawn(async move {
-            let snapshot = {
-                let guard = routes_clone.read().unwrap();
-                guard.clone()
-            };
-            if let Err(e) = handle_client(stream, snapshot).await {
-                error!("Error handling client: {}", e);
-            }
-        });
+        spawn_client(stream, &routes);
+    }
+}
+
+/// Like `run`, listening on a Unix domain socket at `path` instead of a TCP port.
+/// A socket file left behind by an earlier run is replaced, and the socket file is
+/// removed again when the server stops, including when this future is dropped on shutdown.
+#[cfg(unix)]
+pub async fn run_unix(
+    path: &std::path::Path,
+    routes: Arc<RwLock<Option<RoutesData>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::FileTypeExt;
+    use tokio::net::UnixListener;
+
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let _socket_file = SocketFile(path);
+    info!("Server listening on {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        spawn_client(stream, &routes);
+    }
+}
+
+/// Removes the socket file at the path when dropped.
+#[cfg(unix)]
+struct SocketFile<'a>(&'a std::path::Path);
+
+#[cfg(unix)]
+impl Drop for SocketFile<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(self.0) {
+            error!("Could not remove socket {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Serves one connection on its own task.
+fn spawn_client<S>(stream: S, routes: &Arc<RwLock<Option<RoutesData>>>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let routes_clone = Arc::clone(routes);
+    tokio::spawn(async move {
+        if let Err(e) = handle_client(stream, routes_clone).await {
+            error!("Error handling client: {}", e);
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    use super::*;
+
+    fn socket_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rjs-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn unix_socket_serves_and_is_removed_on_shutdown() {
+        let path = socket_path("shutdown");
+        let mut served = Box::pin(run_unix(&path, Arc::new(RwLock::new(None))));
+        let client = async {
+            // give the server a moment to bind
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let mut stream = UnixStream::connect(&path).await.unwrap();
+            stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+            let mut resp = String::new();
+            stream.read_to_string(&mut resp).await.unwrap();
+            resp
+        };
+        let resp = tokio::select! {
+            served = &mut served => panic!("server stopped: {:?}", served.err().map(|e| e.to_string())),
+            resp = client => resp,
+        };
+        // no routes loaded yet
+        assert!(resp.starts_with("HTTP/1.1 503"), "{}", resp);
+        assert!(path.exists());
+
+        // what serve does on Ctrl-C
+        drop(served);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn stale_socket_is_replaced() {
+        let path = socket_path("stale");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let served = tokio::time::timeout(
+            Duration::from_millis(50),
+            run_unix(&path, Arc::new(RwLock::new(None))),
+        )
+        .await;
+        assert!(served.is_err(), "the server should still be running");
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn other_files_are_not_replaced() {
+        let path = socket_path("regular");
+        std::fs::write(&path, "keep me").unwrap();
+        let err = run_unix(&path, Arc::new(RwLock::new(None))).await.unwrap_err();
+        assert!(err.to_string().contains("in use"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+/// Standard reason phrases, sorted by status code.
+const STANDARD_REASONS: &[(u16, &str)] = &[
+    (100, "Continue"),
+    (101, "Switching Protocols"),
+    (102, "Processing"),
+    (103, "Early Hints"),
+    (200, "OK"),
+    (201, "Created"),
+    (202, "Accepted"),
+    (203, "Non-Authoritative Information"),
+    (204, "No Content"),
+    (205, "Reset Content"),
+    (206, "Partial Content"),
+    (207, "Multi-Status"),
+    (208, "Already Reported"),
+    (226, "IM Used"),
+    (300, "Multiple Choices"),
+    (301, "Moved Permanently"),
+    (302, "Found"),
+    (303, "See Other"),
+    (304, "Not Modified"),
+    (305, "Use Proxy"),
+    (307, "Temporary Redirect"),
+    (308, "Permanent Redirect"),
+    (400, "Bad Request"),
+    (401, "Unauthorized"),
+    (402, "Payment Required"),
+    (403, "Forbidden"),
+    (404, "Not Found"),
+    (405, "Method Not Allowed"),
+    (406, "Not Acceptable"),
+    (407, "Proxy Authentication Required"),
+    (408, "Request Timeout"),
+    (409, "Conflict"),
+    (410, "Gone"),
+    (411, "Length Required"),
+    (412, "Precondition Failed"),
+    (413, "Payload Too Large"),
+    (414, "URI Too Long"),
+    (415, "Unsupported Media Type"),
+    (416, "Range Not Satisfiable"),
+    (417, "Expectation Failed"),
+    (418, "I'm a teapot"),
+    (421, "Misdirected Request"),
+    (422, "Unprocessable Entity"),
+    (423, "Locked"),
+    (424, "Failed Dependency"),
+    (425, "Too Early"),
+    (426, "Upgrade Required"),
+    (428, "Precondition Required"),
+    (429, "Too Many Requests"),
+    (431, "Request Header Fields Too Large"),
+    (451, "Unavailable For Legal Reasons"),
+    (500, "Internal Server Error"),
+    (501, "Not Implemented"),
+    (502, "Bad Gateway"),
+    (503, "Service Unavailable"),
+    (504, "Gateway Timeout"),
+    (505, "HTTP Version Not Supported"),
+    (506, "Variant Also Negotiates"),
+    (507, "Insufficient Storage"),
+    (508, "Loop Detected"),
+    (510, "Not Extended"),
+    (511, "Network Authentication Required"),
+];
+
+/// Reason phrase of a registered status code, if any.
+pub fn standard_reason(code: u16) -> Option<&'static str> {
+    STANDARD_REASONS
+        .binary_search_by_key(&code, |(c, _)| *c)
+        .ok()
+        .map(|ix| STANDARD_REASONS[ix].1)
+}
+
+/// Generic phrase for the status class, used when a code has no phrase of its own.
+fn class_reason(code: u16) -> &'static str {
+    match code / 100 {
+        1 => "Informational",
+        2 => "Success",
+        3 => "Redirection",
+        4 => "Client Error",
+        5 => "Server Error",
+        _ => "Unknown",
+    }
+}
+
+/// Validates a status code: must be in 100..=599 and not an interim (1xx) status,
+/// since the handler can only emit final responses.
+pub fn check_status_code(code: f64) -> Result<u16, String> {
+    if code.fract() != 0.0 || !(100.0..=599.0).contains(&code) {
+        return Err(format!("Status code must be an integer in 100..=599, got {}", code));
+    }
+    if code < 200.0 {
+        return Err(format!(
+            "Informational status {} cannot be returned as a final response",
+            code
+        ));
+    }
+    Ok(code as u16)
+}
+
+/// Reason phrase lookup: the standard table merged with the config's `statusReasons`.
+#[derive(Debug, Clone, Default)]
+pub struct StatusReasons {
+    overrides: HashMap<u16, String>,
+}
+
+impl StatusReasons {
+    pub fn new(overrides: HashMap<u16, String>) -> Self {
+        StatusReasons { overrides }
+    }
+
+    pub fn phrase(&self, code: u16) -> &str {
+        if let Some(phrase) = self.overrides.get(&code) {
+            return phrase;
+        }
+        standard_reason(code).unwrap_or_else(|| class_reason(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::rjscript::testing::{eval_script_str_with_world, TestWorld};
+
+    fn reasons(overrides: &[(u16, &str)]) -> StatusReasons {
+        StatusReasons::new(overrides.iter().map(|(c, p)| (*c, p.to_string())).collect())
+    }
+
+    #[test]
+    fn standard_table_is_sorted_for_lookup() {
+        assert!(STANDARD_REASONS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn standard_phrases() {
+        let std = StatusReasons::default();
+        for (code, phrase) in [
+            (200, "OK"),
+            (201, "Created"),
+            (204, "No Content"),
+            (301, "Moved Permanently"),
+            (400, "Bad Request"),
+            (404, "Not Found"),
+            (418, "I'm a teapot"),
+            (429, "Too Many Requests"),
+            (500, "Internal Server Error"),
+            (503, "Service Unavailable"),
+            (511, "Network Authentication Required"),
+        ] {
+            assert_eq!(std.phrase(code), phrase, "{}", code);
+            assert_eq!(standard_reason(code), Some(phrase));
+        }
+    }
+
+    #[test]
+    fn overrides_win_over_the_table() {
+        let merged = reasons(&[(499, "Client Closed Request"), (404, "Nothing Here")]);
+        assert_eq!(merged.phrase(499), "Client Closed Request");
+        assert_eq!(merged.phrase(404), "Nothing Here");
+        assert_eq!(merged.phrase(200), "OK");
+    }
+
+    #[test]
+    fn unmapped_codes_fall_back_to_their_class() {
+        let std = StatusReasons::default();
+        assert_eq!(standard_reason(499), None);
+        assert_eq!(std.phrase(199), "Informational");
+        assert_eq!(std.phrase(299), "Success");
+        assert_eq!(std.phrase(399), "Redirection");
+        assert_eq!(std.phrase(499), "Client Error");
+        assert_eq!(std.phrase(520), "Server Error");
+        assert_eq!(std.phrase(600), "Unknown");
+    }
+
+    #[test]
+    fn status_codes_are_checked() {
+        for code in [200.0, 204.0, 499.0, 599.0] {
+            assert_eq!(check_status_code(code), Ok(code as u16));
+        }
+        for code in [99.0, 600.0, 0.0, -200.0, 200.5, f64::NAN, f64::INFINITY] {
+            let err = check_status_code(code).unwrap_err();
+            assert!(err.starts_with("Status code must be an integer in 100..=599"), "{}: {}", code, err);
+        }
+        for code in [100.0, 103.0, 199.0] {
+            let err = check_status_code(code).unwrap_err();
+            assert!(err.contains("cannot be returned as a final response"), "{}: {}", code, err);
+        }
+    }
+
+    #[test]
+    fn scripts_returning_bad_statuses_fail_at_the_return() {
+        for (status, expected) in [(1000, "100..=599"), (101, "Informational status 101")] {
+            let src = "if (toType(req.body.s) != num) { return 400, false; }\nreturn req.body.s, true;";
+            let err = eval_script_str_with_world(src, json!({ "s": status }), &TestWorld::new()).unwrap_err();
+            assert!(err.contains(expected), "{}", err);
+            assert!(err.contains("at: 2:"), "{}", err);
+        }
+        let ok = eval_script_str_with_world("return 499, true;", Value::Null, &TestWorld::new());
+        assert_eq!(ok.map(|(status, _)| status), Ok(499));
+    }
+}
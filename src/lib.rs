@@ -2,4 +2,6 @@ pub mod rjscript;
 pub mod filewatcher;
 pub mod http;
 pub mod config;
-pub mod rjsdb;
\ No newline at end of file
+pub mod rjsdb;
+pub mod random;
+pub mod digest;
\ No newline at end of file
@@ -1,7 +1,8 @@
 mod commands;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 use commands::{build, serve};
+use rustyjsonserver::rjscript::evaluator::runtime::eval_ctx::{mark_eval_stack_thread, EVAL_STACK_SIZE};
 use tracing::error;
 use std::error::Error;
 use tracing_subscriber::{fmt, EnvFilter};
@@ -12,9 +13,29 @@ use tracing_subscriber::{fmt, EnvFilter};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log more: -v for debug, -vv for trace
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
 }
 
 impl Cli {
+    /// Log filter picked by the verbosity flags, used when `RJSERVER_LOG` is not set.
+    fn log_level(&self) -> &'static str {
+        if self.quiet {
+            return "error";
+        }
+        match self.verbose {
+            0 => "info",
+            1 => "debug",
+            _ => "trace",
+        }
+    }
+
     async fn run(self) -> Result<(), Box<dyn Error>> {
         match self.command {
             Commands::Build(args) => build::run(args).await,
@@ -32,13 +53,15 @@ enum Commands {
     Serve(commands::serve::ServeArgs),
 }
 
-#[tokio::main]
-async fn main() {
-    // Initialize tracing subscriber with env filter (e.g. RJSERVER_LOG=debug)
+fn main() {
+    let cli = Cli::parse();
+
+    // Initialize tracing subscriber with env filter (e.g. RJSERVER_LOG=debug),
+    // which wins over the -q/-v flags
     let filter = match EnvFilter::try_from_env("RJSERVER_LOG") {
         Ok(f) => f,
         Err(_) => {
-            EnvFilter::new("info")
+            EnvFilter::new(cli.log_level())
         }
     };
 
@@ -47,8 +70,15 @@ async fn main() {
         .with_target(false)
         .init();
 
-    let cli = Cli::parse();
-    if let Err(e) = cli.run().await {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        // workers evaluate scripts in place, see `run_on_eval_stack`
+        .thread_stack_size(EVAL_STACK_SIZE)
+        .on_thread_start(mark_eval_stack_thread)
+        .build()
+        .expect("failed to build the tokio runtime");
+
+    if let Err(e) = runtime.block_on(cli.run()) {
         error!("Application error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,129 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Process-wide generator state. Seeded from `RJS_RANDOM_SEED` when set, so
+/// chaos features (delays, injected failures) and the script `random()`/`randomInt()`
+/// builtins can be replayed deterministically. Ids come from `id_u64` instead.
+static STATE: OnceLock<AtomicU64> = OnceLock::new();
+
+fn state() -> &'static AtomicU64 {
+    STATE.get_or_init(|| {
+        let seed = parse_seed(std::env::var("RJS_RANDOM_SEED").ok().as_deref())
+            .unwrap_or_else(|| RandomState::new().build_hasher().finish());
+        AtomicU64::new(seed)
+    })
+}
+
+/// Seed named by a `RJS_RANDOM_SEED` value, `None` when it is unset or not a u64.
+fn parse_seed(value: Option<&str>) -> Option<u64> {
+    value.and_then(|s| s.trim().parse::<u64>().ok())
+}
+
+/// SplitMix64 output for a generator that just advanced to `state`.
+fn mix(state: u64) -> u64 {
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn unit_f64(x: u64) -> f64 {
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The sequence the process-wide generator yields when seeded with `seed`, on its own.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GAMMA);
+        mix(self.state)
+    }
+
+    pub fn next_f64(&mut self) -> f64 {
+        unit_f64(self.next_u64())
+    }
+}
+
+/// Next pseudo-random u64 (SplitMix64).
+pub fn next_u64() -> u64 {
+    mix(state().fetch_add(GAMMA, Ordering::Relaxed).wrapping_add(GAMMA))
+}
+
+/// Next pseudo-random f64 in `[0, 1)`.
+pub fn next_f64() -> f64 {
+    unit_f64(next_u64())
+}
+
+/// Next pseudo-random integer in `[0, n)`; `n` must be non-zero.
+pub fn next_below(n: u64) -> u64 {
+    ((next_u64() as u128 * n as u128) >> 64) as u64
+}
+
+/// Keys of the id hasher, drawn from OS randomness once per process.
+static ID_KEYS: OnceLock<RandomState> = OnceLock::new();
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Next u64 for generated ids (`uuid()`, `uuidShort()`): SipHash under random keys
+/// of a counter. It never follows `RJS_RANDOM_SEED`, so a replayed run still gets
+/// fresh ids, and it cannot be predicted from the seeded sequence.
+pub fn id_u64() -> u64 {
+    let mut hasher = ID_KEYS.get_or_init(RandomState::new).build_hasher();
+    hasher.write_u64(ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn ids_do_not_repeat() {
+        let ids: HashSet<u64> = (0..10_000).map(|_| id_u64()).collect();
+        assert_eq!(ids.len(), 10_000);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        assert!((0..1000).all(|_| next_below(7) < 7));
+        assert_eq!(next_below(1), 0);
+        assert!((0..1000).map(|_| next_f64()).all(|f| (0.0..1.0).contains(&f)));
+    }
+
+    #[test]
+    fn seeded_sequence_is_splitmix64() {
+        // reference outputs of SplitMix64
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        let mut rng = SplitMix64::new(42);
+        assert_eq!(rng.next_u64(), 0xbdd7_3226_2feb_6e95);
+    }
+
+    #[test]
+    fn seed_values() {
+        assert_eq!(parse_seed(Some(" 42\n")), Some(42));
+        assert_eq!(parse_seed(Some(&u64::MAX.to_string())), Some(u64::MAX));
+        assert_eq!(parse_seed(Some("-1")), None);
+        assert_eq!(parse_seed(Some("seed")), None);
+        assert_eq!(parse_seed(None), None);
+    }
+
+    #[test]
+    fn unit_f64_bounds() {
+        assert_eq!(unit_f64(0), 0.0);
+        assert!(unit_f64(u64::MAX) < 1.0);
+    }
+}
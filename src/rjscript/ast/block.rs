@@ -1,4 +1,8 @@
-use crate::rjscript::ast::{node::HasPos, position::Position, stmt::Stmt};
+use crate::rjscript::ast::{
+    node::HasPos,
+    position::Position,
+    stmt::{Stmt, StmtKind},
+};
 
 /// A block of statements delimited by braces.
 #[derive(Debug, Clone)]
@@ -12,6 +16,12 @@ impl Block {
     pub fn new(stmts: Vec<Stmt>, pos: Position) -> Self {
         Self { stmts, pos }
     }
+
+    /// Whether the last statement is `fallthrough`, i.e. a switch case that
+    /// continues into the next one.
+    pub fn ends_in_fallthrough(&self) -> bool {
+        matches!(self.stmts.last().map(|s| &s.kind), Some(StmtKind::Fallthrough))
+    }
 }
 
 impl HasPos for Block {
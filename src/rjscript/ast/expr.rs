@@ -1,5 +1,5 @@
 use crate::rjscript::{
-    ast::{binop::BinOp, literal::Literal, node::Located, request::RequestFieldType},
+    ast::{binop::BinOp, block::Block, literal::Literal, node::Located, param::Param, request::RequestFieldType},
     semantics::types::VarType,
 };
 
@@ -102,6 +102,13 @@ pub enum ExprKind {
         callee: Box<Expr>,
         args: Vec<Expr>,
     },
+
+    /// Anonymous function: `func (params): type { body }`
+    FunctionExpr {
+        params: Vec<Param>,
+        return_type: VarType,
+        body: Block,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,217 @@
+//! Lookup from a source position to the AST node there, for editor features
+//! such as hover, goto and rename.
+//!
+//! Nodes carry a single position (the token they are named after, or their last
+//! token, e.g. the `)` of a call), so each node's range is rebuilt from the
+//! positions in its subtree. A statement also owns the text between the previous
+//! statement and itself, which holds its keywords (`let`, `return`, ...).
+
+use super::{
+    block::Block,
+    expr::{Expr, ExprKind},
+    position::Position,
+    stmt::Stmt,
+    visitor::{walk_expr, walk_stmt, Visit},
+};
+
+/// A statement or expression of the indexed tree.
+#[derive(Debug, Clone, Copy)]
+pub enum NodeRef<'ast> {
+    Stmt(&'ast Stmt),
+    Expr(&'ast Expr),
+}
+
+impl NodeRef<'_> {
+    pub fn pos(&self) -> Position {
+        match self {
+            NodeRef::Stmt(s) => s.pos,
+            NodeRef::Expr(e) => e.pos,
+        }
+    }
+}
+
+struct Entry<'ast> {
+    start: Position,
+    end: Position,
+    /// Nesting depth: the deepest node containing a position is the innermost one
+    depth: usize,
+    node: NodeRef<'ast>,
+}
+
+/// Every statement and expression of a block with the source range it covers.
+pub struct PositionIndex<'ast> {
+    entries: Vec<Entry<'ast>>,
+}
+
+impl<'ast> PositionIndex<'ast> {
+    pub fn new(block: &'ast Block) -> Self {
+        let mut collector = Collector::default();
+        collector.visit_block(block);
+        PositionIndex {
+            entries: collector.entries,
+        }
+    }
+
+    /// The innermost node whose range contains `line:column` (1-based, like `Position`).
+    pub fn node_at(&self, line: usize, column: usize) -> Option<NodeRef<'ast>> {
+        let at = Position::new(line, column);
+        self.entries
+            .iter()
+            .filter(|e| key(e.start) <= key(at) && key(at) <= key(e.end))
+            .max_by_key(|e| e.depth)
+            .map(|e| e.node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn key(p: Position) -> (usize, usize) {
+    (p.line, p.column)
+}
+
+#[derive(Default)]
+struct Collector<'ast> {
+    entries: Vec<Entry<'ast>>,
+    /// Range covered so far by each node being visited, innermost last
+    open: Vec<(Position, Position)>,
+    /// Where the statement about to be visited starts, see the module docs
+    stmt_start: Option<Position>,
+}
+
+impl<'ast> Collector<'ast> {
+    /// Visits a node's children and records it with the range they span together
+    /// with `start..=end`. Returns the end of that range.
+    fn record(
+        &mut self,
+        start: Position,
+        end: Position,
+        node: NodeRef<'ast>,
+        walk: impl FnOnce(&mut Self),
+    ) -> Position {
+        // nodes made up by transforms have no place in the source
+        if node.pos() == Position::UNKNOWN {
+            walk(self);
+            return Position::UNKNOWN;
+        }
+        let depth = self.open.len();
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            start,
+            end,
+            depth,
+            node,
+        });
+        self.open.push((start, end));
+        walk(self);
+        let (start, end) = self.open.pop().expect("pushed above");
+        self.entries[index].start = start;
+        self.entries[index].end = end;
+        if let Some(parent) = self.open.last_mut() {
+            if key(start) < key(parent.0) {
+                parent.0 = start;
+            }
+            if key(end) > key(parent.1) {
+                parent.1 = end;
+            }
+        }
+        end
+    }
+
+    /// Visits a statement, returning the end of its range.
+    fn visit_stmt_ranged(&mut self, s: &'ast Stmt) -> Position {
+        let start = match self.stmt_start.take() {
+            Some(start) if key(start) < key(s.pos) => start,
+            _ => s.pos,
+        };
+        self.record(start, s.pos, NodeRef::Stmt(s), |c| walk_stmt(c, s))
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'ast> {
+    fn visit_block(&mut self, b: &'ast Block) {
+        // the first statement starts after the `{`, or at the top of the script
+        let mut next = if b.pos == Position::UNKNOWN {
+            Position::new(1, 1)
+        } else {
+            Position::new(b.pos.line, b.pos.column + 1)
+        };
+        for s in &b.stmts {
+            self.stmt_start = Some(next);
+            let end = self.visit_stmt_ranged(s);
+            if end != Position::UNKNOWN {
+                next = Position::new(end.line, end.column + 1);
+            }
+        }
+    }
+
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        self.visit_stmt_ranged(s);
+    }
+
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        // identifiers are the one node whose extent is known
+        let end = match &e.kind {
+            ExprKind::Ident(name) => {
+                Position::new(e.pos.line, e.pos.column + name.chars().count().max(1) - 1)
+            }
+            _ => e.pos,
+        };
+        self.record(e.pos, end, NodeRef::Expr(e), |c| walk_expr(c, e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rjscript::parser::parser::parse_script;
+
+    const SRC: &str = "let total: num = 0;
+func add(a: num, b: num): num {
+    return a + b;
+}
+total = add(total, 2);
+return 200, { sum: total };";
+
+    /// `stmt Let`, `expr Ident(total)`, ...: the kind of the node at `line:column`.
+    fn kind_at(index: &PositionIndex, line: usize, column: usize) -> Option<String> {
+        let variant = |debug: String| -> String {
+            let end = debug.find(|c: char| !c.is_alphanumeric()).unwrap_or(debug.len());
+            debug[..end].to_string()
+        };
+        index.node_at(line, column).map(|node| match node {
+            NodeRef::Stmt(s) => format!("stmt {}", variant(format!("{:?}", s.kind))),
+            NodeRef::Expr(e) => match &e.kind {
+                ExprKind::Ident(name) => format!("expr Ident({})", name),
+                kind => format!("expr {}", variant(format!("{:?}", kind))),
+            },
+        })
+    }
+
+    #[test]
+    fn cursor_positions_resolve_to_the_innermost_node() {
+        let block = parse_script(SRC).unwrap();
+        let index = PositionIndex::new(&block);
+        let expected = |kind: &str| Some(kind.to_string());
+        // keywords and names that are no expression belong to their statement
+        assert_eq!(kind_at(&index, 1, 2), expected("stmt Let"));
+        assert_eq!(kind_at(&index, 2, 6), expected("stmt FunctionDecl"));
+        assert_eq!(kind_at(&index, 3, 5), expected("stmt Return"));
+        assert_eq!(kind_at(&index, 1, 18), expected("expr Literal"));
+        assert_eq!(kind_at(&index, 3, 12), expected("expr Ident(a)"));
+        assert_eq!(kind_at(&index, 3, 14), expected("expr BinaryOp"));
+        assert_eq!(kind_at(&index, 3, 16), expected("expr Ident(b)"));
+        assert_eq!(kind_at(&index, 5, 9), expected("expr Ident(add)"));
+        assert_eq!(kind_at(&index, 5, 15), expected("expr Ident(total)"));
+        assert_eq!(kind_at(&index, 6, 2), expected("stmt ReturnStatus"));
+        assert_eq!(kind_at(&index, 6, 22), expected("expr Ident(total)"));
+        assert_eq!(kind_at(&index, 6, 26), expected("expr ObjectLiteral"));
+        assert_eq!(kind_at(&index, 9, 1), None);
+        assert_eq!(index.node_at(5, 15).map(|n| n.pos()), Some(Position::new(5, 13)));
+    }
+}
@@ -6,6 +6,7 @@ pub enum Literal {
     String(String),
     Bool(bool),
     Undefined,
+    Null,
 }
 
 impl Literal {
@@ -15,6 +16,7 @@ impl Literal {
             Literal::String(_) => VarType::String,
             Literal::Bool(_) => VarType::Bool,
             Literal::Undefined => VarType::Undefined,
+            Literal::Null => VarType::Null,
         }
     }
 }
\ No newline at end of file
@@ -2,8 +2,9 @@ pub mod binop;
 pub mod literal;
 pub mod block;
 pub mod stmt;
+pub mod param;
 pub mod expr;
 pub mod request;
 pub mod position;
 pub mod node;
-pub mod visitor;
\ No newline at end of file
+pub mod visitor;pub mod index;
@@ -0,0 +1,16 @@
+use crate::rjscript::{ast::expr::Expr, semantics::types::VarType};
+
+/// A function parameter: `name: ty` or `name: ty = default`.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: VarType,
+    /// Used when the argument is omitted; may reference earlier params
+    pub default: Option<Expr>,
+}
+
+impl Param {
+    pub fn new(name: String, ty: VarType, default: Option<Expr>) -> Self {
+        Param { name, ty, default }
+    }
+}
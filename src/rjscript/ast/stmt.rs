@@ -1,12 +1,14 @@
-use crate::rjscript::{ast::{block::Block, expr::Expr, node::Located}, semantics::types::VarType};
+use crate::rjscript::{ast::{block::Block, expr::Expr, node::Located, param::Param}, semantics::types::VarType};
 
 #[derive(Debug, Clone)]
 pub enum StmtKind  {
-    /// `let name: ty = <expr>?;`
+    /// `let name: ty = <expr>?;` / `const name: ty = <expr>;`
     Let {
         name: String,
         ty: VarType,
         init: Option<Expr>,
+        /// `false` for `const` declarations
+        mutable: bool,
     },
 
     /// `return <expr>;`
@@ -21,7 +23,7 @@ pub enum StmtKind  {
     /// Function declaration.
     FunctionDecl {
         ident: String,
-        params: Vec<(String, VarType)>,
+        params: Vec<Param>,
         return_type: VarType,
         body: Block,
     },
@@ -33,10 +35,11 @@ pub enum StmtKind  {
         else_block: Option<Block>,
     },
 
-    /// switch (cond) { case, case2, default}
+    /// switch (cond) { case a, b: ..., case c: ..., default: ... }
     Switch {
         condition: Expr,
-        cases: Vec<(Expr, Block)>,
+        /// Each case's values (any of them matches) and its block, in source order
+        cases: Vec<(Vec<Expr>, Block)>,
         default: Option<Block>,
     },
 
@@ -52,8 +55,23 @@ pub enum StmtKind  {
         body: Block,
     },
 
+    /// `throw <expr>;`
+    Throw(Expr),
+
+    /// `try { try_block } catch (catch_name) { catch_block }`
+    TryCatch {
+        try_block: Block,
+        catch_name: String,
+        catch_block: Block,
+    },
+
+    /// `include "path";`, replaced by the included functions when the script is compiled
+    Include(String),
+
     Break,
-    Continue
+    Continue,
+    /// `fallthrough;` at the end of a case: go on into the next case's block
+    Fallthrough,
 }
 
 pub type Stmt = Located<StmtKind>;
\ No newline at end of file
@@ -4,28 +4,31 @@ use super::{
     block::Block,
     expr::{Expr, ExprKind, TemplatePart},
     node::Located,
+    param::Param,
     stmt::{Stmt, StmtKind},
 };
 
-pub trait Visit {
-    fn visit_block(&mut self, b: &Block) {
+/// Read-only AST traversal. `'ast` lets a visitor keep references to the nodes it
+/// sees; visitors that do not need them implement `Visit<'_>`.
+pub trait Visit<'ast> {
+    fn visit_block(&mut self, b: &'ast Block) {
         walk_block(self, b)
     }
-    fn visit_stmt(&mut self, s: &Stmt) {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
         walk_stmt(self, s)
     }
-    fn visit_expr(&mut self, e: &Expr) {
+    fn visit_expr(&mut self, e: &'ast Expr) {
         walk_expr(self, e)
     }
 }
 
-pub fn walk_block<V: Visit + ?Sized>(v: &mut V, b: &Block) {
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, b: &'ast Block) {
     for s in &b.stmts {
         v.visit_stmt(s);
     }
 }
 
-pub fn walk_stmt<V: Visit + ?Sized>(v: &mut V, s: &Stmt) {
+pub fn walk_stmt<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, s: &'ast Stmt) {
     match &s.kind {
         StmtKind::Let { init, .. } => {
             if let Some(e) = init {
@@ -38,7 +41,12 @@ pub fn walk_stmt<V: Visit + ?Sized>(v: &mut V, s: &Stmt) {
             v.visit_expr(value);
         }
         StmtKind::ExprStmt(e) => v.visit_expr(e),
-        StmtKind::FunctionDecl { body, .. } => v.visit_block(body),
+        StmtKind::FunctionDecl { params, body, .. } => {
+            for d in params.iter().filter_map(|p| p.default.as_ref()) {
+                v.visit_expr(d);
+            }
+            v.visit_block(body)
+        }
         StmtKind::IfElse {
             condition,
             then_block,
@@ -56,8 +64,10 @@ pub fn walk_stmt<V: Visit + ?Sized>(v: &mut V, s: &Stmt) {
             default,
         } => {
             v.visit_expr(condition);
-            for (e, b) in cases.iter() {
-                v.visit_expr(e);
+            for (es, b) in cases.iter() {
+                for e in es {
+                    v.visit_expr(e);
+                }
                 v.visit_block(b);
             }
             if let Some(b) = default {
@@ -79,11 +89,20 @@ pub fn walk_stmt<V: Visit + ?Sized>(v: &mut V, s: &Stmt) {
             }
             v.visit_block(body);
         }
-        StmtKind::Break | StmtKind::Continue => {}
+        StmtKind::Throw(e) => v.visit_expr(e),
+        StmtKind::TryCatch {
+            try_block,
+            catch_block,
+            ..
+        } => {
+            v.visit_block(try_block);
+            v.visit_block(catch_block);
+        }
+        StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough | StmtKind::Include(_) => {}
     }
 }
 
-pub fn walk_expr<V: Visit + ?Sized>(v: &mut V, e: &Expr) {
+pub fn walk_expr<'ast, V: Visit<'ast> + ?Sized>(v: &mut V, e: &'ast Expr) {
     match &e.kind {
         ExprKind::TypeLiteral(_)
         | ExprKind::Literal(_)
@@ -136,6 +155,12 @@ pub fn walk_expr<V: Visit + ?Sized>(v: &mut V, e: &Expr) {
                 v.visit_expr(a);
             }
         }
+        ExprKind::FunctionExpr { params, body, .. } => {
+            for d in params.iter().filter_map(|p| p.default.as_ref()) {
+                v.visit_expr(d);
+            }
+            v.visit_block(body)
+        }
     }
 }
 
@@ -176,7 +201,12 @@ pub fn walk_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Stmt) {
             v.visit_expr_mut(value);
         }
         StmtKind::ExprStmt(e) => v.visit_expr_mut(e),
-        StmtKind::FunctionDecl { body, .. } => v.visit_block_mut(body),
+        StmtKind::FunctionDecl { params, body, .. } => {
+            for d in params.iter_mut().filter_map(|p| p.default.as_mut()) {
+                v.visit_expr_mut(d);
+            }
+            v.visit_block_mut(body)
+        }
         StmtKind::IfElse {
             condition,
             then_block,
@@ -194,8 +224,10 @@ pub fn walk_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Stmt) {
             default,
         } => {
             v.visit_expr_mut(condition);
-            for (e, b) in cases.iter_mut() {
-                v.visit_expr_mut(e);
+            for (es, b) in cases.iter_mut() {
+                for e in es {
+                    v.visit_expr_mut(e);
+                }
                 v.visit_block_mut(b);
             }
             if let Some(b) = default {
@@ -217,7 +249,16 @@ pub fn walk_stmt_mut<V: VisitMut + ?Sized>(v: &mut V, s: &mut Stmt) {
             }
             v.visit_block_mut(body);
         }
-        StmtKind::Break | StmtKind::Continue => {}
+        StmtKind::Throw(e) => v.visit_expr_mut(e),
+        StmtKind::TryCatch {
+            try_block,
+            catch_block,
+            ..
+        } => {
+            v.visit_block_mut(try_block);
+            v.visit_block_mut(catch_block);
+        }
+        StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough | StmtKind::Include(_) => {}
     }
 }
 
@@ -271,6 +312,12 @@ pub fn walk_expr_mut<V: VisitMut + ?Sized>(v: &mut V, e: &mut Expr) {
                 v.visit_expr_mut(a);
             }
         }
+        ExprKind::FunctionExpr { params, body, .. } => {
+            for d in params.iter_mut().filter_map(|p| p.default.as_mut()) {
+                v.visit_expr_mut(d);
+            }
+            v.visit_block_mut(body)
+        }
     }
 }
 
@@ -296,6 +343,16 @@ pub trait Fold {
     }
 }
 
+fn fold_params<F: Fold + ?Sized>(f: &mut F, params: Vec<Param>) -> Vec<Param> {
+    params
+        .into_iter()
+        .map(|p| Param {
+            default: p.default.map(|d| f.fold_expr(d)),
+            ..p
+        })
+        .collect()
+}
+
 pub fn fold_block<F: Fold + ?Sized>(f: &mut F, mut b: Block) -> Block {
     let stmts = b.stmts.into_iter().map(|s| f.fold_stmt(s)).collect();
     b.stmts = stmts;
@@ -305,9 +362,22 @@ pub fn fold_block<F: Fold + ?Sized>(f: &mut F, mut b: Block) -> Block {
 pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, s: Stmt) -> Stmt {
     let pos = s.pos();
     match s.kind {
-        StmtKind::Let { name, ty, init } => {
+        StmtKind::Let {
+            name,
+            ty,
+            init,
+            mutable,
+        } => {
             let init = init.map(|e| f.fold_expr(e));
-            Located::new(StmtKind::Let { name, ty, init }, pos)
+            Located::new(
+                StmtKind::Let {
+                    name,
+                    ty,
+                    init,
+                    mutable,
+                },
+                pos,
+            )
         }
         StmtKind::Return(e) => Located::new(StmtKind::Return(f.fold_expr(e)), pos),
         StmtKind::ReturnStatus { status, value } => Located::new(
@@ -324,6 +394,7 @@ pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, s: Stmt) -> Stmt {
             return_type,
             body,
         } => {
+            let params = fold_params(f, params);
             let body = f.fold_block(body);
             Located::new(
                 StmtKind::FunctionDecl {
@@ -360,7 +431,10 @@ pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, s: Stmt) -> Stmt {
             let condition = f.fold_expr(condition);
             let cases = cases
                 .into_iter()
-                .map(|(e, b)| (f.fold_expr(e), f.fold_block(b)))
+                .map(|(es, b)| {
+                    let es = es.into_iter().map(|e| f.fold_expr(e)).collect();
+                    (es, f.fold_block(b))
+                })
                 .collect();
             let default = default.map(|b| f.fold_block(b));
             Located::new(
@@ -392,8 +466,27 @@ pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, s: Stmt) -> Stmt {
                 pos,
             )
         }
+        StmtKind::Throw(e) => Located::new(StmtKind::Throw(f.fold_expr(e)), pos),
+        StmtKind::TryCatch {
+            try_block,
+            catch_name,
+            catch_block,
+        } => {
+            let try_block = f.fold_block(try_block);
+            let catch_block = f.fold_block(catch_block);
+            Located::new(
+                StmtKind::TryCatch {
+                    try_block,
+                    catch_name,
+                    catch_block,
+                },
+                pos,
+            )
+        }
         StmtKind::Break => Located::new(StmtKind::Break, pos),
         StmtKind::Continue => Located::new(StmtKind::Continue, pos),
+        StmtKind::Fallthrough => Located::new(StmtKind::Fallthrough, pos),
+        StmtKind::Include(path) => Located::new(StmtKind::Include(path), pos),
     }
 }
 
@@ -475,5 +568,21 @@ pub fn fold_expr<F: Fold + ?Sized>(f: &mut F, e: Expr) -> Expr {
             let args = args.into_iter().map(|a| f.fold_expr(a)).collect();
             Located::new(ExprKind::Call { callee, args }, pos)
         }
+        ExprKind::FunctionExpr {
+            params,
+            return_type,
+            body,
+        } => {
+            let params = fold_params(f, params);
+            let body = f.fold_block(body);
+            Located::new(
+                ExprKind::FunctionExpr {
+                    params,
+                    return_type,
+                    body,
+                },
+                pos,
+            )
+        }
     }
 }
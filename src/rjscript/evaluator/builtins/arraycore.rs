@@ -1,4 +1,5 @@
 use std::{
+    cmp::Ordering,
     collections::HashMap,
     sync::{Arc, OnceLock},
 };
@@ -6,29 +7,69 @@ use std::{
 use crate::rjscript::{
     ast::position::Position,
     evaluator::{
-        builtins::{MutMethodFn, PureMethodFn}, errors::EvalError, runtime::value::RJSValue, EvalResult
+        builtins::{CallFn, CallbackMethodFn, MutMethodFn, PureMethodFn}, errors::EvalError, runtime::value::{ObjectMap, RJSValue}, EvalResult
     },
-    semantics::methods::{ArrayMethod, ARRAY_METHODS_META}
+    semantics::{methods::{ArrayMethod, ARRAY_METHODS_META}, types::VarType}
 };
 
 static ARRAY_METHODS: OnceLock<Arc<HashMap<String, PureMethodFn>>> = OnceLock::new();
 static MUT_ARRAY_METHODS: OnceLock<Arc<HashMap<String, MutMethodFn>>> = OnceLock::new();
+static CALLBACK_ARRAY_METHODS: OnceLock<Arc<HashMap<String, CallbackMethodFn>>> = OnceLock::new();
 
 fn array_method_pure_impl(m: ArrayMethod) -> PureMethodFn {
     match m {
         ArrayMethod::Length => array_length,
-        ArrayMethod::Push | ArrayMethod::Remove | ArrayMethod::RemoveAt => {
+        ArrayMethod::Join => array_join,
+        ArrayMethod::JoinHuman => array_join_human,
+        ArrayMethod::Enumerate => array_enumerate,
+        ArrayMethod::Zip => array_zip,
+        ArrayMethod::IndexOf => array_index_of,
+        ArrayMethod::Sort => array_sort,
+        ArrayMethod::CountBy => array_count_by,
+        ArrayMethod::Push
+        | ArrayMethod::Pop
+        | ArrayMethod::Remove
+        | ArrayMethod::RemoveAt
+        | ArrayMethod::Reverse => {
             unreachable!("mut array method asked as pure")
         }
+        ArrayMethod::Map | ArrayMethod::FlatMap | ArrayMethod::SortBy | ArrayMethod::Partition => {
+            unreachable!("callback array method asked as pure")
+        }
     }
 }
 
 fn array_method_mut_impl(m: ArrayMethod) -> MutMethodFn {
     match m {
         ArrayMethod::Push => array_push,
+        ArrayMethod::Pop => array_pop,
         ArrayMethod::Remove => array_remove,
         ArrayMethod::RemoveAt => array_remove_at,
-        ArrayMethod::Length => unreachable!("pure array method asked as mut"),
+        ArrayMethod::Reverse => array_reverse,
+        ArrayMethod::Length
+        | ArrayMethod::Join
+        | ArrayMethod::JoinHuman
+        | ArrayMethod::Enumerate
+        | ArrayMethod::Zip
+        | ArrayMethod::IndexOf
+        | ArrayMethod::Sort
+        | ArrayMethod::CountBy
+        | ArrayMethod::Map
+        | ArrayMethod::FlatMap
+        | ArrayMethod::SortBy
+        | ArrayMethod::Partition => {
+            unreachable!("pure array method asked as mut")
+        }
+    }
+}
+
+fn array_method_callback_impl(m: ArrayMethod) -> CallbackMethodFn {
+    match m {
+        ArrayMethod::Map => array_map,
+        ArrayMethod::FlatMap => array_flat_map,
+        ArrayMethod::SortBy => array_sort_by,
+        ArrayMethod::Partition => array_partition,
+        _ => unreachable!("array method without callback asked as callback"),
     }
 }
 
@@ -37,7 +78,7 @@ pub fn array_methods_table() -> Arc<HashMap<String, PureMethodFn>> {
         .get_or_init(|| {
             let mut m = HashMap::new();
             for (enum_key, meta) in ARRAY_METHODS_META {
-                if !meta.is_mut {
+                if !meta.is_mut && !meta.takes_callback {
                     m.insert(meta.name.to_string(), array_method_pure_impl(*enum_key));
                 }
             }
@@ -60,6 +101,20 @@ pub fn array_mut_methods_table() -> Arc<HashMap<String, MutMethodFn>> {
         .clone()
 }
 
+pub fn array_callback_methods_table() -> Arc<HashMap<String, CallbackMethodFn>> {
+    CALLBACK_ARRAY_METHODS
+        .get_or_init(|| {
+            let mut m = HashMap::new();
+            for (enum_key, meta) in ARRAY_METHODS_META {
+                if meta.takes_callback {
+                    m.insert(meta.name.to_string(), array_method_callback_impl(*enum_key));
+                }
+            }
+            Arc::new(m)
+        })
+        .clone()
+}
+
 fn array_length(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
     let arr = match obj {
         RJSValue::Array(s) => s,
@@ -71,10 +126,362 @@ fn array_length(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<
     Ok(RJSValue::Number(arr.len() as f64))
 }
 
-fn array_push(target: &mut RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+fn array_join(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("join".into(), 1, pos));
+    }
+    let sep = match &args[0] {
+        RJSValue::String(s) => s,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("join() separator must be a string, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    // Elements are rendered like template interpolations
+    let parts: Vec<String> = arr.iter().map(RJSValue::to_string).collect();
+    Ok(RJSValue::String(parts.join(sep)))
+}
+
+/// `joinHuman(sep, lastSep)`: like `join(sep)`, but the last two elements are
+/// separated by `lastSep`, e.g. `"a, b and c"`.
+fn array_join_human(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("joinHuman".into(), 2, pos));
+    }
+    let (sep, last_sep) = match (&args[0], &args[1]) {
+        (RJSValue::String(sep), RJSValue::String(last_sep)) => (sep, last_sep),
+        (a, b) => {
+            return Err(EvalError::TypeMismatch(
+                format!("joinHuman() separators must be strings, got {} and {}", a.to_type(), b.to_type()),
+                pos,
+            ));
+        }
+    };
+    let parts: Vec<String> = arr.iter().map(RJSValue::to_string).collect();
+    Ok(RJSValue::String(match parts.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, init)) => format!("{}{}{}", init.join(sep), last_sep, last),
+    }))
+}
+
+fn array_enumerate(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("enumerate".into(), 0, pos));
+    }
+    let out = arr
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let mut entry = ObjectMap::new();
+            entry.insert("index".to_string(), RJSValue::Number(i as f64));
+            entry.insert("value".to_string(), v.clone());
+            RJSValue::Object(entry)
+        })
+        .collect();
+    Ok(RJSValue::Array(out))
+}
+
+/// `countBy(field)`: how many elements hold each value of `field`, keyed by the value
+/// rendered like `toString`, in the order the values first appear. Elements without
+/// the field are not counted.
+fn array_count_by(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("countBy".into(), 1, pos));
+    }
+    let field = match &args[0] {
+        RJSValue::String(s) => s,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("countBy() field must be a string, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let mut counts = ObjectMap::new();
+    for (i, elem) in arr.iter().enumerate() {
+        let RJSValue::Object(map) = elem else {
+            return Err(EvalError::TypeMismatch(
+                format!("countBy() needs an array of objects, element {} is {}", i, elem.to_type()),
+                pos,
+            ));
+        };
+        let key = match map.get(field.as_str()) {
+            None | Some(RJSValue::Undefined) => continue,
+            Some(value) => value.to_string(),
+        };
+        let count = counts.entry(key).or_insert(RJSValue::Number(0.0));
+        if let RJSValue::Number(n) = count {
+            *n += 1.0;
+        }
+    }
+    Ok(RJSValue::Object(counts))
+}
+
+fn array_zip(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("zip".into(), 1, pos));
+    }
+    let other = match &args[0] {
+        RJSValue::Array(b) => b,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("zip() argument must be an array, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    // Stops at the end of the shorter array
+    let out = arr
+        .iter()
+        .zip(other.iter())
+        .map(|(a, b)| RJSValue::Array(vec![a.clone(), b.clone()]))
+        .collect();
+    Ok(RJSValue::Array(out))
+}
+
+/// Index of the first element equal to the argument, or -1.
+fn array_index_of(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("indexOf".into(), 1, pos));
+    }
+    let idx = match arr.iter().position(|v| *v == args[0]) {
+        Some(i) => i as f64,
+        None => -1.0,
+    };
+    Ok(RJSValue::Number(idx))
+}
+
+/// Sort keys must be all numbers (no `NaN`) or all strings; other element types
+/// and mixes can't be ordered and are a type mismatch.
+fn check_sort_keys(name: &str, keys: &[RJSValue], pos: Position) -> EvalResult<()> {
+    if keys.iter().all(|v| matches!(v, RJSValue::Number(_))) {
+        if keys.iter().any(|v| matches!(v, RJSValue::Number(n) if n.is_nan())) {
+            return Err(EvalError::TypeMismatch(format!("{}() cannot order NaN", name), pos));
+        }
+        Ok(())
+    } else if keys.iter().all(|v| matches!(v, RJSValue::String(_))) {
+        Ok(())
+    } else {
+        Err(EvalError::TypeMismatch(
+            format!("{}() needs only numbers or only strings to order", name),
+            pos,
+        ))
+    }
+}
+
+/// Numbers ascending, strings by code point; keys went through `check_sort_keys`.
+fn cmp_sort_keys(a: &RJSValue, b: &RJSValue) -> Ordering {
+    match (a, b) {
+        (RJSValue::Number(x), RJSValue::Number(y)) => x.total_cmp(y),
+        (RJSValue::String(x), RJSValue::String(y)) => x.cmp(y),
+        _ => unreachable!(),
+    }
+}
+
+/// Sorted copy: numbers ascending, strings by code point.
+fn array_sort(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("sort".into(), 0, pos));
+    }
+    check_sort_keys("sort", arr, pos)?;
+    let mut out = arr.clone();
+    out.sort_by(cmp_sort_keys);
+    Ok(RJSValue::Array(out))
+}
+
+/// Sorted copy, ordered by the key `fn` returns for each element. `fn` runs once
+/// per element; elements with equal keys keep their order.
+fn array_sort_by(
+    obj: &RJSValue,
+    args: &[RJSValue],
+    call: &mut CallFn<'_>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("sortBy".into(), 1, pos));
+    }
+    let func = match &args[0] {
+        RJSValue::Function(f) => f,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("sortBy() argument must be a function, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let mut keys = Vec::with_capacity(arr.len());
+    for item in arr {
+        keys.push(call(func, vec![item.clone()], pos)?);
+    }
+    check_sort_keys("sortBy", &keys, pos)?;
+
+    let mut order: Vec<usize> = (0..arr.len()).collect();
+    order.sort_by(|&a, &b| cmp_sort_keys(&keys[a], &keys[b]));
+    Ok(RJSValue::Array(order.into_iter().map(|i| arr[i].clone()).collect()))
+}
+
+/// `[matched, unmatched]`: the elements for which `fn` returns a truthy value, then the rest,
+/// each in their original order.
+fn array_partition(
+    obj: &RJSValue,
+    args: &[RJSValue],
+    call: &mut CallFn<'_>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("partition".into(), 1, pos));
+    }
+    let func = match &args[0] {
+        RJSValue::Function(f) => f,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("partition() argument must be a function, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+    for item in arr {
+        if call(func, vec![item.clone()], pos)?.to_bool() {
+            matched.push(item.clone());
+        } else {
+            unmatched.push(item.clone());
+        }
+    }
+    Ok(RJSValue::Array(vec![
+        RJSValue::Array(matched),
+        RJSValue::Array(unmatched),
+    ]))
+}
+
+fn array_map(
+    obj: &RJSValue,
+    args: &[RJSValue],
+    call: &mut CallFn<'_>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("map".into(), 1, pos));
+    }
+    let func = match &args[0] {
+        RJSValue::Function(f) => f,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("map() argument must be a function, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let mut out = Vec::with_capacity(arr.len());
+    for item in arr {
+        out.push(call(func, vec![item.clone()], pos)?);
+    }
+    Ok(RJSValue::Array(out))
+}
+
+fn array_flat_map(
+    obj: &RJSValue,
+    args: &[RJSValue],
+    call: &mut CallFn<'_>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let arr = match obj {
+        RJSValue::Array(a) => a,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("flatMap".into(), 1, pos));
+    }
+    let func = match &args[0] {
+        RJSValue::Function(f) => f,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("flatMap() argument must be a function, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let mut out = Vec::with_capacity(arr.len());
+    for item in arr {
+        match call(func, vec![item.clone()], pos)? {
+            RJSValue::Array(items) => out.extend(items),
+            other => {
+                return Err(EvalError::TypeMismatch(
+                    format!("flatMap() callback must return an array, got {}", other.to_type()),
+                    pos,
+                ));
+            }
+        }
+    }
+    Ok(RJSValue::Array(out))
+}
+
+fn array_push(
+    target: &mut RJSValue,
+    decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
     if args.len() != 1 {
         return Err(EvalError::WrongNumberOfArguments("push".into(), 1, pos));
     }
+    // Enforce the declared element type, e.g. no `str` into a `vec<num>`
+    if let VarType::Array(inner) = decl_ty {
+        if **inner != VarType::Any && !args[0].is_type(inner) {
+            return Err(EvalError::TypeMismatch(
+                format!(
+                    "push() cannot add element of type {} into array of {}",
+                    args[0].to_type(),
+                    inner
+                ),
+                pos,
+            ));
+        }
+    }
     if let RJSValue::Array(ref mut arr) = target {
         arr.push(args[0].clone());
         Ok(RJSValue::Number(arr.len() as f64))
@@ -83,7 +490,45 @@ fn array_push(target: &mut RJSValue, args: &[RJSValue], pos: Position) -> EvalRe
     }
 }
 
-fn array_remove(target: &mut RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+fn array_pop(
+    target: &mut RJSValue,
+    _decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("pop".into(), 0, pos));
+    }
+    if let RJSValue::Array(ref mut arr) = target {
+        Ok(arr.pop().unwrap_or(RJSValue::Undefined))
+    } else {
+        Err(EvalError::General("pop() called on non-array".into(), pos))
+    }
+}
+
+fn array_reverse(
+    target: &mut RJSValue,
+    _decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("reverse".into(), 0, pos));
+    }
+    if let RJSValue::Array(ref mut arr) = target {
+        arr.reverse();
+        Ok(RJSValue::Array(arr.clone()))
+    } else {
+        Err(EvalError::General("reverse() called on non-array".into(), pos))
+    }
+}
+
+fn array_remove(
+    target: &mut RJSValue,
+    _decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
     if args.len() != 1 {
         return Err(EvalError::WrongNumberOfArguments("remove".into(), 1, pos));
     }
@@ -104,6 +549,7 @@ fn array_remove(target: &mut RJSValue, args: &[RJSValue], pos: Position) -> Eval
 
 fn array_remove_at(
     target: &mut RJSValue,
+    _decl_ty: &VarType,
     args: &[RJSValue],
     pos: Position,
 ) -> EvalResult<RJSValue> {
@@ -120,7 +566,7 @@ fn array_remove_at(
         }
         ref other => {
             return Err(EvalError::TypeMismatch(
-                format!("removeAt() index must be number, got {:?}", other),
+                format!("removeAt() index must be number, got {}", other.to_type()),
                 pos,
             ));
         }
@@ -141,3 +587,260 @@ fn array_remove_at(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::rjscript::testing::{eval_expr_str, eval_script_str_with_world, TestWorld};
+
+    const AT: Position = Position::UNKNOWN;
+
+    fn nums(ns: &[f64]) -> RJSValue {
+        RJSValue::Array(ns.iter().map(|n| RJSValue::Number(*n)).collect())
+    }
+
+    fn vec_of(ty: VarType) -> VarType {
+        VarType::Array(Box::new(ty))
+    }
+
+    fn run(src: &str, body: Value) -> Result<(u16, RJSValue), String> {
+        eval_script_str_with_world(src, body, &TestWorld::new())
+    }
+
+    #[test]
+    fn push_then_pop_round_trips() {
+        let mut arr = nums(&[1.0]);
+        let ty = vec_of(VarType::Number);
+        assert_eq!(array_push(&mut arr, &ty, &[RJSValue::Number(2.0)], AT).unwrap(), RJSValue::Number(2.0));
+        assert_eq!(array_pop(&mut arr, &ty, &[], AT).unwrap(), RJSValue::Number(2.0));
+        assert_eq!(array_pop(&mut arr, &ty, &[], AT).unwrap(), RJSValue::Number(1.0));
+        assert_eq!(arr, nums(&[]));
+    }
+
+    #[test]
+    fn pop_on_empty_array_is_undefined() {
+        let mut arr = nums(&[]);
+        assert_eq!(array_pop(&mut arr, &VarType::Any, &[], AT).unwrap(), RJSValue::Undefined);
+        assert_eq!(arr, nums(&[]));
+    }
+
+    #[test]
+    fn push_checks_the_element_type() {
+        let mut arr = nums(&[1.0]);
+        let err = array_push(&mut arr, &vec_of(VarType::Number), &[RJSValue::String("a".into())], AT)
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot add element of type str into array of num"), "{}", err);
+        assert_eq!(arr, nums(&[1.0]));
+
+        // `vec<any>` and undeclared slots take anything
+        for ty in [vec_of(VarType::Any), VarType::Any] {
+            array_push(&mut arr, &ty, &[RJSValue::Bool(true)], AT).unwrap();
+        }
+        assert_eq!(arr.to_string(), "[1,true,true]");
+    }
+
+    #[test]
+    fn push_and_pop_check_arguments_and_receiver() {
+        let mut arr = nums(&[1.0]);
+        assert!(matches!(
+            array_push(&mut arr, &VarType::Any, &[], AT),
+            Err(EvalError::WrongNumberOfArguments(..))
+        ));
+        assert!(matches!(
+            array_pop(&mut arr, &VarType::Any, &[RJSValue::Number(0.0)], AT),
+            Err(EvalError::WrongNumberOfArguments(..))
+        ));
+        let mut not_arr = RJSValue::String("x".into());
+        assert!(array_push(&mut not_arr, &VarType::Any, &[RJSValue::Null], AT).is_err());
+        assert!(array_pop(&mut not_arr, &VarType::Any, &[], AT).is_err());
+    }
+
+    #[test]
+    fn push_and_pop_in_scripts() {
+        let src = "let xs: vec<num> = [1, 2]; let last: num = xs.pop(); xs.push(last * 10); xs.push(3); return 200, xs;";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[1,20,3]".into()));
+    }
+
+    #[test]
+    fn push_type_is_checked_through_indexes() {
+        // the lint cannot see the type of `req.body.*`, so these fail at runtime
+        let src = "let m: vec<vec<num>> = [[1]]; m[0].push(req.body.v); return 200, m;";
+        assert_eq!(run(src, json!({ "v": 2 })).map(|(_, v)| v.to_string()), Ok("[[1,2]]".into()));
+        let err = run(src, json!({ "v": "two" })).unwrap_err();
+        assert!(err.contains("into array of num"), "{}", err);
+
+        let src = "let xs: vec<num> = [1]; xs.push(\"a\"); return 200, xs;";
+        let err = run(src, json!(null)).unwrap_err();
+        assert!(err.contains("cannot push element of type str into array of num"), "{}", err);
+    }
+
+    fn expr(src: &str) -> Result<RJSValue, String> {
+        eval_expr_str(src, &TestWorld::new())
+    }
+
+    fn string(s: &str) -> Result<RJSValue, String> {
+        Ok(RJSValue::String(s.into()))
+    }
+
+    #[test]
+    fn join_converts_elements_like_templates() {
+        assert_eq!(expr("[].join(\", \")"), string(""));
+        assert_eq!(expr("[\"only\"].join(\", \")"), string("only"));
+        assert_eq!(expr("[1, 2.5, -3].join(\"\")"), string("12.5-3"));
+        assert_eq!(
+            expr("[\"a\", 1, true, null, [2, 3], { k: \"v\" }].join(\" | \")"),
+            string("a | 1 | true | null | [2,3] | {\"k\":\"v\"}")
+        );
+        assert_eq!(expr("[\"a\", \"b\"].join(\"\")"), string("ab"));
+        assert!(expr("[1].join(1)").unwrap_err().contains("join"));
+    }
+
+    /// The value of `src` as JSON, numbers as floats.
+    fn expr_json(src: &str) -> Value {
+        RJSValue::rjs_to_json(&expr(src).unwrap())
+    }
+
+    #[test]
+    fn zip_stops_at_the_shorter_array() {
+        assert_eq!(expr_json("[1, 2, 3].zip([\"x\", \"y\"])"), json!([[1.0, "x"], [2.0, "y"]]));
+        assert_eq!(expr_json("[1].zip([\"x\", \"y\"])"), json!([[1.0, "x"]]));
+        assert_eq!(expr_json("[1, 2].zip([])"), json!([]));
+        assert_eq!(expr_json("[].zip([1])"), json!([]));
+        assert!(expr("[1].zip(1)").unwrap_err().contains("zip"));
+    }
+
+    #[test]
+    fn enumerate_pairs_indexes_with_values() {
+        assert_eq!(expr_json("[].enumerate()"), json!([]));
+        assert_eq!(
+            expr_json("[\"a\", \"b\"].enumerate()"),
+            json!([{ "index": 0.0, "value": "a" }, { "index": 1.0, "value": "b" }])
+        );
+        let src = "let pairs: vec<obj> = [\"x\", \"y\"].enumerate();\nlet out: vec<str> = [];\nfor (let i: num = 0; i < pairs.length(); i = i + 1) {\n  out.push(`${pairs[i].index}:${pairs[i].value}`);\n}\nreturn 200, out;";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[\"0:x\",\"1:y\"]".into()));
+    }
+
+    #[test]
+    fn index_of_finds_the_first_equal_element() {
+        let num = |n: f64| Ok(RJSValue::Number(n));
+        assert_eq!(expr("[3, 1, 2, 1].indexOf(1)"), num(1.0));
+        assert_eq!(expr("[3, 1, 2, 1].indexOf(4)"), num(-1.0));
+        assert_eq!(expr("[].indexOf(1)"), num(-1.0));
+        // no conversions between types
+        assert_eq!(expr("[1, \"1\"].indexOf(\"1\")"), num(1.0));
+        assert_eq!(expr("[true, null].indexOf(null)"), num(1.0));
+        // arrays and objects compare by value
+        assert_eq!(expr("[[1], { a: 1 }, { a: 1 }].indexOf({ a: 1 })"), num(1.0));
+        assert_eq!(expr("[[1], [1, 2]].indexOf([1, 2])"), num(1.0));
+        assert_eq!(expr("[\"a\", \"b\"].indexOf(\"b\") * 10"), num(10.0));
+    }
+
+    #[test]
+    fn sort_orders_numbers_and_strings() {
+        assert_eq!(expr_json("[3, -1, 2.5, 10, 0].sort()"), json!([-1.0, 0.0, 2.5, 3.0, 10.0]));
+        // by code point: upper case first, accents last
+        assert_eq!(expr_json("[\"b\", \"é\", \"B\", \"a\", \"ab\"].sort()"), json!(["B", "a", "ab", "b", "é"]));
+        assert_eq!(expr_json("[].sort()"), json!([]));
+        // a sorted copy: the array itself keeps its order
+        let src = "let xs: vec<num> = [2, 1];\nlet sorted: vec<num> = xs.sort();\nreturn 200, [xs, sorted];";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[[2,1],[1,2]]".into()));
+    }
+
+    #[test]
+    fn sort_rejects_what_it_cannot_order() {
+        for src in ["[1, \"a\"].sort()", "[true, false].sort()", "[[1], [0]].sort()", "[null].sort()"] {
+            let err = expr(src).unwrap_err();
+            assert!(err.contains("sort() needs only numbers or only strings to order"), "{}: {}", src, err);
+        }
+        let err = array_sort(&nums(&[1.0, f64::NAN]), &[], AT).unwrap_err();
+        assert!(err.to_string().contains("sort() cannot order NaN"), "{}", err);
+    }
+
+    #[test]
+    fn reverse_changes_the_array_in_place() {
+        let src = "let xs: vec<num> = [1, 2, 3];\nxs.reverse();\nlet empty: vec<str> = [];\nempty.reverse();\nreturn 200, [xs, empty];";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[[3,2,1],[]]".into()));
+        let mut arr = nums(&[1.0, 2.0]);
+        array_reverse(&mut arr, &VarType::Any, &[], AT).unwrap();
+        assert_eq!(arr, nums(&[2.0, 1.0]));
+    }
+
+    #[test]
+    fn flat_map_concatenates_the_returned_arrays() {
+        assert_eq!(expr_json("[1, 2, 3].flatMap((x) => [x, x * 10])"), json!([1.0, 10.0, 2.0, 20.0, 3.0, 30.0]));
+        assert_eq!(expr_json("[1, 2, 3].flatMap((x) => [])"), json!([]));
+        assert_eq!(expr_json("[1, 2, 3, 4].flatMap((x) => { if (x % 2 == 0) { return [x]; } return []; })"), json!([2.0, 4.0]));
+        // only one level is flattened
+        assert_eq!(expr_json("[\"a\", \"b\"].flatMap((s) => [[s]])"), json!([["a"], ["b"]]));
+        assert_eq!(expr_json("[].flatMap((x) => [x, x])"), json!([]));
+    }
+
+    #[test]
+    fn flat_map_needs_a_function_returning_arrays() {
+        let err = expr("[1, 2].flatMap((x) => x)").unwrap_err();
+        assert!(err.contains("flatMap() callback must return an array, got num"), "{}", err);
+        let err = expr("[1].flatMap([1])").unwrap_err();
+        assert!(err.contains("flatMap() argument must be a function, got"), "{}", err);
+    }
+
+    #[test]
+    fn partition_splits_matched_from_unmatched() {
+        assert_eq!(expr_json("[1, 2, 3, 4, 5].partition((x) => x % 2 == 0)"), json!([[2.0, 4.0], [1.0, 3.0, 5.0]]));
+        assert_eq!(expr_json("[1, 2].partition((x) => x > 0)"), json!([[1.0, 2.0], []]));
+        assert_eq!(expr_json("[1, 2].partition((x) => x > 5)"), json!([[], [1.0, 2.0]]));
+        assert_eq!(expr_json("[].partition((x) => true)"), json!([[], []]));
+        // the predicate's result is taken for its truthiness
+        assert_eq!(expr_json("[\"\", \"a\", \"b\"].partition((s) => s)"), json!([["a", "b"], [""]]));
+
+        let src = "let users: vec<obj> = [{ name: \"Ann\", age: 31 }, { name: \"Bob\", age: 15 }];
+let groups: vec<vec<any>> = users.partition((u) => u.age >= 18);
+return 200, [groups[0].length(), groups[1][0].name];";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[1,\"Bob\"]".into()));
+
+        let err = expr("[1].partition(1)").unwrap_err();
+        assert!(err.contains("partition() argument must be a function, got num"), "{}", err);
+    }
+
+    #[test]
+    fn join_human_puts_the_last_separator_before_the_last_element() {
+        let join = |arr: &str| expr(&format!("{}.joinHuman(\", \", \" and \")", arr));
+        assert_eq!(join("[]"), string(""));
+        assert_eq!(join("[\"a\"]"), string("a"));
+        assert_eq!(join("[\"a\", \"b\"]"), string("a and b"));
+        assert_eq!(join("[\"a\", \"b\", \"c\"]"), string("a, b and c"));
+        assert_eq!(join("[\"a\", \"b\", \"c\", \"d\"]"), string("a, b, c and d"));
+        // elements are converted like join converts them
+        assert_eq!(join("[1, true, null]"), string("1, true and null"));
+        assert_eq!(expr("[1, 2, 3].joinHuman(\"\", \"\")"), string("123"));
+
+        let err = expr("[1].joinHuman(\", \")").unwrap_err();
+        assert!(err.contains("joinHuman"), "{}", err);
+        let err = expr("[1].joinHuman(\", \", 1)").unwrap_err();
+        assert!(err.contains("joinHuman() separators must be strings, got str and num"), "{}", err);
+    }
+
+    #[test]
+    fn count_by_counts_field_values_in_first_seen_order() {
+        let events = "[{ type: \"click\" }, { type: \"view\" }, { type: \"click\", x: 1 }, { x: 2 }, { type: undefined }]";
+        assert_eq!(expr_json(&format!("{}.countBy(\"type\")", events)), json!({ "click": 2.0, "view": 1.0 }));
+        // elements missing the field are skipped, so nothing may be counted at all
+        assert_eq!(expr_json(&format!("{}.countBy(\"nope\")", events)), json!({}));
+        assert_eq!(expr_json("[].countBy(\"type\")"), json!({}));
+        // values are keyed by their string form
+        assert_eq!(
+            expr_json("[{ n: 1 }, { n: \"1\" }, { n: true }, { n: null }, { n: [1] }].countBy(\"n\")"),
+            json!({ "1": 2.0, "true": 1.0, "null": 1.0, "[1]": 1.0 })
+        );
+        assert_eq!(
+            expr("[{ t: \"b\" }, { t: \"a\" }, { t: \"b\" }].countBy(\"t\")").map(|v| v.to_string()),
+            Ok("{\"b\":2,\"a\":1}".into())
+        );
+
+        let err = expr("[{ t: 1 }, 2].countBy(\"t\")").unwrap_err();
+        assert!(err.contains("countBy() needs an array of objects, element 1 is num"), "{}", err);
+        let err = expr("[{ t: 1 }].countBy(1)").unwrap_err();
+        assert!(err.contains("countBy() field must be a string, got num"), "{}", err);
+    }
+}
@@ -5,18 +5,33 @@ use std::{
     time::Duration,
 };
 
+use regex::Regex;
+use tracing::Level;
+
 use crate::{
+    digest,
+    http::status::check_status_code,
+    random,
     rjscript::{
         ast::position::Position,
         evaluator::{
-            builtins::BuiltinFn,
+            builtins::{
+                encoding,
+                fetch::{self, FetchRequest},
+                BuiltinFn,
+            },
             errors::EvalError,
-            runtime::{eval_ctx::EvalCtx, value::RJSValue},
+            runtime::{
+                clock::{epoch_ms, UtcDateTime},
+                eval_ctx::EvalCtx,
+                value::{ObjectMap, RJSValue},
+                world::ScriptWorld,
+            },
             EvalResult,
         },
         semantics::methods::{Builtin, BUILTINS_TBL},
     },
-    rjsdb::DbValue,
+    rjsdb::{db::base36_u128, DbValue, FieldFilter, Page, SortKey},
 };
 
 static BUILTINS: OnceLock<Arc<HashMap<String, BuiltinFn>>> = OnceLock::new();
@@ -24,12 +39,64 @@ static BUILTINS: OnceLock<Arc<HashMap<String, BuiltinFn>>> = OnceLock::new();
 fn builtin_impl(b: Builtin) -> BuiltinFn {
     match b {
         Builtin::Print => builtin_print,
+        Builtin::LogInfo => builtin_log_info,
+        Builtin::LogDebug => builtin_log_debug,
+        Builtin::LogWarn => builtin_log_warn,
+        Builtin::LogError => builtin_log_error,
+        Builtin::Error => builtin_error,
+        Builtin::Assert => builtin_assert,
         Builtin::ToType => builtin_to_type,
         Builtin::ToString => builtin_to_string,
+        Builtin::JsonParse => builtin_json_parse,
+        Builtin::JsonStringify => builtin_json_stringify,
+        Builtin::Keys => builtin_keys,
+        Builtin::Values => builtin_values,
+        Builtin::Entries => builtin_entries,
+        Builtin::HasKey => builtin_has_key,
+        Builtin::Merge => builtin_merge,
+        Builtin::DeepMerge => builtin_deep_merge,
+        Builtin::DeepClone => builtin_deep_clone,
+        Builtin::Diff => builtin_diff,
+        Builtin::ParseInt => builtin_parse_int,
+        Builtin::ParseFloat => builtin_parse_float,
+        Builtin::ToNumber => builtin_to_number,
+        Builtin::BodyGet => builtin_body_get,
+        Builtin::BearerToken => builtin_bearer_token,
+        Builtin::BasicAuth => builtin_basic_auth,
+        Builtin::Base64Encode => builtin_base64_encode,
+        Builtin::Base64Decode => builtin_base64_decode,
+        Builtin::UrlEncode => builtin_url_encode,
+        Builtin::UrlDecode => builtin_url_decode,
+        Builtin::Sha256 => builtin_sha256,
+        Builtin::Md5 => builtin_md5,
+        Builtin::HmacSha256 => builtin_hmac_sha256,
+        Builtin::RegexTest => builtin_regex_test,
+        Builtin::RegexMatch => builtin_regex_match,
+        Builtin::RegexReplace => builtin_regex_replace,
+        Builtin::Abs => builtin_abs,
+        Builtin::Floor => builtin_floor,
+        Builtin::Ceil => builtin_ceil,
+        Builtin::Round => builtin_round,
+        Builtin::Min => builtin_min,
+        Builtin::Max => builtin_max,
+        Builtin::Pow => builtin_pow,
+        Builtin::Sqrt => builtin_sqrt,
+        Builtin::Uuid => builtin_uuid,
+        Builtin::UuidShort => builtin_uuid_short,
+        Builtin::Random => builtin_random,
+        Builtin::RandomInt => builtin_random_int,
+        Builtin::Now => builtin_now,
+        Builtin::Timestamp => builtin_timestamp,
+        Builtin::FormatDate => builtin_format_date,
+        Builtin::Env => builtin_env,
+        Builtin::HttpFetch => builtin_http_fetch,
+        Builtin::ReadFile => builtin_read_file,
+        Builtin::ReadJson => builtin_read_json,
         Builtin::Sleep => builtin_sleep,
         Builtin::CacheGet => builtin_cache_get,
         Builtin::CacheSet => builtin_cache_set,
         Builtin::CacheDel => builtin_cache_del,
+        Builtin::CacheTtl => builtin_cache_ttl,
         Builtin::CacheClear => builtin_cache_clear,
         Builtin::DbCreateTable => db_create_table,
         Builtin::DbGetAllTables => db_get_all_tables,
@@ -38,11 +105,15 @@ fn builtin_impl(b: Builtin) -> BuiltinFn {
         Builtin::DbGetAll => db_get_all,
         Builtin::DbGetById => db_get_by_id,
         Builtin::DbGetByFields => db_get_by_fields,
+        Builtin::DbGetAllPaged => db_get_all_paged,
+        Builtin::DbGetByFieldsPaged => db_get_by_fields_paged,
         Builtin::DbUpdateById => db_update_by_id,
+        Builtin::DbReplaceById => db_replace_by_id,
         Builtin::DbUpdateByFields => db_update_by_fields,
         Builtin::DbDeleteById => db_delete_by_id,
         Builtin::DbDeleteByFields => db_delete_by_fields,
         Builtin::DbDrop => db_drop,
+        Builtin::DbCompact => db_compact,
     }
 }
 
@@ -58,46 +129,998 @@ pub fn builtins_table() -> Arc<HashMap<String, BuiltinFn>> {
         .clone()
 }
 
-fn builtin_print(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
-    if args.len() < 1 {
+/// Emits the arguments, joined without separators, as a `tracing` event at `level`,
+/// tagged with the request path and id so `RJSERVER_LOG` filters script output too.
+fn script_log(
+    ctx: &EvalCtx,
+    name: &str,
+    level: Level,
+    args: Vec<RJSValue>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if args.is_empty() {
+        return Err(EvalError::WrongArity { name: name.into(), min: 1, max: None, pos });
+    }
+
+    let out = args
+        .iter()
+        .map(RJSValue::to_string)
+        .collect::<Vec<_>>()
+        .join("");
+    let (path, request_id) = (ctx.req.path.as_str(), ctx.request_id);
+    match level {
+        Level::DEBUG => tracing::debug!(path, request_id, "{}", out),
+        Level::INFO => tracing::info!(path, request_id, "{}", out),
+        Level::WARN => tracing::warn!(path, request_id, "{}", out),
+        _ => tracing::error!(path, request_id, "{}", out),
+    }
+    Ok(RJSValue::Bool(true))
+}
+
+/// `print` predates the leveled builtins and logs at info, like `logInfo`.
+fn builtin_print(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    script_log(ctx, "print", Level::INFO, args, pos)
+}
+
+fn builtin_log_debug(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    script_log(ctx, "logDebug", Level::DEBUG, args, pos)
+}
+
+fn builtin_log_info(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    script_log(ctx, "logInfo", Level::INFO, args, pos)
+}
+
+fn builtin_log_warn(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    script_log(ctx, "logWarn", Level::WARN, args, pos)
+}
+
+fn builtin_log_error(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    script_log(ctx, "logError", Level::ERROR, args, pos)
+}
+
+/// `error(status, body)`: ends the script with an error response, e.g. a 404.
+fn builtin_error(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("error".into(), 2, pos));
+    }
+    let mut args = args.into_iter();
+    let status = number_arg("error", &args.next().unwrap(), pos)?;
+    let status = check_status_code(status).map_err(|msg| EvalError::General(msg, pos))?;
+    Err(EvalError::Http {
+        status,
+        body: args.next().unwrap(),
+        pos,
+    })
+}
+
+/// `assert(cond, detail?)`: ends the script with a 500 when `cond` is falsy. A string
+/// `detail` is the message; any other value is Debug-formatted into it.
+fn builtin_assert(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::WrongArity { name: "assert".into(), min: 1, max: Some(2), pos });
+    }
+    if args[0].to_bool() {
+        return Ok(RJSValue::Bool(true));
+    }
+    let message = match args.get(1) {
+        None => "condition is false".to_string(),
+        Some(RJSValue::String(s)) => s.clone(),
+        Some(other) => format!("{:?}", other),
+    };
+    Err(EvalError::AssertionFailed(message, pos))
+}
+
+fn builtin_to_string(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("toString".into(), 1, pos));
+    }
+
+    let value = args.into_iter().next().unwrap();
+    Ok(RJSValue::String(value.to_string()))
+}
+
+fn builtin_to_type(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("toType".into(), 1, pos));
+    }
+    let value = args.into_iter().next().unwrap();
+    Ok(RJSValue::Type(value.to_type()))
+}
+
+fn builtin_json_parse(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("jsonParse".into(), 1, pos));
+    }
+    match &args[0] {
+        RJSValue::String(text) => {
+            let json: serde_json::Value = serde_json::from_str(text)
+                .map_err(|e| EvalError::General(format!("jsonParse: invalid JSON: {}", e), pos))?;
+            RJSValue::json_to_rjs(&json, pos)
+        }
+        other => Err(EvalError::TypeMismatch(
+            format!("jsonParse() expects a string, got {}", other.to_type()),
+            pos,
+        )),
+    }
+}
+
+/// `jsonStringify(value, pretty?)`: compact JSON, or indented by two spaces when `pretty` is true.
+fn builtin_json_stringify(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::WrongArity { name: "jsonStringify".into(), min: 1, max: Some(2), pos });
+    }
+    let pretty = match args.get(1) {
+        None => false,
+        Some(RJSValue::Bool(b)) => *b,
+        Some(other) => {
+            return Err(EvalError::TypeMismatch(
+                format!("jsonStringify() pretty flag must be a bool, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let json = RJSValue::rjs_to_json(&args[0]);
+    let text = if pretty {
+        serde_json::to_string_pretty(&json)
+            .map_err(|e| EvalError::General(format!("jsonStringify: {}", e), pos))?
+    } else {
+        json.to_string()
+    };
+    Ok(RJSValue::String(text))
+}
+
+/// Entries of an object argument, sorted by key so the output is reproducible.
+fn sorted_entries<'a>(name: &str, value: &'a RJSValue, pos: Position) -> EvalResult<Vec<(&'a String, &'a RJSValue)>> {
+    match value {
+        RJSValue::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Ok(entries)
+        }
+        other => Err(EvalError::TypeMismatch(
+            format!("{}() expects an object, got {}", name, other.to_type()),
+            pos,
+        )),
+    }
+}
+
+fn builtin_keys(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("keys".into(), 1, pos));
+    }
+    let keys = sorted_entries("keys", &args[0], pos)?
+        .into_iter()
+        .map(|(k, _)| RJSValue::String(k.clone()))
+        .collect();
+    Ok(RJSValue::Array(keys))
+}
+
+fn builtin_values(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("values".into(), 1, pos));
+    }
+    let values = sorted_entries("values", &args[0], pos)?
+        .into_iter()
+        .map(|(_, v)| v.clone())
+        .collect();
+    Ok(RJSValue::Array(values))
+}
+
+/// `entries(object)`: `{ key, value }` objects in the order of `keys()`.
+fn builtin_entries(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("entries".into(), 1, pos));
+    }
+    let entries = sorted_entries("entries", &args[0], pos)?
+        .into_iter()
+        .map(|(k, v)| {
+            RJSValue::Object(ObjectMap::from([
+                ("key".to_string(), RJSValue::String(k.clone())),
+                ("value".to_string(), v.clone()),
+            ]))
+        })
+        .collect();
+    Ok(RJSValue::Array(entries))
+}
+
+/// `hasKey(object, key)`: whether the key is present, even when its value is undefined.
+fn builtin_has_key(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("hasKey".into(), 2, pos));
+    }
+    let key = string_arg("hasKey", &args[1], pos)?;
+    match &args[0] {
+        RJSValue::Object(map) => Ok(RJSValue::Bool(map.contains_key(key))),
+        other => Err(EvalError::TypeMismatch(
+            format!("hasKey() expects an object, got {}", other.to_type()),
+            pos,
+        )),
+    }
+}
+
+/// The two objects `merge` and `deepMerge` take.
+fn merge_args(name: &str, args: Vec<RJSValue>, pos: Position) -> EvalResult<(ObjectMap, ObjectMap)> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments(name.into(), 2, pos));
+    }
+    let mut objects = args.into_iter().zip(["first", "second"]).map(|(arg, which)| match arg {
+        RJSValue::Object(map) => Ok(map),
+        other => Err(EvalError::TypeMismatch(
+            format!("{}() expects an object as its {} argument, got {}", name, which, other.to_type()),
+            pos,
+        )),
+    });
+    let base = objects.next().unwrap()?;
+    let patch = objects.next().unwrap()?;
+    Ok((base, patch))
+}
+
+/// Shallow merge: the keys of both objects, taking `b`'s value where both have a key.
+fn builtin_merge(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    let (mut base, patch) = merge_args("merge", args, pos)?;
+    base.extend(patch);
+    Ok(RJSValue::Object(base))
+}
+
+/// Like `merge`, but where both values are objects they are merged in turn.
+fn builtin_deep_merge(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    let (mut base, patch) = merge_args("deepMerge", args, pos)?;
+    deep_merge_into(&mut base, patch);
+    Ok(RJSValue::Object(base))
+}
+
+/// A copy sharing nothing with `value`: arrays and objects are copied at every depth.
+/// Function values are immutable, so the copy may share them.
+fn builtin_deep_clone(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("deepClone".into(), 1, pos));
+    }
+    Ok(args[0].clone())
+}
+
+fn deep_merge_into(base: &mut ObjectMap, patch: ObjectMap) {
+    for (key, value) in patch {
+        match (base.get_mut(&key), value) {
+            (Some(RJSValue::Object(inner)), RJSValue::Object(patch)) => deep_merge_into(inner, patch),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// `{ added, removed, changed }` between two objects, descending into nested objects.
+fn builtin_diff(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("diff".into(), 2, pos));
+    }
+    let (RJSValue::Object(old), RJSValue::Object(new)) = (&args[0], &args[1]) else {
+        return Err(EvalError::TypeMismatch(
+            format!("diff() expects two objects, got {} and {}", args[0].to_type(), args[1].to_type()),
+            pos,
+        ));
+    };
+    let mut changes = ObjectDiff::default();
+    changes.collect("", old, new);
+    Ok(RJSValue::Object(ObjectMap::from([
+        ("added".to_string(), RJSValue::Object(changes.added)),
+        ("removed".to_string(), RJSValue::Object(changes.removed)),
+        ("changed".to_string(), RJSValue::Object(changes.changed)),
+    ])))
+}
+
+/// Differences keyed by dotted path (`address.city`).
+#[derive(Default)]
+struct ObjectDiff {
+    added: ObjectMap,
+    removed: ObjectMap,
+    changed: ObjectMap,
+}
+
+impl ObjectDiff {
+    fn collect(
+        &mut self,
+        prefix: &str,
+        old: &ObjectMap,
+        new: &ObjectMap,
+    ) {
+        let path = |key: &str| {
+            if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", prefix, key)
+            }
+        };
+        for (key, before) in old {
+            match (before, new.get(key)) {
+                (_, None) => {
+                    self.removed.insert(path(key), before.clone());
+                }
+                (RJSValue::Object(a), Some(RJSValue::Object(b))) => self.collect(&path(key), a, b),
+                (_, Some(after)) if after != before => {
+                    let change = ObjectMap::from([
+                        ("from".to_string(), before.clone()),
+                        ("to".to_string(), after.clone()),
+                    ]);
+                    self.changed.insert(path(key), RJSValue::Object(change));
+                }
+                _ => {}
+            }
+        }
+        for (key, after) in new {
+            if !old.contains_key(key) {
+                self.added.insert(path(key), after.clone());
+            }
+        }
+    }
+}
+
+fn string_arg<'a>(name: &str, value: &'a RJSValue, pos: Position) -> EvalResult<&'a str> {
+    match value {
+        RJSValue::String(s) => Ok(s),
+        other => Err(EvalError::TypeMismatch(
+            format!("{}() expects a string, got {}", name, other.to_type()),
+            pos,
+        )),
+    }
+}
+
+/// Base64 of the string's UTF-8 bytes.
+fn builtin_base64_encode(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("base64Encode".into(), 1, pos));
+    }
+    let text = string_arg("base64Encode", &args[0], pos)?;
+    Ok(RJSValue::String(encoding::base64_encode(text.as_bytes())))
+}
+
+/// Decodes base64 whose bytes must be valid UTF-8, since the result is a string.
+/// Text that is not base64 at all yields `undefined`.
+fn builtin_base64_decode(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("base64Decode".into(), 1, pos));
+    }
+    let text = string_arg("base64Decode", &args[0], pos)?;
+    let Ok(bytes) = encoding::base64_decode(text) else {
+        return Ok(RJSValue::Undefined);
+    };
+    let decoded = String::from_utf8(bytes).map_err(|_| {
+        EvalError::TypeMismatch("base64Decode: decoded bytes are not valid UTF-8".into(), pos)
+    })?;
+    Ok(RJSValue::String(decoded))
+}
+
+/// Percent-encodes a string for use as a URL component.
+fn builtin_url_encode(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("urlEncode".into(), 1, pos));
+    }
+    let text = string_arg("urlEncode", &args[0], pos)?;
+    Ok(RJSValue::String(encoding::percent_encode(text)))
+}
+
+/// Decodes a percent-encoded URL component whose bytes must be valid UTF-8.
+fn builtin_url_decode(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("urlDecode".into(), 1, pos));
+    }
+    let text = string_arg("urlDecode", &args[0], pos)?;
+    // malformed input gives `undefined`, like parseInt, so it can be checked with a guard
+    let decoded = encoding::percent_decode(text)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    Ok(decoded.map_or(RJSValue::Undefined, RJSValue::String))
+}
+
+/// Lowercase hex SHA-256 of the string's UTF-8 bytes.
+fn builtin_sha256(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("sha256".into(), 1, pos));
+    }
+    let text = string_arg("sha256", &args[0], pos)?;
+    Ok(RJSValue::String(digest::to_hex(&digest::sha256(text.as_bytes()))))
+}
+
+/// Lowercase hex MD5 of the string's UTF-8 bytes.
+fn builtin_md5(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("md5".into(), 1, pos));
+    }
+    let text = string_arg("md5", &args[0], pos)?;
+    Ok(RJSValue::String(digest::to_hex(&digest::md5(text.as_bytes()))))
+}
+
+/// `hmacSha256(key, message)`: lowercase hex HMAC-SHA256 over the UTF-8 bytes of both.
+fn builtin_hmac_sha256(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("hmacSha256".into(), 2, pos));
+    }
+    let key = string_arg("hmacSha256", &args[0], pos)?;
+    let message = string_arg("hmacSha256", &args[1], pos)?;
+    Ok(RJSValue::String(digest::to_hex(&digest::hmac_sha256(
+        key.as_bytes(),
+        message.as_bytes(),
+    ))))
+}
+
+/// The `pattern` argument of a regex builtin, compiled.
+fn regex_arg(ctx: &EvalCtx, name: &str, pattern: &RJSValue, pos: Position) -> EvalResult<Regex> {
+    let pattern = string_arg(name, pattern, pos)?;
+    ctx.regex(pattern)
+        .map_err(|e| EvalError::General(format!("{}() invalid pattern: {}", name, e), pos))
+}
+
+/// `regexTest(pattern, text)`: whether the pattern matches anywhere in `text`.
+fn builtin_regex_test(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("regexTest".into(), 2, pos));
+    }
+    let re = regex_arg(ctx, "regexTest", &args[0], pos)?;
+    let text = string_arg("regexTest", &args[1], pos)?;
+    Ok(RJSValue::Bool(re.is_match(text)))
+}
+
+/// `regexMatch(pattern, text)`: the first match followed by its capture groups
+/// (`undefined` for groups that did not take part), or `undefined` without a match.
+fn builtin_regex_match(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("regexMatch".into(), 2, pos));
+    }
+    let re = regex_arg(ctx, "regexMatch", &args[0], pos)?;
+    let text = string_arg("regexMatch", &args[1], pos)?;
+    Ok(match re.captures(text) {
+        Some(caps) => RJSValue::Array(
+            caps.iter()
+                .map(|m| m.map_or(RJSValue::Undefined, |m| RJSValue::String(m.as_str().to_string())))
+                .collect(),
+        ),
+        None => RJSValue::Undefined,
+    })
+}
+
+/// `regexReplace(pattern, text, replacement)`: every match replaced, with `$1`,
+/// `${name}` in `replacement` expanding to capture groups.
+fn builtin_regex_replace(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 3 {
+        return Err(EvalError::WrongNumberOfArguments("regexReplace".into(), 3, pos));
+    }
+    let re = regex_arg(ctx, "regexReplace", &args[0], pos)?;
+    let text = string_arg("regexReplace", &args[1], pos)?;
+    let replacement = string_arg("regexReplace", &args[2], pos)?;
+    Ok(RJSValue::String(re.replace_all(text, replacement).into_owned()))
+}
+
+/// `bodyGet(pointer)`: the request body value at a JSON Pointer (RFC 6901), e.g.
+/// `"/items/0/name"`, or `undefined` as soon as a step is missing.
+fn builtin_body_get(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("bodyGet".into(), 1, pos));
+    }
+    let pointer = string_arg("bodyGet", &args[0], pos)?;
+    if pointer.is_empty() {
+        return Ok(ctx.req.body());
+    }
+    let Some(path) = pointer.strip_prefix('/') else {
+        return Err(EvalError::General(
+            format!("bodyGet() pointer must be empty or start with '/', got {:?}", pointer),
+            pos,
+        ));
+    };
+
+    let mut cur = &ctx.req.body;
+    for raw in path.split('/') {
+        let segment = raw.replace("~1", "/").replace("~0", "~");
+        let next = match cur {
+            RJSValue::Object(map) => map.get(&segment),
+            RJSValue::Array(items) => pointer_index(&segment).and_then(|i| items.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(v) => cur = v,
+            None => return Ok(RJSValue::Undefined),
+        }
+    }
+    Ok(cur.clone())
+}
+
+/// Credentials of the request's `Authorization` header when they use `scheme`
+/// (compared case-insensitively), e.g. the token of `Bearer <token>`.
+fn authorization_credentials<'a>(ctx: &'a EvalCtx, scheme: &str) -> Option<&'a str> {
+    let RJSValue::Object(headers) = &ctx.req.headers else {
+        return None;
+    };
+    let value = headers.iter().find_map(|(name, value)| match value {
+        RJSValue::String(v) if name.eq_ignore_ascii_case("authorization") => Some(v.trim()),
+        _ => None,
+    })?;
+    let (given, credentials) = value.split_once(' ')?;
+    let credentials = credentials.trim();
+    (given.eq_ignore_ascii_case(scheme) && !credentials.is_empty()).then_some(credentials)
+}
+
+/// `bearerToken()`: the token of an `Authorization: Bearer <token>` header, or
+/// `undefined` when the header is missing or uses another scheme.
+fn builtin_bearer_token(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("bearerToken".into(), 0, pos));
+    }
+    Ok(authorization_credentials(ctx, "Bearer")
+        .map_or(RJSValue::Undefined, |token| RJSValue::String(token.to_string())))
+}
+
+/// `basicAuth()`: `{ user, pass }` from an `Authorization: Basic <base64>` header,
+/// or `undefined` when it is missing, uses another scheme, or does not decode to
+/// UTF-8 `user:pass`.
+fn builtin_basic_auth(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("basicAuth".into(), 0, pos));
+    }
+    let decoded = authorization_credentials(ctx, "Basic")
+        .and_then(|c| encoding::base64_decode(c).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    let Some((user, pass)) = decoded.as_deref().and_then(|d| d.split_once(':')) else {
+        return Ok(RJSValue::Undefined);
+    };
+    Ok(RJSValue::Object(ObjectMap::from([
+        ("user".to_string(), RJSValue::String(user.to_string())),
+        ("pass".to_string(), RJSValue::String(pass.to_string())),
+    ])))
+}
+
+/// An array index segment: digits only, without leading zeros ("01" and "-1" are not indexes).
+fn pointer_index(segment: &str) -> Option<usize> {
+    let digits = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+    if !digits || (segment.len() > 1 && segment.starts_with('0')) {
+        return None;
+    }
+    segment.parse().ok()
+}
+
+/// `parseInt(text, radix?)`: the whole (trimmed) text must be an optionally signed
+/// integer in `radix` (default 10); anything else yields `undefined`.
+fn builtin_parse_int(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::WrongArity { name: "parseInt".into(), min: 1, max: Some(2), pos });
+    }
+    let text = string_arg("parseInt", &args[0], pos)?;
+    let radix = match args.get(1) {
+        None => 10,
+        Some(r) => {
+            let r = number_arg("parseInt", r, pos)?;
+            if r.fract() != 0.0 || !(2.0..=36.0).contains(&r) {
+                return Err(EvalError::General(
+                    format!("parseInt() radix must be a whole number from 2 to 36, got {}", r),
+                    pos,
+                ));
+            }
+            r as u32
+        }
+    };
+    Ok(match i128::from_str_radix(text.trim(), radix) {
+        Ok(n) => RJSValue::Number(n as f64),
+        Err(_) => RJSValue::Undefined,
+    })
+}
+
+/// `parseFloat(text)`: the whole (trimmed) text must be a finite number, else `undefined`.
+fn builtin_parse_float(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("parseFloat".into(), 1, pos));
+    }
+    let text = string_arg("parseFloat", &args[0], pos)?;
+    Ok(match text.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() => RJSValue::Number(n),
+        _ => RJSValue::Undefined,
+    })
+}
+
+/// `toNumber(value)`: numbers as they are, strings parsed like `parseFloat`,
+/// `undefined` for anything else.
+fn builtin_to_number(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("toNumber".into(), 1, pos));
+    }
+    Ok(match &args[0] {
+        RJSValue::Number(n) => RJSValue::Number(*n),
+        RJSValue::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => RJSValue::Number(n),
+            _ => RJSValue::Undefined,
+        },
+        _ => RJSValue::Undefined,
+    })
+}
+
+fn number_arg(name: &str, value: &RJSValue, pos: Position) -> EvalResult<f64> {
+    match value {
+        RJSValue::Number(n) => Ok(*n),
+        other => Err(EvalError::TypeMismatch(
+            format!("{}() expects a number, got {}", name, other.to_type()),
+            pos,
+        )),
+    }
+}
+
+/// Shared body of the single-argument math builtins.
+fn unary_math(name: &str, args: &[RJSValue], pos: Position, f: fn(f64) -> f64) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments(name.into(), 1, pos));
+    }
+    Ok(RJSValue::Number(f(number_arg(name, &args[0], pos)?)))
+}
+
+fn builtin_abs(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    unary_math("abs", &args, pos, f64::abs)
+}
+
+fn builtin_floor(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    unary_math("floor", &args, pos, f64::floor)
+}
+
+fn builtin_ceil(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    unary_math("ceil", &args, pos, f64::ceil)
+}
+
+/// Rounds half away from zero (`round(2.5)` is 3, `round(-2.5)` is -3).
+fn builtin_round(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    unary_math("round", &args, pos, f64::round)
+}
+
+/// A negative operand is an error rather than NaN, which has no JSON form.
+fn builtin_sqrt(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("sqrt".into(), 1, pos));
+    }
+    let n = number_arg("sqrt", &args[0], pos)?;
+    if n < 0.0 {
+        return Err(EvalError::General(
+            format!("sqrt() of a negative number ({})", n),
+            pos,
+        ));
+    }
+    Ok(RJSValue::Number(n.sqrt()))
+}
+
+fn builtin_pow(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("pow".into(), 2, pos));
+    }
+    let base = number_arg("pow", &args[0], pos)?;
+    let exp = number_arg("pow", &args[1], pos)?;
+    Ok(RJSValue::Number(base.powf(exp)))
+}
+
+/// `min`/`max` take either two or more numbers or a single non-empty `vec<num>`.
+fn min_max(name: &str, args: &[RJSValue], pos: Position, pick: fn(f64, f64) -> f64) -> EvalResult<RJSValue> {
+    let nums = match args {
+        [RJSValue::Array(items)] => {
+            if items.is_empty() {
+                return Err(EvalError::General(
+                    format!("{}() of an empty array", name),
+                    pos,
+                ));
+            }
+            items
+                .iter()
+                .map(|v| number_arg(name, v, pos))
+                .collect::<EvalResult<Vec<f64>>>()?
+        }
+        [_, _, ..] => args
+            .iter()
+            .map(|v| number_arg(name, v, pos))
+            .collect::<EvalResult<Vec<f64>>>()?,
+        _ => return Err(EvalError::WrongArity { name: name.into(), min: 2, max: None, pos }),
+    };
+    let first = nums[0];
+    Ok(RJSValue::Number(nums[1..].iter().fold(first, |acc, n| pick(acc, *n))))
+}
+
+fn builtin_min(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    min_max("min", &args, pos, f64::min)
+}
+
+fn builtin_max(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    min_max("max", &args, pos, f64::max)
+}
+
+/// Random (version 4) UUID in the canonical 8-4-4-4-12 lowercase hex form.
+fn builtin_uuid(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("uuid".into(), 0, pos));
+    }
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random::id_u64().to_be_bytes());
+    bytes[8..].copy_from_slice(&random::id_u64().to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(RJSValue::String(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )))
+}
+
+/// 128 random bits in base36, zero-padded to 25 characters.
+fn builtin_uuid_short(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("uuidShort".into(), 0, pos));
+    }
+    let bits = ((random::id_u64() as u128) << 64) | random::id_u64() as u128;
+    Ok(RJSValue::String(format!("{:0>25}", base36_u128(bits))))
+}
+
+/// Pseudo-random number in `[0, 1)`.
+fn builtin_random(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("random".into(), 0, pos));
+    }
+    Ok(RJSValue::Number(random::next_f64()))
+}
+
+/// 2^53: whole numbers up to this magnitude are exact in an f64.
+const MAX_SAFE_INT: f64 = 9_007_199_254_740_992.0;
+
+/// Pseudo-random whole number in `[min, max]`, both bounds included.
+fn builtin_random_int(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("randomInt".into(), 2, pos));
+    }
+    let min = number_arg("randomInt", &args[0], pos)?;
+    let max = number_arg("randomInt", &args[1], pos)?;
+    for bound in [min, max] {
+        if bound.fract() != 0.0 || bound.abs() > MAX_SAFE_INT {
+            return Err(EvalError::General(
+                format!("randomInt() bounds must be whole numbers, got {}", bound),
+                pos,
+            ));
+        }
+    }
+    if min > max {
+        return Err(EvalError::General(
+            format!("randomInt() min ({}) is greater than max ({})", min, max),
+            pos,
+        ));
+    }
+    let span = (max as i64 - min as i64) as u64 + 1;
+    Ok(RJSValue::Number((min as i64 + random::next_below(span) as i64) as f64))
+}
+
+/// Milliseconds since the UNIX epoch, read from the globals' clock.
+fn builtin_now(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("now".into(), 0, pos));
+    }
+    Ok(RJSValue::Number(epoch_ms(ctx.now())))
+}
+
+/// The current time as an ISO-8601 UTC string, read from the globals' clock.
+fn builtin_timestamp(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("timestamp".into(), 0, pos));
+    }
+    let ms = epoch_ms(ctx.now()) as i64;
+    Ok(RJSValue::String(UtcDateTime::from_epoch_ms(ms).to_iso8601()))
+}
+
+/// Tokens understood by `formatDate`, longest first so `MM` wins over a lone `M`.
+const DATE_TOKENS: &[&str] = &["YYYY", "SSS", "MM", "DD", "hh", "mm", "ss"];
+
+/// `formatDate(msEpoch, format)`: the UTC date with each token of `format`
+/// replaced. Text in `[...]` and characters other than letters are kept as is;
+/// other letters are an error.
+fn builtin_format_date(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 2 {
+        return Err(EvalError::WrongNumberOfArguments("formatDate".into(), 2, pos));
+    }
+    let ms = number_arg("formatDate", &args[0], pos)?;
+    // about 270,000 years either way, the range of a JS Date
+    if !ms.is_finite() || ms.abs() > 8.64e15 {
+        return Err(EvalError::General(
+            format!("formatDate() timestamp out of range: {}", ms),
+            pos,
+        ));
+    }
+    let format = string_arg("formatDate", &args[1], pos)?;
+    let dt = UtcDateTime::from_epoch_ms(ms.floor() as i64);
+
+    let mut out = String::with_capacity(format.len() + 8);
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            let Some(end) = rest.find(']') else {
+                return Err(EvalError::General(
+                    format!("formatDate() unclosed '[' in {:?}", format),
+                    pos,
+                ));
+            };
+            out.push_str(&rest[1..end]);
+            rest = &rest[end + 1..];
+            continue;
+        }
+        match DATE_TOKENS.iter().find(|t| rest.starts_with(**t)) {
+            Some(token) => {
+                match *token {
+                    "YYYY" => out.push_str(&format!("{:04}", dt.year)),
+                    "MM" => out.push_str(&format!("{:02}", dt.month)),
+                    "DD" => out.push_str(&format!("{:02}", dt.day)),
+                    "hh" => out.push_str(&format!("{:02}", dt.hour)),
+                    "mm" => out.push_str(&format!("{:02}", dt.minute)),
+                    "ss" => out.push_str(&format!("{:02}", dt.second)),
+                    _ => out.push_str(&format!("{:03}", dt.millis)),
+                }
+                rest = &rest[token.len()..];
+            }
+            None if c.is_ascii_alphabetic() => {
+                return Err(EvalError::General(
+                    format!(
+                        "formatDate() unknown token at {:?}, expected one of {}",
+                        rest,
+                        DATE_TOKENS.join(", ")
+                    ),
+                    pos,
+                ));
+            }
+            None => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    Ok(RJSValue::String(out))
+}
+
+/// Value of a process environment variable, or `undefined` when it is not set.
+/// With an allowlist on the globals, other names are an error.
+fn builtin_env(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("env".into(), 1, pos));
+    }
+    let name = string_arg("env", &args[0], pos)?;
+    if !ctx.env_allowed(name) {
+        return Err(EvalError::General(
+            format!("env(): `{}` is not allowed, list it in RJS_ENV_ALLOWLIST or pass --allow-env", name),
+            pos,
+        ));
+    }
+    match std::env::var(name) {
+        Ok(value) => Ok(RJSValue::String(value)),
+        Err(std::env::VarError::NotPresent) => Ok(RJSValue::Undefined),
+        Err(std::env::VarError::NotUnicode(_)) => Err(EvalError::General(
+            format!("env(): `{}` is not valid UTF-8", name),
+            pos,
+        )),
+    }
+}
+
+/// Used when `httpFetch` is not given a `timeoutMs`.
+const DEFAULT_FETCH_TIMEOUT_MS: f64 = 10_000.0;
+
+/// `httpFetch(url, options?)` with `options` = `{ method, headers, body, timeoutMs }`.
+/// Returns `{ status, headers, body }`, the body parsed when the response says it is JSON.
+fn builtin_http_fetch(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvalError::WrongArity { name: "httpFetch".into(), min: 1, max: Some(2), pos });
+    }
+    if !ctx.allow_net() {
         return Err(EvalError::General(
-            "'print' expects at least 1 argument".into(),
+            "httpFetch(): outbound requests are disabled (start the server with --allow-net)".into(),
             pos,
         ));
     }
+    let url = string_arg("httpFetch", &args[0], pos)?.to_string();
+    let empty = ObjectMap::new();
+    let options = match args.get(1) {
+        None => &empty,
+        Some(RJSValue::Object(map)) => map,
+        Some(other) => {
+            return Err(EvalError::TypeMismatch(
+                format!("httpFetch() expects an options object, got {}", other.to_type()),
+                pos,
+            ))
+        }
+    };
+    let option = |key: &str| options.get(key).filter(|v| !matches!(v, RJSValue::Undefined));
 
-    let out = args
-        .into_iter()
-        .map(|v| match v {
-            RJSValue::String(s) => s,
-            RJSValue::Number(n) => n.to_string(),
-            RJSValue::Bool(b) => b.to_string(),
-            RJSValue::Array(a) => format!("{:?}", a),
-            RJSValue::Object(o) => format!("{:?}", o),
-            RJSValue::Type(ty) => format!("{:?}", ty),
-            RJSValue::Undefined => format!("undefined"),
-        })
-        .collect::<Vec<_>>()
-        .join("");
-    println!("{}", out);
-    Ok(RJSValue::Bool(true))
-}
+    let method = match option("method") {
+        None => "GET".to_string(),
+        Some(m) => string_arg("httpFetch", m, pos)?.to_ascii_uppercase(),
+    };
+    let mut headers = Vec::new();
+    if let Some(map) = option("headers") {
+        for (name, value) in sorted_entries("httpFetch", map, pos)? {
+            headers.push((name.clone(), string_arg("httpFetch", value, pos)?.to_string()));
+        }
+    }
+    // strings are sent as they are, anything else as JSON
+    let body = match option("body") {
+        None => None,
+        Some(RJSValue::String(s)) => Some(s.clone()),
+        Some(value) => {
+            if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case("content-type")) {
+                headers.push(("Content-Type".into(), "application/json".into()));
+            }
+            Some(RJSValue::rjs_to_json(value).to_string())
+        }
+    };
+    let timeout_ms = match option("timeoutMs") {
+        None => DEFAULT_FETCH_TIMEOUT_MS,
+        Some(t) => number_arg("httpFetch", t, pos)?,
+    };
+    if !(timeout_ms > 0.0 && timeout_ms.is_finite()) {
+        return Err(EvalError::General(
+            format!("httpFetch(): timeoutMs must be a positive number, got {}", timeout_ms),
+            pos,
+        ));
+    }
 
-fn builtin_to_string(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
-    if args.len() < 1 {
-        return Err(EvalError::WrongNumberOfArguments("toString".into(), 1, pos));
+    let request = FetchRequest {
+        method,
+        url,
+        headers,
+        body,
+        timeout: Duration::from_millis(timeout_ms as u64),
+    };
+    // scripts run on the request's worker thread; let the runtime move its other
+    // tasks elsewhere while this one waits on the network
+    let in_multi_thread_runtime = tokio::runtime::Handle::try_current()
+        .is_ok_and(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread);
+    let response = if in_multi_thread_runtime {
+        tokio::task::block_in_place(|| fetch::fetch(&request))
+    } else {
+        fetch::fetch(&request)
     }
+    .map_err(|e| EvalError::General(format!("httpFetch(): {}", e), pos))?;
 
-    let value = args.into_iter().next().unwrap();
-    Ok(RJSValue::String(value.to_string()))
+    let is_json = response
+        .header("content-type")
+        .is_some_and(|ct| ct.to_ascii_lowercase().contains("json"));
+    let body = match serde_json::from_str::<serde_json::Value>(&response.body) {
+        Ok(json) if is_json => RJSValue::json_to_rjs(&json, pos)?,
+        _ => RJSValue::String(response.body.clone()),
+    };
+    let headers = response
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), RJSValue::String(value.clone())))
+        .collect();
+    Ok(RJSValue::Object(ObjectMap::from([
+        ("status".to_string(), RJSValue::Number(response.status as f64)),
+        ("headers".to_string(), RJSValue::Object(headers)),
+        ("body".to_string(), body),
+    ])))
 }
 
-fn builtin_to_type(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+/// Contents of a file under `--data-dir`, shared by `readFile` and `readJson`.
+fn read_data_file(ctx: &EvalCtx, name: &str, args: &[RJSValue], pos: Position) -> EvalResult<Arc<str>> {
     if args.len() != 1 {
-        return Err(EvalError::WrongNumberOfArguments("toType".into(), 1, pos));
+        return Err(EvalError::WrongNumberOfArguments(name.into(), 1, pos));
     }
-    let value = args.into_iter().next().unwrap();
-    Ok(RJSValue::Type(value.to_type()))
+    let path = string_arg(name, &args[0], pos)?;
+    let Some(files) = ctx.files() else {
+        return Err(EvalError::General(
+            format!("{}(): no data directory configured (start the server with --data-dir)", name),
+            pos,
+        ));
+    };
+    files
+        .read(path)
+        .map_err(|e| EvalError::General(format!("{}(): {}", name, e), pos))
+}
+
+fn builtin_read_file(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    let contents = read_data_file(ctx, "readFile", &args, pos)?;
+    Ok(RJSValue::String(contents.to_string()))
+}
+
+fn builtin_read_json(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    let contents = read_data_file(ctx, "readJson", &args, pos)?;
+    let json: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| EvalError::General(format!("readJson(): invalid JSON: {}", e), pos))?;
+    RJSValue::json_to_rjs(&json, pos)
 }
 
 fn builtin_sleep(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
@@ -111,7 +1134,7 @@ fn builtin_sleep(_: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<
             Ok(RJSValue::Bool(true))
         }
         other => Err(EvalError::TypeMismatch(
-            format!("sleep() expects a number, got {:?}", other),
+            format!("sleep() expects a number, got {}", other.to_type()),
             pos,
         )),
     }
@@ -126,7 +1149,7 @@ pub fn builtin_cache_get(
         return Err(EvalError::WrongNumberOfArguments("cacheGet".into(), 1, pos));
     }
     if let RJSValue::String(key) = &args[0] {
-        Ok(ctx.globals.cache.get(&key).unwrap_or(RJSValue::Undefined))
+        Ok(ctx.cache().get(key).unwrap_or(RJSValue::Undefined))
     } else {
         Err(EvalError::TypeMismatch(
             "cacheGet needs a string key".into(),
@@ -142,12 +1165,25 @@ pub fn builtin_cache_set(
 ) -> EvalResult<RJSValue> {
     // cacheSet(key, value, ttlSeconds?)
     if args.len() < 2 || args.len() > 3 {
-        return Err(EvalError::WrongNumberOfArguments("cacheSet".into(), 2, pos));
+        return Err(EvalError::WrongArity { name: "cacheSet".into(), min: 2, max: Some(3), pos });
     }
     if let RJSValue::String(key) = &args[0] {
         let value = args[1].clone();
 
-        ctx.globals.cache.set(key.clone(), value);
+        match args.get(2) {
+            None => ctx.cache().set(key.clone(), value),
+            Some(RJSValue::Number(secs)) if secs.is_finite() && *secs >= 0.0 => {
+                // past what a `Duration` holds is as good as never expiring
+                let ttl = Duration::try_from_secs_f64(*secs).unwrap_or(Duration::MAX);
+                ctx.cache().set_with_ttl(key.clone(), value, ttl);
+            }
+            Some(other) => {
+                return Err(EvalError::TypeMismatch(
+                    format!("cacheSet ttlSeconds must be a non-negative number, got {}", other.to_type()),
+                    pos,
+                ))
+            }
+        }
         Ok(RJSValue::Undefined)
     } else {
         Err(EvalError::TypeMismatch(
@@ -166,7 +1202,7 @@ pub fn builtin_cache_del(
         return Err(EvalError::WrongNumberOfArguments("cacheDel".into(), 1, pos));
     }
     if let RJSValue::String(key) = &args[0] {
-        Ok(RJSValue::Bool(ctx.globals.cache.del(&key)))
+        Ok(RJSValue::Bool(ctx.cache().del(key)))
     } else {
         Err(EvalError::TypeMismatch(
             "cacheDel needs a string key".into(),
@@ -175,6 +1211,27 @@ pub fn builtin_cache_del(
     }
 }
 
+pub fn builtin_cache_ttl(
+    ctx: &EvalCtx,
+    args: Vec<RJSValue>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("cacheTtl".into(), 1, pos));
+    }
+    if let RJSValue::String(key) = &args[0] {
+        Ok(ctx
+            .cache()
+            .ttl(key)
+            .map_or(RJSValue::Undefined, |left| RJSValue::Number(left.as_secs_f64())))
+    } else {
+        Err(EvalError::TypeMismatch(
+            "cacheTtl needs a string key".into(),
+            pos,
+        ))
+    }
+}
+
 pub fn builtin_cache_clear(
     ctx: &EvalCtx,
     args: Vec<RJSValue>,
@@ -187,7 +1244,7 @@ pub fn builtin_cache_clear(
             pos,
         ));
     }
-    ctx.globals.cache.clear();
+    ctx.cache().clear();
     Ok(RJSValue::Bool(true))
 }
 
@@ -210,7 +1267,7 @@ pub fn db_create_table(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> Eva
         }
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             db.create_table(&name)
                 .map_err(|e| EvalError::General(e.to_string(), pos))?;
@@ -232,7 +1289,7 @@ pub fn db_get_all_tables(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> E
         ));
     }
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let tables = db
                 .get_all_tables()
@@ -269,7 +1326,7 @@ pub fn db_drop_table(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalR
         }
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             db.drop_table(&name)
                 .map_err(|e| EvalError::General(e.to_string(), pos))?;
@@ -303,7 +1360,7 @@ pub fn db_create_entry(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> Eva
 
     let entry = &args[1];
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let rjs_to_dbvalue = DbValue::rjs_to_dbvalue(entry);
             let id = db.create_entry(&table_name, rjs_to_dbvalue)
@@ -332,7 +1389,7 @@ pub fn db_get_all(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResu
         }
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let entries = db
                 .get_all(&table_name)
@@ -359,7 +1416,7 @@ pub fn db_get_all(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResu
                             RJSValue::Object(obj)
                         }
                         other => {
-                            let mut obj = HashMap::new();
+                            let mut obj = ObjectMap::new();
                             obj.insert("id".to_string(), RJSValue::String(id));
                             obj.insert("value".to_string(), other);
                             RJSValue::Object(obj)
@@ -400,7 +1457,7 @@ pub fn db_get_by_id(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalRe
         _ => return Err(EvalError::TypeMismatch("id must be string".into(), pos)),
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             match db
                 .get_by_id(&table_name, &id)
@@ -424,7 +1481,7 @@ pub fn db_get_by_id(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalRe
                             Ok(RJSValue::Object(obj))
                         }
                         other => {
-                            let mut obj = HashMap::new();
+                            let mut obj = ObjectMap::new();
                             obj.insert("id".to_string(), RJSValue::String(id));
                             obj.insert("value".to_string(), other);
                             Ok(RJSValue::Object(obj))
@@ -480,7 +1537,7 @@ pub fn db_get_by_fields(
         }
     };
     
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let entries = db
                 .get_by_fields(&table_name, &field_filter)
@@ -504,7 +1561,7 @@ pub fn db_get_by_fields(
                             RJSValue::Object(obj)
                         }
                         other => {
-                            let mut obj = HashMap::new();
+                            let mut obj = ObjectMap::new();
                             obj.insert("id".to_string(), RJSValue::String(id));
                             obj.insert("value".to_string(), other);
                             RJSValue::Object(obj)
@@ -521,6 +1578,117 @@ pub fn db_get_by_fields(
     }
 }
 
+/// An entry as scripts see it: the stored object with its `id` added, other values
+/// wrapped as `{ id, value }`.
+fn db_entry_to_rjs(id: String, value: DbValue, pos: Position) -> RJSValue {
+    let converted = match value {
+        DbValue::Null => RJSValue::Undefined,
+        other => RJSValue::dbvalue_to_rjs(&other, pos).unwrap_or(RJSValue::Undefined),
+    };
+    match converted {
+        RJSValue::Object(mut obj) => {
+            obj.insert("id".to_string(), RJSValue::String(id));
+            RJSValue::Object(obj)
+        }
+        other => RJSValue::Object(ObjectMap::from([
+            ("id".to_string(), RJSValue::String(id)),
+            ("value".to_string(), other),
+        ])),
+    }
+}
+
+/// The `limit, offset, sortField?` arguments of the paged queries. A `sortField`
+/// starting with `-` sorts descending.
+fn page_args(name: &str, args: &[RJSValue], pos: Position) -> EvalResult<Page> {
+    let count = |value: &RJSValue, what: &str| -> EvalResult<usize> {
+        let n = number_arg(name, value, pos)?;
+        if n < 0.0 || n.fract() != 0.0 {
+            return Err(EvalError::General(
+                format!("{}() {} must be a non-negative integer, got {}", name, what, n),
+                pos,
+            ));
+        }
+        Ok(n as usize)
+    };
+    let sort = match args.get(2) {
+        None => None,
+        Some(field) => {
+            let field = string_arg(name, field, pos)?;
+            Some(match field.strip_prefix('-') {
+                Some(field) => SortKey { field: field.to_string(), descending: true },
+                None => SortKey { field: field.to_string(), descending: false },
+            })
+        }
+    };
+    Ok(Page {
+        limit: Some(count(&args[0], "limit")?),
+        offset: count(&args[1], "offset")?,
+        sort,
+    })
+}
+
+/// Runs a paged query and converts its rows.
+fn db_page(
+    ctx: &EvalCtx,
+    table: &str,
+    filter: &FieldFilter,
+    page: &Page,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let Some(db) = ctx.db() else {
+        return Err(EvalError::General(
+            "Persistent DB not configured (set RJS_DB_DIR)".into(),
+            pos,
+        ));
+    };
+    let entries = db
+        .get_page(table, filter, page)
+        .map_err(|e| EvalError::General(e.to_string(), pos))?;
+    Ok(RJSValue::Array(
+        entries
+            .into_iter()
+            .map(|(id, value)| db_entry_to_rjs(id, value, pos))
+            .collect(),
+    ))
+}
+
+/// `dbGetAllPaged(table, limit, offset, sortField?)`: one page of a table.
+pub fn db_get_all_paged(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !(3..=4).contains(&args.len()) {
+        return Err(EvalError::WrongArity { name: "dbGetAllPaged".into(), min: 3, max: Some(4), pos });
+    }
+    let table = string_arg("dbGetAllPaged", &args[0], pos)?;
+    let page = page_args("dbGetAllPaged", &args[1..], pos)?;
+    db_page(ctx, table, &FieldFilter::new(), &page, pos)
+}
+
+/// `dbGetByFieldsPaged(table, filter, limit, offset, sortField?)`: one page of the
+/// entries `dbGetByFields` would return.
+pub fn db_get_by_fields_paged(
+    ctx: &EvalCtx,
+    args: Vec<RJSValue>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if !(4..=5).contains(&args.len()) {
+        return Err(EvalError::WrongArity { name: "dbGetByFieldsPaged".into(), min: 4, max: Some(5), pos });
+    }
+    let table = string_arg("dbGetByFieldsPaged", &args[0], pos)?;
+    let filter: FieldFilter = match &args[1] {
+        RJSValue::Object(o) => o
+            .iter()
+            .map(|(k, v)| (k.clone(), RJSValue::rjs_to_json(v)))
+            .collect(),
+        _ => {
+            return Err(EvalError::TypeMismatch(
+                "field filter must be an object".into(),
+                pos,
+            ))
+        }
+    };
+    let page = page_args("dbGetByFieldsPaged", &args[2..], pos)?;
+    db_page(ctx, table, &filter, &page, pos)
+}
+
 pub fn db_update_by_id(
     ctx: &EvalCtx,
     args: Vec<RJSValue>,
@@ -551,7 +1719,7 @@ pub fn db_update_by_id(
 
     let patch = &args[2];
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let updated = db
                 .update_by_id(&table_name, &id, DbValue::rjs_to_dbvalue(patch))
@@ -565,6 +1733,50 @@ pub fn db_update_by_id(
     }
 }
 
+pub fn db_replace_by_id(
+    ctx: &EvalCtx,
+    args: Vec<RJSValue>,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    if args.len() != 3 {
+        return Err(EvalError::WrongNumberOfArguments(
+            "dbReplaceById".into(),
+            3,
+            pos,
+        ));
+    }
+
+    let table_name = match &args[0] {
+        RJSValue::String(s) => s.clone(),
+        _ => {
+            return Err(EvalError::TypeMismatch(
+                "table name must be string".into(),
+                pos,
+            ))
+        }
+    };
+
+    let id = match &args[1] {
+        RJSValue::String(s) => s.clone(),
+        _ => return Err(EvalError::TypeMismatch("id must be string".into(), pos)),
+    };
+
+    let value = &args[2];
+
+    match ctx.db() {
+        Some(db) => {
+            let replaced = db
+                .replace_by_id(&table_name, &id, DbValue::rjs_to_dbvalue(value))
+                .map_err(|e| EvalError::General(e.to_string(), pos))?;
+            Ok(RJSValue::Bool(replaced))
+        }
+        None => Err(EvalError::General(
+            "Persistent DB not configured (set RJS_DB_DIR)".into(),
+            pos,
+        )),
+    }
+}
+
 pub fn db_update_by_fields(
     ctx: &EvalCtx,
     args: Vec<RJSValue>,
@@ -606,7 +1818,7 @@ pub fn db_update_by_fields(
 
     let patch = &args[2];
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let updated_count = db
                 .update_by_fields(&table_name, &field_filter, DbValue::rjs_to_dbvalue(patch))
@@ -648,7 +1860,7 @@ pub fn db_delete_by_id(
         _ => return Err(EvalError::TypeMismatch("id must be string".into(), pos)),
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let deleted = db
                 .delete_by_id(&table_name, &id)
@@ -701,7 +1913,7 @@ pub fn db_delete_by_fields(
         }
     };
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             let deleted_count = db
                 .delete_by_fields(&table_name, &field_filter)
@@ -720,7 +1932,7 @@ pub fn db_drop(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<
         return Err(EvalError::WrongNumberOfArguments("dbDrop".into(), 0, pos));
     }
 
-    match ctx.globals.db.as_ref() {
+    match ctx.db() {
         Some(db) => {
             db.drop_db()
                 .map_err(|e| EvalError::General(e.to_string(), pos))?;
@@ -731,4 +1943,652 @@ pub fn db_drop(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<
             pos,
         )),
     }
-}
\ No newline at end of file
+}
+
+pub fn db_compact(ctx: &EvalCtx, args: Vec<RJSValue>, pos: Position) -> EvalResult<RJSValue> {
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("dbCompact".into(), 0, pos));
+    }
+
+    match ctx.db() {
+        Some(db) => {
+            db.compact()
+                .map_err(|e| EvalError::General(e.to_string(), pos))?;
+            Ok(RJSValue::Undefined)
+        }
+        None => Err(EvalError::General(
+            "Persistent DB not configured (set RJS_DB_DIR)".into(),
+            pos,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        http::request::Request,
+        rjscript::{
+            evaluator::runtime::{
+                value::{ObjectMap, RJSValue},
+                world::ScriptWorld,
+            },
+            testing::{eval_expr_str, eval_request_str_with_world, eval_script_str_with_world, TestWorld},
+        },
+    };
+
+    #[test]
+    fn cache_is_private_to_its_world() {
+        let a = TestWorld::new();
+        let b = TestWorld::new();
+        eval_script_str_with_world("cacheSet(\"k\", 1); return 200, true;", json!(null), &a).unwrap();
+
+        assert_eq!(a.cache().get("k"), Some(RJSValue::Number(1.0)));
+        assert_eq!(eval_expr_str("cacheGet(\"k\")", &b), Ok(RJSValue::Undefined));
+        assert!(b.cache_entries().is_empty());
+    }
+
+    #[test]
+    fn cache_ttl_and_delete() {
+        let world = TestWorld::new();
+        eval_script_str_with_world(
+            "cacheSet(\"t\", \"v\", 60); cacheSet(\"p\", \"v\"); return 200, true;",
+            json!(null),
+            &world,
+        )
+        .unwrap();
+
+        let ttl = world.cache().ttl("t").expect("expiring entry");
+        assert!(ttl > Duration::from_secs(58) && ttl <= Duration::from_secs(60));
+        assert_eq!(world.cache().ttl("p"), None);
+        assert_eq!(eval_expr_str("cacheDel(\"p\")", &world), Ok(RJSValue::Bool(true)));
+        assert_eq!(eval_expr_str("cacheDel(\"p\")", &world), Ok(RJSValue::Bool(false)));
+        assert_eq!(world.cache_entries().len(), 1);
+    }
+
+    #[test]
+    fn cache_ttl_reads_the_world_cache() {
+        let world = TestWorld::new();
+        world.cache().set_with_ttl("t".into(), RJSValue::Bool(true), Duration::from_secs(60));
+        world.cache().set("p".into(), RJSValue::Bool(true));
+
+        let Ok(RJSValue::Number(left)) = eval_expr_str("cacheTtl(\"t\")", &world) else { panic!() };
+        assert!(left > 58.0 && left <= 60.0, "{}", left);
+        assert_eq!(eval_expr_str("cacheTtl(\"p\")", &world), Ok(RJSValue::Undefined));
+        assert_eq!(eval_expr_str("cacheTtl(\"t\")", &TestWorld::new()), Ok(RJSValue::Undefined));
+        let err = eval_expr_str("cacheTtl(1)", &world).unwrap_err();
+        assert!(err.contains("cacheTtl needs a string key"), "{}", err);
+    }
+
+    #[test]
+    fn now_reads_the_world_clock() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let world = TestWorld::with_clock(Arc::new(move || at));
+        assert_eq!(eval_expr_str("now()", &world), Ok(RJSValue::Number(1_700_000_000_123.0)));
+        assert_eq!(world.now(), at);
+    }
+
+    #[test]
+    fn env_outside_the_allowlist_is_an_error() {
+        let world = TestWorld::with_env_allowlist(&["PATH"]);
+        assert!(world.env_allowed("PATH"));
+        assert!(matches!(eval_expr_str("env(\"PATH\")", &world), Ok(RJSValue::String(_))));
+
+        let err = eval_expr_str("env(\"HOME\")", &world).unwrap_err();
+        assert!(err.contains("`HOME` is not allowed"), "{}", err);
+    }
+
+    #[test]
+    fn capabilities_are_off_in_a_test_world() {
+        let world = TestWorld::new();
+        let err = eval_expr_str("httpFetch(\"http://127.0.0.1:9\")", &world).unwrap_err();
+        assert!(err.contains("--allow-net"), "{}", err);
+        let err = eval_expr_str("readFile(\"a.txt\")", &world).unwrap_err();
+        assert!(err.contains("--data-dir"), "{}", err);
+        let err = eval_expr_str("dbGetAllTables()", &world).unwrap_err();
+        assert!(err.contains("not configured"), "{}", err);
+        let err = eval_expr_str("env(\"PATH\")", &world).unwrap_err();
+        assert!(err.contains("RJS_ENV_ALLOWLIST"), "{}", err);
+    }
+
+    #[test]
+    fn read_file_errors_are_positioned() {
+        let dir = std::env::temp_dir().join(format!("rjs-read-file-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("data")).unwrap();
+        std::fs::write(dir.join("data/a.txt"), "hello").unwrap();
+        std::fs::write(dir.join("data/d.json"), "{\"n\": [1, 2]}").unwrap();
+        std::fs::write(dir.join("data/bad.json"), "{").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let world = TestWorld::with_data_dir(dir.join("data")).unwrap();
+
+        assert_eq!(eval_expr_str("readFile(\"a.txt\")", &world), Ok(RJSValue::String("hello".into())));
+        let (_, body) = eval_script_str_with_world("return 200, readJson(\"d.json\");", json!(null), &world).unwrap();
+        assert_eq!(RJSValue::rjs_to_json(&body), json!({ "n": [1.0, 2.0] }));
+
+        let absolute = format!("{}", dir.join("secret.txt").display());
+        for (call, message) in [
+            ("readFile(\"../secret.txt\")", "readFile(): '../secret.txt' is outside the data directory".to_string()),
+            (&format!("readJson({:?})", absolute), format!("readJson(): '{}' is outside the data directory", absolute)),
+            ("readJson(\"bad.json\")", "readJson(): invalid JSON".to_string()),
+        ] {
+            let src = format!("let ok: bool = true;\nreturn 200, {};", call);
+            let err = eval_script_str_with_world(&src, json!(null), &world).unwrap_err();
+            assert!(err.starts_with(&message), "{}", err);
+            assert!(err.contains(", at: 2:"), "{}", err);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn arity_errors_name_the_expected_count() {
+        let world = TestWorld::new();
+        for (call, message) in [
+            // fixed
+            ("now(1)", "'now()' expects exactly 0 arguments"),
+            ("sqrt()", "'sqrt()' expects exactly 1 argument"),
+            ("error(404)", "'error()' expects exactly 2 arguments"),
+            ("error(404, 1, 2)", "'error()' expects exactly 2 arguments"),
+            // ranged
+            ("cacheSet(\"k\")", "'cacheSet()' expects 2 to 3 arguments"),
+            ("cacheSet(\"k\", 1, 2, 3)", "'cacheSet()' expects 2 to 3 arguments"),
+            ("parseInt()", "'parseInt()' expects 1 to 2 arguments"),
+            ("parseInt(\"1\", 10, 2)", "'parseInt()' expects 1 to 2 arguments"),
+            // variadic
+            ("print()", "'print()' expects at least 1 argument"),
+            ("min(1)", "'min()' expects at least 2 arguments"),
+        ] {
+            // `return <call>;`: calls are positioned at their closing parenthesis
+            let expected = format!("{}, at: 1:{}", message, "return ".len() + call.len());
+            assert_eq!(eval_expr_str(call, &world), Err(expected), "{}", call);
+        }
+    }
+
+    /// `src` evaluated for a request with the given `Authorization` header, if any.
+    fn with_authorization(src: &str, header: Option<&str>) -> RJSValue {
+        let headers = header
+            .map(|h| HashMap::from([("Authorization".to_string(), h.to_string())]))
+            .unwrap_or_default();
+        let req = Request::new(Value::Null, HashMap::new(), HashMap::new(), headers);
+        eval_request_str_with_world(&format!("return 200, {};", src), &req, &TestWorld::new())
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn bearer_tokens() {
+        let token = |header| with_authorization("bearerToken()", header);
+        for header in ["Bearer abc.def", "bearer abc.def", "BEARER   abc.def  "] {
+            assert_eq!(token(Some(header)), RJSValue::String("abc.def".into()), "{:?}", header);
+        }
+        // absent, malformed, or another scheme
+        for header in [None, Some(""), Some("Bearer"), Some("Bearer   "), Some("Bearerabc"), Some("Basic YTpi"), Some("Token abc")] {
+            assert_eq!(token(header), RJSValue::Undefined, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn basic_auth_credentials() {
+        let auth = |header| with_authorization("basicAuth()", header);
+        let creds = |user: &str, pass: &str| {
+            RJSValue::Object(ObjectMap::from([
+                ("user".to_string(), RJSValue::String(user.into())),
+                ("pass".to_string(), RJSValue::String(pass.into())),
+            ]))
+        };
+        // "ann:s3cr:et", the password keeps its colons
+        assert_eq!(auth(Some("Basic YW5uOnMzY3I6ZXQ=")), creds("ann", "s3cr:et"));
+        assert_eq!(auth(Some("basic YW5uOg==")), creds("ann", ""));
+        for header in [
+            None,
+            Some("Basic"),
+            // not base64
+            Some("Basic ***"),
+            // "annpass" has no colon
+            Some("Basic YW5ucGFzcw=="),
+            // not UTF-8
+            Some("Basic //46YQ=="),
+            Some("Bearer YW5uOnB3"),
+        ] {
+            assert_eq!(auth(header), RJSValue::Undefined, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn changing_a_copy_leaves_the_original_alone() {
+        let world = TestWorld::new();
+        let src = "cacheSet(\"user\", { name: \"Ann\", tags: [\"a\"], address: { city: \"Oslo\" } });
+let user: obj = cacheGet(\"user\");
+user.name = \"Bob\";
+user.tags.push(\"b\");
+user.address.city = \"Rome\";
+let copy: obj = deepClone(user);
+copy.address.city = \"Paris\";
+copy.tags.push(\"c\");
+return 200, { cached: cacheGet(\"user\"), user: user, copy: copy };";
+        let (_, out) = eval_script_str_with_world(src, json!(null), &world).unwrap();
+        assert_eq!(
+            RJSValue::rjs_to_json(&out),
+            json!({
+                "cached": { "name": "Ann", "tags": ["a"], "address": { "city": "Oslo" } },
+                "user": { "name": "Bob", "tags": ["a", "b"], "address": { "city": "Rome" } },
+                "copy": { "name": "Bob", "tags": ["a", "b", "c"], "address": { "city": "Paris" } }
+            })
+        );
+        assert_eq!(
+            world.cache().get("user").map(|v| RJSValue::rjs_to_json(&v)),
+            Some(json!({ "name": "Ann", "tags": ["a"], "address": { "city": "Oslo" } }))
+        );
+    }
+
+    #[test]
+    fn replace_drops_fields_that_update_keeps() {
+        let world = TestWorld::with_mem_db();
+        let src = "let a: str = dbCreateEntry(\"users\", { name: \"Ann\", age: 31 });
+let b: str = dbCreateEntry(\"users\", { name: \"Bob\", age: 40 });
+let replaced: bool = dbReplaceById(\"users\", a, { name: \"Ada\" });
+let updated: bool = dbUpdateById(\"users\", b, { name: \"Ben\" });
+let missing: bool = dbReplaceById(\"users\", \"nope\", { name: \"x\" });
+return 200, { replaced: replaced, updated: updated, missing: missing, a: dbGetById(\"users\", a), b: dbGetById(\"users\", b) };";
+        let (_, out) = eval_script_str_with_world(src, json!(null), &world).unwrap();
+        let out = RJSValue::rjs_to_json(&out);
+        assert_eq!((&out["replaced"], &out["updated"], &out["missing"]), (&json!(true), &json!(true), &json!(false)));
+        assert_eq!(out["a"]["name"], json!("Ada"));
+        assert!(out["a"].get("age").is_none(), "{}", out);
+        assert_eq!((&out["b"]["name"], &out["b"]["age"]), (&json!("Ben"), &json!(40.0)));
+
+        let users = &world.db_dump().unwrap()["users"];
+        let stored: Vec<Value> = users.values().map(|v| v.to_json()).collect();
+        assert!(stored.contains(&json!({ "name": "Ada" })), "{:?}", stored);
+    }
+
+    #[test]
+    fn json_round_trips_and_rejects_malformed_text() {
+        let world = TestWorld::new();
+        let src = "let user: obj = { name: \"Ann\", tags: [\"a\", \"b\"], address: { city: \"Oslo\" }, age: 31, admin: false, nick: null };
+let text: str = jsonStringify(user);
+return 200, { text: text, back: jsonParse(text), pretty: jsonStringify([1, 2], true) };";
+        let (_, out) = eval_script_str_with_world(src, json!(null), &world).unwrap();
+        let out = RJSValue::rjs_to_json(&out);
+        let user = json!({ "name": "Ann", "tags": ["a", "b"], "address": { "city": "Oslo" }, "age": 31.0, "admin": false, "nick": null });
+        assert_eq!(out["back"], user);
+        assert_eq!(
+            out["text"],
+            json!(r#"{"name":"Ann","tags":["a","b"],"address":{"city":"Oslo"},"age":31.0,"admin":false,"nick":null}"#)
+        );
+        assert_eq!(out["pretty"], json!("[\n  1.0,\n  2.0\n]"));
+
+        let err = eval_expr_str("jsonParse(\"{ name: 1 }\")", &world).unwrap_err();
+        assert!(err.starts_with("jsonParse: invalid JSON: "), "{}", err);
+        let err = eval_expr_str("jsonParse(1)", &world).unwrap_err();
+        assert!(err.contains("jsonParse() expects a string, got num"), "{}", err);
+        let err = eval_expr_str("jsonStringify(1, \"yes\")", &world).unwrap_err();
+        assert!(err.contains("pretty flag must be a bool, got str"), "{}", err);
+    }
+
+    #[test]
+    fn math_builtins() {
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world).map(|v| RJSValue::rjs_to_json(&v));
+        for (src, want) in [
+            ("abs(-3.5)", 3.5),
+            ("abs(2)", 2.0),
+            ("floor(-1.5)", -2.0),
+            ("ceil(1.2)", 2.0),
+            ("round(2.5)", 3.0),
+            ("round(-2.5)", -3.0),
+            ("pow(2, 10)", 1024.0),
+            ("pow(4, 0.5)", 2.0),
+            ("sqrt(9)", 3.0),
+            ("sqrt(0)", 0.0),
+            ("min(3, 1)", 1.0),
+            ("max(3, 1, 7)", 7.0),
+            ("min([4, -2, 9])", -2.0),
+            ("max([4])", 4.0),
+        ] {
+            assert_eq!(eval(src), Ok(json!(want)), "{}", src);
+        }
+
+        // NaN has no JSON form, so a negative square root is an error
+        let err = eval_expr_str("sqrt(-4)", &world).unwrap_err();
+        assert!(err.starts_with("sqrt() of a negative number (-4)"), "{}", err);
+        let err = eval_expr_str("min([])", &world).unwrap_err();
+        assert!(err.starts_with("min() of an empty array"), "{}", err);
+        for (src, want) in [
+            ("abs(\"1\")", "abs() expects a number, got str"),
+            ("pow(2, null)", "pow() expects a number, got null"),
+            ("max([1, \"2\"])", "max() expects a number, got str"),
+            ("min(1)", "min"),
+        ] {
+            let err = eval_expr_str(src, &world).unwrap_err();
+            assert!(err.contains(want), "{}: {}", src, err);
+        }
+        let err = eval_script_str_with_world("return 200, abs(req.body.n);", json!({ "n": -1 }), &world).unwrap_err();
+        assert!(err.contains("toType"), "{}", err);
+        let src = "if (toType(req.body.n) != num) { return 400, null; }\nreturn 200, abs(req.body.n);";
+        assert_eq!(eval_script_str_with_world(src, json!({ "n": -1 }), &world), Ok((200, RJSValue::Number(1.0))));
+    }
+
+    #[test]
+    fn parse_int_and_parse_float() {
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world);
+        for (src, want) in [
+            ("parseInt(\"42\")", 42.0),
+            ("parseInt(\" -7 \")", -7.0),
+            ("parseInt(\"+3\")", 3.0),
+            ("parseInt(\"ff\", 16)", 255.0),
+            ("parseInt(\"FF\", 16)", 255.0),
+            ("parseInt(\"-101\", 2)", -5.0),
+            ("parseInt(\"z\", 36)", 35.0),
+            ("parseInt(\"17\", 8)", 15.0),
+            ("parseFloat(\"19.99\")", 19.99),
+            ("parseFloat(\"-1e3\")", -1000.0),
+            ("parseFloat(\" 2 \")", 2.0),
+        ] {
+            assert_eq!(eval(src), Ok(RJSValue::Number(want)), "{}", src);
+        }
+        for src in [
+            "parseInt(\"\")",
+            "parseInt(\"1.5\")",
+            "parseInt(\"12abc\")",
+            "parseInt(\"2\", 2)",
+            "parseInt(\"g\", 16)",
+            "parseFloat(\"abc\")",
+            "parseFloat(\"NaN\")",
+            "parseFloat(\"inf\")",
+        ] {
+            assert_eq!(eval(src), Ok(RJSValue::Undefined), "{}", src);
+        }
+        for (src, want) in [
+            ("parseInt(\"1\", 1)", "radix must be a whole number from 2 to 36, got 1"),
+            ("parseInt(\"1\", 37)", "radix must be a whole number from 2 to 36, got 37"),
+            ("parseInt(\"1\", 2.5)", "radix must be a whole number from 2 to 36, got 2.5"),
+            ("parseInt(\"1\", \"16\")", "parseInt() expects a number, got str"),
+            ("parseInt(12)", "parseInt"),
+        ] {
+            let err = eval(src).unwrap_err();
+            assert!(err.contains(want), "{}: {}", src, err);
+        }
+    }
+
+    #[test]
+    fn parsed_request_values_need_a_guard() {
+        let query = |page: &str| HashMap::from([("page".to_string(), page.to_string())]);
+        let run = |src: &str, page: &str| {
+            let req = Request::new(Value::Null, query(page), HashMap::new(), HashMap::new());
+            eval_request_str_with_world(src, &req, &TestWorld::new())
+        };
+        let err = run("return 200, parseInt(req.query.page) > 1;", "2").unwrap_err();
+        assert!(err.contains("toType"), "{}", err);
+
+        let src = "if (toType(parseInt(req.query.page, 16)) != num) { return 400, null; }
+return 200, parseInt(req.query.page, 16) > 1;";
+        assert_eq!(run(src, "a"), Ok((200, RJSValue::Bool(true))));
+        assert_eq!(run(src, "x"), Ok((400, RJSValue::Null)));
+    }
+
+    #[test]
+    fn body_get_follows_json_pointers() {
+        let world = TestWorld::new();
+        let body = json!({
+            "user": { "name": "Ann", "tags": ["a", "b"], "address": null },
+            "a/b": 1.0,
+            "m~n": 2.0,
+            "": 3.0
+        });
+        let get = |pointer: &str| {
+            eval_script_str_with_world(&format!("return 200, bodyGet({:?});", pointer), body.clone(), &world)
+                .map(|(_, v)| v)
+        };
+        let present = |pointer: &str, want: Value| {
+            assert_eq!(get(pointer).map(|v| RJSValue::rjs_to_json(&v)), Ok(want), "{}", pointer);
+        };
+        present("", body.clone());
+        present("/user/name", json!("Ann"));
+        present("/user/tags", json!(["a", "b"]));
+        present("/user/tags/1", json!("b"));
+        present("/user/address", json!(null));
+        present("/a~1b", json!(1.0));
+        present("/m~0n", json!(2.0));
+        present("/", json!(3.0));
+
+        for absent in [
+            "/nope",
+            "/nope/deeper/still",
+            "/user/age",
+            "/user/name/first",
+            "/user/address/city",
+            "/user/tags/2",
+            "/user/tags/01",
+            "/user/tags/-1",
+            "/user/tags/x",
+        ] {
+            assert_eq!(get(absent), Ok(RJSValue::Undefined), "{}", absent);
+        }
+
+        let err = get("user/name").unwrap_err();
+        assert!(err.contains("bodyGet() pointer must be empty or start with '/', got \"user/name\""), "{}", err);
+        let err = eval_script_str_with_world("return 200, bodyGet(1);", body.clone(), &world).unwrap_err();
+        assert!(err.contains("bodyGet"), "{}", err);
+    }
+
+    #[test]
+    fn base64_round_trips_and_padding() {
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world);
+        let string = |s: &str| Ok(RJSValue::String(s.into()));
+        for (text, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("user:pa ss", "dXNlcjpwYSBzcw=="),
+            ("h\\u{e9}llo \\u{1F600}", "aMOpbGxvIPCfmIA="),
+        ] {
+            assert_eq!(eval(&format!("base64Encode(\"{}\")", text)), string(encoded), "{}", text);
+            let round_trip = format!("base64Decode(base64Encode(\"{0}\")) == \"{0}\"", text);
+            assert_eq!(eval(&round_trip), Ok(RJSValue::Bool(true)), "{}", text);
+        }
+        // padding is optional, and the URL-safe alphabet is accepted
+        assert_eq!(eval("base64Decode(\"Zg\")"), string("f"));
+        assert_eq!(eval("base64Decode(\"Zm8\")"), string("fo"));
+        assert_eq!(eval("base64Decode(\"-_-_\")"), eval("base64Decode(\"+/+/\")"));
+
+        // not base64 at all
+        for bad in ["Z", "Zg=", "Zg===", "Zm9v!", "Zm=v"] {
+            assert_eq!(eval(&format!("base64Decode(\"{}\")", bad)), Ok(RJSValue::Undefined), "{}", bad);
+        }
+        // base64, but not of text
+        let err = eval("base64Decode(\"/w==\")").unwrap_err();
+        assert!(err.contains("base64Decode: decoded bytes are not valid UTF-8"), "{}", err);
+        let err = eval("base64Encode(1)").unwrap_err();
+        assert!(err.contains("base64Encode"), "{}", err);
+    }
+
+    #[test]
+    fn keys_and_values_are_sorted_by_key() {
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world).map(|v| RJSValue::rjs_to_json(&v));
+        let obj = "{ zeta: 1, alpha: \"a\", Beta: [true], mid: { x: null } }";
+        assert_eq!(eval(&format!("keys({})", obj)), Ok(json!(["Beta", "alpha", "mid", "zeta"])));
+        assert_eq!(eval(&format!("values({})", obj)), Ok(json!([[true], "a", { "x": null }, 1.0])));
+        assert_eq!(eval("keys({})"), Ok(json!([])));
+        assert_eq!(eval("values({})"), Ok(json!([])));
+
+        // the same order whatever order the keys were added in
+        let src = "let a: obj = {};\na.set(\"b\", 2);\na.set(\"a\", 1);\nlet b: obj = { a: 1, b: 2 };\nreturn 200, [keys(a), keys(b), values(a), values(b)];";
+        let (_, out) = eval_script_str_with_world(src, json!(null), &world).unwrap();
+        assert_eq!(RJSValue::rjs_to_json(&out), json!([["a", "b"], ["a", "b"], [1.0, 2.0], [1.0, 2.0]]));
+        let body = json!({ "z": 1, "y": 2, "x": 3 });
+        for _ in 0..5 {
+            let (_, out) = eval_script_str_with_world("return 200, keys(req.body);", body.clone(), &world).unwrap();
+            assert_eq!(RJSValue::rjs_to_json(&out), json!(["x", "y", "z"]));
+        }
+
+        for (src, want) in [
+            ("keys([1])", "keys() expects an object, got vec"),
+            ("values(\"ab\")", "values() expects an object, got str"),
+            ("keys(null)", "keys() expects an object, got null"),
+        ] {
+            let err = eval(src).unwrap_err();
+            assert!(err.contains(want), "{}: {}", src, err);
+        }
+    }
+
+    #[test]
+    fn has_key_tells_absent_from_undefined() {
+        let world = TestWorld::new();
+        let src = "let user: obj = { name: \"Ann\", nick: null, age: undefined };
+return 200, [hasKey(user, \"name\"), hasKey(user, \"nick\"), hasKey(user, \"age\"), hasKey(user, \"email\"), user.get(\"age\") == user.get(\"email\")];";
+        let (_, out) = eval_script_str_with_world(src, json!(null), &world).unwrap();
+        assert_eq!(RJSValue::rjs_to_json(&out), json!([true, true, true, false, true]));
+
+        let body = json!({ "a": null });
+        let (_, out) = eval_script_str_with_world("return 200, [hasKey(req.body, \"a\"), hasKey(req.body, \"b\")];", body, &world).unwrap();
+        assert_eq!(RJSValue::rjs_to_json(&out), json!([true, false]));
+
+        for (src, want) in [
+            ("hasKey([1], \"0\")", "hasKey() expects an object, got vec"),
+            ("hasKey({ a: 1 }, 1)", "hasKey"),
+            ("hasKey({ a: 1 })", "hasKey"),
+        ] {
+            let err = eval_expr_str(src, &world).unwrap_err();
+            assert!(err.contains(want), "{}: {}", src, err);
+        }
+    }
+
+    #[test]
+    fn url_encoding_round_trips() {
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world);
+        let string = |s: &str| Ok(RJSValue::String(s.into()));
+        for (text, encoded) in [
+            ("", ""),
+            ("a b", "a%20b"),
+            ("AZaz09-._~", "AZaz09-._~"),
+            ("a+b=c&d", "a%2Bb%3Dc%26d"),
+            (":/?#[]@!$'()*,;", "%3A%2F%3F%23%5B%5D%40%21%24%27%28%29%2A%2C%3B"),
+            ("100%", "100%25"),
+            ("caf\\u{e9} \\u{1F600}", "caf%C3%A9%20%F0%9F%98%80"),
+        ] {
+            assert_eq!(eval(&format!("urlEncode(\"{}\")", text)), string(encoded), "{}", text);
+            let round_trip = format!("urlDecode(urlEncode(\"{0}\")) == \"{0}\"", text);
+            assert_eq!(eval(&round_trip), Ok(RJSValue::Bool(true)), "{}", text);
+        }
+        // either hex case; `+` is not a space
+        assert_eq!(eval("urlDecode(\"caf%c3%a9\")"), string("caf\u{e9}"));
+        assert_eq!(eval("urlDecode(\"a+b%20c\")"), string("a+b c"));
+        assert_eq!(eval("urlDecode(\"plain\")"), string("plain"));
+
+        // malformed sequences and bytes that are not UTF-8
+        for bad in ["%", "%2", "a%zz", "%%20", "%C3", "%FF"] {
+            assert_eq!(eval(&format!("urlDecode(\"{}\")", bad)), Ok(RJSValue::Undefined), "{}", bad);
+        }
+        let err = eval("urlEncode(1)").unwrap_err();
+        assert!(err.contains("urlEncode"), "{}", err);
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_paths() {
+        let world = TestWorld::new();
+        let diff = |old: &str, new: &str| {
+            eval_expr_str(&format!("diff({}, {})", old, new), &world).map(|v| RJSValue::rjs_to_json(&v))
+        };
+        let empty = json!({ "added": {}, "removed": {}, "changed": {} });
+        assert_eq!(diff("{}", "{}"), Ok(empty.clone()));
+        assert_eq!(diff("{ a: 1, b: [1, { c: 2 }] }", "{ b: [1, { c: 2 }], a: 1 }"), Ok(empty));
+
+        let old = "{ name: \"Ann\", age: 31, tags: [\"a\"], nick: null, address: { city: \"Oslo\", zip: \"0150\", geo: { lat: 59 } } }";
+        let new = "{ name: \"Ann\", age: 32, tags: [\"a\", \"b\"], email: \"ann@x.io\", address: { city: \"Rome\", street: \"Via\", geo: { lat: 59 } } }";
+        assert_eq!(
+            diff(old, new),
+            Ok(json!({
+                "added": { "email": "ann@x.io", "address.street": "Via" },
+                "removed": { "nick": null, "address.zip": "0150" },
+                "changed": {
+                    "age": { "from": 31.0, "to": 32.0 },
+                    "tags": { "from": ["a"], "to": ["a", "b"] },
+                    "address.city": { "from": "Oslo", "to": "Rome" }
+                }
+            }))
+        );
+        // an object replaced by another kind of value is a change of the whole field
+        assert_eq!(
+            diff("{ a: { b: 1 }, c: 1 }", "{ a: 1, c: { d: 1 } }"),
+            Ok(json!({
+                "added": {},
+                "removed": {},
+                "changed": {
+                    "a": { "from": { "b": 1.0 }, "to": 1.0 },
+                    "c": { "from": 1.0, "to": { "d": 1.0 } }
+                }
+            }))
+        );
+
+        let err = eval_expr_str("diff({}, [1])", &world).unwrap_err();
+        assert!(err.contains("diff() expects two objects, got obj and vec"), "{}", err);
+    }
+
+    #[test]
+    fn uuids_are_v4_and_distinct() {
+        let world = TestWorld::new();
+        let RJSValue::String(a) = eval_expr_str("uuid()", &world).unwrap() else { panic!() };
+        let RJSValue::String(b) = eval_expr_str("uuid()", &world).unwrap() else { panic!() };
+        assert_ne!(a, b);
+        let groups: Vec<usize> = a.split('-').map(str::len).collect();
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert_eq!(&a[14..15], "4");
+        assert!(matches!(&a[19..20], "8" | "9" | "a" | "b"), "{}", a);
+
+        let RJSValue::String(short) = eval_expr_str("uuidShort()", &world).unwrap() else { panic!() };
+        assert_eq!(short.len(), 25);
+        assert!(short.chars().all(|c| c.is_ascii_digit() || c.is_ascii_lowercase()));
+    }
+
+    #[test]
+    fn db_writes_land_in_the_world_db() {
+        let world = TestWorld::with_mem_db();
+        let (status, id) = eval_script_str_with_world(
+            "dbCreateTable(\"users\");\n\
+             return 201, dbCreateEntry(\"users\", { name: \"Ann\", age: 30 });",
+            json!(null),
+            &world,
+        )
+        .unwrap();
+        assert_eq!(status, 201);
+        let RJSValue::String(id) = id else { panic!("id is {:?}", id) };
+
+        let dump = world.db_dump().unwrap();
+        assert_eq!(dump["users"][&id].to_json(), json!({ "name": "Ann", "age": 30.0 }));
+        assert_eq!(
+            eval_expr_str(&format!("dbGetById(\"users\", \"{}\").name", id), &world),
+            Ok(RJSValue::String("Ann".into()))
+        );
+        assert_eq!(eval_expr_str("dbGetById(\"users\", \"nope\")", &world), Ok(RJSValue::Undefined));
+    }
+
+    #[test]
+    fn db_survives_a_reopen_of_the_temp_dir() {
+        let world = TestWorld::with_temp_db().unwrap();
+        eval_script_str_with_world(
+            "dbCreateTable(\"t\"); dbCreateEntry(\"t\", { n: 1 }); return 200, true;",
+            json!(null),
+            &world,
+        )
+        .unwrap();
+        let dir = world.db_dir().unwrap().to_path_buf();
+        let reopened = crate::rjsdb::db::JsonTableDb::open(&dir).unwrap();
+        assert_eq!(crate::rjsdb::TableDb::get_all(&reopened, "t").unwrap().len(), 1);
+
+        drop(world);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,63 @@
+//! Outbound HTTP requests for the `httpFetch` builtin.
+
+use std::time::Duration;
+
+pub struct FetchRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// Limit for the whole exchange, from connecting to reading the body
+    pub timeout: Duration,
+}
+
+pub struct FetchResponse {
+    pub status: u16,
+    /// Lowercase names; repeated headers are joined with `", "`
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl FetchResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Sends the request and reads the whole response. Error statuses are responses
+/// like any other; only failing to get one (bad URL, refused connection, timeout)
+/// is an error.
+pub fn fetch(req: &FetchRequest) -> Result<FetchResponse, String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(req.timeout)
+        .redirects(5)
+        .build();
+    let mut request = agent.request(&req.method, &req.url);
+    for (name, value) in &req.headers {
+        request = request.set(name, value);
+    }
+    let result = match &req.body {
+        Some(body) => request.send_string(body),
+        None => request.call(),
+    };
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(e)) => return Err(e.to_string()),
+    };
+
+    let status = response.status();
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for name in response.headers_names() {
+        if !headers.iter().any(|(n, _)| *n == name) {
+            let value = response.all(&name).join(", ");
+            headers.push((name, value));
+        }
+    }
+    let body = response
+        .into_string()
+        .map_err(|e| format!("reading the response body: {}", e))?;
+    Ok(FetchResponse { status, headers, body })
+}
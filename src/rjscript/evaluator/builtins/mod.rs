@@ -1,11 +1,17 @@
-use crate::rjscript::{ast::position::Position, evaluator::{runtime::{eval_ctx::EvalCtx, value::RJSValue}, EvalResult}};
+use crate::rjscript::{ast::position::Position, evaluator::{runtime::{eval_ctx::EvalCtx, value::{FunctionValue, RJSValue}}, EvalResult}, semantics::types::VarType};
 
 pub mod core;
 pub mod stringcore;
 pub mod arraycore;
+pub mod objectcore;
+pub mod encoding;
+pub mod fetch;
 
 pub type BuiltinFn = fn(ctx: &EvalCtx, Vec<RJSValue>, Position) -> EvalResult<RJSValue>;
 /// Methods that do NOT mutate the receiver
 pub type PureMethodFn  = fn(&RJSValue, &[RJSValue], Position) -> EvalResult<RJSValue>;
-/// Methods that DO mutate the receiver
-pub type MutMethodFn   = fn(&mut RJSValue, &[RJSValue], Position) -> EvalResult<RJSValue>;
\ No newline at end of file
+/// Methods that DO mutate the receiver; they also get the receiver's declared type
+pub type MutMethodFn   = fn(&mut RJSValue, &VarType, &[RJSValue], Position) -> EvalResult<RJSValue>;/// Invokes a function value with the given arguments, in the caller's context
+pub type CallFn<'a> = dyn FnMut(&FunctionValue, Vec<RJSValue>, Position) -> EvalResult<RJSValue> + 'a;
+/// Methods that take function arguments (e.g. `map`); they do NOT mutate the receiver
+pub type CallbackMethodFn = fn(&RJSValue, &[RJSValue], &mut CallFn<'_>, Position) -> EvalResult<RJSValue>;
@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use crate::rjscript::{
+    ast::position::Position,
+    evaluator::{
+        builtins::{MutMethodFn, PureMethodFn}, errors::EvalError, runtime::value::RJSValue, EvalResult
+    },
+    semantics::{methods::{ObjectMethod, OBJECT_METHODS_META}, types::VarType}
+};
+
+static OBJECT_METHODS: OnceLock<Arc<HashMap<String, PureMethodFn>>> = OnceLock::new();
+static MUT_OBJECT_METHODS: OnceLock<Arc<HashMap<String, MutMethodFn>>> = OnceLock::new();
+
+fn object_method_pure_impl(m: ObjectMethod) -> PureMethodFn {
+    match m {
+        ObjectMethod::Has => object_has,
+        ObjectMethod::Get => object_get,
+        ObjectMethod::Set | ObjectMethod::Delete => {
+            unreachable!("mut object method asked as pure")
+        }
+    }
+}
+
+fn object_method_mut_impl(m: ObjectMethod) -> MutMethodFn {
+    match m {
+        ObjectMethod::Set => object_set,
+        ObjectMethod::Delete => object_delete,
+        ObjectMethod::Has | ObjectMethod::Get => {
+            unreachable!("pure object method asked as mut")
+        }
+    }
+}
+
+pub fn object_methods_table() -> Arc<HashMap<String, PureMethodFn>> {
+    OBJECT_METHODS
+        .get_or_init(|| {
+            let mut m = HashMap::new();
+            for (enum_key, meta) in OBJECT_METHODS_META {
+                if !meta.is_mut {
+                    m.insert(meta.name.to_string(), object_method_pure_impl(*enum_key));
+                }
+            }
+            Arc::new(m)
+        })
+        .clone()
+}
+
+pub fn object_mut_methods_table() -> Arc<HashMap<String, MutMethodFn>> {
+    MUT_OBJECT_METHODS
+        .get_or_init(|| {
+            let mut m = HashMap::new();
+            for (enum_key, meta) in OBJECT_METHODS_META {
+                if meta.is_mut {
+                    m.insert(meta.name.to_string(), object_method_mut_impl(*enum_key));
+                }
+            }
+            Arc::new(m)
+        })
+        .clone()
+}
+
+/// The key argument of `name`, which takes `arity` arguments.
+fn key_arg<'a>(name: &str, args: &'a [RJSValue], arity: u64, pos: Position) -> EvalResult<&'a str> {
+    if args.len() as u64 != arity {
+        return Err(EvalError::WrongNumberOfArguments(name.into(), arity, pos));
+    }
+    match &args[0] {
+        RJSValue::String(key) => Ok(key),
+        other => Err(EvalError::TypeMismatch(
+            format!("{}() key must be a string, got {}", name, other.to_type()),
+            pos,
+        )),
+    }
+}
+
+fn object_has(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let RJSValue::Object(map) = obj else {
+        unreachable!()
+    };
+    let key = key_arg("has", args, 1, pos)?;
+    Ok(RJSValue::Bool(map.contains_key(key)))
+}
+
+/// The value under `key`, or `undefined` when there is none.
+fn object_get(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let RJSValue::Object(map) = obj else {
+        unreachable!()
+    };
+    let key = key_arg("get", args, 1, pos)?;
+    Ok(map.get(key).cloned().unwrap_or(RJSValue::Undefined))
+}
+
+/// Adds or replaces `key`, and returns the value stored. A new key goes last.
+fn object_set(
+    target: &mut RJSValue,
+    _decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let key = key_arg("set", args, 2, pos)?;
+    if let RJSValue::Object(map) = target {
+        map.insert(key.to_string(), args[1].clone());
+        Ok(args[1].clone())
+    } else {
+        Err(EvalError::General("set() called on non-object".into(), pos))
+    }
+}
+
+/// Removes `key`, keeping the order of the other keys; false when it was not there.
+fn object_delete(
+    target: &mut RJSValue,
+    _decl_ty: &VarType,
+    args: &[RJSValue],
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let key = key_arg("delete", args, 1, pos)?;
+    if let RJSValue::Object(map) = target {
+        Ok(RJSValue::Bool(map.shift_remove(key).is_some()))
+    } else {
+        Err(EvalError::General("delete() called on non-object".into(), pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::rjscript::testing::{eval_expr_str, eval_script_str_with_world, TestWorld};
+
+    fn run(src: &str, body: Value) -> Result<(u16, RJSValue), String> {
+        eval_script_str_with_world(src, body, &TestWorld::new())
+    }
+
+    fn expr(src: &str) -> Result<RJSValue, String> {
+        eval_expr_str(src, &TestWorld::new())
+    }
+
+    #[test]
+    fn has_and_get_read_dynamic_keys() {
+        assert_eq!(expr("{ a: 1, b: null }.has(\"b\")"), Ok(RJSValue::Bool(true)));
+        assert_eq!(expr("{ a: 1 }.has(\"c\")"), Ok(RJSValue::Bool(false)));
+        assert_eq!(expr("{}.has(\"\")"), Ok(RJSValue::Bool(false)));
+        assert_eq!(expr("{ a: 1 }.get(\"a\")"), Ok(RJSValue::Number(1.0)));
+        assert_eq!(expr("{ a: 1 }.get(\"c\")"), Ok(RJSValue::Undefined));
+        let src = "let key: str = \"na\" + \"me\";\nlet user: obj = { name: \"Ann\" };\nreturn 200, [user.has(key), user.get(key)];";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("[true,\"Ann\"]".into()));
+    }
+
+    #[test]
+    fn set_and_delete_change_the_object() {
+        let src = "let user: obj = { name: \"Ann\", age: 31 };
+let set: num = user.set(\"age\", 32);
+user.set(\"email\", \"ann@x.io\");
+let removed: bool = user.delete(\"name\");
+let missing: bool = user.delete(\"name\");
+return 200, [set, removed, missing, user];";
+        assert_eq!(
+            run(src, json!(null)).map(|(_, v)| v.to_string()),
+            Ok("[32,true,false,{\"age\":32,\"email\":\"ann@x.io\"}]".into())
+        );
+        // a nested object is changed in place
+        let src = "let o: obj = { inner: { a: 1 } };\no.inner.set(\"b\", 2);\no.inner.delete(\"a\");\nreturn 200, o;";
+        assert_eq!(run(src, json!(null)).map(|(_, v)| v.to_string()), Ok("{\"inner\":{\"b\":2}}".into()));
+    }
+
+    #[test]
+    fn keys_must_be_strings() {
+        for (src, want) in [
+            ("{ a: 1 }.has(1)", "has() key must be a string, got num"),
+            ("{ a: 1 }.get(null)", "get() key must be a string, got null"),
+            ("{ a: 1 }.has()", "has"),
+            ("{ a: 1 }.get(\"a\", \"b\")", "get"),
+        ] {
+            let err = expr(src).unwrap_err();
+            assert!(err.contains(want), "{}: {}", src, err);
+        }
+        let err = run("let o: obj = {};\no.set(\"a\");\nreturn 200, o;", json!(null)).unwrap_err();
+        assert!(err.contains("set"), "{}", err);
+        let err = run("let o: obj = {};\no.delete(true);\nreturn 200, o;", json!(null)).unwrap_err();
+        assert!(err.contains("delete() key must be a string, got bool"), "{}", err);
+    }
+
+    #[test]
+    fn request_values_need_an_obj_guard() {
+        let body = json!({ "user": { "name": "Ann" } });
+        let err = run("return 200, req.body.user.has(\"name\");", body.clone()).unwrap_err();
+        assert!(err.contains("toType"), "{}", err);
+        let src = "if (toType(req.body.user) != obj) { return 400, null; }\nreturn 200, [req.body.user.has(\"name\"), req.body.user.get(\"name\")];";
+        assert_eq!(run(src, body).map(|(_, v)| v.to_string()), Ok("[true,\"Ann\"]".into()));
+    }
+}
@@ -3,6 +3,8 @@ use std::{
     sync::{Arc, OnceLock},
 };
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use crate::rjscript::{
     ast::position::Position, evaluator::{builtins::PureMethodFn, errors::EvalError, runtime::value::RJSValue, EvalResult}, semantics::methods::{StringMethod, STRING_METHODS_META}
 };
@@ -12,11 +14,17 @@ static STRING_METHODS: OnceLock<Arc<HashMap<String, PureMethodFn>>> = OnceLock::
 fn string_method_impl(m: StringMethod) -> PureMethodFn {
     match m {
         StringMethod::Length      => string_length,
+        StringMethod::GraphemeLength => string_grapheme_length,
         StringMethod::Contains => string_contains,
         StringMethod::Split => string_split,
         StringMethod::Substring        => string_substring,
         StringMethod::ToChars       => string_to_chars,
         StringMethod::Replace    => string_replace,
+        StringMethod::ToUpperCase => string_to_upper,
+        StringMethod::ToLowerCase => string_to_lower,
+        StringMethod::IndexOf => string_index_of,
+        StringMethod::ToNumber => string_to_number,
+        StringMethod::ToBool => string_to_bool,
     }
 }
 
@@ -49,6 +57,31 @@ fn string_contains(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResu
     }
 }
 
+/// Char index of the first occurrence of `needle`, or -1.
+fn string_index_of(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if args.len() != 1 {
+        return Err(EvalError::WrongNumberOfArguments("indexOf".into(), 1, pos));
+    }
+    let needle = match &args[0] {
+        RJSValue::String(n) => n,
+        other => {
+            return Err(EvalError::TypeMismatch(
+                format!("indexOf() argument must be a string, got {}", other.to_type()),
+                pos,
+            ));
+        }
+    };
+    let idx = match s.find(needle.as_str()) {
+        Some(byte_idx) => s[..byte_idx].chars().count() as f64,
+        None => -1.0,
+    };
+    Ok(RJSValue::Number(idx))
+}
+
 fn string_split(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
     if args.len() != 1 {
         return Err(EvalError::WrongNumberOfArguments("split".into(), 1, pos));
@@ -93,6 +126,41 @@ fn string_length(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult
     Ok(RJSValue::Number(s.chars().count() as f64))
 }
 
+/// Counts user-perceived characters (extended grapheme clusters), so a flag emoji
+/// or a letter with combining accents counts once.
+fn string_grapheme_length(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("graphemeLength".into(), 0, pos));
+    }
+    Ok(RJSValue::Number(s.graphemes(true).count() as f64))
+}
+
+fn string_to_upper(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("toUpperCase".into(), 0, pos));
+    }
+    Ok(RJSValue::String(s.to_uppercase()))
+}
+
+fn string_to_lower(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("toLowerCase".into(), 0, pos));
+    }
+    Ok(RJSValue::String(s.to_lowercase()))
+}
+
 fn string_to_chars(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
     if !args.is_empty() {
         return Err(EvalError::WrongNumberOfArguments(
@@ -148,13 +216,15 @@ fn string_substring(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalRes
     }
     if let RJSValue::String(s) = obj {
         if let (RJSValue::Number(st), RJSValue::Number(ed)) = (&args[0], &args[1]) {
+            // Indices count characters, like `length()`, not bytes
             let start = *st as usize;
             let end = *ed as usize;
-            if start <= end && end <= s.len() {
-                Ok(RJSValue::String(s[start..end].to_string()))
+            let len = s.chars().count();
+            if start <= end && end <= len {
+                Ok(RJSValue::String(s.chars().skip(start).take(end - start).collect()))
             } else {
                 Err(EvalError::General(
-                    "substring() indices out of bounds".into(),
+                    format!("substring() indices {}..{} out of bounds (len={})", start, end, len),
                     pos,
                 ))
             }
@@ -170,4 +240,171 @@ fn string_substring(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalRes
             pos,
         ))
     }
-}
\ No newline at end of file
+}
+
+/// Parses the (trimmed) string as a finite number, `"1.5"`, `"-2"` and `"1e3"` included.
+fn string_to_number(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("toNumber".into(), 0, pos));
+    }
+    match s.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(RJSValue::Number(n)),
+        _ => Err(EvalError::General(
+            format!("toNumber: cannot convert {:?} to a number", s),
+            pos,
+        )),
+    }
+}
+
+/// Accepts exactly `"true"` or `"false"`, ignoring surrounding whitespace.
+fn string_to_bool(obj: &RJSValue, args: &[RJSValue], pos: Position) -> EvalResult<RJSValue> {
+    let s = match obj {
+        RJSValue::String(s) => s,
+        _ => unreachable!(),
+    };
+    if !args.is_empty() {
+        return Err(EvalError::WrongNumberOfArguments("toBool".into(), 0, pos));
+    }
+    match s.trim() {
+        "true" => Ok(RJSValue::Bool(true)),
+        "false" => Ok(RJSValue::Bool(false)),
+        _ => Err(EvalError::General(
+            format!("toBool: cannot convert {:?} to a bool, expected \"true\" or \"false\"", s),
+            pos,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rjscript::{
+        evaluator::runtime::value::RJSValue,
+        testing::{eval_expr_str, eval_script_str_with_world, TestWorld},
+    };
+
+    fn eval(src: &str) -> Result<RJSValue, String> {
+        eval_expr_str(src, &TestWorld::new())
+    }
+
+    fn num(n: f64) -> Result<RJSValue, String> {
+        Ok(RJSValue::Number(n))
+    }
+
+    #[test]
+    fn grapheme_length_counts_what_a_reader_sees() {
+        for (s, chars, graphemes) in [
+            ("abc", 3.0, 3.0),
+            ("", 0.0, 0.0),
+            // a flag is two regional indicators
+            ("\\u{1F1F3}\\u{1F1F4}", 2.0, 1.0),
+            // e + combining acute accent
+            ("e\\u{301}", 2.0, 1.0),
+            ("cafe\\u{301}s", 6.0, 5.0),
+            // family emoji: three people joined by zero width joiners
+            ("\\u{1F468}\\u{200D}\\u{1F469}\\u{200D}\\u{1F467}", 5.0, 1.0),
+            ("\\r\\n", 2.0, 1.0),
+        ] {
+            assert_eq!(eval(&format!("\"{}\".length()", s)), num(chars), "{}", s);
+            assert_eq!(eval(&format!("\"{}\".graphemeLength()", s)), num(graphemes), "{}", s);
+        }
+        assert!(eval("\"a\".graphemeLength(1)").is_err());
+    }
+
+    fn string(s: &str) -> Result<RJSValue, String> {
+        Ok(RJSValue::String(s.into()))
+    }
+
+    #[test]
+    fn substring_indexes_characters() {
+        assert_eq!(eval("\"café\".substring(0, 4)"), string("café"));
+        assert_eq!(eval("\"café\".substring(3, 4)"), string("é"));
+        assert_eq!(eval("\"a😀b\".substring(1, 2)"), string("😀"));
+        assert_eq!(eval("\"a😀b\".substring(2, 3)"), string("b"));
+        assert_eq!(eval("\"日本語\".substring(1, 1)"), string(""));
+        // the flag's two code points can be split apart
+        assert_eq!(eval("\"\\u{1F1F3}\\u{1F1F4}\".substring(0, 1)"), string("\u{1F1F3}"));
+
+        for (call, message) in [
+            ("\"café\".substring(0, 5)", "substring() indices 0..5 out of bounds (len=4)"),
+            ("\"😀\".substring(1, 0)", "substring() indices 1..0 out of bounds (len=1)"),
+        ] {
+            let err = eval(call).unwrap_err();
+            assert!(err.starts_with(message), "{}", err);
+        }
+    }
+
+    #[test]
+    fn case_conversion_is_unicode_aware() {
+        assert_eq!(eval("\"straße\".toUpperCase()"), string("STRASSE"));
+        assert_eq!(eval("\"Ça Va, Ñandú\".toUpperCase()"), string("ÇA VA, ÑANDÚ"));
+        assert_eq!(eval("\"ÇA VA, ÑANDÚ\".toLowerCase()"), string("ça va, ñandú"));
+        // a final sigma lowercases differently
+        assert_eq!(eval("\"ΟΔΟΣ\".toLowerCase()"), string("οδος"));
+        assert_eq!(eval("\"ǅ\".toUpperCase()"), string("Ǆ"));
+        assert_eq!(eval("\"日本 123\".toLowerCase()"), string("日本 123"));
+    }
+
+    #[test]
+    fn case_conversion_needs_a_guarded_request_value() {
+        let world = TestWorld::new();
+        let body = serde_json::json!({ "name": "Ünïcode" });
+        let err = eval_script_str_with_world("return 200, req.body.name.toUpperCase();", body.clone(), &world)
+            .unwrap_err();
+        assert!(err.contains("toType"), "{}", err);
+
+        let src = "if (toType(req.body.name) != str) { return 400, null; }\nreturn 200, req.body.name.toLowerCase();";
+        assert_eq!(eval_script_str_with_world(src, body, &world), Ok((200, RJSValue::String("ünïcode".into()))));
+    }
+
+    #[test]
+    fn index_of_counts_characters() {
+        assert_eq!(eval("\"banana\".indexOf(\"an\")"), num(1.0));
+        assert_eq!(eval("\"banana\".indexOf(\"x\")"), num(-1.0));
+        assert_eq!(eval("\"banana\".indexOf(\"\")"), num(0.0));
+        // 5 characters in, 6 bytes
+        assert_eq!(eval("\"café au lait\".indexOf(\"au\")"), num(5.0));
+        assert_eq!(eval("\"😀😀!\".indexOf(\"!\")"), num(2.0));
+        // a number, so it can be computed with
+        assert_eq!(eval("\"key=value\".indexOf(\"=\") + 1"), num(4.0));
+        let err = eval("\"a\".indexOf(1)").unwrap_err();
+        assert!(err.starts_with("Type mismatch: 'indexOf() argument must be a string, got num'"), "{}", err);
+    }
+
+    #[test]
+    fn to_number_and_to_bool_parse_or_fail() {
+        assert_eq!(eval("\"42\".toNumber()"), num(42.0));
+        assert_eq!(eval("\" -1.5 \".toNumber()"), num(-1.5));
+        assert_eq!(eval("\"1e3\".toNumber() + 1"), num(1001.0));
+        assert_eq!(eval("\"a,7\".split(\",\")[1].toNumber()"), num(7.0));
+        for bad in ["", "abc", "1x", "inf", "NaN", "1e999"] {
+            let err = eval(&format!("\"{}\".toNumber()", bad)).unwrap_err();
+            assert!(err.contains(&format!("toNumber: cannot convert {:?} to a number", bad)), "{}", err);
+        }
+
+        assert_eq!(eval("\"true\".toBool()"), Ok(RJSValue::Bool(true)));
+        assert_eq!(eval("\"false\".toBool()"), Ok(RJSValue::Bool(false)));
+        for bad in ["True", "1", "yes", ""] {
+            let err = eval(&format!("\"{}\".toBool()", bad)).unwrap_err();
+            assert!(err.contains("expected \"true\" or \"false\""), "{}: {}", bad, err);
+        }
+    }
+
+    #[test]
+    fn conversions_need_a_guarded_request_value() {
+        let world = TestWorld::new();
+        let body = serde_json::json!({ "n": "5", "b": "true" });
+        for src in ["return 200, req.body.n.toNumber();", "return 200, req.body.b.toBool();"] {
+            let err = eval_script_str_with_world(src, body.clone(), &world).unwrap_err();
+            assert!(err.contains("toType"), "{}: {}", src, err);
+        }
+
+        let src = "if (toType(req.body.n) != str) { return 400, null; }
+if (toType(req.body.b) != str) { return 400, null; }
+return 200, [req.body.n.toNumber(), req.body.b.toBool()];";
+        assert_eq!(eval_script_str_with_world(src, body, &world).map(|(_, v)| v.to_string()), Ok("[5,true]".into()));
+    }
+}
@@ -13,15 +13,20 @@ impl BinOp {
                     s.push_str(b);
                     Ok(RJSValue::String(s))
                 }
+                (RJSValue::Array(a), RJSValue::Array(b)) => {
+                    let mut items = a.clone();
+                    items.extend(b.iter().cloned());
+                    Ok(RJSValue::Array(items))
+                }
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot add {:?} + {:?}", lv, rv),
+                    format!("Cannot add {} + {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::Sub => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Number(a - b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot subtract {:?} - {:?}", lv, rv),
+                    format!("Cannot subtract {} - {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
@@ -29,7 +34,7 @@ impl BinOp {
             BinOp::Mul => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Number(a * b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot multiply {:?} * {:?}", lv, rv),
+                    format!("Cannot multiply {} * {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
@@ -38,7 +43,7 @@ impl BinOp {
                 (RJSValue::Number(_), RJSValue::Number(0.0)) => Err(EvalError::DivisionByZero(pos)),
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Number(a / b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot divide {:?} / {:?}", lv, rv),
+                    format!("Cannot divide {} / {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
@@ -46,7 +51,7 @@ impl BinOp {
                 (RJSValue::Number(_), RJSValue::Number(0.0)) => Err(EvalError::DivisionByZero(pos)),
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Number(a % b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot modulo {:?} % {:?}", lv, rv),
+                    format!("Cannot modulo {} % {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
@@ -56,43 +61,47 @@ impl BinOp {
             BinOp::Ne => Ok(RJSValue::Bool(lv != rv)),
             BinOp::Lt => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Bool(a < b)),
+                (RJSValue::String(a), RJSValue::String(b)) => Ok(RJSValue::Bool(a < b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot compare {:?} < {:?}", lv, rv),
+                    format!("Cannot compare {} < {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::Le => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Bool(a <= b)),
+                (RJSValue::String(a), RJSValue::String(b)) => Ok(RJSValue::Bool(a <= b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot compare {:?} <= {:?}", lv, rv),
+                    format!("Cannot compare {} <= {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::Gt => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Bool(a > b)),
+                (RJSValue::String(a), RJSValue::String(b)) => Ok(RJSValue::Bool(a > b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot compare {:?} > {:?}", lv, rv),
+                    format!("Cannot compare {} > {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::Ge => match (&lv, &rv) {
                 (RJSValue::Number(a), RJSValue::Number(b)) => Ok(RJSValue::Bool(a >= b)),
+                (RJSValue::String(a), RJSValue::String(b)) => Ok(RJSValue::Bool(a >= b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot compare {:?} >= {:?}", lv, rv),
+                    format!("Cannot compare {} >= {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::And => match (&lv, &rv) {
                 (RJSValue::Bool(a), RJSValue::Bool(b)) => Ok(RJSValue::Bool(*a && *b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot apply && to {:?} and {:?}", lv, rv),
+                    format!("Cannot apply && to {} and {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
             BinOp::Or => match (&lv, &rv) {
                 (RJSValue::Bool(a), RJSValue::Bool(b)) => Ok(RJSValue::Bool(*a || *b)),
                 _ => Err(EvalError::TypeMismatch(
-                    format!("Cannot apply || to {:?} and {:?}", lv, rv),
+                    format!("Cannot apply || to {} and {}", lv.to_type(), rv.to_type()),
                     pos,
                 )),
             },
@@ -4,6 +4,10 @@ pub enum ControlFlow {
     None(Position),
     Break(Position),
     Continue(Position),
+    /// `fallthrough`, handled by the enclosing `switch`
+    Fallthrough(Position),
     Return(RJSValue, Position),
     ReturnStatus(u16, RJSValue, Position),
+    /// `throw <value>`, unwinding until a `try`/`catch` or the top level
+    Throw(RJSValue, Position),
 }
\ No newline at end of file
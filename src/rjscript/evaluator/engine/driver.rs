@@ -1,21 +1,96 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use crate::{http::request::Request, rjscript::{ast::block::Block, evaluator::{engine::controlflow::ControlFlow, errors::EvalError, runtime::{env::Env, eval_ctx::EvalCtx, request_cache::RequestCache, runtime_globals::RuntimeGlobals, value::RJSValue}, EvalResult}}};
+use crate::{http::{request::Request, status::check_status_code}, rjscript::{ast::{block::Block, position::Position, stmt::StmtKind}, evaluator::{engine::controlflow::ControlFlow, errors::EvalError, runtime::{env::{Env, EnvRef}, eval_ctx::{run_on_eval_stack, EvalCtx}, request_cache::RequestCache, runtime_globals::RuntimeGlobals, value::{ObjectMap, RJSValue}}, EvalResult}, semantics::types::VarType}};
 
 /// Evaluate top-level script
 pub fn eval_script(block: &Block, req: &Request) -> EvalResult<(u16, RJSValue)> {
-    let globals = RuntimeGlobals::get();
+    eval_script_with_globals(block, req, RuntimeGlobals::get())
+}
 
+/// Evaluate top-level script against the given globals instead of the process-wide ones
+pub fn eval_script_with_globals(
+    block: &Block,
+    req: &Request,
+    globals: Arc<RuntimeGlobals>,
+) -> EvalResult<(u16, RJSValue)> {
+    run_script(block, req, globals, None, 200)
+}
+
+/// Runs the config's `startup` script against an empty request and returns its
+/// status. Evaluation errors and error statuses (an uncaught throw included) fail it.
+pub fn run_startup_script(block: &Block, globals: Arc<RuntimeGlobals>) -> Result<u16, String> {
+    let req = Request::new(serde_json::Value::Null, HashMap::new(), HashMap::new(), HashMap::new());
+    let (status, body) = eval_script_with_globals(block, &req, globals)
+        .map_err(|e| format!("startup script failed: {}", e))?;
+    if status >= 400 {
+        return Err(format!(
+            "startup script failed with status {}: {}",
+            status,
+            RJSValue::rjs_to_json(&body)
+        ));
+    }
+    Ok(status)
+}
+
+/// Evaluate an `after` script on the response of a handler. The response is the
+/// `response` constant (`{ status, body }`); a `return` without a status keeps `status`.
+pub fn eval_after_script(
+    block: &Block,
+    req: &Request,
+    status: u16,
+    body: RJSValue,
+) -> EvalResult<(u16, RJSValue)> {
+    let response = RJSValue::Object(ObjectMap::from([
+        ("status".to_string(), RJSValue::Number(status as f64)),
+        ("body".to_string(), body),
+    ]));
+    run_script(block, req, RuntimeGlobals::get(), Some(response), status)
+}
+
+/// Runs `block`, with the `response` constant when given, on a thread with a known
+/// stack size; a plain `return value;` answers with `plain_status`.
+fn run_script(
+    block: &Block,
+    req: &Request,
+    globals: Arc<RuntimeGlobals>,
+    response: Option<RJSValue>,
+    plain_status: u16,
+) -> EvalResult<(u16, RJSValue)> {
+    run_on_eval_stack(|| {
+        let env = Env::new_ref();
+        if let Some(response) = response {
+            env.borrow_mut()
+                .declare_const("response", VarType::Object, response, Position::UNKNOWN)?;
+        }
+        eval_in_env(block, req, globals, &env, plain_status)
+    })
+    .map_err(|e| EvalError::General(format!("cannot start the script thread: {}", e), Position::UNKNOWN))?
+}
+
+fn eval_in_env(
+    block: &Block,
+    req: &Request,
+    globals: Arc<RuntimeGlobals>,
+    env: &EnvRef,
+    plain_status: u16,
+) -> EvalResult<(u16, RJSValue)> {
     // Per-request ctx
     let req_ctx = Arc::new(RequestCache::from_request(req.clone())?);
     let ctx = EvalCtx::new(globals, req_ctx);
 
-    let env = Env::new_ref();
+    hoist_functions(block, env)?;
+
+    let flow = match block.eval_block(&ctx, env) {
+        Err(EvalError::Thrown(v, pos)) => ControlFlow::Throw(v, pos),
+        other => other?,
+    };
 
-    match block.eval_block(&ctx, &env)? {
+    match flow {
         ControlFlow::ReturnStatus(code, v, _) => Ok((code, v)),
 
-        ControlFlow::Return(v, _) => Ok((200, v)),
+        ControlFlow::Throw(v, pos) => uncaught_throw_response(v, pos),
+
+        ControlFlow::Return(v, _) => Ok((plain_status, v)),
 
         ControlFlow::None(pos) => Err(EvalError::General(
             "Script must return a status code and a value, no return found".into(),
@@ -30,5 +105,78 @@ pub fn eval_script(block: &Block, req: &Request) -> EvalResult<(u16, RJSValue)>
             "Unexpected `continue` at top level".into(),
             pos,
         )),
+        ControlFlow::Fallthrough(pos) => Err(EvalError::General(
+            "Unexpected `fallthrough` outside of a switch case".into(),
+            pos,
+        )),
+    }
+}
+
+/// Registers every top-level function before running the script, so calls may
+/// appear above the declaration and functions can be mutually recursive.
+fn hoist_functions(block: &Block, env: &EnvRef) -> EvalResult<()> {
+    for stmt in &block.stmts {
+        if let StmtKind::FunctionDecl { .. } = stmt.kind {
+            stmt.define_function(env)?;
+        }
+    }
+    Ok(())
+}
+
+/// An uncaught `throw` becomes a 500 with the thrown value as body, unless the
+/// value is an object with a numeric `status` and a `body`, which are used as-is.
+fn uncaught_throw_response(value: RJSValue, pos: Position) -> EvalResult<(u16, RJSValue)> {
+    if let RJSValue::Object(map) = &value {
+        if let (Some(RJSValue::Number(status)), Some(body)) = (map.get("status"), map.get("body")) {
+            let code = check_status_code(*status).map_err(|msg| EvalError::General(msg, pos))?;
+            return Ok((code, body.clone()));
+        }
+    }
+    Ok((500, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::rjscript::{
+        evaluator::runtime::eval_ctx::DEFAULT_MAX_CALL_DEPTH,
+        testing::{eval_script_str_with_world, TestWorld},
+    };
+
+    fn run(src: &str) -> Result<(u16, String), String> {
+        eval_script_str_with_world(src, Value::Null, &TestWorld::new())
+            .map(|(status, v)| (status, v.to_string()))
+    }
+
+    // test threads have a small native stack, so these only pass because every
+    // evaluation gets the eval stack
+
+    #[test]
+    fn runaway_recursion_is_an_error() {
+        let err = run("func f(n: num): num { return f(n + 1) + 1; }\nreturn f(0);").unwrap_err();
+        let expected = format!("Maximum call depth of {} exceeded", DEFAULT_MAX_CALL_DEPTH);
+        assert!(err.contains(&expected), "{}", err);
+    }
+
+    #[test]
+    fn runaway_recursion_through_callbacks_is_an_error() {
+        let src = "func f(n: num): num {\n\
+                   let xs: vec<num> = [n];\n\
+                   return xs.map((x) => f(x + 1))[0];\n\
+                   }\n\
+                   return f(0);";
+        let err = run(src).unwrap_err();
+        assert!(err.contains("Maximum call depth"), "{}", err);
+    }
+
+    #[test]
+    fn recursion_up_to_the_limit_runs() {
+        let src = format!(
+            "func down(n: num): num {{ if (n == 0) {{ return 0; }} return down(n - 1) + 1; }}\n\
+             return down({});",
+            DEFAULT_MAX_CALL_DEPTH - 1
+        );
+        assert_eq!(run(&src), Ok((200, format!("{}", DEFAULT_MAX_CALL_DEPTH - 1))));
     }
-}
\ No newline at end of file
+}
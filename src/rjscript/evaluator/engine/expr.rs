@@ -1,14 +1,16 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{rc::Rc, sync::Arc};
 
 
 use crate::rjscript::{
         ast::{
+            binop::BinOp,
             expr::{Expr, ExprKind, TemplatePart},
             node::HasPos,
+            position::Position,
             request::RequestFieldType,
         },
         evaluator::{
-            engine::{controlflow::ControlFlow, lvalue::{navigate_mut_slot, resolve_var_and_path, LhsStep}}, errors::EvalError, runtime::{env::{Env, EnvRef}, eval_ctx::EvalCtx, runtime_globals::MethodImpl, value::RJSValue}, EvalResult
+            engine::{controlflow::ControlFlow, lvalue::{declared_slot_type, navigate_mut_slot, resolve_var_and_path, LhsStep}}, errors::EvalError, runtime::{env::{Env, EnvRef, UserFunction}, eval_ctx::{max_call_depth, EvalCtx}, runtime_globals::MethodImpl, value::{FunctionValue, ObjectMap, RJSValue}}, EvalResult
         },
     };
 
@@ -17,25 +19,12 @@ impl Expr {
         match &self.kind {
             ExprKind::Literal(lit) => Ok(RJSValue::from_literal(lit.clone())),
 
-            ExprKind::Template(parts) => {
-                // Evaluate each part and build a single string
-                let mut out = String::new();
-                for part in parts {
-                    match part {
-                        TemplatePart::Text(t) => out.push_str(t),
-                        TemplatePart::Expr(e) => {
-                            let v = e.eval_expr(ctx, env)?;
-                            out.push_str(&v.to_string());
-                        }
-                    }
-                }
-                Ok(RJSValue::String(out))
-            }
+            ExprKind::Template(parts) => self.eval_template(parts, ctx, env),
 
             ExprKind::TypeLiteral(ty) => Ok(RJSValue::Type(ty.clone())),
 
             ExprKind::ObjectLiteral { fields } => {
-                let mut map = HashMap::new();
+                let mut map = ObjectMap::new();
                 for (key, expr) in fields {
                     let v = expr.eval_expr(ctx, env)?;
                     map.insert(key.clone(), v);
@@ -60,38 +49,7 @@ impl Expr {
                 }
             }
 
-            ExprKind::BinaryOp { op, left, right } => {
-                let pos = self.pos();
-
-                match op {
-                    // Short-circuit AND: if left is false, return false without evaluating right
-                    crate::rjscript::ast::binop::BinOp::And => {
-                        let lv = left.eval_expr(ctx, env)?;
-                        if !lv.to_bool() {
-                            return Ok(RJSValue::Bool(false));
-                        }
-                        let rv = right.eval_expr(ctx, env)?;
-                        Ok(RJSValue::Bool(rv.to_bool()))
-                    }
-
-                    // Short-circuit OR: if left is true, return true without evaluating right
-                    crate::rjscript::ast::binop::BinOp::Or => {
-                        let lv = left.eval_expr(ctx, env)?;
-                        if lv.to_bool() {
-                            return Ok(RJSValue::Bool(true));
-                        }
-                        let rv = right.eval_expr(ctx, env)?;
-                        Ok(RJSValue::Bool(rv.to_bool()))
-                    }
-
-                    // All other binary ops keep the existing eager evaluation
-                    _ => {
-                        let lv = left.eval_expr(ctx, env)?;
-                        let rv = right.eval_expr(ctx, env)?;
-                        op.eval_binop(&lv, &rv, pos)
-                    }
-                }
-            }
+            ExprKind::BinaryOp { op, left, right } => self.eval_binary(op, left, right, ctx, env),
 
             // Array literal: evaluate all elements into a Vec<RJSValue>
             ExprKind::Array(elements) => {
@@ -113,113 +71,16 @@ impl Expr {
                 object,
                 property,
                 value,
-            } => {
-                let pos = self.pos();
-                let (root, mut path) = resolve_var_and_path(object, ctx, env)?;
-                path.push(LhsStep::Field(property.clone()));
-                let v = value.eval_expr(ctx, env)?;
-                let env_ref = Rc::clone(env);
-                Env::with_var_slot(&env_ref, &root, |_decl_ty, root_slot| {
-                    let slot = navigate_mut_slot(root_slot, &path, pos)?;
-                    *slot = v.clone();
-                    Ok(v.clone())
-                })
-                .ok_or_else(|| EvalError::VariableNotFound(root, pos))?
-            }
+            } => self.eval_assign_member(object, property, value, ctx, env),
 
             ExprKind::AssignIndex {
                 object,
                 index,
                 value,
-            } => {
-                // turn the index expr into a usize step and append
-                let pos = self.pos();
-                let (root, mut path) = resolve_var_and_path(object, ctx, env)?;
-                let idx_val = index.eval_expr(ctx, env)?;
-                let idx = match idx_val {
-                    RJSValue::Number(n) if n >= 0.0 => n as usize,
-                    RJSValue::Number(n) => {
-                        return Err(EvalError::General(format!("Negative index {}", n), pos))
-                    }
-                    other => {
-                        return Err(EvalError::TypeMismatch(
-                            format!("Index must be number, got {:?}", other),
-                            pos,
-                        ))
-                    }
-                };
-                path.push(LhsStep::Index(idx));
-
-                let v = value.eval_expr(ctx, env)?;
-                let env_ref = Rc::clone(env);
-                Env::with_var_slot(&env_ref, &root, |_decl_ty, root_slot| {
-                    let slot = navigate_mut_slot(root_slot, &path, pos)?;
-                    *slot = v.clone();
-                    Ok(v.clone())
-                })
-                .ok_or_else(|| EvalError::VariableNotFound(root, pos))?
-            }
+            } => self.eval_assign_index(object, index, value, ctx, env),
 
             // Indexing: first eval object, then index, then bound-check
-            ExprKind::Index { object, index } => {
-                let pos = self.pos();
-                let obj_val = object.eval_expr(ctx, env)?;
-                let idx_val = index.eval_expr(ctx, env)?;
-
-                // Ensure index is a non-negative number
-                match idx_val {
-                    RJSValue::Number(idx) => {
-                        if idx >= 0.0 {
-                            // Ensure we’re indexing into an array
-                            if let RJSValue::Array(arr) = obj_val {
-                                let arr_index = idx as usize;
-                                if arr_index < arr.len() {
-                                    Ok(arr[arr_index].clone())
-                                } else {
-                                    Err(EvalError::General(
-                                        format!("Index {} out of bounds (len={})", idx, arr.len()),
-                                        pos,
-                                    ))
-                                }
-                            } else {
-                                Err(EvalError::TypeMismatch(
-                                    format!(
-                                        "Cannot index into non-array or non-object value {:?}",
-                                        obj_val
-                                    ),
-                                    pos,
-                                ))
-                            }
-                        } else {
-                            return Err(EvalError::General(format!("Negative index {}", idx), pos));
-                        }
-                    }
-                    RJSValue::String(key) => {
-                        // Ensure we’re indexing into an array
-                        if let RJSValue::Object(obj) = obj_val {
-                            if let Some(value) = obj.get(&key) {
-                                Ok(value.clone())
-                            } else {
-                                Err(EvalError::General(
-                                    format!("Object doesn't contain key: {}", key),
-                                    pos,
-                                ))
-                            }
-                        } else {
-                            Err(EvalError::TypeMismatch(
-                                format!("Cannot search for key on non-object value {:?}", obj_val),
-                                pos,
-                            ))
-                        }
-                    }
-                    other => {
-                        return Err(EvalError::TypeMismatch(
-                            format!("Array index must be a number, got {:?}", other),
-                            pos,
-                        ));
-                    }
-                }
-            }
+            ExprKind::Index { object, index } => self.eval_index(object, index, ctx, env),
 
             // Property access: obj.prop
             ExprKind::Member { object, property } => {
@@ -234,137 +95,423 @@ impl Expr {
                 }
             }
 
-            ExprKind::Call { callee, args } => {
-                let pos = self.pos();
-                if let ExprKind::Member { object, property } = &callee.kind {
-                    // Evaluate receiver value and its type
-                    let obj_val = object.eval_expr(ctx, env)?;
-                    let recv_ty = obj_val.to_type();
-                    // Evaluate arguments now
-                    let arg_vals = args
-                        .iter()
-                        .map(|e| e.eval_expr(ctx, env))
-                        .collect::<Result<Vec<_>, _>>()?;
-
-                    // 2) resolve mut method in a short scope to drop the Ref<Env> immediately
-                    let mut_impl = ctx.globals.resolve_method(&recv_ty, property, /* wants_mut */ true);
-
-                    // Mutating methods first (only on owned variables, not request-derived)
-                    if let Some(MethodImpl::Mut(f)) = mut_impl {
-                        if object.is_request_derived() {
-                            return Err(EvalError::General(
-                                format!(
-                                    "Cannot call mutating method '{}' on request fields",
-                                    property
-                                ),
-                                pos,
-                            ));
-                        }
+            ExprKind::FunctionExpr {
+                params,
+                return_type,
+                body,
+            } => Ok(RJSValue::Function(Arc::new(FunctionValue {
+                params: params.clone(),
+                return_type: return_type.clone(),
+                body: body.clone(),
+            }))),
 
-                        let (root, path) = resolve_var_and_path(object, ctx, env)?;
-                        let env_ref = Rc::clone(env);
+            ExprKind::Call { callee, args } => self.eval_call(callee, args, ctx, env),
+        }
+    }
+    /// A binary operation; `&&` and `||` short-circuit.
+    fn eval_binary(
+        &self,
+        op: &BinOp,
+        left: &Expr,
+        right: &Expr,
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        let pos = self.pos();
 
-                        return Env::with_var_slot(&env_ref, &root, |_decl_ty, root_slot| {
-                            let target = navigate_mut_slot(root_slot, &path, pos)?;
-                            f(target, &arg_vals, pos)
-                        })
-                        .ok_or_else(|| EvalError::VariableNotFound(root.clone(), pos))?;
-                    }
+        match op {
+            // Short-circuit AND: if left is false, return false without evaluating right
+            BinOp::And => {
+                let lv = left.eval_expr(ctx, env)?;
+                if !lv.to_bool() {
+                    return Ok(RJSValue::Bool(false));
+                }
+                let rv = right.eval_expr(ctx, env)?;
+                Ok(RJSValue::Bool(rv.to_bool()))
+            }
 
-                    // 3) same for pure methods
-                    let pure_impl = ctx.globals.resolve_method(&recv_ty, property, /* wants_mut */ false);
+            // Short-circuit OR: if left is true, return true without evaluating right
+            BinOp::Or => {
+                let lv = left.eval_expr(ctx, env)?;
+                if lv.to_bool() {
+                    return Ok(RJSValue::Bool(true));
+                }
+                let rv = right.eval_expr(ctx, env)?;
+                Ok(RJSValue::Bool(rv.to_bool()))
+            }
 
-                    if let Some(MethodImpl::Pure(f)) = pure_impl {
-                        return f(&obj_val, &arg_vals, pos);
-                    }
+            // All other binary ops keep the existing eager evaluation
+            _ => {
+                let lv = left.eval_expr(ctx, env)?;
+                let rv = right.eval_expr(ctx, env)?;
+                op.eval_binop(&lv, &rv, pos)
+            }
+        }
+    }
+
+    /// Builds the string of a template literal.
+    fn eval_template(
+        &self,
+        parts: &[TemplatePart],
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        // Evaluate each part and build a single string
+        let mut out = String::new();
+        for part in parts {
+            match part {
+                TemplatePart::Text(t) => out.push_str(t),
+                TemplatePart::Expr(e) => {
+                    let v = e.eval_expr(ctx, env)?;
+                    out.push_str(&v.to_string());
                 }
+            }
+        }
+        Ok(RJSValue::String(out))
+    }
 
-                if let ExprKind::Ident(ref name) = callee.kind {
-                    // 1) built-in?
-                    if let Some(builtin) = ctx.globals.get_builtin(name).cloned() {
-                        // evaluate args…
-                        let evaluated = args
-                            .iter()
-                            .map(|e| e.eval_expr(ctx, env))
-                            .collect::<Result<Vec<_>, _>>()?;
-                        return builtin(ctx, evaluated, pos);
-                    }
+    /// `object.property = value`.
+    fn eval_assign_member(
+        &self,
+        object: &Expr,
+        property: &str,
+        value: &Expr,
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        let pos = self.pos();
+        let (root, mut path) = resolve_var_and_path(object, ctx, env)?;
+        if env.borrow().is_const(&root) {
+            return Err(EvalError::AssignToConst(root, pos));
+        }
+        path.push(LhsStep::Field(property.to_string()));
+        let v = value.eval_expr(ctx, env)?;
+        let env_ref = Rc::clone(env);
+        Env::with_var_slot(&env_ref, &root, |_decl_ty, root_slot| {
+            let slot = navigate_mut_slot(root_slot, &path, pos)?;
+            *slot = v.clone();
+            Ok(v.clone())
+        })
+        .ok_or_else(|| EvalError::VariableNotFound(root, pos))?
+    }
 
-                    // Then check user-defined functions
-                    if let Some(func) = env.borrow().get_fn(name) {
-                        if func.params.len() != args.len() {
-                            return Err(EvalError::General(
-                                format!(
-                                    "Expected {} args but got {}",
-                                    func.params.len(),
-                                    args.len()
-                                ),
-                                pos,
-                            ));
-                        }
-                        // 1) Evaluate all args into owned values (env borrow ends after collect)
-                        let arg_vals: Vec<RJSValue> = args
-                            .iter()
-                            .map(|arg| arg.eval_expr(ctx, env))
-                            .collect::<Result<_, EvalError>>()?;
-
-                        // 2) Now bind them into the new call_env
-                        let call_env = Env::push_scope(&func.env);
-                        for ((param_name, param_type), arg_val) in func.params.iter().zip(arg_vals)
-                        {
-                            if !arg_val.is_type(param_type) {
-                                return Err(EvalError::General(
-                                    format!(
-                                        "Type mismatch for {}: expected {:?}, got {:?}",
-                                        param_name, param_type, arg_val
-                                    ),
-                                    pos,
-                                ));
-                            }
-                            call_env.borrow_mut().declare_var(
-                                param_name,
-                                param_type.clone(),
-                                arg_val,
-                                pos,
-                            )?;
-                        }
+    /// `object[index] = value`.
+    fn eval_assign_index(
+        &self,
+        object: &Expr,
+        index: &Expr,
+        value: &Expr,
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        // turn the index expr into a usize step and append
+        let pos = self.pos();
+        let (root, mut path) = resolve_var_and_path(object, ctx, env)?;
+        if env.borrow().is_const(&root) {
+            return Err(EvalError::AssignToConst(root, pos));
+        }
+        let idx_val = index.eval_expr(ctx, env)?;
+        let idx = match idx_val {
+            RJSValue::Number(n) if n >= 0.0 => n as usize,
+            RJSValue::Number(n) => {
+                return Err(EvalError::General(format!("Negative index {}", n), pos))
+            }
+            other => {
+                return Err(EvalError::TypeMismatch(
+                    format!("Index must be number, got {}", other.to_type()),
+                    pos,
+                ))
+            }
+        };
+        path.push(LhsStep::Index(idx));
+
+        let v = value.eval_expr(ctx, env)?;
+        let env_ref = Rc::clone(env);
+        Env::with_var_slot(&env_ref, &root, |_decl_ty, root_slot| {
+            let slot = navigate_mut_slot(root_slot, &path, pos)?;
+            *slot = v.clone();
+            Ok(v.clone())
+        })
+        .ok_or_else(|| EvalError::VariableNotFound(root, pos))?
+    }
+
+    /// `object[index]`: an array element or an object field.
+    fn eval_index(
+        &self,
+        object: &Expr,
+        index: &Expr,
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        let pos = self.pos();
+        let obj_val = object.eval_expr(ctx, env)?;
+        let idx_val = index.eval_expr(ctx, env)?;
 
-                        match func.body.eval_block(ctx, &call_env)? {
-                            ControlFlow::Return(v, pos) => {
-                                if !v.is_type(&func.return_type) {
-                                    return Err(EvalError::General(
-                                        format!("Function '{}' returned type mismatch: expected {:?}, got {:?}",
-                                        name, func.return_type, v), pos));
-                                }
-                                return Ok(v);
-                            }
-                            ControlFlow::ReturnStatus(_, _, pos) => {
-                                // <- forbid status-returns here
-                                return Err(EvalError::General(
-                                    "`return status, body` is only allowed at the top level".into(),
-                                    pos,
-                                ));
-                            }
-                            ControlFlow::Break(pos) | ControlFlow::Continue(pos) => {
-                                return Err(EvalError::General(
-                                    "`break`/`continue` not allowed inside functions".into(),
-                                    pos,
-                                ));
-                            }
-                            ControlFlow::None(pos) => {
-                                return Err(EvalError::General(
-                                    format!("Function '{}' missing return value", name),
-                                    pos,
-                                ));
-                            }
+        // Ensure index is a non-negative number
+        match idx_val {
+            RJSValue::Number(idx) => {
+                if idx >= 0.0 {
+                    // Ensure we’re indexing into an array
+                    if let RJSValue::Array(arr) = obj_val {
+                        let arr_index = idx as usize;
+                        if arr_index < arr.len() {
+                            Ok(arr[arr_index].clone())
+                        } else {
+                            Err(EvalError::General(
+                                format!("Index {} out of bounds (len={})", idx, arr.len()),
+                                pos,
+                            ))
                         }
+                    } else {
+                        Err(EvalError::TypeMismatch(
+                            format!(
+                                "Cannot index into non-array or non-object value of type {}",
+                                obj_val.to_type()
+                            ),
+                            pos,
+                        ))
                     }
+                } else {
+                    Err(EvalError::General(format!("Negative index {}", idx), pos))
                 }
-                Err(EvalError::General(
-                    format!("Unknown function call: {:?}", callee),
+            }
+            RJSValue::String(key) => {
+                // Ensure we’re indexing into an array
+                if let RJSValue::Object(obj) = obj_val {
+                    if let Some(value) = obj.get(&key) {
+                        Ok(value.clone())
+                    } else {
+                        Err(EvalError::General(
+                            format!("Object doesn't contain key: {}", key),
+                            pos,
+                        ))
+                    }
+                } else {
+                    Err(EvalError::TypeMismatch(
+                        format!("Cannot search for key on non-object value of type {}", obj_val.to_type()),
+                        pos,
+                    ))
+                }
+            }
+            other => {
+                Err(EvalError::TypeMismatch(
+                    format!("Array index must be a number, got {}", other.to_type()),
                     pos,
                 ))
             }
         }
     }
+
+    /// A call of a method, builtin, declared function or function value.
+    fn eval_call(
+        &self,
+        callee: &Expr,
+        args: &[Expr],
+        ctx: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<RJSValue> {
+        let pos = self.pos();
+        if let ExprKind::Member { object, property } = &callee.kind {
+            // Evaluate receiver value and its type
+            let obj_val = object.eval_expr(ctx, env)?;
+            let recv_ty = obj_val.to_type();
+            // Evaluate arguments now
+            let arg_vals = args
+                .iter()
+                .map(|e| e.eval_expr(ctx, env))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // 2) resolve mut method in a short scope to drop the Ref<Env> immediately
+            let mut_impl = ctx.globals.resolve_method(&recv_ty, property, /* wants_mut */ true);
+
+            // Mutating methods first (only on owned variables, not request-derived)
+            if let Some(MethodImpl::Mut(f)) = mut_impl {
+                if object.is_request_derived() {
+                    return Err(EvalError::General(
+                        format!(
+                            "Cannot call mutating method '{}' on request fields",
+                            property
+                        ),
+                        pos,
+                    ));
+                }
+
+                let (root, path) = resolve_var_and_path(object, ctx, env)?;
+                if env.borrow().is_const(&root) {
+                    return Err(EvalError::AssignToConst(root, pos));
+                }
+                let env_ref = Rc::clone(env);
+
+                return Env::with_var_slot(&env_ref, &root, |decl_ty, root_slot| {
+                    let slot_ty = declared_slot_type(decl_ty, &path);
+                    let target = navigate_mut_slot(root_slot, &path, pos)?;
+                    f(target, &slot_ty, &arg_vals, pos)
+                })
+                .ok_or_else(|| EvalError::VariableNotFound(root.clone(), pos))?;
+            }
+
+            // 3) same for pure methods
+            let pure_impl = ctx.globals.resolve_method(&recv_ty, property, /* wants_mut */ false);
+
+            match pure_impl {
+                Some(MethodImpl::Pure(f)) => return f(&obj_val, &arg_vals, pos),
+                Some(MethodImpl::Callback(f)) => {
+                    let mut call = |func: &FunctionValue, call_args: Vec<RJSValue>, pos| {
+                        let func = UserFunction::from_value(func, env);
+                        call_user_function("<anonymous>", &func, call_args, ctx, pos)
+                    };
+                    return f(&obj_val, &arg_vals, &mut call, pos);
+                }
+                _ => {}
+            }
+        }
+
+        if let ExprKind::Ident(ref name) = callee.kind {
+            // 1) built-in?
+            if let Some(builtin) = ctx.globals.get_builtin(name).cloned() {
+                // evaluate args…
+                let evaluated = args
+                    .iter()
+                    .map(|e| e.eval_expr(ctx, env))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return builtin(ctx, evaluated, pos);
+            }
+
+            // Then check user-defined functions
+            let func = env.borrow().get_fn(name);
+            if let Some(func) = func {
+                // Evaluate all args into owned values (env borrow ends after collect)
+                let arg_vals: Vec<RJSValue> = args
+                    .iter()
+                    .map(|arg| arg.eval_expr(ctx, env))
+                    .collect::<Result<_, EvalError>>()?;
+                return call_user_function(name, &func, arg_vals, ctx, pos);
+            }
+
+            // Finally a variable holding a function value
+            let var = env.borrow().get_var(name);
+            if let Some((_, RJSValue::Function(func))) = var {
+                let arg_vals: Vec<RJSValue> = args
+                    .iter()
+                    .map(|arg| arg.eval_expr(ctx, env))
+                    .collect::<Result<_, EvalError>>()?;
+                let func = UserFunction::from_value(&func, env);
+                return call_user_function(name, &func, arg_vals, ctx, pos);
+            }
+        } else if !matches!(callee.kind, ExprKind::Member { .. }) {
+            // Any other callee must evaluate to a function value, e.g. `(func ...)(1)`
+            if let RJSValue::Function(func) = callee.eval_expr(ctx, env)? {
+                let arg_vals: Vec<RJSValue> = args
+                    .iter()
+                    .map(|arg| arg.eval_expr(ctx, env))
+                    .collect::<Result<_, EvalError>>()?;
+                let func = UserFunction::from_value(&func, env);
+                return call_user_function("<anonymous>", &func, arg_vals, ctx, pos);
+            }
+        }
+        Err(EvalError::General(
+            format!("Unknown function call: {:?}", callee),
+            pos,
+        ))
+    }
+}
+
+/// Binds the arguments in a fresh scope of `func`'s environment and runs its body.
+/// Missing trailing arguments take their default, evaluated in that scope so it
+/// can refer to the params bound before it.
+fn call_user_function(
+    name: &str,
+    func: &UserFunction,
+    arg_vals: Vec<RJSValue>,
+    ctx: &EvalCtx,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let max = func.params.len();
+    let min = func.params.iter().take_while(|p| p.default.is_none()).count();
+    if arg_vals.len() < min || arg_vals.len() > max {
+        let expected = if min == max {
+            max.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(EvalError::General(
+            format!("Expected {} args but got {}", expected, arg_vals.len()),
+            pos,
+        ));
+    }
+
+    // Fail cleanly instead of overflowing the native stack on runaway recursion
+    let depth = ctx.call_depth.get();
+    let max_depth = max_call_depth();
+    if depth >= max_depth {
+        return Err(EvalError::RecursionLimit(max_depth, pos));
+    }
+    ctx.call_depth.set(depth + 1);
+    let result = bind_and_run(name, func, arg_vals, ctx, pos);
+    ctx.call_depth.set(depth);
+    result
+}
+
+fn bind_and_run(
+    name: &str,
+    func: &UserFunction,
+    arg_vals: Vec<RJSValue>,
+    ctx: &EvalCtx,
+    pos: Position,
+) -> EvalResult<RJSValue> {
+    let call_env = Env::push_scope(&func.env);
+    let mut arg_vals = arg_vals.into_iter();
+    for param in &func.params {
+        let arg_val = match (arg_vals.next(), &param.default) {
+            (Some(v), _) => v,
+            (None, Some(default)) => default.eval_expr(ctx, &call_env)?,
+            (None, None) => unreachable!("arity checked above"),
+        };
+        if !arg_val.is_type(&param.ty) {
+            return Err(EvalError::General(
+                format!(
+                    "Type mismatch for {}: expected {}, got {}",
+                    param.name, param.ty, arg_val.to_type()
+                ),
+                pos,
+            ));
+        }
+        call_env
+            .borrow_mut()
+            .declare_var(&param.name, param.ty.clone(), arg_val, pos)?;
+    }
+
+    match func.body.eval_block(ctx, &call_env)? {
+        ControlFlow::Return(v, pos) => {
+            if !v.is_type(&func.return_type) {
+                return Err(EvalError::General(
+                    format!("Function '{}' returned type mismatch: expected {}, got {}",
+                    name, func.return_type, v.to_type()), pos));
+            }
+            Ok(v)
+        }
+        ControlFlow::ReturnStatus(_, _, pos) => {
+            // <- forbid status-returns here
+            Err(EvalError::General(
+                "`return status, body` is only allowed at the top level".into(),
+                pos,
+            ))
+        }
+        ControlFlow::Break(pos) | ControlFlow::Continue(pos) => Err(EvalError::General(
+            "`break`/`continue` not allowed inside functions".into(),
+            pos,
+        )),
+        ControlFlow::Fallthrough(pos) => Err(EvalError::General(
+            "`fallthrough` is only allowed at the end of a switch case".into(),
+            pos,
+        )),
+        ControlFlow::Throw(v, pos) => {
+            // keep unwinding through the expression evaluator
+            Err(EvalError::Thrown(v, pos))
+        }
+        ControlFlow::None(pos) => Err(EvalError::General(
+            format!("Function '{}' missing return value", name),
+            pos,
+        )),
+    }
 }
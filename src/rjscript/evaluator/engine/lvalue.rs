@@ -5,6 +5,7 @@ use crate::rjscript::evaluator::runtime::env::EnvRef;
 use crate::rjscript::evaluator::runtime::eval_ctx::EvalCtx;
 use crate::rjscript::evaluator::runtime::value::RJSValue;
 use crate::rjscript::evaluator::{errors::EvalError, EvalResult};
+use crate::rjscript::semantics::types::VarType;
 
 #[derive(Debug, Clone)]
 pub enum LhsStep {
@@ -57,7 +58,7 @@ pub fn resolve_var_and_path(
                     RJSValue::String(s) => steps.push(LhsStep::Field(s)),
                     other => {
                         return Err(EvalError::TypeMismatch(
-                            format!("Index must be number or string, got {:?}", other),
+                            format!("Index must be number or string, got {}", other.to_type()),
                             index.pos(),
                         ));
                     }
@@ -119,3 +120,16 @@ pub fn navigate_mut_slot<'a>(
     }
     Ok(cur)
 }
+
+/// Declared type of the slot reached by `path` from a root declared as `root_ty`.
+/// Object fields carry no declared type, so anything past a field step is `any`.
+pub fn declared_slot_type(root_ty: &VarType, path: &[LhsStep]) -> VarType {
+    let mut cur = root_ty.clone();
+    for step in path {
+        cur = match (step, cur) {
+            (LhsStep::Index(_), VarType::Array(inner)) => *inner,
+            _ => VarType::Any,
+        };
+    }
+    cur
+}
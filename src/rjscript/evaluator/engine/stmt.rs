@@ -1,46 +1,48 @@
+use crate::http::status::check_status_code;
 use crate::rjscript::{
         ast::{
+            block::Block,
+            expr::Expr,
             node::HasPos,
             stmt::{Stmt, StmtKind},
         },
         evaluator::{
             engine::controlflow::ControlFlow, errors::EvalError, runtime::{env::{Env, EnvRef, UserFunction}, eval_ctx::EvalCtx, value::RJSValue}, EvalResult
         },
+        semantics::types::VarType,
     };
 
 impl Stmt {
+    /// Registers a function declaration in `env`. The function's closure env has no
+    /// locals but shares the function table of `env`, so the body can call itself and
+    /// any other function of the script, including ones declared after it.
+    pub fn define_function(&self, env: &EnvRef) -> EvalResult<()> {
+        if let StmtKind::FunctionDecl {
+            ident,
+            params,
+            return_type,
+            body,
+        } = &self.kind
+        {
+            let func = UserFunction {
+                params: params.clone(),
+                return_type: return_type.clone(),
+                body: body.clone(),
+                env: Env::share_functions(env),
+            };
+            env.borrow_mut().define_fn(ident, func, self.pos())?;
+        }
+        Ok(())
+    }
+
     pub fn eval_stmt(&self, req: &EvalCtx, env: &EnvRef) -> EvalResult<ControlFlow> {
         match &self.kind {
-            StmtKind::FunctionDecl {
-                ident,
-                params,
-                return_type,
-                body,
-            } => {
-                let pos = self.pos();
-
-                // Capture current env for closure
-                let closure_env = Env::capture_functions_only(env);
-                let func = UserFunction {
-                    params: params.clone(),
-                    return_type: return_type.clone(),
-                    body: body.clone(),
-                    env: closure_env.clone(),
-                };
-
-                // 1) Insert into *its own* closure env so recursive calls resolve:
-                {
-                    let mut clo = closure_env.borrow_mut();
-                    clo.define_fn(ident, func.clone(), pos)?;
+            StmtKind::FunctionDecl { ident, .. } => {
+                // Normally already registered by `hoist_functions`
+                if env.borrow().get_fn(ident).is_none() {
+                    self.define_function(env)?;
                 }
-
-                // 2) Also insert into the *global* env so downstream code sees it:
-                {
-                    let mut global = env.borrow_mut();
-                    global.define_fn(ident, func.clone(), pos)?;
-                }
-
-                return Ok(ControlFlow::None(pos));
+                Ok(ControlFlow::None(self.pos()))
             }
             StmtKind::IfElse {
                 condition,
@@ -68,136 +70,235 @@ impl Stmt {
                 condition,
                 cases,
                 default,
-            } => {
-                let pos = self.pos();
-                // 1) evaluate discriminant
-                let dv = condition.eval_expr(req, env)?;
-
-                // 2) try each case in order
-                let mut hit = false;
-                for (case_expr, block) in cases {
-                    let cv = case_expr.eval_expr(req, env)?;
-                    if cv.eq(&dv) {
-                        hit = true;
-                        match block.eval_block(req, env)? {
-                            ControlFlow::None(_) => {}
-                            ControlFlow::Break(_) => {}
-                            other => return Ok(other), // ignore continue inside switch
-                        }
-                        break;
-                    }
-                }
-
-                // 3) if no case matched, run default (if any)
-                if !hit {
-                    if let Some(def_blk) = default {
-                        match def_blk.eval_block(req, env)? {
-                            ControlFlow::None(_) => {}
-                            other => return Ok(other),
-                        }
-                    }
-                }
-
-                return Ok(ControlFlow::None(pos));
-            }
+            } => self.eval_switch(condition, cases, default.as_ref(), req, env),
             StmtKind::For {
                 init,
                 condition,
                 increment,
                 body,
-            } => {
-                let pos = self.pos();
-                // Create a fresh child scope for the loop
-                let loop_env = Env::push_scope(&env);
+            } => self.eval_for(init.as_deref(), condition, increment.as_ref(), body, req, env),
+            StmtKind::Break => return Ok(ControlFlow::Break(self.pos())),
+            StmtKind::Fallthrough => Ok(ControlFlow::Fallthrough(self.pos())),
+            StmtKind::Continue => return Ok(ControlFlow::Continue(self.pos())),
+            StmtKind::Include(path) => {
+                // Includes are spliced in when the config is compiled
+                Err(EvalError::General(
+                    format!("`include \"{}\"` was not resolved before running the script", path),
+                    self.pos(),
+                ))
+            }
+            StmtKind::Return(expr) => {
+                let val = expr.eval_expr(req, env)?;
+                return Ok(ControlFlow::Return(val, self.pos()));
+            }
+            StmtKind::ReturnStatus{status, value} => self.eval_return_status(status, value, req, env),
+            StmtKind::Let{name, ty, init, mutable} => self.eval_let(name, ty, init.as_ref(), *mutable, req, env),
+            StmtKind::ExprStmt(expr) => {
+                expr.eval_expr(req, env)?;
+                Ok(ControlFlow::None(self.pos()))
+            }
+            StmtKind::Throw(expr) => {
+                let val = expr.eval_expr(req, env)?;
+                Ok(ControlFlow::Throw(val, self.pos()))
+            }
+            StmtKind::TryCatch {
+                try_block,
+                catch_name,
+                catch_block,
+            } => self.eval_try_catch(try_block, catch_name, catch_block, req, env),
+        }
+    }
 
-                // run initializer once
-                if let Some(init_stmt) = init {
-                    init_stmt.eval_stmt(req, &loop_env)?;
+    /// Runs the matching case, and the ones it falls through to.
+    fn eval_switch(
+        &self,
+        condition: &Expr,
+        cases: &[(Vec<Expr>, Block)],
+        default: Option<&Block>,
+        req: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<ControlFlow> {
+        let pos = self.pos();
+        // 1) evaluate discriminant
+        let dv = condition.eval_expr(req, env)?;
+
+        // 2) find the first case with a value equal to the discriminant
+        let mut hit = None;
+        'find: for (idx, (case_exprs, _)) in cases.iter().enumerate() {
+            for case_expr in case_exprs {
+                let cv = case_expr.eval_expr(req, env)?;
+                if cv.eq(&dv) {
+                    hit = Some(idx);
+                    break 'find;
                 }
+            }
+        }
 
-                // loop
-                loop {
-                    // evaluate condition
-                    let cond_v = condition.eval_expr(req, &loop_env)?;
-                    if !cond_v.to_bool() {
-                        break;
-                    }
+        // 3) run it, then the following blocks for as long as they end in
+        //    `fallthrough`; the default comes after the last case.
+        //    With no match, only the default runs.
+        let start = hit.unwrap_or(cases.len());
+        let blocks = cases[start..]
+            .iter()
+            .map(|(_, block)| block)
+            .chain(default);
+        for block in blocks {
+            match block.eval_block(req, env)? {
+                ControlFlow::Fallthrough(_) => continue,
+                ControlFlow::None(_) | ControlFlow::Break(_) => break,
+                other => return Ok(other), // ignore continue inside switch
+            }
+        }
 
-                    let body_env = Env::push_scope(&loop_env);
-
-                    // body
-                    match body.eval_block(req, &body_env)? {
-                        ControlFlow::Break(_) => break,
-                        ControlFlow::Continue(_) => {
-                            if let Some(inc_e) = increment {
-                                inc_e.eval_expr(req, &loop_env)?;
-                            }
-                            continue;
-                        }
-                        ControlFlow::Return(v, pos) => return Ok(ControlFlow::Return(v, pos)),
-                        ControlFlow::ReturnStatus(code, v, pos) => {
-                            return Ok(ControlFlow::ReturnStatus(code, v, pos))
-                        }
-                        ControlFlow::None(_) => {}
-                    }
+        Ok(ControlFlow::None(pos))
+    }
+
+    /// Runs a C-style `for` loop in its own scope.
+    fn eval_for(
+        &self,
+        init: Option<&Stmt>,
+        condition: &Expr,
+        increment: Option<&Expr>,
+        body: &Block,
+        req: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<ControlFlow> {
+        let pos = self.pos();
+        // Create a fresh child scope for the loop
+        let loop_env = Env::push_scope(env);
+
+        // run initializer once
+        if let Some(init_stmt) = init {
+            init_stmt.eval_stmt(req, &loop_env)?;
+        }
 
-                    // increment
-                    if let Some(inc_expr) = increment {
-                        // ignore its value; side-effects only
-                        inc_expr.eval_expr(req, &loop_env)?;
+        // loop
+        loop {
+            // evaluate condition
+            let cond_v = condition.eval_expr(req, &loop_env)?;
+            if !cond_v.to_bool() {
+                break;
+            }
+
+            let body_env = Env::push_scope(&loop_env);
+
+            // body
+            match body.eval_block(req, &body_env)? {
+                ControlFlow::Break(_) => break,
+                ControlFlow::Continue(_) => {
+                    if let Some(inc_e) = increment {
+                        inc_e.eval_expr(req, &loop_env)?;
                     }
+                    continue;
                 }
-                Ok(ControlFlow::None(pos))
+                ControlFlow::Return(v, pos) => return Ok(ControlFlow::Return(v, pos)),
+                ControlFlow::ReturnStatus(code, v, pos) => {
+                    return Ok(ControlFlow::ReturnStatus(code, v, pos))
+                }
+                ControlFlow::Throw(v, pos) => return Ok(ControlFlow::Throw(v, pos)),
+                ControlFlow::Fallthrough(pos) => return Ok(ControlFlow::Fallthrough(pos)),
+                ControlFlow::None(_) => {}
             }
-            StmtKind::Break => return Ok(ControlFlow::Break(self.pos())),
-            StmtKind::Continue => return Ok(ControlFlow::Continue(self.pos())),
-            StmtKind::Return(expr) => {
-                let val = expr.eval_expr(req, env)?;
-                return Ok(ControlFlow::Return(val, self.pos()));
+
+            // increment
+            if let Some(inc_expr) = increment {
+                // ignore its value; side-effects only
+                inc_expr.eval_expr(req, &loop_env)?;
             }
-            StmtKind::ReturnStatus{status, value} => {
-                let pos = self.pos();
-                let status_val = status.eval_expr(req, env)?;
-                let status_num = match status_val {
-                    RJSValue::Number(n) => n as u16,
-                    other => {
-                        return Err(EvalError::TypeMismatch(
-                            format!("Status code must be a number, got {:?}", other),
-                            pos,
-                        ));
-                    }
-                };
-                let response_val = value.eval_expr(req, env)?;
+        }
+        Ok(ControlFlow::None(pos))
+    }
 
-                return Ok(ControlFlow::ReturnStatus(status_num, response_val, pos));
+    /// `return status, value;`
+    fn eval_return_status(
+        &self,
+        status: &Expr,
+        value: &Expr,
+        req: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<ControlFlow> {
+        let pos = self.pos();
+        let status_val = status.eval_expr(req, env)?;
+        let status_num = match status_val {
+            RJSValue::Number(n) => {
+                check_status_code(n).map_err(|msg| EvalError::General(msg, pos))?
             }
-            StmtKind::Let{name, ty, init} => {
-                let pos = self.pos();
-                // Evaluate or default to Undefined
-                let val = if let Some(expr) = init {
-                    let v = expr.eval_expr(req, env)?;
-                    if !v.is_type(&ty) {
-                        return Err(EvalError::General(
-                            format!(
-                                "Type mismatch initializing {}: expected {:?}, got {:?}",
-                                name, ty, v
-                            ),
-                            pos,
-                        ));
-                    }
-                    v
-                } else {
-                    // no initializer → undefined
-                    RJSValue::Undefined
-                };
-                env.borrow_mut()
-                    .declare_var(&name.clone(), ty.clone(), val, pos)?;
-                Ok(ControlFlow::None(pos))
+            other => {
+                return Err(EvalError::TypeMismatch(
+                    format!("Status code must be a number, got {}", other.to_type()),
+                    pos,
+                ));
             }
-            StmtKind::ExprStmt(expr) => {
-                expr.eval_expr(req, env)?;
-                Ok(ControlFlow::None(self.pos()))
+        };
+        let response_val = value.eval_expr(req, env)?;
+
+        Ok(ControlFlow::ReturnStatus(status_num, response_val, pos))
+    }
+
+    /// Declares a `let` or `const`.
+    fn eval_let(
+        &self,
+        name: &str,
+        ty: &VarType,
+        init: Option<&Expr>,
+        mutable: bool,
+        req: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<ControlFlow> {
+        let pos = self.pos();
+        // Evaluate or default to Undefined
+        let val = if let Some(expr) = init {
+            let v = expr.eval_expr(req, env)?;
+            if !v.is_type(ty) {
+                return Err(EvalError::General(
+                    format!(
+                        "Type mismatch initializing {}: expected {}, got {}",
+                        name, ty, v.to_type()
+                    ),
+                    pos,
+                ));
             }
+            v
+        } else {
+            // no initializer → undefined
+            RJSValue::Undefined
+        };
+        if mutable {
+            env.borrow_mut()
+                .declare_var(name, ty.clone(), val, pos)?;
+        } else {
+            env.borrow_mut()
+                .declare_const(name, ty.clone(), val, pos)?;
+        }
+        Ok(ControlFlow::None(pos))
+    }
+
+    /// Runs `try_block`, and `catch_block` with what it throws.
+    fn eval_try_catch(
+        &self,
+        try_block: &Block,
+        catch_name: &str,
+        catch_block: &Block,
+        req: &EvalCtx,
+        env: &EnvRef,
+    ) -> EvalResult<ControlFlow> {
+        let pos = self.pos();
+        let try_env = Env::push_scope(env);
+        // Throws surface either as control flow (same function) or as an
+        // error (escaped from a function call); both are caught here.
+        let thrown = match try_block.eval_block(req, &try_env) {
+            Ok(ControlFlow::Throw(v, _)) | Err(EvalError::Thrown(v, _)) => v,
+            Ok(ControlFlow::None(_)) => return Ok(ControlFlow::None(pos)),
+            other => return other,
+        };
+
+        let catch_env = Env::push_scope(env);
+        catch_env
+            .borrow_mut()
+            .declare_var(catch_name, thrown.to_type(), thrown, pos)?;
+        match catch_block.eval_block(req, &catch_env)? {
+            ControlFlow::None(_) => Ok(ControlFlow::None(pos)),
+            other => Ok(other),
         }
     }
 }
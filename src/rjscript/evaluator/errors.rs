@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::rjscript::ast::position::Position;
+use crate::rjscript::{ast::position::Position, evaluator::runtime::value::RJSValue};
 
 #[derive(Debug)]
 pub enum EvalError {
@@ -11,6 +11,27 @@ pub enum EvalError {
     TypeMismatch(String, Position),
     DivisionByZero(Position),
     WrongNumberOfArguments(String, u64, Position),
+    /// A call outside a `min..=max` argument range; no `max` for variadic functions
+    WrongArity {
+        name: String,
+        min: u64,
+        max: Option<u64>,
+        pos: Position,
+    },
+    AssignToConst(String, Position),
+    /// A `throw` that escaped a function call; caught like `ControlFlow::Throw`
+    Thrown(RJSValue, Position),
+    /// An intended non-2xx response from `error(status, body)`: not caught by
+    /// `try`, and sent as-is instead of the blanket 500 of the other errors
+    Http {
+        status: u16,
+        body: RJSValue,
+        pos: Position,
+    },
+    /// A failed `assert(cond, message?)`: a broken assumption of the script, not caught by `try`
+    AssertionFailed(String, Position),
+    /// User function calls nested deeper than the configured maximum
+    RecursionLimit(usize, Position),
     General(String, Position),
 }
 
@@ -33,10 +54,33 @@ impl fmt::Display for EvalError {
                 write!(f, "Type mismatch: '{}', at: {}:{}", field, pos.line, pos.column)
             },
             EvalError::DivisionByZero(pos) => write!(f, "Division by zero, at: {}:{}", pos.line, pos.column),
-            EvalError::WrongNumberOfArguments(method, arg_number, pos) => write!(f, "'{}()' expects exactly {} arguments, at: {}:{}", method, arg_number, pos.line, pos.column),
+            EvalError::WrongNumberOfArguments(method, arg_number, pos) => write!(f, "'{}()' expects exactly {} {}, at: {}:{}", method, arg_number, plural_args(*arg_number), pos.line, pos.column),
+            EvalError::WrongArity { name, min, max, pos } => match max {
+                Some(max) => write!(f, "'{}()' expects {} to {} arguments, at: {}:{}", name, min, max, pos.line, pos.column),
+                None => write!(f, "'{}()' expects at least {} {}, at: {}:{}", name, min, plural_args(*min), pos.line, pos.column),
+            },
+            EvalError::AssignToConst(name, pos) => {
+                write!(f, "Cannot assign to const '{}', at: {}:{}", name, pos.line, pos.column)
+            },
+            EvalError::Thrown(value, pos) => {
+                write!(f, "Uncaught throw: {}, at: {}:{}", value.to_string(), pos.line, pos.column)
+            },
+            EvalError::Http { status, body, pos } => {
+                write!(f, "Error response {}: {}, at: {}:{}", status, body.to_string(), pos.line, pos.column)
+            },
+            EvalError::AssertionFailed(msg, pos) => {
+                write!(f, "Assertion failed: {}, at: {}:{}", msg, pos.line, pos.column)
+            },
+            EvalError::RecursionLimit(max, pos) => {
+                write!(f, "Maximum call depth of {} exceeded, at: {}:{}", max, pos.line, pos.column)
+            },
             EvalError::General(msg, pos) => write!(f, "{}, at: {}:{}", msg, pos.line, pos.column),
         }
     }
 }
 
 impl std::error::Error for EvalError {}
+
+fn plural_args(n: u64) -> &'static str {
+    if n == 1 { "argument" } else { "arguments" }
+}
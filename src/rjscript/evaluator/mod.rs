@@ -1,6 +1,6 @@
 pub mod engine;
 pub mod runtime;
 pub mod errors;
-mod builtins;
+pub(crate) mod builtins;
 
 pub type EvalResult<T>  = std::result::Result<T, crate::rjscript::evaluator::errors::EvalError>;
\ No newline at end of file
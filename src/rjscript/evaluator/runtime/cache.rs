@@ -1,38 +1,124 @@
 use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 
 use crate::rjscript::evaluator::runtime::value::RJSValue;
 
+/// Source of the current time, replaceable so expiry can be tested without sleeping.
+pub type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+#[derive(Clone)]
+struct CacheEntry {
+    value: RJSValue,
+    /// `None` never expires
+    expires_at: Option<Instant>,
+}
+
+impl CacheEntry {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|at| now < at)
+    }
+}
+
 #[derive(Clone)]
 pub struct GlobalCache {
-    map: Arc<RwLock<HashMap<String, RJSValue>>>,
+    map: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    clock: Clock,
 }
 
 impl GlobalCache {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(Instant::now))
+    }
+
+    pub fn with_clock(clock: Clock) -> Self {
         Self {
             map: Arc::new(RwLock::new(HashMap::new())),
+            clock,
         }
     }
 
+    /// Expired entries are treated as missing and removed; the rest are dropped on
+    /// the next `set`.
     pub fn get(&self, key: &str) -> Option<RJSValue> {
-        self.map.read().ok().and_then(|guard| guard.get(key).cloned())
+        let now = (self.clock)();
+        let expired = {
+            let guard = self.map.read().ok()?;
+            match guard.get(key) {
+                Some(e) if e.is_live(now) => return Some(e.value.clone()),
+                found => found.is_some(),
+            }
+        };
+        if expired {
+            self.remove_expired(key, now);
+        }
+        None
+    }
+
+    /// Time left before the entry at `key` expires; `None` if it is missing or
+    /// never expires.
+    pub fn ttl(&self, key: &str) -> Option<Duration> {
+        let now = (self.clock)();
+        let guard = self.map.read().ok()?;
+        let entry = guard.get(key).filter(|e| e.is_live(now))?;
+        entry.expires_at.map(|at| at.duration_since(now))
+    }
+
+    fn remove_expired(&self, key: &str, now: Instant) {
+        if let Ok(mut guard) = self.map.write() {
+            // it may have been set again since the read lock was released
+            if guard.get(key).is_some_and(|e| !e.is_live(now)) {
+                guard.remove(key);
+            }
+        }
     }
 
     pub fn has(&self, key: &str) -> bool {
-        self.map.read().map(|guard| guard.contains_key(key)).unwrap_or(false)
+        self.get(key).is_some()
     }
 
     pub fn set(&self, key: String, value: RJSValue) {
+        self.insert(key, value, None);
+    }
+
+    /// Like `set`, but the entry is gone once `ttl` has passed.
+    pub fn set_with_ttl(&self, key: String, value: RJSValue, ttl: Duration) {
+        let expires_at = (self.clock)().checked_add(ttl);
+        self.insert(key, value, expires_at);
+    }
+
+    fn insert(&self, key: String, value: RJSValue, expires_at: Option<Instant>) {
+        let now = (self.clock)();
         if let Ok(mut guard) = self.map.write() {
-            guard.insert(key, value);
+            guard.retain(|_, e| e.is_live(now));
+            guard.insert(key, CacheEntry { value, expires_at });
         }
     }
 
     pub fn del(&self, key: &str) -> bool {
-        self.map.write().ok().and_then(|mut guard| guard.remove(key)).is_some()
+        let now = (self.clock)();
+        self.map
+            .write()
+            .ok()
+            .and_then(|mut guard| guard.remove(key))
+            .is_some_and(|e| e.is_live(now))
+    }
+
+    /// Copy of all live entries, for inspection.
+    pub fn snapshot(&self) -> HashMap<String, RJSValue> {
+        let now = (self.clock)();
+        self.map
+            .read()
+            .map(|guard| {
+                guard
+                    .iter()
+                    .filter(|(_, e)| e.is_live(now))
+                    .map(|(k, e)| (k.clone(), e.value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn clear(&self) {
@@ -41,3 +127,143 @@ impl GlobalCache {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::rjscript::{
+        evaluator::runtime::world::ScriptWorld,
+        testing::{eval_expr_str, TestWorld},
+    };
+
+    /// A cache whose clock only moves when the returned handle is advanced.
+    fn frozen() -> (GlobalCache, impl Fn(u64)) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let clock_now = Arc::clone(&now);
+        let cache = GlobalCache::with_clock(Arc::new(move || *clock_now.lock().unwrap()));
+        let advance = move |secs| *now.lock().unwrap() += Duration::from_secs(secs);
+        (cache, advance)
+    }
+
+    fn num(n: f64) -> RJSValue {
+        RJSValue::Number(n)
+    }
+
+    fn stored(cache: &GlobalCache) -> usize {
+        cache.map.read().unwrap().len()
+    }
+
+    #[test]
+    fn set_get_del_round_trip() {
+        let (cache, _) = frozen();
+        cache.set("k".into(), num(1.0));
+        assert_eq!(cache.get("k"), Some(num(1.0)));
+        assert!(cache.has("k"));
+        assert_eq!(cache.ttl("k"), None);
+        assert!(cache.del("k"));
+        assert!(!cache.del("k"));
+        assert_eq!(cache.get("k"), None);
+    }
+
+    #[test]
+    fn entry_expires_at_its_deadline() {
+        let (cache, advance) = frozen();
+        cache.set_with_ttl("k".into(), num(1.0), Duration::from_secs(10));
+        assert_eq!(cache.ttl("k"), Some(Duration::from_secs(10)));
+
+        advance(9);
+        assert_eq!(cache.get("k"), Some(num(1.0)));
+        assert_eq!(cache.ttl("k"), Some(Duration::from_secs(1)));
+
+        advance(1);
+        assert_eq!(cache.get("k"), None);
+        assert_eq!(cache.ttl("k"), None);
+        assert!(!cache.has("k"));
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let (cache, advance) = frozen();
+        cache.set_with_ttl("a".into(), num(1.0), Duration::from_secs(1));
+        cache.set_with_ttl("b".into(), num(2.0), Duration::from_secs(1));
+        cache.set("keep".into(), num(3.0));
+        advance(1);
+
+        // reading an expired key removes it, a write sweeps the rest
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(stored(&cache), 2);
+        assert!(!cache.del("missing"));
+        cache.set("c".into(), num(4.0));
+        assert_eq!(stored(&cache), 2);
+        assert_eq!(cache.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn del_of_expired_entry_reports_missing() {
+        let (cache, advance) = frozen();
+        cache.set_with_ttl("k".into(), num(1.0), Duration::from_secs(1));
+        advance(2);
+        assert!(!cache.del("k"));
+        assert_eq!(stored(&cache), 0);
+    }
+
+    #[test]
+    fn set_replaces_the_ttl() {
+        let (cache, advance) = frozen();
+        cache.set_with_ttl("k".into(), num(1.0), Duration::from_secs(1));
+        cache.set("k".into(), num(2.0));
+        advance(5);
+        assert_eq!(cache.get("k"), Some(num(2.0)));
+
+        cache.set_with_ttl("k".into(), num(3.0), Duration::from_secs(1));
+        advance(1);
+        assert_eq!(cache.get("k"), None);
+    }
+
+    #[test]
+    fn ttl_bounds() {
+        let (cache, advance) = frozen();
+        cache.set_with_ttl("zero".into(), num(1.0), Duration::ZERO);
+        assert_eq!(cache.get("zero"), None);
+
+        // past what an `Instant` can hold: never expires
+        cache.set_with_ttl("forever".into(), num(1.0), Duration::MAX);
+        advance(u32::MAX as u64);
+        assert_eq!(cache.get("forever"), Some(num(1.0)));
+        assert_eq!(cache.ttl("forever"), None);
+    }
+
+    #[test]
+    fn cache_set_with_a_huge_ttl_never_expires() {
+        let world = TestWorld::new();
+        for (key, secs) in [("a", "1e30"), ("b", "1.8e19"), ("c", "0x7FFFFFFFFFFFFFFF")] {
+            let src = format!("cacheSet(\"{}\", 1, {})", key, secs);
+            assert_eq!(eval_expr_str(&src, &world), Ok(RJSValue::Undefined), "{}", src);
+            assert_eq!(world.cache().get(key), Some(num(1.0)));
+            assert_eq!(world.cache().ttl(key), None);
+        }
+    }
+
+    #[test]
+    fn cache_set_rejects_bad_ttls() {
+        let world = TestWorld::new();
+        for ttl in ["-1", "\"60\"", "req.body"] {
+            let src = format!("cacheSet(\"k\", 1, {})", ttl);
+            let err = eval_expr_str(&src, &world).unwrap_err();
+            assert!(err.contains("ttlSeconds must be a non-negative number"), "{}: {}", src, err);
+        }
+        assert!(world.cache_entries().is_empty());
+    }
+
+    #[test]
+    fn clones_share_entries() {
+        let (cache, _) = frozen();
+        let other = cache.clone();
+        cache.set("k".into(), num(1.0));
+        assert_eq!(other.get("k"), Some(num(1.0)));
+        other.clear();
+        assert!(cache.snapshot().is_empty());
+    }
+}
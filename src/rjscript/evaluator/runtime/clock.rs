@@ -0,0 +1,138 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of wall-clock time for `now()` and `timestamp()`, replaceable so time-dependent scripts
+/// can be evaluated against a fixed instant.
+pub type WallClock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+pub fn system_clock() -> WallClock {
+    Arc::new(SystemTime::now)
+}
+
+/// Clock of the process-wide globals: stopped at `RJS_FROZEN_TIME` (milliseconds since the
+/// UNIX epoch) when it is set, so time-dependent responses are reproducible, else the system clock.
+pub fn clock_from_env() -> WallClock {
+    clock_frozen_at(std::env::var("RJS_FROZEN_TIME").ok().as_deref())
+}
+
+/// Clock stopped at the instant named by a `RJS_FROZEN_TIME` value, or the system clock
+/// when it is unset or not a u64.
+fn clock_frozen_at(value: Option<&str>) -> WallClock {
+    match value.and_then(|s| s.trim().parse::<u64>().ok()) {
+        Some(ms) => {
+            let at = UNIX_EPOCH + Duration::from_millis(ms);
+            Arc::new(move || at)
+        }
+        None => system_clock(),
+    }
+}
+
+/// Milliseconds since the UNIX epoch; instants before it are negative.
+pub fn epoch_ms(at: SystemTime) -> f64 {
+    match at.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as f64,
+        Err(e) => -(e.duration().as_millis() as f64),
+    }
+}
+
+/// A UTC calendar date and time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub millis: u32,
+}
+
+impl UtcDateTime {
+    /// Splits milliseconds since the UNIX epoch (proleptic Gregorian calendar).
+    pub fn from_epoch_ms(ms: i64) -> Self {
+        let days = ms.div_euclid(86_400_000);
+        let rem = ms.rem_euclid(86_400_000) as u32;
+
+        // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        UtcDateTime {
+            year,
+            month,
+            day,
+            hour: rem / 3_600_000,
+            minute: rem / 60_000 % 60,
+            second: rem / 1000 % 60,
+            millis: rem % 1000,
+        }
+    }
+
+    /// ISO-8601 form with milliseconds, e.g. `2024-05-01T09:30:00.000Z`.
+    pub fn to_iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second, self.millis
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rjscript::{
+        evaluator::runtime::value::RJSValue,
+        testing::{eval_expr_str, TestWorld},
+    };
+
+    const FROZEN: &str = "1700000000123";
+
+    #[test]
+    fn frozen_time_values() {
+        for value in [FROZEN, " 1700000000123\n"] {
+            assert_eq!(epoch_ms(clock_frozen_at(Some(value))()), 1_700_000_000_123.0, "{:?}", value);
+        }
+        assert_eq!(clock_frozen_at(Some("0"))(), UNIX_EPOCH);
+
+        // anything else keeps the real clock running
+        for value in [None, Some(""), Some("-1"), Some("1.5"), Some("yesterday")] {
+            let before = SystemTime::now();
+            let now = clock_frozen_at(value)();
+            assert!(now >= before && now <= SystemTime::now(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn scripts_see_the_frozen_time() {
+        let world = TestWorld::with_clock(clock_frozen_at(Some(FROZEN)));
+        for _ in 0..2 {
+            assert_eq!(eval_expr_str("now()", &world), Ok(RJSValue::Number(1_700_000_000_123.0)));
+            assert_eq!(
+                eval_expr_str("timestamp()", &world),
+                Ok(RJSValue::String("2023-11-14T22:13:20.123Z".into()))
+            );
+            assert_eq!(
+                eval_expr_str("formatDate(now(), \"YYYY-MM-DD hh:mm:ss.SSS\")", &world),
+                Ok(RJSValue::String("2023-11-14 22:13:20.123".into()))
+            );
+        }
+    }
+
+    #[test]
+    fn dates_split_around_the_epoch() {
+        let at = |ms| UtcDateTime::from_epoch_ms(ms).to_iso8601();
+        assert_eq!(at(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(-1), "1969-12-31T23:59:59.999Z");
+        assert_eq!(at(951_782_400_000), "2000-02-29T00:00:00.000Z");
+        assert_eq!(epoch_ms(UNIX_EPOCH - Duration::from_millis(1500)), -1500.0);
+    }
+}
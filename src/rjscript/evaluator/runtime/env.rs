@@ -1,16 +1,17 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     rc::Rc,
 };
 
 use crate::rjscript::{
     ast::{
         block::Block,
+        param::Param,
         position::Position
     },
     evaluator::{
-        errors::EvalError, runtime::value::RJSValue, EvalResult
+        errors::EvalError, runtime::value::{FunctionValue, RJSValue}, EvalResult
     }, semantics::types::VarType,
 };
 
@@ -18,15 +19,29 @@ pub type EnvRef = Rc<RefCell<Env>>;
 
 #[derive(Clone)]
 pub struct UserFunction {
-    pub params: Vec<(String, VarType)>,
+    pub params: Vec<Param>,
     pub return_type: VarType,
     pub body: Block,
     pub env: EnvRef,
 }
 
+impl UserFunction {
+    /// Binds a function value to the functions visible from `env`.
+    pub fn from_value(func: &FunctionValue, env: &EnvRef) -> Self {
+        UserFunction {
+            params: func.params.clone(),
+            return_type: func.return_type.clone(),
+            body: func.body.clone(),
+            env: Env::capture_functions_only(env),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Env {
     locals: HashMap<String, (VarType, RJSValue)>,
+    /// Names of locals declared with `const`
+    consts: HashSet<String>,
     functions: Rc<RefCell<HashMap<String, UserFunction>>>,
     parent: Option<EnvRef>,
 }
@@ -35,6 +50,7 @@ impl Env {
     pub fn new_ref() -> EnvRef {
         let e = Env {
             locals: HashMap::new(),
+            consts: HashSet::new(),
             functions: Rc::new(RefCell::new(HashMap::new())),
             parent: None,
         };
@@ -47,6 +63,7 @@ impl Env {
         let parent_borrow = parent.borrow();
         let child = Env {
             locals: HashMap::new(),
+            consts: HashSet::new(),
             functions: Rc::clone(&parent_borrow.functions),
             parent: Some(Rc::clone(parent)),
         };
@@ -59,12 +76,25 @@ impl Env {
         let src = source.borrow();
         let base = Env {
             locals: HashMap::new(), // empty: no closure over surrounding vars
+            consts: HashSet::new(),
             functions: Rc::new(RefCell::new(src.functions.borrow().clone())),
             parent: None, // don’t chain into original locals
         };
         Rc::new(RefCell::new(base))
     }
 
+    /// Make a new environment without locals that shares (not copies) the function
+    /// table of `source`, so functions defined there later are visible too.
+    pub fn share_functions(source: &EnvRef) -> EnvRef {
+        let base = Env {
+            locals: HashMap::new(),
+            consts: HashSet::new(),
+            functions: Rc::clone(&source.borrow().functions),
+            parent: None,
+        };
+        Rc::new(RefCell::new(base))
+    }
+
     /// Recursively checks for a name in ancestor locals (used for shadowing rules)
     fn has_in_ancestors(&self, name: &str) -> bool {
         if let Some(ref parent_rc) = self.parent {
@@ -110,6 +140,30 @@ impl Env {
         Ok(())
     }
 
+    /// Declare an immutable local; later assignments fail with `AssignToConst`.
+    pub fn declare_const(
+        &mut self,
+        name: &str,
+        val_type: VarType,
+        val: RJSValue,
+        pos: Position,
+    ) -> EvalResult<()> {
+        self.declare_var(name, val_type, val, pos)?;
+        self.consts.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Whether the nearest declaration of `name` in the scope chain is a `const`.
+    pub fn is_const(&self, name: &str) -> bool {
+        if self.locals.contains_key(name) {
+            self.consts.contains(name)
+        } else if let Some(ref parent_rc) = self.parent {
+            parent_rc.borrow().is_const(name)
+        } else {
+            false
+        }
+    }
+
     /// Walk the scope chain, find `name`, and call `f` with (&declared_type, &mut value).
     /// Returns `Some(result)` if the variable exists, else `None`.
     pub fn with_var_slot<F, R>(env: &EnvRef, name: &str, mut f: F) -> Option<R>
@@ -138,12 +192,15 @@ impl Env {
     }
 
     pub fn assign_var(&mut self, name: &str, val: RJSValue, pos: Position) -> EvalResult<RJSValue> {
+        if self.is_const(name) {
+            return Err(EvalError::AssignToConst(name.to_string(), pos));
+        }
         if let Some((expected_type, _)) = self.get_var(name) {
             if !val.is_type(&expected_type) {
                 return Err(EvalError::General(
                     format!(
-                        "Type mismatch on assignment to {}: expected {:?}, got {:?}",
-                        name, expected_type, val
+                        "Type mismatch on assignment to {}: expected {}, got {}",
+                        name, expected_type, val.to_type()
                     ),
                     pos,
                 ));
@@ -182,3 +239,69 @@ impl Env {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::rjscript::testing::{eval_script_str_with_world, TestWorld};
+
+    const AT: Position = Position::UNKNOWN;
+
+    fn run(src: &str) -> Result<(u16, RJSValue), String> {
+        eval_script_str_with_world(src, Value::Null, &TestWorld::new())
+    }
+
+    #[test]
+    fn const_is_not_assignable() {
+        let env = Env::new_ref();
+        env.borrow_mut().declare_const("x", VarType::Number, RJSValue::Number(1.0), AT).unwrap();
+        let err = env.borrow_mut().assign_var("x", RJSValue::Number(2.0), AT).unwrap_err();
+        assert!(matches!(err, EvalError::AssignToConst(ref name, _) if name == "x"), "{}", err);
+        assert_eq!(env.borrow().get_var("x").unwrap().1, RJSValue::Number(1.0));
+    }
+
+    #[test]
+    fn const_stays_const_in_inner_scopes() {
+        let outer = Env::new_ref();
+        outer.borrow_mut().declare_const("x", VarType::Number, RJSValue::Number(1.0), AT).unwrap();
+        outer.borrow_mut().declare_var("y", VarType::Number, RJSValue::Number(1.0), AT).unwrap();
+        let inner = Env::push_scope(&outer);
+
+        assert!(inner.borrow().is_const("x"));
+        assert!(!inner.borrow().is_const("y"));
+        assert!(!inner.borrow().is_const("missing"));
+        assert!(inner.borrow_mut().assign_var("x", RJSValue::Number(2.0), AT).is_err());
+        inner.borrow_mut().assign_var("y", RJSValue::Number(2.0), AT).unwrap();
+        assert_eq!(outer.borrow().get_var("y").unwrap().1, RJSValue::Number(2.0));
+        // shadowing is not allowed, for consts either
+        let err = inner.borrow_mut().declare_var("x", VarType::Number, RJSValue::Null, AT).unwrap_err();
+        assert!(matches!(err, EvalError::VariableAlreadyDeclared(..)), "{}", err);
+    }
+
+    #[test]
+    fn let_assignment_checks_the_declared_type() {
+        let env = Env::new_ref();
+        env.borrow_mut().declare_var("n", VarType::Number, RJSValue::Number(1.0), AT).unwrap();
+        let err = env.borrow_mut().assign_var("n", RJSValue::Bool(true), AT).unwrap_err();
+        assert!(err.to_string().contains("expected num, got bool"), "{}", err);
+        let err = env.borrow_mut().assign_var("nope", RJSValue::Null, AT).unwrap_err();
+        assert!(matches!(err, EvalError::UndeclaredVariable(..)), "{}", err);
+    }
+
+    #[test]
+    fn const_in_scripts() {
+        assert_eq!(run("const n: num = 2; return 200, n * 3;"), Ok((200, RJSValue::Number(6.0))));
+        for (src, expected) in [
+            ("const n: num = 2; n = 3; return 200, n;", "const"),
+            ("const xs: vec<num> = [1]; xs.push(2); return 200, xs;", "const"),
+            ("const o: obj = { a: 1 }; o.a = 2; return 200, o;", "const"),
+            ("const xs: vec<num> = [1]; xs[0] = 2; return 200, xs;", "const"),
+            ("const n: num; return 200, n;", "must be initialized"),
+        ] {
+            let err = run(src).unwrap_err();
+            assert!(err.contains(expected), "{}: {}", src, err);
+        }
+    }
+}
@@ -1,16 +1,140 @@
-use std::sync::Arc;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io, panic,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+};
+
+use regex::Regex;
 
 use crate::rjscript::evaluator::runtime::{request_cache::RequestCache, runtime_globals::RuntimeGlobals};
 
+/// Default for the deepest nesting of user function calls, see `max_call_depth`.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+/// Highest `RJS_MAX_CALL_DEPTH` honoured: what fits in `EVAL_STACK_SIZE` in a debug build.
+pub const MAX_CALL_DEPTH_LIMIT: usize = 512;
+
+/// Native stack every script evaluation runs on, see `run_on_eval_stack`.
+pub const EVAL_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+static MAX_CALL_DEPTH: OnceLock<usize> = OnceLock::new();
 
+/// Deepest nesting of user function calls a script may reach before failing with
+/// `EvalError::RecursionLimit`, from `RJS_MAX_CALL_DEPTH` when it is set. Larger
+/// values are clamped to `MAX_CALL_DEPTH_LIMIT`.
+pub fn max_call_depth() -> usize {
+    *MAX_CALL_DEPTH.get_or_init(|| call_depth_from(std::env::var("RJS_MAX_CALL_DEPTH").ok().as_deref()))
+}
+
+/// The call depth a `RJS_MAX_CALL_DEPTH` value asks for: the default when it is
+/// missing or not a positive number, at most `MAX_CALL_DEPTH_LIMIT`.
+fn call_depth_from(value: Option<&str>) -> usize {
+    value
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .map_or(DEFAULT_MAX_CALL_DEPTH, |n| n.min(MAX_CALL_DEPTH_LIMIT))
+}
+
+thread_local! {
+    static ON_EVAL_STACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Declares that the current thread was spawned with at least `EVAL_STACK_SIZE`
+/// of stack, so `run_on_eval_stack` runs evaluations on it directly. The server's
+/// runtime workers call this when they start.
+pub fn mark_eval_stack_thread() {
+    ON_EVAL_STACK.with(|on| on.set(true));
+}
+
+/// Runs `f` on a thread with `EVAL_STACK_SIZE` of stack: the current one when it is
+/// marked with `mark_eval_stack_thread`, otherwise a new one that is joined before
+/// returning. Deep script recursion then hits `max_call_depth` before the end of
+/// the native stack, whichever thread (main, test, worker) evaluates.
+pub fn run_on_eval_stack<T: Send>(f: impl FnOnce() -> T + Send) -> io::Result<T> {
+    if ON_EVAL_STACK.with(Cell::get) {
+        return Ok(f());
+    }
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .name("rjs-eval".into())
+            .stack_size(EVAL_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                mark_eval_stack_thread();
+                f()
+            })?;
+        Ok(handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+    })
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Everything a script evaluation can reach besides its local `Env`.
+///
+/// A context is built per request. `req` is read-only; `globals` is shared
+/// across requests, see `RuntimeGlobals` for how its state may be mutated.
 #[derive(Clone)]
 pub struct EvalCtx {
     pub globals: Arc<RuntimeGlobals>,
     pub req: Arc<RequestCache>,
+    /// Sequence number of the evaluation, tagging its log lines
+    pub request_id: u64,
+    /// User function calls currently on the stack
+    pub call_depth: Cell<usize>,
+    /// Patterns compiled by the regex builtins, so a loop compiles each one once
+    regexes: RefCell<HashMap<String, Regex>>,
 }
 
 impl EvalCtx {
     pub fn new(globals: Arc<RuntimeGlobals>, req: Arc<RequestCache>) -> Self {
-        Self { globals, req }
+        Self {
+            globals,
+            req,
+            request_id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+            call_depth: Cell::new(0),
+            regexes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// `pattern` compiled, from the cache of this evaluation when it was seen before.
+    pub fn regex(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        if let Some(re) = self.regexes.borrow().get(pattern) {
+            return Ok(re.clone());
+        }
+        let re = Regex::new(pattern)?;
+        self.regexes
+            .borrow_mut()
+            .insert(pattern.to_string(), re.clone());
+        Ok(re)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_depth_defaults_and_clamps() {
+        assert_eq!(call_depth_from(None), DEFAULT_MAX_CALL_DEPTH);
+        assert_eq!(call_depth_from(Some("0")), DEFAULT_MAX_CALL_DEPTH);
+        assert_eq!(call_depth_from(Some("lots")), DEFAULT_MAX_CALL_DEPTH);
+        assert_eq!(call_depth_from(Some(" 64 ")), 64);
+        assert_eq!(call_depth_from(Some("512")), MAX_CALL_DEPTH_LIMIT);
+        assert_eq!(call_depth_from(Some("1000000")), MAX_CALL_DEPTH_LIMIT);
+    }
+
+    #[test]
+    fn eval_stack_runs_in_place_once_marked() {
+        let outer = thread::current().id();
+        let first = run_on_eval_stack(|| thread::current().id()).unwrap();
+        assert_ne!(first, outer);
+
+        mark_eval_stack_thread();
+        let second = run_on_eval_stack(|| thread::current().id()).unwrap();
+        assert_eq!(second, outer);
     }
 }
@@ -0,0 +1,207 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+/// How many files `FileSandbox` keeps in memory.
+const CACHED_FILES: usize = 32;
+
+struct CachedFile {
+    path: PathBuf,
+    modified: SystemTime,
+    contents: Arc<str>,
+}
+
+/// The directory `readFile()`/`readJson()` may read from (`serve --data-dir`), with a
+/// small LRU of recently read files. A cached file is read again once its mtime changes.
+pub struct FileSandbox {
+    root: PathBuf,
+    /// Most recently used last
+    cache: Mutex<VecDeque<CachedFile>>,
+}
+
+impl FileSandbox {
+    pub fn new(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = fs::canonicalize(root)?;
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", root.display()),
+            ));
+        }
+        Ok(FileSandbox {
+            root,
+            cache: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Contents of the UTF-8 file at `relative`, which must stay inside the root: no
+    /// absolute paths, no `..`, and no symlinks pointing outside of it.
+    pub fn read(&self, relative: &str) -> Result<Arc<str>, String> {
+        let path = self.resolve(relative)?;
+        let modified = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map_err(|e| format!("{}: {}", relative, e))?;
+
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(i) = cache.iter().position(|f| f.path == path) {
+            let file = cache.remove(i).expect("index from position");
+            if file.modified == modified {
+                let contents = Arc::clone(&file.contents);
+                cache.push_back(file);
+                return Ok(contents);
+            }
+        }
+        // read under the lock, so a hot file missing from the cache is read only once
+        let contents: Arc<str> = fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", relative, e))?
+            .into();
+        if cache.len() == CACHED_FILES {
+            cache.pop_front();
+        }
+        cache.push_back(CachedFile {
+            path,
+            modified,
+            contents: Arc::clone(&contents),
+        });
+        Ok(contents)
+    }
+
+    fn resolve(&self, relative: &str) -> Result<PathBuf, String> {
+        let outside = || format!("'{}' is outside the data directory", relative);
+        let rel = Path::new(relative);
+        if relative.is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            return Err(outside());
+        }
+        let path = fs::canonicalize(self.root.join(rel)).map_err(|e| format!("{}: {}", relative, e))?;
+        if !path.starts_with(&self.root) {
+            return Err(outside());
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs::File,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    /// `<tmp>/rjs-files-<pid>-<name>/` holding `root/` and a `secret.txt` beside it.
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rjs-files-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("root/nested")).unwrap();
+            fs::write(dir.join("secret.txt"), "secret").unwrap();
+            fs::write(dir.join("root/a.txt"), "hello").unwrap();
+            fs::write(dir.join("root/nested/b.json"), "{\"n\": 1}").unwrap();
+            Fixture { dir }
+        }
+
+        fn root(&self) -> PathBuf {
+            self.dir.join("root")
+        }
+
+        fn sandbox(&self) -> FileSandbox {
+            FileSandbox::new(self.root()).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn set_mtime(path: &Path, secs: u64) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+    }
+
+    #[test]
+    fn reads_files_inside_the_root() {
+        let fixture = Fixture::new("inside");
+        let files = fixture.sandbox();
+        assert_eq!(&*files.read("a.txt").unwrap(), "hello");
+        assert_eq!(&*files.read("./nested/b.json").unwrap(), "{\"n\": 1}");
+        let err = files.read("missing.txt").unwrap_err();
+        assert!(err.starts_with("missing.txt: "), "{}", err);
+    }
+
+    #[test]
+    fn parent_and_absolute_paths_are_rejected() {
+        let fixture = Fixture::new("traversal");
+        let files = fixture.sandbox();
+        let secret = fixture.dir.join("secret.txt");
+        for path in ["../secret.txt", "nested/../../secret.txt", "nested/../a.txt", "", secret.to_str().unwrap()] {
+            assert_eq!(
+                files.read(path).unwrap_err(),
+                format!("'{}' is outside the data directory", path)
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_may_not_escape_the_root() {
+        use std::os::unix::fs::symlink;
+
+        let fixture = Fixture::new("symlinks");
+        symlink(fixture.dir.join("secret.txt"), fixture.root().join("escape.txt")).unwrap();
+        symlink(fixture.dir.as_path(), fixture.root().join("up")).unwrap();
+        symlink(fixture.root().join("a.txt"), fixture.root().join("alias.txt")).unwrap();
+        let files = fixture.sandbox();
+
+        for path in ["escape.txt", "up/secret.txt"] {
+            assert_eq!(
+                files.read(path).unwrap_err(),
+                format!("'{}' is outside the data directory", path)
+            );
+        }
+        assert_eq!(&*files.read("alias.txt").unwrap(), "hello");
+    }
+
+    #[test]
+    fn cached_files_are_read_again_once_they_change() {
+        let fixture = Fixture::new("mtime");
+        let path = fixture.root().join("a.txt");
+        set_mtime(&path, 1_000);
+        let files = fixture.sandbox();
+        assert_eq!(&*files.read("a.txt").unwrap(), "hello");
+
+        // same mtime: served from the cache
+        fs::write(&path, "changed").unwrap();
+        set_mtime(&path, 1_000);
+        assert_eq!(&*files.read("a.txt").unwrap(), "hello");
+
+        set_mtime(&path, 2_000);
+        assert_eq!(&*files.read("a.txt").unwrap(), "changed");
+        assert_eq!(&*files.read("a.txt").unwrap(), "changed");
+    }
+
+    #[test]
+    fn least_recently_used_files_are_evicted() {
+        let fixture = Fixture::new("lru");
+        for i in 0..=CACHED_FILES {
+            fs::write(fixture.root().join(format!("{}.txt", i)), i.to_string()).unwrap();
+        }
+        let files = fixture.sandbox();
+        for i in 0..=CACHED_FILES {
+            files.read(&format!("{}.txt", i)).unwrap();
+        }
+        let cache = files.cache.lock().unwrap();
+        assert_eq!(cache.len(), CACHED_FILES);
+        assert!(cache.iter().all(|f| !f.path.ends_with("0.txt")));
+        assert!(cache.back().unwrap().path.ends_with(format!("{}.txt", CACHED_FILES)));
+    }
+}
@@ -3,4 +3,6 @@ pub mod eval_ctx;
 pub mod request_cache;
 pub mod runtime_globals;
 pub mod value;
-pub mod cache;
\ No newline at end of file
+pub mod cache;
+pub mod clock;
+pub mod files;pub mod world;
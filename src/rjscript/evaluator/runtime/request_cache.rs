@@ -9,6 +9,8 @@ pub struct RequestCache {
     pub route_params: RJSValue,
     pub query_params: RJSValue,
     pub headers: RJSValue,
+    /// Request path, for tagging script log lines
+    pub path: String,
 }
 
 impl RequestCache {
@@ -19,6 +21,7 @@ impl RequestCache {
             route_params: RJSValue::string_map_to_rjs(&req.route_params),
             query_params: RJSValue::string_map_to_rjs(&req.query_params),
             headers: RJSValue::string_map_to_rjs(&req.headers),
+            path: req.path,
         })
     }
 
@@ -1,13 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, OnceLock};
 
 use crate::rjscript::evaluator::runtime::cache::GlobalCache;
+use crate::rjscript::evaluator::runtime::clock::{clock_from_env, system_clock, WallClock};
+use crate::rjscript::evaluator::runtime::files::FileSandbox;
 use crate::rjscript::{
     evaluator::builtins::{
-        arraycore::{array_methods_table, array_mut_methods_table},
+        arraycore::{array_callback_methods_table, array_methods_table, array_mut_methods_table},
         core::builtins_table,
+        objectcore::{object_methods_table, object_mut_methods_table},
         stringcore::string_methods_table,
-        BuiltinFn, MutMethodFn, PureMethodFn,
+        BuiltinFn, CallbackMethodFn, MutMethodFn, PureMethodFn,
     },
     semantics::{methods::receiver_from_vartype, methods::Receiver, types::VarType},
 };
@@ -17,21 +20,60 @@ use crate::rjsdb::TableDb;
 pub enum MethodImpl {
     Pure(PureMethodFn),
     Mut(MutMethodFn),
+    Callback(CallbackMethodFn),
 }
 
+/// The environment variables `env()` may read: the listed names, plus every name
+/// starting with one of the prefixes. The default is empty and allows none.
+#[derive(Debug, Default)]
+pub struct EnvAllowlist {
+    pub names: HashSet<String>,
+    pub prefixes: Vec<String>,
+}
+
+impl EnvAllowlist {
+    pub fn allows(&self, name: &str) -> bool {
+        self.names.contains(name) || self.prefixes.iter().any(|p| name.starts_with(p.as_str()))
+    }
+}
+
+/// What `serve` lets scripts reach beyond the request; the default allows nothing.
+#[derive(Default)]
+pub struct RuntimeOptions {
+    pub env_allowlist: EnvAllowlist,
+    pub allow_net: bool,
+    pub files: Option<FileSandbox>,
+}
+
+/// State shared by every script evaluation.
+///
+/// Builtins only get `&EvalCtx`, so everything they mutate lives behind interior
+/// mutability: the cache is an `RwLock`ed map and the db backend does its own locking.
+/// The builtin and method tables are immutable after construction.
 #[derive(Clone)]
 pub struct RuntimeGlobals {
     builtins: Arc<HashMap<String, BuiltinFn>>,
     methods: Arc<HashMap<(Receiver, String), MethodImpl>>,
     pub cache: Arc<GlobalCache>,
     pub db: Option<Arc<dyn TableDb>>,
+    /// Read by `now()`
+    pub clock: WallClock,
+    /// The only variables `env()` may read
+    pub env_allowlist: Arc<EnvAllowlist>,
+    /// Whether `httpFetch()` may make outbound requests (`serve --allow-net`)
+    pub allow_net: bool,
+    /// Where `readFile()`/`readJson()` read from; `None` disables them
+    pub files: Option<Arc<FileSandbox>>,
 }
 
 static GLOBALS: OnceLock<Arc<RuntimeGlobals>> = OnceLock::new();
 
 impl RuntimeGlobals {
-    // single, process-wide instance
-    fn build(db: Option<Arc<dyn TableDb>>) -> Arc<Self> {
+    fn build(
+        db: Option<Arc<dyn TableDb>>,
+        clock: WallClock,
+        options: RuntimeOptions,
+    ) -> Arc<Self> {
         // Build builtins
         let builtins = builtins_table();
 
@@ -46,21 +88,69 @@ impl RuntimeGlobals {
         for (name, f) in array_mut_methods_table().iter() {
             methods.insert((Receiver::Array, name.clone()), MethodImpl::Mut(*f));
         }
+        for (name, f) in array_callback_methods_table().iter() {
+            methods.insert((Receiver::Array, name.clone()), MethodImpl::Callback(*f));
+        }
+        for (name, f) in object_methods_table().iter() {
+            methods.insert((Receiver::Object, name.clone()), MethodImpl::Pure(*f));
+        }
+        for (name, f) in object_mut_methods_table().iter() {
+            methods.insert((Receiver::Object, name.clone()), MethodImpl::Mut(*f));
+        }
 
         Arc::new(RuntimeGlobals {
             builtins,
             methods: Arc::new(methods),
             cache: Arc::new(GlobalCache::new()),
             db,
+            clock,
+            env_allowlist: Arc::new(options.env_allowlist),
+            allow_net: options.allow_net,
+            files: options.files.map(Arc::new),
         })
     }
 
-    pub fn init_with_db(db: Option<Arc<dyn TableDb>>) -> Arc<Self> {
-        GLOBALS.get_or_init(|| Self::build(db)).clone()
+    /// Initializes the single, process-wide instance.
+    pub fn init_with_db(
+        db: Option<Arc<dyn TableDb>>,
+        options: RuntimeOptions,
+    ) -> Arc<Self> {
+        GLOBALS.get_or_init(|| Self::build(db, clock_from_env(), options)).clone()
     }
 
     pub fn get() -> Arc<Self> {
-        GLOBALS.get_or_init(|| Self::build(None)).clone()
+        GLOBALS.get_or_init(|| Self::build(None, clock_from_env(), RuntimeOptions::default())).clone()
+    }
+
+    /// A fresh instance with its own empty cache, independent of the process-wide one.
+    pub fn isolated(db: Option<Arc<dyn TableDb>>) -> Arc<Self> {
+        Self::build(db, system_clock(), RuntimeOptions::default())
+    }
+
+    /// Like `isolated`, with `clock` as the time source for `now()`.
+    pub fn isolated_with_clock(db: Option<Arc<dyn TableDb>>, clock: WallClock) -> Arc<Self> {
+        Self::build(db, clock, RuntimeOptions::default())
+    }
+
+    /// Like `isolated`, with `env()` limited to the given variable names.
+    pub fn isolated_with_env_allowlist(
+        db: Option<Arc<dyn TableDb>>,
+        env_allowlist: HashSet<String>,
+    ) -> Arc<Self> {
+        let options = RuntimeOptions {
+            env_allowlist: EnvAllowlist { names: env_allowlist, prefixes: Vec::new() },
+            ..RuntimeOptions::default()
+        };
+        Self::build(db, system_clock(), options)
+    }
+
+    /// Like `isolated`, with `readFile()`/`readJson()` reading from `files`.
+    pub fn isolated_with_files(db: Option<Arc<dyn TableDb>>, files: FileSandbox) -> Arc<Self> {
+        let options = RuntimeOptions {
+            files: Some(files),
+            ..RuntimeOptions::default()
+        };
+        Self::build(db, system_clock(), options)
     }
 
     #[inline]
@@ -83,7 +173,35 @@ impl RuntimeGlobals {
             (Some(MethodImpl::Mut(f)), true) => Some(MethodImpl::Mut(*f)),
             (Some(MethodImpl::Mut(_)), false) => None,
             (Some(MethodImpl::Pure(f)), _) => Some(MethodImpl::Pure(*f)),
+            (Some(MethodImpl::Callback(f)), _) => Some(MethodImpl::Callback(*f)),
             (None, _) => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_env_allowlist_allows_nothing() {
+        let allowlist = EnvAllowlist::default();
+        assert!(!allowlist.allows("PATH"));
+        assert!(!allowlist.allows(""));
+        assert!(!RuntimeGlobals::isolated(None).env_allowlist.allows("HOME"));
+    }
+
+    #[test]
+    fn env_allowlist_matches_names_and_prefixes() {
+        let allowlist = EnvAllowlist {
+            names: HashSet::from(["PORT".to_string()]),
+            prefixes: vec!["MOCK_".to_string()],
+        };
+        assert!(allowlist.allows("PORT"));
+        assert!(!allowlist.allows("PORTS"));
+        assert!(allowlist.allows("MOCK_KEY"));
+        assert!(allowlist.allows("MOCK_"));
+        assert!(!allowlist.allows("MOCK"));
+        assert!(!allowlist.allows("mock_key"));
+    }
+}
@@ -1,27 +1,68 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, sync::Arc};
 
 use crate::{rjscript::{
-    ast::{literal::Literal, position::Position},
+    ast::{block::Block, literal::Literal, param::Param, position::Position},
     evaluator::{EvalResult, errors::EvalError},
     semantics::types::VarType,
 }, rjsdb::DbValue};
+use indexmap::IndexMap;
 use serde_json::Value as JsonValue;
 
+/// Fields of an `RJSValue::Object`, kept in insertion order so responses list keys
+/// the way the script or the stored document wrote them.
+pub type ObjectMap = IndexMap<String, RJSValue>;
+
+/// 2^53: whole numbers below this magnitude are exact in an f64 and fit an i64.
+const MAX_EXACT_INT: f64 = 9_007_199_254_740_992.0;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum RJSValue {
     Number(f64),
     String(String),
     Bool(bool),
     Array(Vec<RJSValue>),
-    Object(HashMap<String, RJSValue>),
+    Object(ObjectMap),
     Type(VarType),
     Undefined,
+    Null,
+    Function(Arc<FunctionValue>),
+}
+
+/// A function created by a `func (...) { ... }` expression.
+///
+/// It keeps no environment, so values stay `Send` and can go into the cache.
+/// Like declared functions it does not see the surrounding locals; the caller's
+/// functions are bound when it is invoked (see `UserFunction::from_value`).
+pub struct FunctionValue {
+    pub params: Vec<Param>,
+    pub return_type: VarType,
+    pub body: Block,
+}
+
+impl fmt::Debug for FunctionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.ty))
+            .collect();
+        write!(f, "func({}): {}", params.join(", "), self.return_type)
+    }
+}
+
+/// Function values are equal only to themselves.
+impl PartialEq for FunctionValue {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
 }
 
 impl RJSValue {
     /// Check whether this value conforms to the requested VarType.
     pub fn is_type(&self, var_type: &VarType) -> bool {
         match (self, var_type) {
+            (_, VarType::Any) => true,
+            (value, VarType::Union(members)) => members.iter().any(|m| value.is_type(m)),
             (RJSValue::Bool(_), VarType::Bool) => true,
             (RJSValue::Number(_), VarType::Number) => true,
             (RJSValue::String(_), VarType::String) => true,
@@ -35,6 +76,8 @@ impl RJSValue {
             // Interpret a type-literal value as matching its wrapped type.
             (RJSValue::Type(rjs_type), wanted) => rjs_type == wanted,
             (RJSValue::Undefined, VarType::Undefined) => true,
+            (RJSValue::Null, VarType::Null) => true,
+            (RJSValue::Function(_), VarType::Function) => true,
             _ => false,
         }
     }
@@ -60,6 +103,8 @@ impl RJSValue {
             }
             RJSValue::Type(rjs_type) => rjs_type.clone(),
             RJSValue::Undefined => VarType::Undefined,
+            RJSValue::Null => VarType::Null,
+            RJSValue::Function(_) => VarType::Function,
         }
     }
 
@@ -68,10 +113,14 @@ impl RJSValue {
             RJSValue::String(s) => s.clone(),
             RJSValue::Number(n) => n.to_string(),
             RJSValue::Bool(b) => b.to_string(),
-            RJSValue::Array(a) => format!("{:?}", a),
-            RJSValue::Object(o) => format!("{:?}", o),
+            // compact JSON, object keys in insertion order
+            RJSValue::Array(_) | RJSValue::Object(_) => {
+                serde_json::to_string(&RJSValue::display_json(self)).unwrap_or_default()
+            }
             RJSValue::Type(t) => format!("{:?}", t),
             RJSValue::Undefined => "undefined".into(),
+            RJSValue::Null => "null".into(),
+            RJSValue::Function(func) => format!("{:?}", func),
         }
     }
 
@@ -84,6 +133,8 @@ impl RJSValue {
             RJSValue::Object(o) => o.keys().len() > 0,
             RJSValue::Type(_) => false,
             RJSValue::Undefined => false,
+            RJSValue::Null => false,
+            RJSValue::Function(_) => true,
         }
     }
 
@@ -93,17 +144,37 @@ impl RJSValue {
             Literal::String(s) => RJSValue::String(s),
             Literal::Bool(b) => RJSValue::Bool(b),
             Literal::Undefined => RJSValue::Undefined,
+            Literal::Null => RJSValue::Null,
         }
     }
 
     pub fn string_map_to_rjs(map: &HashMap<String, String>) -> RJSValue {
-        let obj: HashMap<String, RJSValue> = map
+        let obj: ObjectMap = map
             .iter()
             .map(|(k, v)| (k.clone(), RJSValue::String(v.clone())))
             .collect();
         RJSValue::Object(obj)
     }
 
+    /// `rjs_to_json` for `to_string`: whole numbers are written without a fraction at
+    /// every depth, the way a number on its own prints (`1`, not `1.0`).
+    fn display_json(value: &RJSValue) -> serde_json::Value {
+        match value {
+            RJSValue::Number(n) if n.fract() == 0.0 && n.abs() < MAX_EXACT_INT => {
+                serde_json::Value::Number((*n as i64).into())
+            }
+            RJSValue::Array(items) => {
+                serde_json::Value::Array(items.iter().map(RJSValue::display_json).collect())
+            }
+            RJSValue::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), RJSValue::display_json(v)))
+                    .collect(),
+            ),
+            other => RJSValue::rjs_to_json(other),
+        }
+    }
+
     pub fn rjs_to_json(value: &RJSValue) -> serde_json::Value {
         match value {
             RJSValue::Number(n) => serde_json::Number::from_f64(*n)
@@ -124,7 +195,10 @@ impl RJSValue {
                 serde_json::Value::Object(m)
             }
             RJSValue::Type(ty) => serde_json::Value::String(format!("{:?}", ty)),
-            RJSValue::Undefined => serde_json::Value::Null,
+            // functions have no JSON form
+            RJSValue::Undefined | RJSValue::Null | RJSValue::Function(_) => {
+                serde_json::Value::Null
+            }
         }
     }
 
@@ -137,6 +211,7 @@ impl RJSValue {
                 .ok_or_else(|| EvalError::General("Invalid number in JSON".into(), pos)),
             JsonValue::String(s) => Ok(RJSValue::String(s.clone())),
             JsonValue::Bool(b) => Ok(RJSValue::Bool(*b)),
+            JsonValue::Null => Ok(RJSValue::Null),
             JsonValue::Array(arr) => {
                 let mut items = Vec::with_capacity(arr.len());
                 for elem in arr {
@@ -145,13 +220,12 @@ impl RJSValue {
                 Ok(RJSValue::Array(items))
             }
             JsonValue::Object(obj) => {
-                let mut map = HashMap::new();
+                let mut map = ObjectMap::with_capacity(obj.len());
                 for (k, v) in obj {
                     map.insert(k.clone(), RJSValue::json_to_rjs(v, pos)?);
                 }
                 Ok(RJSValue::Object(map))
             }
-            _ => Ok(RJSValue::Undefined),
         }
     }
 
@@ -160,9 +234,95 @@ impl RJSValue {
             DbValue::Number(n) => Ok(RJSValue::Number(*n)),
             DbValue::Bool(b) => Ok(RJSValue::Bool(*b)),
             DbValue::String(s) => Ok(RJSValue::String(s.clone())),
-            DbValue::Null => Ok(RJSValue::Undefined),
+            DbValue::Null => Ok(RJSValue::Null),
 
             DbValue::Json(json) => RJSValue::json_to_rjs(json, pos),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::rjscript::testing::{eval_expr_str, eval_script_str_with_world, TestWorld};
+
+    fn display(src: &str) -> String {
+        eval_expr_str(src, &TestWorld::new()).unwrap().to_string()
+    }
+
+    #[test]
+    fn templates_interpolate_json() {
+        let world = TestWorld::new();
+        let (_, v) = eval_script_str_with_world(
+            "return 200, `body: ${req.body}`;",
+            json!({ "a": "x" }),
+            &world,
+        )
+        .unwrap();
+        assert_eq!(v, RJSValue::String("body: {\"a\":\"x\"}".into()));
+    }
+
+    #[test]
+    fn whole_numbers_print_without_fraction_at_every_depth() {
+        assert_eq!(display("1"), "1");
+        assert_eq!(display("[1, 2.5, -3]"), "[1,2.5,-3]");
+        assert_eq!(display("{ b: [1, { c: 2 }], a: 0.5 }"), "{\"b\":[1,{\"c\":2}],\"a\":0.5}");
+        assert_eq!(display("toString([[[7]]])"), "[[[7]]]");
+    }
+
+    #[test]
+    fn huge_numbers_keep_their_float_form() {
+        let v = RJSValue::Array(vec![RJSValue::Number(1e300), RJSValue::Number(-0.0)]);
+        assert_eq!(v.to_string(), "[1e300,0]");
+        let v = RJSValue::Array(vec![RJSValue::Number(MAX_EXACT_INT)]);
+        assert_eq!(v.to_string(), "[9007199254740992.0]");
+    }
+
+    #[test]
+    fn scalars_are_unchanged() {
+        assert_eq!(display("\"s\""), "s");
+        assert_eq!(display("true"), "true");
+        assert_eq!(display("null"), "null");
+        assert_eq!(display("undefined"), "undefined");
+    }
+
+    #[test]
+    fn type_errors_name_types() {
+        let body = json!({ "n": 1, "b": true });
+        let err = eval_script_str_with_world("return 200, req.body.n + req.body.b;", body, &TestWorld::new())
+            .unwrap_err();
+        assert!(err.contains("Cannot add num + bool"), "{}", err);
+        let err = eval_expr_str("jsonParse(1)", &TestWorld::new()).unwrap_err();
+        assert!(err.contains("got num"), "{}", err);
+        assert!(!err.contains("Number("), "{}", err);
+    }
+
+    #[test]
+    fn object_keys_keep_insertion_order() {
+        assert_eq!(display("{ b: 1, a: 2 }"), "{\"b\":1,\"a\":2}");
+        assert_eq!(display("jsonStringify({ b: 1, a: 2 })"), "{\"b\":1.0,\"a\":2.0}");
+        assert_eq!(
+            RJSValue::rjs_to_json(&eval_expr_str("{ z: 1, a: { y: 2, b: 3 } }", &TestWorld::new()).unwrap()).to_string(),
+            "{\"z\":1.0,\"a\":{\"y\":2.0,\"b\":3.0}}"
+        );
+
+        // a new key goes last, an existing one keeps its place, removing one keeps the rest in order
+        let src = "let o: obj = { c: 1, b: 2, a: 3 };\no.set(\"d\", 4);\no.set(\"c\", 5);\no.delete(\"b\");\nreturn 200, o;";
+        let (_, v) = eval_script_str_with_world(src, json!(null), &TestWorld::new()).unwrap();
+        assert_eq!(v.to_string(), "{\"c\":5,\"a\":3,\"d\":4}");
+
+        // request bodies and stored documents come back in the order they were written
+        let body = json!({ "zeta": 1, "alpha": { "y": 2, "b": 3 } });
+        let (_, v) = eval_script_str_with_world("return 200, req.body;", body, &TestWorld::new()).unwrap();
+        assert_eq!(v.to_string(), "{\"zeta\":1,\"alpha\":{\"y\":2,\"b\":3}}");
+        let src = "let id: str = dbCreateEntry(\"t\", { zeta: 1, alpha: 2, mid: 3 });\nreturn 200, dbGetById(\"t\", id);";
+        let (_, v) = eval_script_str_with_world(src, json!(null), &TestWorld::with_mem_db()).unwrap();
+        let keys: Vec<_> = match &v {
+            RJSValue::Object(map) => map.keys().map(String::as_str).collect(),
+            other => panic!("not an object: {:?}", other),
+        };
+        assert_eq!(keys, ["zeta", "alpha", "mid", "id"]);
+    }
+}
@@ -0,0 +1,70 @@
+use std::{sync::Arc, time::SystemTime};
+
+use crate::rjscript::evaluator::runtime::{
+    cache::GlobalCache, eval_ctx::EvalCtx, files::FileSandbox, runtime_globals::RuntimeGlobals,
+};
+use crate::rjsdb::TableDb;
+
+/// What builtins can reach beyond their arguments and the request: the cache, the db,
+/// the clock and the capabilities `serve` grants (environment, network, data files).
+///
+/// Builtins go through this trait instead of the fields of `RuntimeGlobals`, so the
+/// state a builtin touches is spelled out in one place. Every implementation hands
+/// out one `RuntimeGlobals`, and the capabilities are provided methods on top of it:
+/// calls are statically dispatched and inline to the field access they replace.
+/// `EvalCtx` implements it for builtins, `testing::TestWorld` for tests that inspect
+/// what a script left behind.
+pub trait ScriptWorld {
+    /// The globals evaluations in this world run against.
+    fn globals(&self) -> &Arc<RuntimeGlobals>;
+
+    /// The key/value store behind `cacheSet()`/`cacheGet()`.
+    #[inline]
+    fn cache(&self) -> &GlobalCache {
+        &self.globals().cache
+    }
+
+    /// The db behind the `db*` builtins, if one is configured.
+    #[inline]
+    fn db(&self) -> Option<&Arc<dyn TableDb>> {
+        self.globals().db.as_ref()
+    }
+
+    /// The current time as seen by `now()` and `timestamp()`.
+    #[inline]
+    fn now(&self) -> SystemTime {
+        (self.globals().clock)()
+    }
+
+    /// Whether `env()` may read the variable `name`.
+    #[inline]
+    fn env_allowed(&self, name: &str) -> bool {
+        self.globals().env_allowlist.allows(name)
+    }
+
+    /// Whether `httpFetch()` may make outbound requests.
+    #[inline]
+    fn allow_net(&self) -> bool {
+        self.globals().allow_net
+    }
+
+    /// Where `readFile()`/`readJson()` read from, if anywhere.
+    #[inline]
+    fn files(&self) -> Option<&FileSandbox> {
+        self.globals().files.as_deref()
+    }
+}
+
+impl ScriptWorld for Arc<RuntimeGlobals> {
+    #[inline]
+    fn globals(&self) -> &Arc<RuntimeGlobals> {
+        self
+    }
+}
+
+impl ScriptWorld for EvalCtx {
+    #[inline]
+    fn globals(&self) -> &Arc<RuntimeGlobals> {
+        &self.globals
+    }
+}
@@ -2,4 +2,5 @@ pub mod ast;
 pub mod parser;
 pub mod evaluator;
 pub mod preprocess;
-pub mod semantics;
\ No newline at end of file
+pub mod semantics;
+pub mod testing;
\ No newline at end of file
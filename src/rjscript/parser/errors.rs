@@ -17,6 +17,14 @@ pub enum ParseError {
     MissingClosingParen(Position),
     ExtraCharacters(Position),
     InvalidEscape(char, Position),
+    /// `\u` not followed by `{`, at the character after the `u`
+    MissingUnicodeBrace(Position),
+    /// A character that is not a hex digit in `\xNN` or `\u{...}`, at that character
+    InvalidHexDigit(char, Position),
+    /// `\u{` without its closing `}`, at the backslash
+    UnterminatedUnicodeEscape(Position),
+    /// The digits of a `\u{...}` escape that do not name a Unicode scalar value, at the first digit
+    InvalidCodePoint(String, Position),
     UnterminatedString(Position),
     InvalidAssignmentTarget(Position),
     ExpectedExpression(Position),
@@ -25,24 +33,28 @@ pub enum ParseError {
 
 impl ParseError {
     pub fn pos(&self) -> Position {
-        let pos: Position = match *self {
-            ParseError::UnexpectedEOF(position) => position,
-            ParseError::UnexpectedChar(_, position) => position,
-            ParseError::ExpectedIdentifier(_, position) => position,
-            ParseError::ExpectedNumber(position) => position,
-            ParseError::MissingEqualsAfterLet(position) => position,
-            ParseError::UnexpectedValueAfterReq(_, position) => position,
-            ParseError::MissingDotAfterReq(position) => position,
-            ParseError::MissingDotAfterBody(position) => position,
-            ParseError::MissingDotAfterParams(position) => position,
-            ParseError::MissingDotAfterQuery(position) => position,
-            ParseError::MissingClosingParen(position) => position,
-            ParseError::ExtraCharacters(position) => position,
-            ParseError::InvalidEscape(_, position) => position,
-            ParseError::UnterminatedString(position) => position,
-            ParseError::InvalidAssignmentTarget(position) => position,
-            ParseError::ExpectedExpression(position) => position,
-            ParseError::General(_, position) => position,
+        let pos: Position = match self {
+            ParseError::UnexpectedEOF(position) => *position,
+            ParseError::UnexpectedChar(_, position) => *position,
+            ParseError::ExpectedIdentifier(_, position) => *position,
+            ParseError::ExpectedNumber(position) => *position,
+            ParseError::MissingEqualsAfterLet(position) => *position,
+            ParseError::UnexpectedValueAfterReq(_, position) => *position,
+            ParseError::MissingDotAfterReq(position) => *position,
+            ParseError::MissingDotAfterBody(position) => *position,
+            ParseError::MissingDotAfterParams(position) => *position,
+            ParseError::MissingDotAfterQuery(position) => *position,
+            ParseError::MissingClosingParen(position) => *position,
+            ParseError::ExtraCharacters(position) => *position,
+            ParseError::InvalidEscape(_, position) => *position,
+            ParseError::MissingUnicodeBrace(position) => *position,
+            ParseError::InvalidHexDigit(_, position) => *position,
+            ParseError::UnterminatedUnicodeEscape(position) => *position,
+            ParseError::InvalidCodePoint(_, position) => *position,
+            ParseError::UnterminatedString(position) => *position,
+            ParseError::InvalidAssignmentTarget(position) => *position,
+            ParseError::ExpectedExpression(position) => *position,
+            ParseError::General(_, position) => *position,
         };
 
         pos
@@ -123,6 +135,26 @@ impl fmt::Display for ParseError {
                     ch, at.line, at.column
                 )
             }
+            ParseError::MissingUnicodeBrace(at) => {
+                write!(f, "Expected '{{' after \\u, at {}:{}", at.line, at.column)
+            }
+            ParseError::InvalidHexDigit(ch, at) => {
+                write!(f, "Invalid hex digit '{}' in escape, at {}:{}", ch, at.line, at.column)
+            }
+            ParseError::UnterminatedUnicodeEscape(at) => {
+                write!(
+                    f,
+                    "Unterminated \\u{{...}} escape, expected '}}', at {}:{}",
+                    at.line, at.column
+                )
+            }
+            ParseError::InvalidCodePoint(digits, at) => {
+                write!(
+                    f,
+                    "Invalid code point '{}' in \\u{{...}}, expected 1 to 6 hex digits naming a Unicode scalar value, at {}:{}",
+                    digits, at.line, at.column
+                )
+            }
             ParseError::UnterminatedString(at) => {
                 write!(
                     f,
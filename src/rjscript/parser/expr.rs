@@ -1,10 +1,16 @@
 use crate::rjscript::ast::binop::BinOp;
+use crate::rjscript::ast::block::Block;
+use crate::rjscript::ast::stmt::StmtKind;
+use crate::rjscript::semantics::types::VarType;
 use crate::rjscript::ast::expr::{Expr, ExprKind, TemplatePart};
 use crate::rjscript::ast::literal::Literal;
 use crate::rjscript::ast::node::Located;
+use crate::rjscript::ast::param::Param;
 use crate::rjscript::parser::errors::ParseError;
-use crate::rjscript::parser::lexer::token::TokenKind;
+use crate::rjscript::parser::lexer::token::{TemplateChunk, TokenKind};
 use crate::rjscript::parser::parser::Parser;
+use crate::rjscript::parser::block::parse_block;
+use crate::rjscript::parser::stmt::{parse_fn_signature, parse_param_default};
 use crate::rjscript::parser::ParseResult;
 
 /// Operator precedences, from lowest to highest.
@@ -90,32 +96,25 @@ fn parse_prefix(parser: &mut Parser) -> ParseResult<Expr> {
                 parser.last_pos,
             ))
         }
-        TokenKind::Template(raw) => {
+        TokenKind::Template(chunks) => {
             parser.advance()?; // consume the Template token
-            let mut parts = Vec::new();
-            let mut rest = raw.as_str();
-            while let Some(idx) = rest.find("${") {
-                // literal text before ${
-                if idx > 0 {
-                    parts.push(TemplatePart::Text(rest[..idx].to_string()));
+            let mut parts = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                match chunk {
+                    TemplateChunk::Text(text) => parts.push(TemplatePart::Text(text)),
+                    TemplateChunk::Expr(src) => {
+                        // parse the interpolated sub‐expression by re-lexing its source
+                        let mut subp = Parser::new(&src)?;
+                        let expr = parse_expr(&mut subp)?;
+                        if !subp.is_at_end()? {
+                            return Err(ParseError::General(
+                                format!("Unexpected input after expression in ${{{}}}", src.trim()),
+                                parser.last_pos,
+                            ));
+                        }
+                        parts.push(TemplatePart::Expr(expr));
+                    }
                 }
-                // drop the `${`
-                rest = &rest[idx + 2..];
-                // find the closing `}`
-                let end = rest.find('}').ok_or_else(|| {
-                    ParseError::General("Unclosed ${ in template".into(), parser.last_pos)
-                })?;
-                let expr_src = &rest[..end];
-                // parse that sub‐expression by re-lexing
-                let mut subp = Parser::new(expr_src)?;
-                let expr = parse_expr(&mut subp)?;
-                parts.push(TemplatePart::Expr(expr));
-                // advance past `}`
-                rest = &rest[end + 1..];
-            }
-            // any trailing text
-            if !rest.is_empty() {
-                parts.push(TemplatePart::Text(rest.to_string()));
             }
             return Ok(Located::new(ExprKind::Template(parts), parser.last_pos));
         }
@@ -127,6 +126,11 @@ fn parse_prefix(parser: &mut Parser) -> ParseResult<Expr> {
                 parser.last_pos,
             ))
         }
+        // Null literal
+        TokenKind::Null => {
+            parser.advance()?;
+            Ok(Located::new(ExprKind::Literal(Literal::Null), parser.last_pos))
+        }
         // Array literal
         LBracket => {
             parser.advance()?;
@@ -150,7 +154,11 @@ fn parse_prefix(parser: &mut Parser) -> ParseResult<Expr> {
                 loop {
                     // Parse key: either bare identifier or string literal
                     let key = match parser.peek_kind()?.clone() {
-                        TokenKind::Ident(_) => parser.consume_ident()?,
+                        TokenKind::Ident(_)
+                        | TokenKind::Body
+                        | TokenKind::Params
+                        | TokenKind::Query
+                        | TokenKind::Headers => parser.consume_property_name()?,
                         TokenKind::String(s) => {
                             parser.advance()?;
                             s
@@ -177,15 +185,34 @@ fn parse_prefix(parser: &mut Parser) -> ParseResult<Expr> {
         | TokenKind::ObjType
         | TokenKind::VecType
         | TokenKind::AnyType
-        | TokenKind::UndefinedType => {
+        | TokenKind::UndefinedType
+        | TokenKind::NullType
+        | TokenKind::FnType => {
             let ty = parser.parse_type()?;
             Ok(Located::new(ExprKind::TypeLiteral(ty), parser.last_pos))
         }
+        // Anonymous function: func (x: num): num { ... }
+        Func => {
+            parser.advance()?; // consume 'func'
+            let start_pos = parser.last_pos;
+            let (params, return_type) = parse_fn_signature(parser)?;
+            let body = parse_block(parser)?;
+            Ok(Located::new(
+                ExprKind::FunctionExpr {
+                    params,
+                    return_type,
+                    body,
+                },
+                start_pos,
+            ))
+        }
         // Variable or identifier
         Ident(_) => {
             let name = parser.consume_ident()?;
             Ok(Located::new(ExprKind::Ident(name), parser.last_pos))
         }
+        // Arrow function: (x, y: num) => expr | (x) => { ... }
+        LParen if parser.is_arrow_fn_ahead() => parse_arrow_fn(parser),
         // Grouping
         LParen => {
             parser.advance()?;
@@ -214,6 +241,49 @@ fn parse_prefix(parser: &mut Parser) -> ParseResult<Expr> {
     }
 }
 
+/// Parses an arrow function into the same `FunctionExpr` as the `func` form.
+/// Params without a type annotation, and the return value, are `any`.
+fn parse_arrow_fn(parser: &mut Parser) -> ParseResult<Expr> {
+    parser.expect_kind(TokenKind::LParen)?;
+    let start_pos = parser.last_pos;
+    let mut params = Vec::new();
+    if !parser.match_kind(TokenKind::RParen)? {
+        loop {
+            let name = parser.consume_ident()?;
+            let ty = if parser.match_kind(TokenKind::Colon)? {
+                parser.parse_assignment_type()?
+            } else {
+                VarType::Any
+            };
+            let default = parse_param_default(parser, &params, &name)?;
+            params.push(Param::new(name, ty, default));
+            if parser.match_kind(TokenKind::RParen)? {
+                break;
+            }
+            parser.expect_kind(TokenKind::Comma)?;
+        }
+    }
+    parser.expect_kind(TokenKind::FatArrow)?;
+
+    // A `{` starts a block body; anything else is a single returned expression
+    let body = if *parser.peek_kind()? == TokenKind::LBrace {
+        parse_block(parser)?
+    } else {
+        let value = parse_expr(parser)?;
+        let pos = value.pos;
+        Block::new(vec![Located::new(StmtKind::Return(value), pos)], pos)
+    };
+
+    Ok(Located::new(
+        ExprKind::FunctionExpr {
+            params,
+            return_type: VarType::Any,
+            body,
+        },
+        start_pos,
+    ))
+}
+
 fn parse_precedence(parser: &mut Parser, min_prec: Precedence) -> ParseResult<Expr> {
     // 1) Parse the left-hand side via prefix
     let mut left = parse_prefix(parser)?;
@@ -221,7 +291,7 @@ fn parse_precedence(parser: &mut Parser, min_prec: Precedence) -> ParseResult<Ex
     // 2) Handle postfix operators (calls, indexing, member)
     loop {
         if parser.match_kind(TokenKind::Dot)? {
-            let prop = parser.consume_ident()?;
+            let prop = parser.consume_property_name()?;
             left = Located::new(
                 ExprKind::Member {
                     object: Box::new(left),
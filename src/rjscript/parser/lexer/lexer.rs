@@ -1,7 +1,7 @@
 use std::{iter::Peekable, str::Chars};
 
 use crate::rjscript::{
-    ast::position::Position, parser::{errors::ParseError, lexer::token::{Token, TokenKind}}
+    ast::position::Position, parser::{errors::ParseError, lexer::token::{TemplateChunk, Token, TokenKind}}
 };
 
 #[derive(Clone)]
@@ -19,6 +19,8 @@ Lexer<'a> {
     line: usize,
     /// 1-based column
     column: usize,
+    /// Emit comments as `TokenKind::Comment` instead of skipping them
+    keep_comments: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -34,9 +36,24 @@ impl<'a> Lexer<'a> {
             finished: false,
             line: 1,
             column: 1,
+            keep_comments: false,
         }
     }
 
+    /// Like `new`, but comments come out as `TokenKind::Comment` trivia tokens
+    /// for tools that need them (a formatter). The evaluator never uses this mode.
+    pub fn with_comments(input: &'a str) -> Self {
+        Lexer {
+            keep_comments: true,
+            ..Lexer::new(input)
+        }
+    }
+
+    /// Whether a `//` or `/*` comment starts at the current character.
+    fn at_comment(&mut self) -> bool {
+        self.current_char() == Some('/') && matches!(self.peek_next(), Some('/' | '*'))
+    }
+
     /// Advance one character by popping from `chars`, updating `pos` and `current`.
     fn advance(&mut self) {
         if let Some(ch) = self.current {
@@ -73,6 +90,10 @@ impl<'a> Lexer<'a> {
                     break;
                 }
             }
+            if self.keep_comments && self.at_comment() {
+                // left for `next_token` to emit
+                break;
+            }
             // Skip line comments
             if self.current_char() == Some('/') && self.peek_next() == Some('/') {
                 // consume '//'
@@ -104,6 +125,31 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Consume the comment at the current character, without its delimiters.
+    /// An unterminated block comment runs to the end of the input.
+    fn lex_comment(&mut self) -> TokenKind {
+        self.advance(); // '/'
+        let block = self.current_char() == Some('*');
+        self.advance(); // '/' or '*'
+        let start = self.pos;
+        if !block {
+            let text = self.scan_while(start, |ch| ch != '\n');
+            return TokenKind::Comment { text: text.to_string(), block };
+        }
+        let mut end = self.pos;
+        while let Some(ch) = self.current_char() {
+            if ch == '*' && self.peek_next() == Some('/') {
+                end = self.pos;
+                self.advance();
+                self.advance();
+                break;
+            }
+            self.advance();
+            end = self.pos;
+        }
+        TokenKind::Comment { text: self.input[start..end].to_string(), block }
+    }
+
     /// Scan characters while `pred` holds true, returning the slice from `start` to current `pos`.
     fn scan_while<F>(&mut self, start: usize, pred: F) -> &'a str
     where
@@ -126,23 +172,62 @@ impl<'a> Lexer<'a> {
         slice.to_string()
     }
 
-    /// Consume a numeric literal: digits with optional single dot.
-    fn lex_number(&mut self) -> Result<f64, ParseError> {
-        let start = self.pos;
-        let start_pos = Position {
+    /// Position of the current character.
+    fn here(&self) -> Position {
+        Position {
             line: self.line,
             column: self.column,
-        };
+        }
+    }
+
+    /// Consume a numeric literal: `0x` hex digits, or digits with an optional
+    /// fraction and an optional `e`/`E` exponent (`1.5e-3`).
+    fn lex_number(&mut self) -> Result<f64, ParseError> {
+        let start = self.pos;
+        let start_pos = self.here();
+
+        // hexadecimal integer
+        if self.current_char() == Some('0') && matches!(self.peek_next(), Some('x' | 'X')) {
+            self.advance(); // '0'
+            self.advance(); // 'x'
+            let digits_start = self.pos;
+            let digits = self.scan_while(digits_start, |ch| ch.is_ascii_hexdigit());
+            if digits.is_empty() || self.current_char().is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '_') {
+                return Err(ParseError::ExpectedNumber(self.here()));
+            }
+            return u64::from_str_radix(digits, 16)
+                .map(|n| n as f64)
+                .map_err(|_| ParseError::ExpectedNumber(start_pos));
+        }
+
         // integer part
         self.scan_while(start, |ch| ch.is_ascii_digit());
         // optional fractional part
         if self.current_char() == Some('.') {
             self.advance();
             self.scan_while(self.pos, |ch| ch.is_ascii_digit());
+            // a second `.digit` would silently split into two tokens
+            if self.current_char() == Some('.') && self.peek_next().is_some_and(|ch| ch.is_ascii_digit()) {
+                return Err(ParseError::ExpectedNumber(self.here()));
+            }
+        }
+        // optional exponent
+        if matches!(self.current_char(), Some('e' | 'E')) {
+            self.advance();
+            if matches!(self.current_char(), Some('+' | '-')) {
+                self.advance();
+            }
+            let exp = self.scan_while(self.pos, |ch| ch.is_ascii_digit());
+            if exp.is_empty() {
+                return Err(ParseError::ExpectedNumber(self.here()));
+            }
         }
         let text = &self.input[start..self.pos];
+        // `1e400` parses as infinity, which no literal means
         text.parse::<f64>()
-            .map_err(|_| ParseError::ExpectedNumber(start_pos))
+            .ok()
+            .filter(|n| n.is_finite())
+            .ok_or(ParseError::ExpectedNumber(start_pos))
     }
 
     fn lex_string(&mut self) -> Result<String, ParseError> {
@@ -159,6 +244,7 @@ impl<'a> Lexer<'a> {
                 self.advance(); // consume closing quote
                 return Ok(s);
             } else if ch == '\\' {
+                let escape_pos = self.here();
                 self.advance(); // consume '\'
                 match self.current_char() {
                     Some('n') => {
@@ -181,7 +267,9 @@ impl<'a> Lexer<'a> {
                         s.push('\\');
                         self.advance();
                     }
-                    Some(other) => return Err(ParseError::InvalidEscape(other, start_pos)),
+                    Some('u') => s.push(self.lex_unicode_escape(escape_pos)?),
+                    Some('x') => s.push(self.lex_hex_escape(escape_pos)?),
+                    Some(other) => return Err(ParseError::InvalidEscape(other, escape_pos)),
                     None => return Err(ParseError::UnexpectedEOF(start_pos)),
                 }
             } else {
@@ -192,18 +280,54 @@ impl<'a> Lexer<'a> {
         Err(ParseError::UnterminatedString(start_pos))
     }
 
-    fn lex_template(&mut self) -> Result<String, ParseError> {
+    fn lex_template(&mut self) -> Result<Vec<TemplateChunk>, ParseError> {
         let start_pos = Position {
             line: self.line,
             column: self.column,
         };
 
         self.advance(); // eat the `
+        let mut chunks = Vec::new();
         let mut buf = String::new();
         while let Some(c) = self.current_char() {
             if c == '`' {
                 self.advance(); // eat the closing backtick
-                return Ok(buf);
+                if !buf.is_empty() {
+                    chunks.push(TemplateChunk::Text(buf));
+                }
+                return Ok(chunks);
+            }
+            if c == '$' && self.peek_next() == Some('{') {
+                if !buf.is_empty() {
+                    chunks.push(TemplateChunk::Text(std::mem::take(&mut buf)));
+                }
+                let interp_pos = self.here();
+                self.advance(); // eat '$'
+                self.advance(); // eat '{'
+                let src = self.lex_interpolation(interp_pos)?;
+                chunks.push(TemplateChunk::Expr(src.to_string()));
+                continue;
+            }
+            if c == '\\' {
+                let escape_pos = self.here();
+                self.advance(); // consume '\'
+                match self.current_char() {
+                    // `\${` is literal text, not an interpolation
+                    Some(ch @ ('$' | '`' | '\\')) => {
+                        buf.push(ch);
+                        self.advance();
+                    }
+                    Some('u') => buf.push(self.lex_unicode_escape(escape_pos)?),
+                    Some('x') => buf.push(self.lex_hex_escape(escape_pos)?),
+                    // everything else is kept verbatim
+                    Some(other) => {
+                        buf.push('\\');
+                        buf.push(other);
+                        self.advance();
+                    }
+                    None => break,
+                }
+                continue;
             }
             buf.push(c);
             self.advance();
@@ -212,6 +336,88 @@ impl<'a> Lexer<'a> {
         Err(ParseError::UnterminatedString(start_pos))
     }
 
+    /// Scan the body of a `${...}` up to its matching `}` and return its source.
+    /// Braces are counted, and string and template literals are skipped whole, so
+    /// object literals, nested templates and `"}"` inside the expression are fine.
+    fn lex_interpolation(&mut self, interp_pos: Position) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.current_char() {
+                None => {
+                    return Err(ParseError::General(
+                        "Unclosed ${ in template".into(),
+                        interp_pos,
+                    ))
+                }
+                Some('"') => {
+                    self.lex_string()?;
+                }
+                Some('`') => {
+                    self.lex_template()?;
+                }
+                Some('{') => {
+                    depth += 1;
+                    self.advance();
+                }
+                Some('}') if depth == 0 => {
+                    let src = &self.input[start..self.pos];
+                    self.advance(); // eat the closing '}'
+                    return Ok(src);
+                }
+                Some('}') => {
+                    depth -= 1;
+                    self.advance();
+                }
+                Some(_) => self.advance(),
+            }
+        }
+    }
+
+    /// Decode `u{XXXX}` (1 to 6 hex digits) after a backslash; `escape_pos` points at the backslash.
+    fn lex_unicode_escape(&mut self, escape_pos: Position) -> Result<char, ParseError> {
+        self.advance(); // consume 'u'
+        match self.current_char() {
+            Some('{') => self.advance(),
+            Some(_) => return Err(ParseError::MissingUnicodeBrace(self.here())),
+            None => return Err(ParseError::UnexpectedEOF(escape_pos)),
+        }
+        let digits_pos = self.here();
+        let start = self.pos;
+        let digits = self.scan_while(start, |ch| ch.is_ascii_hexdigit());
+        match self.current_char() {
+            Some('}') => {}
+            // the string or the line ends before the escape does
+            Some('"' | '\n') | None => return Err(ParseError::UnterminatedUnicodeEscape(escape_pos)),
+            Some(other) => return Err(ParseError::InvalidHexDigit(other, self.here())),
+        }
+        let ch = (1..=6)
+            .contains(&digits.len())
+            .then(|| u32::from_str_radix(digits, 16).ok())
+            .flatten()
+            .and_then(char::from_u32)
+            .ok_or_else(|| ParseError::InvalidCodePoint(digits.to_string(), digits_pos))?;
+        self.advance(); // consume '}'
+        Ok(ch)
+    }
+
+    /// Decode `xNN` (exactly two hex digits, U+0000 to U+00FF) after a backslash.
+    fn lex_hex_escape(&mut self, escape_pos: Position) -> Result<char, ParseError> {
+        self.advance(); // consume 'x'
+        let mut code = 0u32;
+        for _ in 0..2 {
+            match self.current_char() {
+                Some(ch) if ch.is_ascii_hexdigit() => {
+                    code = code * 16 + ch.to_digit(16).unwrap_or(0);
+                    self.advance();
+                }
+                Some(other) => return Err(ParseError::InvalidHexDigit(other, self.here())),
+                None => return Err(ParseError::UnexpectedEOF(escape_pos)),
+            }
+        }
+        Ok(char::from(code as u8))
+    }
+
     /// Produce the next single `Token`.  On error, returns a `ParseError`.
     pub fn next_token(&mut self) -> Result<Token, ParseError> {
         self.skip_whitespace();
@@ -221,6 +427,7 @@ impl<'a> Lexer<'a> {
         };
 
         let tok = match self.current_char() {
+            Some('/') if self.keep_comments && self.at_comment() => self.lex_comment(),
             None => {
                 // EOF
                 self.finished = true;
@@ -233,6 +440,10 @@ impl<'a> Lexer<'a> {
                         self.advance();
                         self.advance();
                         TokenKind::EqEq
+                    } else if let Some('>') = self.peek_next() {
+                        self.advance();
+                        self.advance();
+                        TokenKind::FatArrow
                     } else {
                         self.advance();
                         TokenKind::Eq
@@ -285,7 +496,8 @@ impl<'a> Lexer<'a> {
                         self.advance();
                         TokenKind::OrOr
                     } else {
-                        return Err(ParseError::UnexpectedChar('|', start_pos));
+                        self.advance();
+                        TokenKind::Pipe
                     }
                 }
                 else if ch == '+' {
@@ -344,6 +556,11 @@ impl<'a> Lexer<'a> {
                     let ident = self.lex_identifier();
                     match ident.as_str() {
                         "let" => TokenKind::Let,
+                        "const" => TokenKind::Const,
+                        "throw" => TokenKind::Throw,
+                        "try" => TokenKind::Try,
+                        "catch" => TokenKind::Catch,
+                        "include" => TokenKind::Include,
                         "return" => TokenKind::Return,
                         "if" => TokenKind::If,
                         "else" => TokenKind::Else,
@@ -354,6 +571,7 @@ impl<'a> Lexer<'a> {
                         "func" => TokenKind::Func,
                         "break" => TokenKind::Break,
                         "continue" => TokenKind::Continue,
+                        "fallthrough" => TokenKind::Fallthrough,
                         "req" => TokenKind::Req,
                         "body" => TokenKind::Body,
                         "params" => TokenKind::Params,
@@ -362,6 +580,7 @@ impl<'a> Lexer<'a> {
                         "true" => TokenKind::Bool(true),
                         "false" => TokenKind::Bool(false),
                         "undefined" => TokenKind::Undefined,
+                        "null" => TokenKind::Null,
 
                         // type keywords
                         "bool" => TokenKind::BoolType,
@@ -371,6 +590,8 @@ impl<'a> Lexer<'a> {
                         "obj" => TokenKind::ObjType,
                         "any" => TokenKind::AnyType,
                         "Undefined" => TokenKind::UndefinedType,
+                        "Null" => TokenKind::NullType,
+                        "fn" => TokenKind::FnType,
 
                         // Just an indentifier
                         _ => TokenKind::Ident(ident),
@@ -405,3 +626,242 @@ impl<'a> Iterator for Lexer<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value of the string literal `src`, or the error lexing it.
+    fn string(src: &str) -> Result<String, ParseError> {
+        match Lexer::new(src).next_token()?.kind {
+            TokenKind::String(s) => Ok(s),
+            other => panic!("not a string: {:?}", other),
+        }
+    }
+
+    /// The value of the number literal `src`, or the error lexing it.
+    fn number(src: &str) -> Result<f64, ParseError> {
+        match Lexer::new(src).next_token()?.kind {
+            TokenKind::Number(n) => Ok(n),
+            other => panic!("not a number: {:?}", other),
+        }
+    }
+
+    fn at(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+
+    #[test]
+    fn escapes_decode() {
+        assert_eq!(string(r#""a\n\t\r\"\\b""#).unwrap(), "a\n\t\r\"\\b");
+        assert_eq!(string(r#""\x41\x7e\xff""#).unwrap(), "A~\u{ff}");
+        assert_eq!(string(r#""\u{41}\u{1F600}""#).unwrap(), "A\u{1F600}");
+        assert_eq!(string(r#""\u{0}\u{10FFFF}\u{00000a}""#).unwrap(), "\0\u{10FFFF}\n");
+    }
+
+    #[test]
+    fn unknown_escape_points_at_the_backslash() {
+        let err = string(r#""ab\q""#).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidEscape('q', p) if p == at(1, 4)), "{:?}", err);
+    }
+
+    #[test]
+    fn unicode_escape_without_brace() {
+        let err = string(r#""\u0041""#).unwrap_err();
+        assert!(matches!(err, ParseError::MissingUnicodeBrace(p) if p == at(1, 4)), "{:?}", err);
+    }
+
+    #[test]
+    fn unicode_escape_bad_digit() {
+        let err = string(r#""\u{12g4}""#).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidHexDigit('g', p) if p == at(1, 7)), "{:?}", err);
+    }
+
+    #[test]
+    fn unicode_escape_unterminated() {
+        for src in [r#""x\u{41""#, "\"x\\u{41\n\"", "\"x\\u{41"] {
+            let err = string(src).unwrap_err();
+            assert!(matches!(err, ParseError::UnterminatedUnicodeEscape(p) if p == at(1, 3)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn unicode_escape_out_of_range() {
+        for (src, digits) in [
+            (r#""\u{}""#, ""),
+            (r#""\u{D800}""#, "D800"),
+            (r#""\u{110000}""#, "110000"),
+            (r#""\u{0000041}""#, "0000041"),
+        ] {
+            let err = string(src).unwrap_err();
+            assert!(
+                matches!(&err, ParseError::InvalidCodePoint(d, p) if d == digits && *p == at(1, 5)),
+                "{}: {:?}",
+                src,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn hex_escape_needs_two_digits() {
+        let err = string(r#""\x4""#).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidHexDigit('"', p) if p == at(1, 5)), "{:?}", err);
+        let err = string(r#""\xg0""#).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidHexDigit('g', p) if p == at(1, 4)), "{:?}", err);
+        assert!(matches!(string("\"\\x"), Err(ParseError::UnexpectedEOF(_))));
+    }
+
+    #[test]
+    fn errors_print_their_position() {
+        let err = string(r#""\u{D800}""#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid code point 'D800' in \\u{...}, expected 1 to 6 hex digits naming a Unicode scalar value, at 1:5"
+        );
+        assert_eq!(err.pos(), at(1, 5));
+    }
+
+    #[test]
+    fn numbers_lex() {
+        for (src, n) in [
+            ("0", 0.0),
+            ("42", 42.0),
+            ("3.25", 3.25),
+            ("0x1F", 31.0),
+            ("0XfF", 255.0),
+            ("1e3", 1000.0),
+            ("2E+2", 200.0),
+            ("1.5e-3", 0.0015),
+            ("0xFFFFFFFFFFFFFFFF", u64::MAX as f64),
+            ("1.7976931348623157e308", f64::MAX),
+            ("1e-400", 0.0),
+        ] {
+            assert_eq!(number(src).unwrap(), n, "{}", src);
+        }
+    }
+
+    #[test]
+    fn number_ends_at_the_literal() {
+        let mut lexer = Lexer::new("0x10+1e1;");
+        let kinds: Vec<TokenKind> = std::iter::from_fn(|| {
+            let tok = lexer.next_token().unwrap();
+            (tok.kind != TokenKind::EOF).then_some(tok.kind)
+        })
+        .collect();
+        assert_eq!(
+            kinds,
+            [TokenKind::Number(16.0), TokenKind::Plus, TokenKind::Number(10.0), TokenKind::Semicolon]
+        );
+    }
+
+    #[test]
+    fn malformed_numbers_point_at_the_bad_character() {
+        for (src, col) in [
+            ("0x", 3),
+            ("0x;", 3),
+            ("0x1g", 4),
+            ("0x1_0", 4),
+            ("1e", 3),
+            ("1e+", 4),
+            ("1.5e-x", 6),
+            ("1.2.3", 4),
+        ] {
+            let err = number(src).unwrap_err();
+            assert!(matches!(err, ParseError::ExpectedNumber(p) if p == at(1, col)), "{}: {:?}", src, err);
+        }
+    }
+
+    #[test]
+    fn overflowing_literals_point_at_the_literal() {
+        for src in ["0x10000000000000000", "1e400", "1.8e308", "9".repeat(400).as_str()] {
+            let err = number(src).unwrap_err();
+            assert!(matches!(err, ParseError::ExpectedNumber(p) if p == at(1, 1)), "{}: {:?}", src, err);
+        }
+    }
+
+    /// Every token of `lexer` up to EOF, with its position.
+    fn tokens(lexer: Lexer) -> Vec<(TokenKind, Position)> {
+        lexer
+            .map(|tok| tok.unwrap())
+            .take_while(|tok| tok.kind != TokenKind::EOF)
+            .map(|tok| (tok.kind, tok.pos))
+            .collect()
+    }
+
+    fn comment(text: &str, block: bool) -> TokenKind {
+        TokenKind::Comment { text: text.into(), block }
+    }
+
+    const COMMENTED: &str = "let x: num = 1; // one\n/* two\n lines */ return x /* mid */ + 1; /* end";
+
+    #[test]
+    fn comments_are_kept_as_tokens_on_request() {
+        let comments: Vec<_> = tokens(Lexer::with_comments(COMMENTED))
+            .into_iter()
+            .filter(|(kind, _)| matches!(kind, TokenKind::Comment { .. }))
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                (comment(" one", false), at(1, 17)),
+                (comment(" two\n lines ", true), at(2, 1)),
+                (comment(" mid ", true), at(3, 20)),
+                // unterminated: runs to the end of the input
+                (comment(" end", true), at(3, 35)),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_do_not_change_the_other_tokens() {
+        let plain = tokens(Lexer::new(COMMENTED));
+        assert!(plain.iter().all(|(kind, _)| !matches!(kind, TokenKind::Comment { .. })));
+        let kept: Vec<_> = tokens(Lexer::with_comments(COMMENTED))
+            .into_iter()
+            .filter(|(kind, _)| !matches!(kind, TokenKind::Comment { .. }))
+            .collect();
+        assert_eq!(plain, kept);
+        assert_eq!(plain.last(), Some(&(TokenKind::Semicolon, at(3, 33))));
+    }
+
+    #[test]
+    fn a_lone_slash_is_still_division() {
+        let kinds: Vec<_> = tokens(Lexer::with_comments("6 / 2 //")).into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [TokenKind::Number(6.0), TokenKind::Slash, TokenKind::Number(2.0), comment("", false)]);
+    }
+
+    /// The chunks of the template literal `src`, or the error lexing it.
+    fn template(src: &str) -> Result<Vec<TemplateChunk>, ParseError> {
+        match Lexer::new(src).next_token()?.kind {
+            TokenKind::Template(chunks) => Ok(chunks),
+            other => panic!("not a template: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn interpolations_end_at_their_matching_brace() {
+        use TemplateChunk::{Expr, Text};
+        let text = |s: &str| Text(s.to_string());
+        let expr = |s: &str| Expr(s.to_string());
+        assert_eq!(
+            template(r#"`count: ${ {a: {b: 1}}["a"] }!`"#).unwrap(),
+            vec![text("count: "), expr(r#" {a: {b: 1}}["a"] "#), text("!")]
+        );
+        assert_eq!(template(r#"`${"}"}`"#).unwrap(), vec![expr(r#""}""#)]);
+        assert_eq!(template("`a ${`b ${c}`} d`").unwrap(), vec![text("a "), expr("`b ${c}`"), text(" d")]);
+        assert_eq!(template("`} and {} ${x}}`").unwrap(), vec![text("} and {} "), expr("x"), text("}")]);
+        assert!(matches!(template("`${ {a: 1} `"), Err(ParseError::UnterminatedString(_))));
+    }
+
+    #[test]
+    fn templates_with_nested_braces_evaluate() {
+        use crate::rjscript::testing::{eval_expr_str, TestWorld};
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world).map(|v| v.to_string());
+        assert_eq!(eval(r#"`count: ${ {a: 1}["a"] }`"#), Ok("count: 1".into()));
+        assert_eq!(eval(r#"`${ {a: {b: [1, 2]}}["a"]["b"].length() } items`"#), Ok("2 items".into()));
+        assert_eq!(eval(r#"`<${ `[${ "}" }]` }>`"#), Ok("<[}]>".into()));
+        assert_eq!(eval("`{ ok: ${1 + 1} }`"), Ok("{ ok: 2 }".into()));
+    }
+}
@@ -23,6 +23,7 @@ pub enum TokenKind {
     // One- or two-character tokens
     Eq,     // '='
     EqEq,   // '=='
+    FatArrow, // '=>'
     BangEq, // '!='
     Lt,     // '<'
     LtEq,   // '<='
@@ -30,13 +31,15 @@ pub enum TokenKind {
     GtEq,   // '>='
     AndAnd, // '&&'
     OrOr,   // '||'
+    Pipe,   // '|'
 
     // Literals
     Number(f64),
     String(String), // "...."
-    Template(String), // '`${var}`'
+    Template(Vec<TemplateChunk>), // '`${var}`'
     Bool(bool),
     Undefined,
+    Null,
 
     //Types
     NumberType,
@@ -46,10 +49,17 @@ pub enum TokenKind {
     ObjType,
     AnyType,
     UndefinedType,
+    NullType,
+    FnType,
 
     // --- identifiers & keywords ---
     Ident(String),
     Let,    // 'let'
+    Const,  // 'const'
+    Throw,  // 'throw'
+    Try,    // 'try'
+    Catch,  // 'catch'
+    Include, // 'include'
     Return, // 'return'
     If,     // 'if'
     Else,   // 'else'
@@ -59,6 +69,7 @@ pub enum TokenKind {
     For,   // 'for'
     Break, // 'break'
     Continue, // 'continue'
+    Fallthrough, // 'fallthrough'
     Func,   // 'for'
     Req,    // 'req'
     Body,   // 'body'
@@ -68,6 +79,16 @@ pub enum TokenKind {
 
     /// End‐of‐input sentinel
     EOF,
+
+    /// `// text` or `/* text */`, only from `Lexer::with_comments`
+    Comment { text: String, block: bool },
+}
+
+/// A piece of a template literal: plain text, or the source of a `${...}` interpolation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateChunk {
+    Text(String),
+    Expr(String),
 }
 
 #[derive(Debug, Clone)]
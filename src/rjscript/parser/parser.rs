@@ -16,6 +16,25 @@ use crate::rjscript::semantics::types::VarType;
 pub struct Parser<'a> {
     pub last_pos: Position,
     pub tokens: Peekable<Lexer<'a>>,
+    /// Comments taken out of the token stream, when the lexer keeps them
+    pub comments: Vec<Comment>,
+    /// Line of the last consumed token, for telling trailing comments apart
+    last_line: Option<usize>,
+}
+
+/// A source comment kept by `parse_script_with_comments`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Text between the delimiters
+    pub text: String,
+    /// `/* */` rather than `//`
+    pub block: bool,
+    pub pos: Position,
+    /// Start of the token that follows, so the AST node starting there. At the end
+    /// of a block that is its `}`, at the end of the script the EOF position.
+    pub before: Position,
+    /// On the same line as the token before it (`x = 1; // note`)
+    pub trailing: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -25,11 +44,43 @@ impl<'a> Parser<'a> {
         Ok(Parser {
             last_pos: Position { line: 1, column: 1 },
             tokens: lexer.peekable(),
+            comments: Vec::new(),
+            last_line: None,
         })
     }
 
+    /// Like `new`, also collecting comments into `comments`.
+    pub fn with_comments(input: &'a str) -> Self {
+        Parser {
+            last_pos: Position { line: 1, column: 1 },
+            tokens: Lexer::with_comments(input).peekable(),
+            comments: Vec::new(),
+            last_line: None,
+        }
+    }
+
+    /// Moves comment tokens ahead of the parser into `comments`, so the grammar
+    /// never sees them.
+    fn take_comments(&mut self) {
+        let first = self.comments.len();
+        while let Some(Ok(Token { kind: TokenKind::Comment { .. }, .. })) = self.tokens.peek() {
+            let Some(Ok(Token { kind: TokenKind::Comment { text, block }, pos })) = self.tokens.next() else {
+                unreachable!("peeked a comment");
+            };
+            let trailing = self.last_line == Some(pos.line);
+            self.comments.push(Comment { text, block, pos, before: pos, trailing });
+        }
+        if let Some(Ok(next)) = self.tokens.peek() {
+            let before = next.pos;
+            for comment in &mut self.comments[first..] {
+                comment.before = before;
+            }
+        }
+    }
+
     /// Peek at the current token without consuming it.
     pub fn peek(&mut self) -> Result<&Token, ParseError> {
+        self.take_comments();
         match self.tokens.peek() {
             Some(Ok(tok)) => Ok(tok),
             Some(Err(err)) => Err(err.clone()),
@@ -44,9 +95,11 @@ impl<'a> Parser<'a> {
 
     /// Advance one token and return it.
     pub fn advance(&mut self) -> Result<Token, ParseError> {
+        self.take_comments();
         match self.tokens.next().transpose()? {
             Some(tok) => {
                 self.last_pos = tok.pos;
+                self.last_line = Some(tok.pos.line);
                 Ok(tok)
             }
             None => Err(ParseError::UnexpectedEOF(self.last_pos)),
@@ -63,6 +116,31 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Whether the upcoming `( ... )` is followed by `=>`, i.e. starts an arrow function.
+    /// Scans a copy of the token stream, so nothing is consumed.
+    pub fn is_arrow_fn_ahead(&self) -> bool {
+        let mut toks = self
+            .tokens
+            .clone()
+            .filter(|t| !matches!(t, Ok(Token { kind: TokenKind::Comment { .. }, .. })));
+        let mut depth = 0usize;
+        while let Some(Ok(tok)) = toks.next() {
+            match tok.kind {
+                TokenKind::LParen => depth += 1,
+                TokenKind::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                TokenKind::EOF => return false,
+                _ if depth == 0 => return false,
+                _ => {}
+            }
+        }
+        matches!(toks.next(), Some(Ok(Token { kind: TokenKind::FatArrow, .. })))
+    }
+
     /// Expect a specific kind, or error.
     pub fn expect_kind(&mut self, expected: TokenKind) -> Result<(), ParseError> {
         if self.match_kind(expected.clone())? {
@@ -100,6 +178,20 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Like `consume_ident`, for object keys and `.` member names, which may also be
+    /// the `req` section keywords (`{ body: x }`, `res.headers`).
+    pub fn consume_property_name(&mut self) -> Result<String, ParseError> {
+        let name = match self.peek_kind()? {
+            TokenKind::Body => "body",
+            TokenKind::Params => "params",
+            TokenKind::Query => "query",
+            TokenKind::Headers => "headers",
+            _ => return self.consume_ident(),
+        };
+        self.advance()?;
+        Ok(name.to_string())
+    }
+
     /// Utility: if current token is a number, return its f64 (and consume); otherwise Err.
     pub fn consume_number(&mut self) -> Result<f64, ParseError> {
         if let TokenKind::Number(n) = self.peek_kind()? {
@@ -170,6 +262,10 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(VarType::Object)
             }
+            TokenKind::FnType => {
+                self.advance()?;
+                Ok(VarType::Function)
+            }
             TokenKind::VecType => {
                 self.advance()?; // consume 'vec'
                 self.expect_kind(TokenKind::Lt)?; // consume '<'
@@ -185,6 +281,10 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(VarType::Undefined)
             }
+            TokenKind::NullType => {
+                self.advance()?;
+                Ok(VarType::Null)
+            }
             _ => Err(ParseError::General(
                 format!("Unknown type: {:?}", tk),
                 self.last_pos,
@@ -212,6 +312,10 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(VarType::Object)
             }
+            TokenKind::FnType => {
+                self.advance()?;
+                Ok(VarType::Function)
+            }
             TokenKind::AnyType => {
                 self.advance()?;
                 Ok(VarType::Any)
@@ -227,6 +331,10 @@ impl<'a> Parser<'a> {
                 "Variables and functions cannot be declared as Undefined type".into(),
                 self.last_pos,
             )),
+            TokenKind::NullType => Err(ParseError::General(
+                "Variables and functions cannot be declared as Null type".into(),
+                self.last_pos,
+            )),
             _ => Err(ParseError::General(
                 format!("Unknown type: {:?}", tk),
                 self.last_pos,
@@ -234,7 +342,35 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses a type annotation, either a single type or a union like `str|num|null`.
     pub fn parse_assignment_type(&mut self) -> ParseResult<VarType> {
+        let mut members = vec![self.parse_union_member()?];
+        while self.match_kind(TokenKind::Pipe)? {
+            let ty = self.parse_union_member()?;
+            if !members.contains(&ty) {
+                members.push(ty);
+            }
+        }
+        match members.as_slice() {
+            [VarType::Null] => Err(ParseError::General(
+                "Variables and functions cannot be declared as Null type".into(),
+                self.last_pos,
+            )),
+            [_] => Ok(members.remove(0)),
+            _ => Ok(VarType::Union(members)),
+        }
+    }
+
+    /// One member of a (possibly single-member) union. `null` is accepted here so
+    /// that `str|null` works; on its own it is rejected by `parse_assignment_type`.
+    fn parse_union_member(&mut self) -> ParseResult<VarType> {
+        if self.match_kind(TokenKind::NullType)? {
+            return Ok(VarType::Null);
+        }
+        self.parse_single_type()
+    }
+
+    fn parse_single_type(&mut self) -> ParseResult<VarType> {
         // Peek on the TokenKind
         let tk = self.peek_kind()?.clone();
         match tk {
@@ -254,6 +390,10 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(VarType::Object)
             }
+            TokenKind::FnType => {
+                self.advance()?;
+                Ok(VarType::Function)
+            }
             TokenKind::VecType => {
                 self.advance()?; // consume 'vec'
                 self.expect_kind(TokenKind::Lt)?; // consume '<'
@@ -294,3 +434,133 @@ pub fn parse_script(input: &str) -> ParseResult<Block> {
         pos: block_start,
     })
 }
+
+/// Like `parse_script`, also returning the script's comments in source order, each
+/// attached to the token after it. For tooling such as a formatter; evaluation
+/// parses without them.
+pub fn parse_script_with_comments(input: &str) -> ParseResult<(Block, Vec<Comment>)> {
+    let mut parser = Parser::with_comments(input);
+    let stmts = parser.parse_script()?;
+    let block = Block {
+        stmts,
+        pos: Position { line: 0, column: 0 },
+    };
+    Ok((block, parser.comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rjscript::ast::stmt::StmtKind;
+
+    fn at(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+
+    const COMMENTED: &str = "// header
+let x: num = 1; // one
+if (x > 0) {
+    /* inside */
+    return 200, x;
+    // last
+}
+let y: num = x /* mid */ + 2;
+return 404, y; // eof";
+
+    #[test]
+    fn comments_attach_to_the_following_token() {
+        let (_, comments) = parse_script_with_comments(COMMENTED).unwrap();
+        let found: Vec<_> = comments
+            .iter()
+            .map(|c| (c.text.as_str(), c.block, c.pos, c.before, c.trailing))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (" header", false, at(1, 1), at(2, 1), false),
+                (" one", false, at(2, 17), at(3, 1), true),
+                (" inside ", true, at(4, 5), at(5, 5), false),
+                // the end of a block attaches to its `}`
+                (" last", false, at(6, 5), at(7, 1), false),
+                (" mid ", true, at(8, 16), at(8, 26), true),
+                // the end of the script attaches to EOF
+                (" eof", false, at(9, 16), at(9, 22), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_leave_the_ast_alone() {
+        let plain = parse_script(COMMENTED).unwrap();
+        let (with_comments, _) = parse_script_with_comments(COMMENTED).unwrap();
+        assert_eq!(format!("{:?}", plain), format!("{:?}", with_comments));
+
+        let (block, comments) = parse_script_with_comments("/* only */").unwrap();
+        assert!(block.stmts.is_empty());
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].before, at(1, 11));
+    }
+
+    /// The params (name and type) and body statements of the arrow function in `let f: fn = <src>;`.
+    fn arrow(src: &str) -> (Vec<(String, VarType)>, Vec<Stmt>) {
+        let block = parse_script(&format!("let f: fn = {};", src)).unwrap();
+        let StmtKind::Let { init: Some(init), .. } = &block.stmts[0].kind else {
+            panic!("not a let: {:?}", block.stmts[0]);
+        };
+        let ExprKind::FunctionExpr { params, return_type, body } = &init.kind else {
+            panic!("not a function: {:?}", init);
+        };
+        assert_eq!(*return_type, VarType::Any);
+        (params.iter().map(|p| (p.name.clone(), p.ty.clone())).collect(), body.stmts.clone())
+    }
+
+    #[test]
+    fn arrows_parse_like_function_expressions() {
+        let (params, body) = arrow("(x) => x * 2");
+        assert_eq!(params, [("x".to_string(), VarType::Any)]);
+        assert!(matches!(&body[..], [s] if matches!(s.kind, StmtKind::Return(_))), "{:?}", body);
+
+        let (params, _) = arrow("(a, b: num, c: vec<str>) => a");
+        assert_eq!(
+            params,
+            [
+                ("a".to_string(), VarType::Any),
+                ("b".to_string(), VarType::Number),
+                ("c".to_string(), VarType::Array(Box::new(VarType::String))),
+            ]
+        );
+
+        let (params, body) = arrow("() => { let y: num = 1; return y; }");
+        assert!(params.is_empty());
+        assert_eq!(body.len(), 2);
+
+        // a parenthesized expression is not an arrow
+        let block = parse_script("let b: bool = (1) >= (2) == (3) <= 4;").unwrap();
+        assert_eq!(block.stmts.len(), 1);
+        assert!(parse_script("let f: fn = (1) => 1;").is_err());
+        assert!(parse_script("let f: fn = (x) =>;").is_err());
+    }
+
+    #[test]
+    fn arrow_forms_evaluate() {
+        use crate::rjscript::testing::{eval_expr_str, eval_script_str_with_world, TestWorld};
+        let world = TestWorld::new();
+        let eval = |src: &str| eval_expr_str(src, &world).map(|v| v.to_string());
+        assert_eq!(eval("[1, 2, 3].map((x) => x * 2)"), Ok("[2,4,6]".into()));
+        assert_eq!(eval("[1, 2, 3].map((x: num) => { return x + 1; })"), Ok("[2,3,4]".into()));
+        assert_eq!(eval("[3, 4].map((x) => { if (x > 3) { return \"big\"; } return \"small\"; })"), Ok("[\"small\",\"big\"]".into()));
+
+        let src = "let add: fn = (a: num, b: num) => a + b;
+let greet: fn = (name, greeting = \"hi\") => `${greeting} ${name}`;
+let answer: fn = () => 42;
+let label: fn = (n) => {
+    if (n > 100) { return \"big\"; }
+    return \"small\";
+};
+return 200, [add(1, 2), greet(\"Ann\"), greet(\"Bob\", \"yo\"), answer(), label(101), label(1)];";
+        assert_eq!(
+            eval_script_str_with_world(src, serde_json::Value::Null, &world).map(|(_, v)| v.to_string()),
+            Ok("[3,\"hi Ann\",\"yo Bob\",42,\"big\",\"small\"]".into())
+        );
+    }
+}
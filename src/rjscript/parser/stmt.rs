@@ -3,7 +3,9 @@ use crate::rjscript::{
         block::Block,
         expr::ExprKind,
         literal::Literal,
+        expr::Expr,
         node::Located,
+        param::Param,
         stmt::{Stmt, StmtKind},
     }, parser::{
         block::parse_block, errors::ParseError, expr::parse_expr, lexer::token::TokenKind,
@@ -11,20 +13,23 @@ use crate::rjscript::{
     }, semantics::{methods::builtin_names_set, types::VarType}
 };
 
-fn parse_function_decl(parser: &mut Parser) -> ParseResult<Stmt> {
-    parser.advance()?;
-    let ident = parser.consume_ident()?;
-
-    if builtin_names_set().contains(ident.as_str()) {
+/// Parses the optional `= <expr>` after a parameter. Once a parameter has a
+/// default, all the following ones need one too.
+pub fn parse_param_default(parser: &mut Parser, params: &[Param], name: &str) -> ParseResult<Option<Expr>> {
+    if parser.match_kind(TokenKind::Eq)? {
+        return Ok(Some(parse_expr(parser)?));
+    }
+    if params.iter().any(|p| p.default.is_some()) {
         return Err(ParseError::General(
-            format!(
-                "{} is a reserved function name",
-                ident
-            ),
+            format!("Parameter `{}` must have a default value, it follows a defaulted parameter", name),
             parser.last_pos,
         ));
     }
+    Ok(None)
+}
 
+/// Parses `(name: type [= default], ...): type`, shared by declarations and function expressions
+pub fn parse_fn_signature(parser: &mut Parser) -> ParseResult<(Vec<Param>, VarType)> {
     parser.expect_kind(TokenKind::LParen)?;
     let mut params = Vec::new();
     if !parser.match_kind(TokenKind::RParen)? {
@@ -32,7 +37,8 @@ fn parse_function_decl(parser: &mut Parser) -> ParseResult<Stmt> {
             let name = parser.consume_ident()?;
             parser.expect_kind(TokenKind::Colon)?;
             let ty: VarType = parser.parse_assignment_type()?;
-            params.push((name, ty));
+            let default = parse_param_default(parser, &params, &name)?;
+            params.push(Param::new(name, ty, default));
             if parser.match_kind(TokenKind::RParen)? {
                 break;
             }
@@ -42,6 +48,24 @@ fn parse_function_decl(parser: &mut Parser) -> ParseResult<Stmt> {
     // must have return type annotation
     parser.expect_kind(TokenKind::Colon)?;
     let return_type = parser.parse_assignment_type()?;
+    Ok((params, return_type))
+}
+
+fn parse_function_decl(parser: &mut Parser) -> ParseResult<Stmt> {
+    parser.advance()?;
+    let ident = parser.consume_ident()?;
+
+    if builtin_names_set().contains(ident.as_str()) {
+        return Err(ParseError::General(
+            format!(
+                "{} is a reserved function name",
+                ident
+            ),
+            parser.last_pos,
+        ));
+    }
+
+    let (params, return_type) = parse_fn_signature(parser)?;
     let body = parse_block(parser)?;
     Ok(Located::new(
         StmtKind::FunctionDecl {
@@ -68,7 +92,11 @@ fn parse_switch_stmt(parser: &mut Parser) -> ParseResult<Stmt> {
 
     while !parser.match_kind(TokenKind::RBrace)? {
         if parser.match_kind(TokenKind::Case)? {
-            let case_expr = parse_expr(parser)?;
+            // `case a, b:` matches either value
+            let mut case_exprs = vec![parse_expr(parser)?];
+            while parser.match_kind(TokenKind::Comma)? {
+                case_exprs.push(parse_expr(parser)?);
+            }
             parser.expect_kind(TokenKind::Colon)?;
             // collect statements until next case/default/}
             let mut stmts = Vec::new();
@@ -78,7 +106,7 @@ fn parse_switch_stmt(parser: &mut Parser) -> ParseResult<Stmt> {
             ) {
                 stmts.push(parse_stmt(parser, false)?);
             }
-            cases.push((case_expr, Block::new(stmts, switch_start_pos)));
+            cases.push((case_exprs, Block::new(stmts, switch_start_pos)));
         } else if parser.match_kind(TokenKind::Default)? {
             parser.expect_kind(TokenKind::Colon)?;
             let mut stmts = Vec::new();
@@ -178,6 +206,32 @@ pub fn parse_stmt(parser: &mut Parser, is_top_level: bool) -> ParseResult<Stmt>
                     name,
                     ty: var_type,
                     init: initializer,
+                    mutable: true,
+                },
+                parser.last_pos,
+            ))
+        }
+
+        TokenKind::Const => {
+            // const x = <expr>, the initializer is mandatory
+            parser.advance()?; // consume 'const'
+            let name = parser.consume_ident()?;
+            parser.expect_kind(TokenKind::Colon)?;
+            let var_type = parser.parse_assignment_type()?;
+            if !parser.match_kind(TokenKind::Eq)? {
+                return Err(ParseError::General(
+                    format!("const `{}` must be initialized", name),
+                    parser.last_pos,
+                ));
+            }
+            let e = parse_expr(parser)?;
+            parser.expect_kind(TokenKind::Semicolon)?;
+            Ok(Located::new(
+                StmtKind::Let {
+                    name,
+                    ty: var_type,
+                    init: Some(e),
+                    mutable: false,
                 },
                 parser.last_pos,
             ))
@@ -275,6 +329,57 @@ pub fn parse_stmt(parser: &mut Parser, is_top_level: bool) -> ParseResult<Stmt>
             }
         }
 
+        TokenKind::Include => {
+            // include "path"; only on toplevel
+            parser.advance()?; // consume 'include'
+            let include_pos = parser.last_pos;
+            if !is_top_level {
+                return Err(ParseError::General(
+                    "`include` can only be used at top level".to_string(),
+                    include_pos,
+                ));
+            }
+            let path = match parser.advance()?.kind {
+                TokenKind::String(s) => s,
+                other => {
+                    return Err(ParseError::General(
+                        format!("Expected a file path string after `include`, found {:?}", other),
+                        parser.last_pos,
+                    ))
+                }
+            };
+            parser.expect_kind(TokenKind::Semicolon)?;
+            Ok(Located::new(StmtKind::Include(path), include_pos))
+        }
+
+        TokenKind::Throw => {
+            // throw <expr>;
+            parser.advance()?; // consume 'throw'
+            let value = parse_expr(parser)?;
+            parser.expect_kind(TokenKind::Semicolon)?;
+            Ok(Located::new(StmtKind::Throw(value), parser.last_pos))
+        }
+
+        TokenKind::Try => {
+            // try { ... } catch (name) { ... }
+            parser.advance()?; // consume 'try'
+            let try_pos = parser.last_pos;
+            let try_block = parse_block(parser)?;
+            parser.expect_kind(TokenKind::Catch)?;
+            parser.expect_kind(TokenKind::LParen)?;
+            let catch_name = parser.consume_ident()?;
+            parser.expect_kind(TokenKind::RParen)?;
+            let catch_block = parse_block(parser)?;
+            Ok(Located::new(
+                StmtKind::TryCatch {
+                    try_block,
+                    catch_name,
+                    catch_block,
+                },
+                try_pos,
+            ))
+        }
+
         TokenKind::Break => {
             parser.advance()?;
             parser.expect_kind(TokenKind::Semicolon)?;
@@ -287,6 +392,13 @@ pub fn parse_stmt(parser: &mut Parser, is_top_level: bool) -> ParseResult<Stmt>
             Ok(Located::new(StmtKind::Continue, parser.last_pos))
         }
 
+        TokenKind::Fallthrough => {
+            parser.advance()?;
+            let pos = parser.last_pos;
+            parser.expect_kind(TokenKind::Semicolon)?;
+            Ok(Located::new(StmtKind::Fallthrough, pos))
+        }
+
         // Otherwise, it must be an expression‐statement (e.g. starting with '(' or a literal)
         _ => {
             let e = parse_expr(parser)?;
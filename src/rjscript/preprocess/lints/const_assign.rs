@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::rjscript::ast::{
+    block::Block,
+    expr::{Expr, ExprKind},
+    position::Position,
+    stmt::{Stmt, StmtKind},
+    visitor::{walk_block, walk_expr, walk_stmt, Visit},
+};
+use crate::rjscript::preprocess::lints::error::LintError;
+use crate::rjscript::preprocess::lints::util::{is_mutating_method_any, receiver_and_method_from_callee};
+
+pub fn run(block: &Block) -> Vec<LintError> {
+    let mut v = ConstAssign::default();
+    v.visit_block(block);
+    v.errors
+}
+
+#[derive(Default)]
+struct ConstAssign {
+    errors: Vec<LintError>,
+    /// Lexical scope stack: variable name -> declared mutable (`let`) or not (`const`)
+    scopes: Vec<HashMap<String, bool>>,
+}
+
+impl ConstAssign {
+    fn declare(&mut self, name: &str, mutable: bool) {
+        if let Some(top) = self.scopes.last_mut() {
+            top.insert(name.to_string(), mutable);
+        }
+    }
+
+    fn is_const(&self, name: &str) -> bool {
+        for m in self.scopes.iter().rev() {
+            if let Some(mutable) = m.get(name) {
+                return !mutable;
+            }
+        }
+        false
+    }
+
+    fn check_root(&mut self, target: &Expr, pos: Position) {
+        if let Some(root) = Expr::root_ident(target) {
+            if self.is_const(root) {
+                let msg = format!("Cannot mutate const `{}`", root);
+                self.errors.push(LintError::new(pos, msg));
+            }
+        }
+    }
+}
+
+impl Visit<'_> for ConstAssign {
+    fn visit_block(&mut self, b: &Block) {
+        self.scopes.push(HashMap::new());
+        walk_block(self, b);
+        self.scopes.pop();
+    }
+
+    fn visit_stmt(&mut self, s: &Stmt) {
+        match &s.kind {
+            StmtKind::Let { name, mutable, .. } => {
+                walk_stmt(self, s);
+                self.declare(name, *mutable);
+            }
+            // Functions do not see the surrounding locals, only their params.
+            StmtKind::FunctionDecl { params, body, .. } => {
+                let outer = std::mem::take(&mut self.scopes);
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    self.declare(&param.name, true);
+                }
+                self.visit_block(body);
+                self.scopes = outer;
+            }
+            // The loop initializer lives in the loop's own scope.
+            StmtKind::For { .. } => {
+                self.scopes.push(HashMap::new());
+                walk_stmt(self, s);
+                self.scopes.pop();
+            }
+            StmtKind::TryCatch {
+                try_block,
+                catch_name,
+                catch_block,
+            } => {
+                self.visit_block(try_block);
+                self.scopes.push(HashMap::new());
+                self.declare(catch_name, true);
+                self.visit_block(catch_block);
+                self.scopes.pop();
+            }
+            _ => walk_stmt(self, s),
+        }
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        match &e.kind {
+            ExprKind::AssignVar { name, .. } if self.is_const(name) => {
+                let msg = format!("Cannot assign to const `{}`", name);
+                self.errors.push(LintError::new(e.pos, msg));
+            }
+            ExprKind::AssignMember { object, .. } | ExprKind::AssignIndex { object, .. } => {
+                self.check_root(object, e.pos);
+            }
+            ExprKind::Call { callee, .. } => {
+                if let Some((recv, method)) = receiver_and_method_from_callee(callee) {
+                    if is_mutating_method_any(method) {
+                        self.check_root(recv, e.pos);
+                    }
+                }
+            }
+            // Like declared functions, only the params are in scope.
+            ExprKind::FunctionExpr { params, body, .. } => {
+                let outer = std::mem::take(&mut self.scopes);
+                self.scopes.push(HashMap::new());
+                for param in params {
+                    self.declare(&param.name, true);
+                }
+                self.visit_block(body);
+                self.scopes = outer;
+                return;
+            }
+            _ => {}
+        }
+        walk_expr(self, e);
+    }
+}
@@ -67,7 +67,7 @@ impl Declarations {
     }
 }
 
-impl Visit for Declarations {
+impl Visit<'_> for Declarations {
     fn visit_stmt(&mut self, s: &Stmt) {
         match &s.kind {
             // Variables: flag only if an *outer* variable with same name exists.
@@ -94,8 +94,8 @@ impl Visit for Declarations {
 
                 // Create a dedicated scope for the function body; insert params as variables.
                 let body_scope = Scope::push_child(&self.cur_scope);
-                for (pname, _pty) in params {
-                    Scope::declare_var(&body_scope, pname);
+                for param in params {
+                    Scope::declare_var(&body_scope, &param.name);
                 }
 
                 // Visit the body within that scope (no extra push/pop).
@@ -146,11 +146,36 @@ impl Visit for Declarations {
                 self.visit_expr(e);
             }
 
+            StmtKind::Throw(e) => {
+                self.visit_expr(e);
+            }
+
+            StmtKind::TryCatch {
+                try_block,
+                catch_name,
+                catch_block,
+            } => {
+                self.in_child_scope(|this| {
+                    this.visit_block(try_block);
+                });
+                // The catch name is a variable of the catch block's scope.
+                if Scope::has_var_in_chain(&self.cur_scope, catch_name) {
+                    self.err(s.pos, format!("`{}` already declared", catch_name));
+                }
+                let catch_scope = Scope::push_child(&self.cur_scope);
+                Scope::declare_var(&catch_scope, catch_name);
+                self.with_scope(catch_scope, |this| {
+                    this.visit_block(catch_block);
+                });
+            }
+
             // The rest do not declare names.
             StmtKind::Return(_)
             | StmtKind::ReturnStatus { .. }
             | StmtKind::Break
-            | StmtKind::Continue => {}
+            | StmtKind::Include(_)
+            | StmtKind::Continue
+            | StmtKind::Fallthrough => {}
         }
     }
 
@@ -178,6 +203,18 @@ impl Visit for Declarations {
             | ExprKind::BinaryOp {.. }
             | ExprKind::Member { .. }
             | ExprKind::Call { .. } => {}
+
+            // Params are variables of the function body only.
+            ExprKind::FunctionExpr { params, body, .. } => {
+                let body_scope = Scope::push_child(&self.cur_scope);
+                for param in params {
+                    Scope::declare_var(&body_scope, &param.name);
+                }
+                self.with_scope(body_scope, |this| {
+                    this.visit_block(body);
+                });
+                return;
+            }
         }
         walk_expr(self, e)
     }
@@ -39,6 +39,8 @@ impl VarKey {
 #[derive(Clone, Default)]
 struct AssignFacts {
     set: HashSet<VarKey>,
+    /// The path ended in a `return` or `throw`; it never reaches the merge point.
+    diverges: bool,
 }
 
 impl AssignFacts {
@@ -51,7 +53,14 @@ impl AssignFacts {
     }
 
     /// Intersection for "must be assigned on all paths".
+    /// A diverging path does not constrain the result.
     fn intersect(&self, other: &AssignFacts) -> AssignFacts {
+        if self.diverges {
+            return other.clone();
+        }
+        if other.diverges {
+            return self.clone();
+        }
         let mut out = AssignFacts::default();
         for k in self.set.iter() {
             if other.set.contains(k) {
@@ -124,7 +133,7 @@ impl DefAssign {
     }
 }
 
-impl Visit for DefAssign {
+impl Visit<'_> for DefAssign {
     fn visit_stmt(&mut self, s: &Stmt) {
         match &s.kind {
             StmtKind::Let { name, init, .. } => {
@@ -141,11 +150,45 @@ impl Visit for DefAssign {
 
             StmtKind::Return(e) => {
                 self.visit_expr(e);
+                self.facts.diverges = true;
             }
 
             StmtKind::ReturnStatus { status, value } => {
                 self.visit_expr(status);
                 self.visit_expr(value);
+                self.facts.diverges = true;
+            }
+
+            StmtKind::Throw(e) => {
+                self.visit_expr(e);
+                self.facts.diverges = true;
+            }
+
+            // try { ... } catch (name) { ... }
+            // The catch may start from any point of the try block, so it only
+            // relies on the incoming facts; the catch name is assigned on entry.
+            StmtKind::TryCatch {
+                try_block,
+                catch_name,
+                catch_block,
+            } => {
+                let incoming = self.facts.clone();
+
+                let try_scope = Scope::push_child(&self.cur_scope);
+                let mut try_facts = incoming.clone();
+                self.with_scope_and_facts(try_scope, &mut try_facts, |this| {
+                    this.visit_block(try_block);
+                });
+
+                let catch_scope = Scope::push_child(&self.cur_scope);
+                Scope::declare_var(&catch_scope, catch_name);
+                let mut catch_facts = incoming;
+                catch_facts.mark(VarKey::new(&catch_scope, catch_name));
+                self.with_scope_and_facts(catch_scope, &mut catch_facts, |this| {
+                    this.visit_block(catch_block);
+                });
+
+                self.facts = try_facts.intersect(&catch_facts);
             }
 
             StmtKind::IfElse {
@@ -235,9 +278,11 @@ impl Visit for DefAssign {
                 let mut paths: Vec<AssignFacts> = Vec::with_capacity(cases.len() + 1);
 
                 // Cases
-                for (case_expr, case_block) in cases {
-                    // Evaluate case expr (reads only)
-                    self.visit_expr(case_expr);
+                for (case_exprs, case_block) in cases {
+                    // Evaluate case exprs (reads only)
+                    for case_expr in case_exprs {
+                        self.visit_expr(case_expr);
+                    }
 
                     // Case block in its own scope, forked facts from incoming
                     let case_scope = Scope::push_child(&self.cur_scope);
@@ -245,7 +290,11 @@ impl Visit for DefAssign {
                     self.with_scope_and_facts(case_scope, &mut f, |this| {
                         this.visit_block(case_block);
                     });
-                    paths.push(f);
+                    // A case ending in `fallthrough` leaves through the next block,
+                    // which is also entered directly, so it adds no path of its own.
+                    if !case_block.ends_in_fallthrough() {
+                        paths.push(f);
+                    }
                 }
 
                 // Default
@@ -289,9 +338,9 @@ impl Visit for DefAssign {
 
                 // Params are vars and considered assigned on entry:
                 // declare them in the body scope and mark as assigned in inner_facts
-                for (pname, _pty) in params {
-                    Scope::declare_var(&body_scope, pname);
-                    if let Some(k) = VarKey::varkey_from_decl(&body_scope, pname) {
+                for param in params {
+                    Scope::declare_var(&body_scope, &param.name);
+                    if let Some(k) = VarKey::varkey_from_decl(&body_scope, &param.name) {
                         inner_facts.mark(k);
                     }
                 }
@@ -302,7 +351,7 @@ impl Visit for DefAssign {
                 });
             }
 
-            StmtKind::Break | StmtKind::Continue => {
+            StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough | StmtKind::Include(_) => {
                 // Nothing for DA here; control-flow merging handled at branch level.
             }
         }
@@ -363,6 +412,22 @@ impl Visit for DefAssign {
                 }
             }
 
+            // Function expression: same as a declaration, params assigned on entry
+            // and nothing flows in or out.
+            ExprKind::FunctionExpr { params, body, .. } => {
+                let body_scope = Scope::push_child(&self.cur_scope);
+                let mut inner_facts = AssignFacts::default();
+                for param in params {
+                    Scope::declare_var(&body_scope, &param.name);
+                    if let Some(k) = VarKey::varkey_from_decl(&body_scope, &param.name) {
+                        inner_facts.mark(k);
+                    }
+                }
+                self.with_scope_and_facts(body_scope, &mut inner_facts, |this| {
+                    this.visit_block(body);
+                });
+            }
+
             // Literal / type / request field: no reads
             ExprKind::Literal(_) | ExprKind::TypeLiteral(_) | ExprKind::RequestField(_) => {}
 
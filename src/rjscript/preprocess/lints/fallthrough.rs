@@ -0,0 +1,64 @@
+use crate::rjscript::ast::{
+    block::Block,
+    stmt::{Stmt, StmtKind},
+    visitor::{walk_stmt, Visit},
+};
+use crate::rjscript::preprocess::lints::error::LintError;
+
+pub fn run(block: &Block) -> Vec<LintError> {
+    let mut v = Fallthrough::default();
+    v.visit_block(block);
+    v.errors
+}
+
+/// `fallthrough` may only end a switch case that has another case (or the
+/// default) after it.
+#[derive(Default)]
+struct Fallthrough {
+    errors: Vec<LintError>,
+}
+
+impl Visit<'_> for Fallthrough {
+    fn visit_stmt(&mut self, s: &Stmt) {
+        match &s.kind {
+            StmtKind::Switch {
+                condition,
+                cases,
+                default,
+            } => {
+                self.visit_expr(condition);
+                for (idx, (exprs, block)) in cases.iter().enumerate() {
+                    for e in exprs {
+                        self.visit_expr(e);
+                    }
+                    let (last, rest) = match block.stmts.split_last() {
+                        Some((last, rest)) if matches!(last.kind, StmtKind::Fallthrough) => (last, rest),
+                        _ => {
+                            self.visit_block(block);
+                            continue;
+                        }
+                    };
+                    for stmt in rest {
+                        self.visit_stmt(stmt);
+                    }
+                    if idx + 1 == cases.len() && default.is_none() {
+                        self.errors.push(LintError::new(
+                            last.pos,
+                            "`fallthrough` in the last case has no case to fall into",
+                        ));
+                    }
+                }
+                if let Some(b) = default {
+                    self.visit_block(b);
+                }
+            }
+            StmtKind::Fallthrough => {
+                self.errors.push(LintError::new(
+                    s.pos,
+                    "`fallthrough` must be the last statement of a switch case",
+                ));
+            }
+            _ => walk_stmt(self, s),
+        }
+    }
+}
@@ -4,23 +4,38 @@ pub mod type_assign;
 pub mod req_type_guard;
 pub mod definite_assign;
 pub mod req_imutability;
+pub mod const_assign;
 pub mod declarations;
 pub mod unknown_calls;
+pub mod param_defaults;
+pub mod fallthrough;
 pub mod util;
 
 use crate::rjscript::{ast::block::Block, preprocess::lints::error::LintError};
 
 /// Returns a flat list of error strings (empty if OK).
 pub fn run_lints(block: &Block) -> Vec<LintError> {
+    run_all(block, true)
+}
+
+/// Same as `run_lints` for an included file, which does not return by itself.
+pub fn run_include_lints(block: &Block) -> Vec<LintError> {
+    run_all(block, false)
+}
+
+fn run_all(block: &Block, script_level: bool) -> Vec<LintError> {
     let mut errs = Vec::new();
 
-    errs.extend(must_return::run(block));
+    errs.extend(must_return::run(block, script_level));
     errs.extend(type_assign::run(block));
     errs.extend(req_imutability::run(block));
+    errs.extend(const_assign::run(block));
     errs.extend(req_type_guard::run(block));
     errs.extend(definite_assign::run(block));
     errs.extend(declarations::run(block));
     errs.extend(unknown_calls::run(block));
+    errs.extend(param_defaults::run(block));
+    errs.extend(fallthrough::run(block));
 
     errs.sort();
     errs
@@ -1,13 +1,18 @@
 use crate::rjscript::ast::{
     block::Block,
+    expr::{Expr, ExprKind},
     stmt::{Stmt, StmtKind},
-    visitor::{walk_stmt, Visit},
+    visitor::{walk_expr, walk_stmt, Visit},
 };
-use crate::rjscript::preprocess::lints::error::LintError;
+use crate::rjscript::preprocess::lints::{error::LintError, util::ident_name_from_callee};
+use crate::rjscript::semantics::methods::{builtin_from_name, Builtin};
 
-pub fn run(block: &Block) -> Vec<LintError> {
+/// `script_level` requires the block itself to return; included files only hold functions.
+pub fn run(block: &Block, script_level: bool) -> Vec<LintError> {
     let mut v = MustReturn { errors: Vec::new() };
-    v.check_top_level(block);
+    if script_level {
+        v.check_top_level(block);
+    }
     v.visit_block(block);
     v.errors
 }
@@ -15,7 +20,11 @@ pub fn run(block: &Block) -> Vec<LintError> {
 pub fn block_returns(b: &Block) -> bool {
     for s in &b.stmts {
         match &s.kind {
-            StmtKind::Return(_) | StmtKind::ReturnStatus { .. } => {
+            StmtKind::Return(_) | StmtKind::ReturnStatus { .. } | StmtKind::Throw(_) => {
+                return true;
+            }
+            // `error(status, body)` ends the script with that response
+            StmtKind::ExprStmt(e) if is_error_call(e) => {
                 return true;
             }
             StmtKind::IfElse {
@@ -33,8 +42,11 @@ pub fn block_returns(b: &Block) -> bool {
                 }
             }
             StmtKind::Switch { cases, default, .. } => {
-                // require: every case and default returns
-                let all_cases = cases.iter().all(|(_, b)| block_returns(b));
+                // require: every case and default returns; a case ending in
+                // `fallthrough` relies on the clauses after it
+                let all_cases = cases
+                    .iter()
+                    .all(|(_, b)| b.ends_in_fallthrough() || block_returns(b));
                 let has_default = default.as_ref().map(|b| block_returns(b)).unwrap_or(false);
                 if all_cases && has_default {
                     return true;
@@ -45,16 +57,37 @@ pub fn block_returns(b: &Block) -> bool {
                     return true;
                 }
             }
+            StmtKind::TryCatch {
+                try_block,
+                catch_block,
+                ..
+            } => {
+                if block_returns(try_block) && block_returns(catch_block) {
+                    return true;
+                }
+            }
             StmtKind::FunctionDecl { .. }
             | StmtKind::Break
             | StmtKind::ExprStmt(_)
             | StmtKind::Let { .. }
-            | StmtKind::Continue => {}
+            | StmtKind::Include(_)
+            | StmtKind::Continue
+            | StmtKind::Fallthrough => {}
         }
     }
     return false;
 }
 
+fn is_error_call(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::Call { callee, .. } => matches!(
+            ident_name_from_callee(callee).and_then(builtin_from_name),
+            Some(Builtin::Error)
+        ),
+        _ => false,
+    }
+}
+
 struct MustReturn {
     errors: Vec<LintError>,
 }
@@ -75,7 +108,7 @@ impl MustReturn {
     }
 }
 
-impl Visit for MustReturn {
+impl Visit<'_> for MustReturn {
     fn visit_stmt(&mut self, s: &Stmt) {
         if let StmtKind::FunctionDecl { ident, body, .. } = &s.kind {
             if !block_returns(body) {
@@ -84,4 +117,16 @@ impl Visit for MustReturn {
         }
         walk_stmt(self, s);
     }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::FunctionExpr { body, .. } = &e.kind {
+            if !block_returns(body) {
+                self.errors.push(LintError::new(
+                    e.pos,
+                    "Function expression does not return on all paths",
+                ));
+            }
+        }
+        walk_expr(self, e);
+    }
 }
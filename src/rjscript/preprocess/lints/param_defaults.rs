@@ -0,0 +1,100 @@
+use crate::rjscript::ast::{
+    block::Block,
+    expr::{Expr, ExprKind, TemplatePart},
+    param::Param,
+    stmt::{Stmt, StmtKind},
+    visitor::{walk_block, walk_expr, walk_stmt, Visit},
+};
+use crate::rjscript::preprocess::lints::error::LintError;
+
+pub fn run(block: &Block) -> Vec<LintError> {
+    let mut v = ParamDefaults::default();
+    v.visit_block(block);
+    v.errors
+}
+
+/// Default values must be constant: literals, or expressions over the
+/// parameters declared before them.
+#[derive(Default)]
+struct ParamDefaults {
+    errors: Vec<LintError>,
+}
+
+impl ParamDefaults {
+    fn check_params(&mut self, params: &[Param]) {
+        for (i, param) in params.iter().enumerate() {
+            if let Some(d) = &param.default {
+                self.check_default(&param.name, &params[..i], d);
+            }
+        }
+    }
+
+    fn check_default(&mut self, pname: &str, earlier: &[Param], e: &Expr) {
+        match &e.kind {
+            ExprKind::Literal(_) | ExprKind::TypeLiteral(_) | ExprKind::FunctionExpr { .. } => {}
+            ExprKind::Ident(name) => {
+                if !earlier.iter().any(|p| &p.name == name) {
+                    let msg = format!(
+                        "Default value of `{}` can only reference earlier parameters, found `{}`",
+                        pname, name
+                    );
+                    self.errors.push(LintError::new(e.pos, msg));
+                }
+            }
+            ExprKind::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Expr(inner) = part {
+                        self.check_default(pname, earlier, inner);
+                    }
+                }
+            }
+            ExprKind::ObjectLiteral { fields } => {
+                for (_, inner) in fields {
+                    self.check_default(pname, earlier, inner);
+                }
+            }
+            ExprKind::Array(items) => {
+                for inner in items {
+                    self.check_default(pname, earlier, inner);
+                }
+            }
+            ExprKind::BinaryOp { left, right, .. } => {
+                self.check_default(pname, earlier, left);
+                self.check_default(pname, earlier, right);
+            }
+            ExprKind::Member { object, .. } => self.check_default(pname, earlier, object),
+            ExprKind::Index { object, index } => {
+                self.check_default(pname, earlier, object);
+                self.check_default(pname, earlier, index);
+            }
+            ExprKind::RequestField(_)
+            | ExprKind::Call { .. }
+            | ExprKind::AssignVar { .. }
+            | ExprKind::AssignMember { .. }
+            | ExprKind::AssignIndex { .. } => {
+                let msg = format!("Default value of `{}` must be a constant expression", pname);
+                self.errors.push(LintError::new(e.pos, msg));
+            }
+        }
+    }
+}
+
+impl Visit<'_> for ParamDefaults {
+    fn visit_block(&mut self, b: &Block) {
+        walk_block(self, b);
+    }
+
+    fn visit_stmt(&mut self, s: &Stmt) {
+        if let StmtKind::FunctionDecl { params, .. } = &s.kind {
+            self.check_params(params);
+        }
+        walk_stmt(self, s);
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::FunctionExpr { params, .. } = &e.kind {
+            self.check_params(params);
+        }
+        walk_expr(self, e);
+    }
+}
@@ -23,7 +23,7 @@ impl ReqImmut {
     }
 }
 
-impl Visit for ReqImmut {
+impl Visit<'_> for ReqImmut {
     fn visit_block(&mut self, b: &Block) {
         walk_block(self, b);
     }
@@ -18,7 +18,10 @@ use crate::rjscript::{
             receiver_and_method_from_callee,
         }
     },
-    semantics::{methods::Receiver, types::VarType},
+    semantics::{
+        methods::{builtin_from_name, Builtin, Receiver},
+        types::VarType,
+    },
 };
 
 
@@ -116,6 +119,9 @@ fn fingerprint_expr(e: &Expr) -> ExprKey {
                 go(right, s);
                 s.push(')');
             }
+            FunctionExpr { params, .. } => {
+                s.push_str(&format!("Func({:?})", params));
+            }
             Call { callee, args } => {
                 s.push_str("Call(");
                 go(callee, s);
@@ -176,8 +182,10 @@ impl Facts {
     fn set(&mut self, k: ExprKey, t: VarType) {
         self.map.insert(k, t);
     }
+    /// The known type fits `want`: a `str` fact satisfies a `str|num` target, but
+    /// a `str|num` fact (from `toType(x) == str || toType(x) == num`) does not satisfy `str`.
     fn has_type(&self, k: &ExprKey, want: &VarType) -> bool {
-        self.get(k).map(|t| t == want).unwrap_or(false)
+        self.get(k).map(|t| t.is_within(want)).unwrap_or(false)
     }
 }
 
@@ -231,7 +239,7 @@ impl ReqTypeGuard {
     fn check_stmt(&mut self, s: &Stmt, facts: &mut Facts, scope: &mut Scope) {
         match &s.kind {
             // let x: T = <expr>;
-            StmtKind::Let { name, ty, init } => {
+            StmtKind::Let { name, ty, init, .. } => {
                 if let Some(rhs) = init {
                     self.enforce_guard_if_req(rhs, ty, s.pos(), facts);
                     // numeric usage inside initializer
@@ -330,9 +338,11 @@ impl ReqTypeGuard {
             } => {
                 enforce_numeric_usage_on_expr(self, condition, facts);
                 self.check_expr_for_assignments(condition, facts, scope);
-                for (e, b) in cases {
-                    enforce_numeric_usage_on_expr(self, e, facts);
-                    self.check_expr_for_assignments(e, facts, scope);
+                for (es, b) in cases {
+                    for e in es {
+                        enforce_numeric_usage_on_expr(self, e, facts);
+                        self.check_expr_for_assignments(e, facts, scope);
+                    }
                     let mut inner = facts.clone();
                     self.check_block(b, &mut inner, scope);
                 }
@@ -344,15 +354,36 @@ impl ReqTypeGuard {
 
             StmtKind::FunctionDecl { params, body, .. } => {
                 scope.push();
-                for (pname, pty) in params {
-                    scope.declare(pname, pty.clone());
+                for param in params {
+                    scope.declare(&param.name, param.ty.clone());
                 }
                 let mut inner = Facts::default(); // do not inherit outer facts
                 self.check_block(body, &mut inner, scope);
                 scope.pop();
             }
 
-            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Throw(e) => {
+                enforce_numeric_usage_on_expr(self, e, facts);
+                self.check_expr_for_assignments(e, facts, scope);
+            }
+
+            // try/catch: the catch may run from any point of the try block,
+            // so neither block's facts flow out.
+            StmtKind::TryCatch {
+                try_block,
+                catch_name,
+                catch_block,
+            } => {
+                let mut inner = facts.clone();
+                self.check_block(try_block, &mut inner, scope);
+                scope.push();
+                scope.declare(catch_name, VarType::Any);
+                let mut inner = facts.clone();
+                self.check_block(catch_block, &mut inner, scope);
+                scope.pop();
+            }
+
+            StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough | StmtKind::Include(_) => {}
         }
     }
 
@@ -404,12 +435,21 @@ impl ReqTypeGuard {
                     }
                 }
             }
+            ExprKind::FunctionExpr { params, body, .. } => {
+                scope.push();
+                for param in params {
+                    scope.declare(&param.name, param.ty.clone());
+                }
+                let mut inner = Facts::default(); // do not inherit outer facts
+                self.check_block(body, &mut inner, scope);
+                scope.pop();
+            }
             _ => {}
         }
     }
 
     fn enforce_guard_if_req(&mut self, val: &Expr, want: &VarType, at: Position, facts: &Facts) {
-        if !val.is_request_derived() {
+        if !request_derived(val) {
             return;
         }
 
@@ -437,22 +477,49 @@ impl ReqTypeGuard {
         at: Position,
         facts: &Facts,
     ) {
-        if val.is_request_derived() {
+        if request_derived(val) {
             let key = fingerprint_expr(val);
             if !facts.has_type(&key, want) {
-                self.err(
-                    at,
-                    format!(
+                let msg = match want {
+                    // ordering operands: either type will do
+                    VarType::Union(members) => format!(
+                        "Using request-derived value in an ordering comparison requires a prior type check to {} \
+                         (e.g., `if (toType(<expr>) == {}) {{ ... }}` or the negated early-return form)",
+                        want, members[0]
+                    ),
+                    _ => format!(
                         "Using request-derived value in a numeric operation requires a prior type check to {:?} \
                          (e.g., `if (toType(<expr>) == {:?}) {{ ... }}` or the negated early-return form)",
                         want, want
                     ),
-                );
+                };
+                self.err(at, msg);
             }
         }
     }
 }
 
+/// `Expr::is_request_derived`, plus the builtins whose result is request data:
+/// - `parseInt(<request value>)` / `parseFloat(<request value>)` / `toNumber(<request value>)`
+///   are `undefined` when the text is not a number, so need a `num` guard like the raw value;
+/// - `bodyGet(pointer)` returns whatever the body holds at `pointer`;
+/// - `bearerToken()` / `basicAuth()` are `undefined` without usable credentials.
+fn request_derived(e: &Expr) -> bool {
+    if e.is_request_derived() {
+        return true;
+    }
+    let ExprKind::Call { callee, args } = &e.kind else {
+        return false;
+    };
+    match ident_name_from_callee(callee).and_then(builtin_from_name) {
+        Some(Builtin::ParseInt | Builtin::ParseFloat | Builtin::ToNumber) => {
+            args.first().is_some_and(|a| a.is_request_derived())
+        }
+        Some(Builtin::BodyGet | Builtin::BearerToken | Builtin::BasicAuth) => true,
+        _ => false,
+    }
+}
+
 /// Require that a request-derived receiver is guarded to an allowed type for that method.
 fn require_guard_for_method_on_request(
     l: &mut ReqTypeGuard,
@@ -461,15 +528,16 @@ fn require_guard_for_method_on_request(
     at: Position,
     facts: &Facts,
 ) {
-    if !object.is_request_derived() {
+    if !request_derived(object) {
         return;
     }
 
     // Check support per receiver kind using util meta
     let on_str = method_meta_for_receiver(Receiver::String, method).is_some();
     let on_arr = method_meta_for_receiver(Receiver::Array, method).is_some();
+    let on_obj = method_meta_for_receiver(Receiver::Object, method).is_some();
 
-    if !on_str && !on_arr {
+    if !on_str && !on_arr && !on_obj {
         // Unknown method; other lints (unknown_calls) handle it.
         return;
     }
@@ -483,16 +551,17 @@ fn require_guard_for_method_on_request(
         // receiver-guard to "array of any"
         allowed_types.push(VarType::Array(Box::new(VarType::Any)));
     }
+    if on_obj {
+        allowed_types.push(VarType::Object);
+    }
 
     // We accept any matching guard on the same receiver expression
     if !allowed_types.iter().any(|t| facts.has_type(&key, t)) {
-        let want_desc = if on_str && on_arr {
-            "str or vec"
-        } else if on_str {
-            "str"
-        } else {
-            "vec"
-        };
+        let kinds: Vec<&str> = [(on_str, "str"), (on_arr, "vec"), (on_obj, "obj")]
+            .into_iter()
+            .filter_map(|(on, kind)| on.then_some(kind))
+            .collect();
+        let want_desc = kinds.join(" or ");
 
         l.err(
             at,
@@ -501,13 +570,13 @@ fn require_guard_for_method_on_request(
                  guarding the receiver to {} (e.g., `if (toType(<expr>) == {}) {{ ... }}`)",
                 method,
                 want_desc,
-                if on_str && !on_arr { "str" } else { "vec" }
+                kinds[kinds.len() - 1]
             ),
         );
     }
 }
 
-/// Enforce numeric requirement for `<`, `<=`, `>`, `>=`, `-`, `*`, `/`, `%`.
+/// Enforce numeric requirement for `-`, `*`, `/`, `%`, and num-or-str for `<`, `<=`, `>`, `>=`.
 fn enforce_numeric_usage_on_expr(l: &mut ReqTypeGuard, e: &Expr, facts: &Facts) {
     use ExprKind::*;
     match &e.kind {
@@ -521,24 +590,25 @@ fn enforce_numeric_usage_on_expr(l: &mut ReqTypeGuard, e: &Expr, facts: &Facts)
                 | BinOp::Mul
                 | BinOp::Div
                 | BinOp::Rem => {
+                    let want = if matches!(op, BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge) {
+                        VarType::Number.union(VarType::String)
+                    } else {
+                        VarType::Number
+                    };
+
                     // LEFT operand
                     if let Some(obj) = as_length_call_on(left) {
                         // `.length()` is numeric if the receiver is an array or string under guard
                         require_array_or_string_receiver_for_length(l, obj, left.pos(), facts);
                     } else {
-                        l.require_guard_type_if_request(left, &VarType::Number, left.pos(), facts);
+                        l.require_guard_type_if_request(left, &want, left.pos(), facts);
                     }
 
                     // RIGHT operand
                     if let Some(obj) = as_length_call_on(right) {
                         require_array_or_string_receiver_for_length(l, obj, right.pos(), facts);
                     } else {
-                        l.require_guard_type_if_request(
-                            right,
-                            &VarType::Number,
-                            right.pos(),
-                            facts,
-                        );
+                        l.require_guard_type_if_request(right, &want, right.pos(), facts);
                     }
                 }
                 _ => {
@@ -553,6 +623,7 @@ fn enforce_numeric_usage_on_expr(l: &mut ReqTypeGuard, e: &Expr, facts: &Facts)
             enforce_numeric_usage_on_expr(l, index, facts);
         }
         Call { callee, args } => {
+            require_numeric_builtin_args(l, callee, args, facts);
             enforce_numeric_usage_on_expr(l, callee, facts);
             for a in args {
                 enforce_numeric_usage_on_expr(l, a, facts);
@@ -583,6 +654,36 @@ fn enforce_numeric_usage_on_expr(l: &mut ReqTypeGuard, e: &Expr, facts: &Facts)
     }
 }
 
+/// Math builtins (`abs`, `pow`, `min`, `randomInt`, ...) only take numbers, so request-derived
+/// arguments need a `num` guard; `min`/`max` over a single array need a `vec` guard.
+fn require_numeric_builtin_args(l: &mut ReqTypeGuard, callee: &Expr, args: &[Expr], facts: &Facts) {
+    let Some(builtin) = ident_name_from_callee(callee).and_then(builtin_from_name) else {
+        return;
+    };
+    match (builtin, args) {
+        (Builtin::Min | Builtin::Max, [arg]) => {
+            l.require_guard_type_if_request(arg, &VarType::Array(Box::new(VarType::Any)), arg.pos(), facts);
+        }
+        (
+            Builtin::Abs
+            | Builtin::Floor
+            | Builtin::Ceil
+            | Builtin::Round
+            | Builtin::Min
+            | Builtin::Max
+            | Builtin::Pow
+            | Builtin::Sqrt
+            | Builtin::RandomInt,
+            _,
+        ) => {
+            for arg in args {
+                l.require_guard_type_if_request(arg, &VarType::Number, arg.pos(), facts);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Like `enforce_numeric_usage_on_expr`, but aware of `&&` guards in `if` conditions`:
 /// if ( toType(expr)==num && ( expr < 5 ) )
 fn enforce_numeric_usage_in_condition(l: &mut ReqTypeGuard, cond: &Expr, facts: &Facts) {
@@ -614,7 +715,7 @@ fn require_array_or_string_receiver_for_length(
     at: Position,
     facts: &Facts,
 ) {
-    if !object.is_request_derived() {
+    if !request_derived(object) {
         return;
     }
 
@@ -643,6 +744,7 @@ fn require_array_or_string_receiver_for_length(
 /// Identify a `toType(expr)` equality/inequality check and return the guarded expression's key.
 ///   - toType(expr) == TypeLiteral(T)   (or flipped)
 ///   - toType(expr) != TypeLiteral(T)   (or flipped)
+///   - toType(expr) == A || toType(expr) == B   (guards to the union `A|B`)
 /// Also: if the condition is an `AND` chain, any conjunct guard suffices.
 #[derive(Copy, Clone)]
 enum GuardKind {
@@ -677,7 +779,7 @@ fn extract_type_guard(cond: &Expr) -> Option<(ExprKey, VarType, GuardKind)> {
         if matches!(op, BinOp::Eq | BinOp::Ne) {
             // toType(x) <op> TypeLiteral(T)
             if let (Some(arg), Some(ty)) = (as_to_type_call(left), as_type_literal(right)) {
-                if arg.is_request_derived() {
+                if request_derived(arg) {
                     let key = fingerprint_expr(arg);
                     return Some((
                         key,
@@ -692,7 +794,7 @@ fn extract_type_guard(cond: &Expr) -> Option<(ExprKey, VarType, GuardKind)> {
             }
             // TypeLiteral(T) <op> toType(x)
             if let (Some(ty), Some(arg)) = (as_type_literal(left), as_to_type_call(right)) {
-                if arg.is_request_derived() {
+                if request_derived(arg) {
                     let key = fingerprint_expr(arg);
                     return Some((
                         key,
@@ -706,6 +808,16 @@ fn extract_type_guard(cond: &Expr) -> Option<(ExprKey, VarType, GuardKind)> {
                 }
             }
         }
+        // Disjunction of equality guards on the same expression: a union
+        if let BinOp::Or = op {
+            if let (Some((lk, lt, GuardKind::Eq)), Some((rk, rt, GuardKind::Eq))) =
+                (extract_type_guard(left), extract_type_guard(right))
+            {
+                if lk == rk {
+                    return Some((lk, lt.union(rt), GuardKind::Eq));
+                }
+            }
+        }
         // Conjunction: recurse
         if let BinOp::And = op {
             if let Some(hit) = extract_type_guard(left) {
@@ -729,7 +841,7 @@ fn is_guarded_req_method_result(expr: &Expr, expected_ty: &VarType, facts: &Fact
     let Some((recv, method)) = receiver_and_method_from_callee(callee) else {
         return false;
     };
-    if !recv.is_request_derived() {
+    if !request_derived(recv) {
         return false;
     }
 
@@ -746,6 +858,11 @@ fn is_guarded_req_method_result(expr: &Expr, expected_ty: &VarType, facts: &Fact
             facts.has_type(&recv_fp, &VarType::Array(Box::new(VarType::Any)))
                 && method_meta_for_receiver(Receiver::Array, method).is_some()
         }
+        VarType::Bool => {
+            // `has` answers a bool whatever the object holds; `get` returns its raw fields.
+            let recv_fp = fingerprint_expr(recv);
+            facts.has_type(&recv_fp, &VarType::Object) && method == "has"
+        }
         _ => false, // for other target types keep the old strict behavior
     }
 }
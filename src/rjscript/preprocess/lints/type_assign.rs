@@ -6,9 +6,10 @@ use crate::rjscript::{
         block::Block,
         expr::{Expr, ExprKind},
         node::HasPos,
+        param::Param,
         position::Position,
         stmt::{Stmt, StmtKind},
-    }, evaluator::runtime::value::RJSValue, preprocess::lints::{error::LintError, util::{method_meta_for_vartype, receiver_and_method_from_callee}}, semantics::types::VarType
+    }, evaluator::runtime::value::RJSValue, preprocess::lints::{error::LintError, must_return::block_returns, util::{ident_name_from_callee, is_mutating_method_any, method_meta_for_vartype, receiver_and_method_from_callee}}, semantics::{methods::builtin_from_name, types::VarType}
 };
 
 pub fn run(block: &Block) -> Vec<LintError> {
@@ -17,26 +18,173 @@ pub fn run(block: &Block) -> Vec<LintError> {
     tc.errors
 }
 
+/// A type a `toType(...)` guard established for a local or a path into one.
+#[derive(Clone)]
+struct Narrowing {
+    ty: VarType,
+    /// Variables the path reads; assigning any of them ends the narrowing
+    vars: Vec<String>,
+}
+
+type Facts = Vec<(String, Narrowing)>;
+
 #[derive(Default)]
 struct TypeChecker {
     errors: Vec<LintError>,
     scopes: Vec<HashMap<String, VarType>>, // lexical scope stack
+    /// Narrowed types per scope, by `narrow_key`
+    narrowed: Vec<HashMap<String, Narrowing>>,
+}
+
+/// Key of a local, or of a member or index path into one (`xs[0]`, `xs[i]`, `user.name`),
+/// pushing the variables it reads onto `vars`. Other expressions are not narrowed.
+fn narrow_key(e: &Expr, vars: &mut Vec<String>) -> Option<String> {
+    match &e.kind {
+        ExprKind::Ident(name) => {
+            vars.push(name.clone());
+            Some(name.clone())
+        }
+        ExprKind::Member { object, property } => {
+            Some(format!("{}.{}", narrow_key(object, vars)?, property))
+        }
+        ExprKind::Index { object, index } => {
+            let object = narrow_key(object, vars)?;
+            let index = match &index.kind {
+                ExprKind::Literal(lit) => format!("{:?}", lit),
+                ExprKind::Ident(name) => {
+                    vars.push(name.clone());
+                    name.clone()
+                }
+                _ => return None,
+            };
+            Some(format!("{}[{}]", object, index))
+        }
+        _ => None,
+    }
+}
+
+/// `x` in `toType(x)`.
+fn as_to_type_arg(e: &Expr) -> Option<&Expr> {
+    match &e.kind {
+        ExprKind::Call { callee, args } if args.len() == 1 && ident_name_from_callee(callee) == Some("toType") => {
+            Some(&args[0])
+        }
+        _ => None,
+    }
 }
 
 impl TypeChecker {
     fn push_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.narrowed.push(HashMap::new());
     }
     fn pop_scope(&mut self) {
         self.scopes.pop();
+        self.narrowed.pop();
     }
 
     fn declare(&mut self, name: &str, ty: VarType) {
+        // a new variable of the same name shadows whatever was known about the old one
+        self.forget(name, false);
         if let Some(top) = self.scopes.last_mut() {
             top.insert(name.to_string(), ty);
         }
     }
 
+    /// Drops the narrowings that read `var`; with `paths_only`, those of `var` itself
+    /// are kept, for changes to its elements or fields.
+    fn forget(&mut self, var: &str, paths_only: bool) {
+        for frame in &mut self.narrowed {
+            frame.retain(|key, n| (paths_only && key == var) || !n.vars.iter().any(|v| v == var));
+        }
+    }
+
+    fn narrowed_type(&self, key: &str) -> Option<VarType> {
+        self.narrowed
+            .iter()
+            .rev()
+            .find_map(|frame| frame.get(key))
+            .map(|n| n.ty.clone())
+    }
+
+    /// Type of a narrowable expression as far as the checker knows it, without visiting it.
+    fn current_type(&self, key: &str, vars: &[String]) -> Option<VarType> {
+        self.narrowed_type(key).or_else(|| match vars {
+            [var] if var == key => self.lookup(key),
+            _ => None,
+        })
+    }
+
+    fn narrow(&mut self, facts: Facts) {
+        if let Some(top) = self.narrowed.last_mut() {
+            top.extend(facts);
+        }
+    }
+
+    /// What a condition tells about types when it is true, and when it is false:
+    /// `toType(x) == T` narrows `x` to `T`, or to the rest of its union type.
+    /// `&&` combines what both sides tell when true, `||` when false.
+    fn guard_facts(&self, cond: &Expr) -> (Facts, Facts) {
+        let ExprKind::BinaryOp { op, left, right } = &cond.kind else {
+            return (Vec::new(), Vec::new());
+        };
+        match op {
+            BinOp::Eq | BinOp::Ne => {
+                let guard = match (&left.kind, &right.kind) {
+                    (_, ExprKind::TypeLiteral(ty)) => as_to_type_arg(left).map(|arg| (arg, ty)),
+                    (ExprKind::TypeLiteral(ty), _) => as_to_type_arg(right).map(|arg| (arg, ty)),
+                    _ => None,
+                };
+                let Some((arg, ty)) = guard else {
+                    return (Vec::new(), Vec::new());
+                };
+                let mut vars = Vec::new();
+                let Some(key) = narrow_key(arg, &mut vars) else {
+                    return (Vec::new(), Vec::new());
+                };
+                let is = vec![(key.clone(), Narrowing { ty: ty.clone(), vars: vars.clone() })];
+                let is_not = self
+                    .current_type(&key, &vars)
+                    .and_then(|current| current.without(ty))
+                    .map(|rest| vec![(key, Narrowing { ty: rest, vars })])
+                    .unwrap_or_default();
+                if *op == BinOp::Eq {
+                    (is, is_not)
+                } else {
+                    (is_not, is)
+                }
+            }
+            BinOp::And => {
+                let (mut when_true, _) = self.guard_facts(left);
+                when_true.extend(self.guard_facts(right).0);
+                (when_true, Vec::new())
+            }
+            BinOp::Or => {
+                let (left_true, mut when_false) = self.guard_facts(left);
+                let (right_true, right_false) = self.guard_facts(right);
+                when_false.extend(right_false);
+                // `toType(x) == str || toType(x) == num`: x is str|num
+                let when_true = match (left_true.as_slice(), right_true.as_slice()) {
+                    ([(lk, ln)], [(rk, rn)]) if lk == rk => vec![(
+                        lk.clone(),
+                        Narrowing { ty: ln.ty.clone().union(rn.ty.clone()), vars: ln.vars.clone() },
+                    )],
+                    _ => Vec::new(),
+                };
+                (when_true, when_false)
+            }
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Checks `b` with `facts` holding inside it.
+    fn check_narrowed_block(&mut self, b: &Block, facts: Facts) {
+        self.push_scope();
+        self.narrow(facts);
+        self.check_block(b);
+        self.pop_scope();
+    }
+
     fn lookup(&self, name: &str) -> Option<VarType> {
         for m in self.scopes.iter().rev() {
             if let Some(t) = m.get(name) {
@@ -56,6 +204,10 @@ impl TypeChecker {
             // allow default-undefined into anything if you want
             (_, Undefined) => true,
 
+            // every member of a union source must fit; a union destination takes any member
+            (_, Union(members)) => members.iter().all(|m| Self::is_assignable(dst, m)),
+            (Union(members), _) => members.iter().any(|m| Self::is_assignable(m, src)),
+
             (Array(d), Array(s)) => match (&**d, &**s) {
                 (Any, _) => true,  // vec<any> destination accepts either
                 (_, Any) => false, // concrete destination rejects any source
@@ -65,6 +217,41 @@ impl TypeChecker {
         }
     }
 
+    /// Whether a value of type `a` can ever compare equal to one of type `b`.
+    fn may_be_equal(a: &VarType, b: &VarType) -> bool {
+        use VarType::*;
+        match (a, b) {
+            // unset variables hold undefined whatever their declared type
+            (Any, _) | (_, Any) | (Undefined, _) | (_, Undefined) => true,
+            (Union(members), other) | (other, Union(members)) => {
+                members.iter().any(|m| Self::may_be_equal(m, other))
+            }
+            (Array(_), Array(_)) => true,
+            _ => a == b,
+        }
+    }
+
+    /// Declares the params in order, checking each default against its type
+    /// with the earlier params already in scope.
+    fn declare_params(&mut self, params: &[Param]) {
+        for param in params {
+            if let Some(d) = &param.default {
+                if let Some(dt) = self.infer_expr(d) {
+                    if param.ty != VarType::Any && !Self::is_assignable(&param.ty, &dt) {
+                        self.err(
+                            d.pos(),
+                            format!(
+                                "Type mismatch: default value of parameter '{}' is {}, expected {}",
+                                param.name, dt, param.ty
+                            ),
+                        );
+                    }
+                }
+            }
+            self.declare(&param.name, param.ty.clone());
+        }
+    }
+
     fn err(&mut self, pos: Position, msg: String) {
         self.errors.push(LintError::new(pos.into(), msg));
     }
@@ -79,7 +266,7 @@ impl TypeChecker {
 
     fn check_stmt(&mut self, s: &Stmt) {
         match &s.kind {
-            StmtKind::Let { name, ty, init } => {
+            StmtKind::Let { name, ty, init, .. } => {
                 if let Some(e) = init {
                     if let Some(rhs_ty) = self.infer_expr(e) {
                         // Special-case: allow `let xs: vec<T> = []` for any T.
@@ -126,9 +313,16 @@ impl TypeChecker {
                 else_block,
             } => {
                 self.infer_expr(condition);
-                self.check_block(then_block);
+                let (when_true, when_false) = self.guard_facts(condition);
+                self.check_narrowed_block(then_block, when_true.clone());
                 if let Some(b) = else_block {
-                    self.check_block(b);
+                    self.check_narrowed_block(b, when_false.clone());
+                }
+                // past a branch that always leaves, the other branch's facts hold
+                if block_returns(then_block) {
+                    self.narrow(when_false);
+                } else if else_block.as_ref().is_some_and(block_returns) {
+                    self.narrow(when_true);
                 }
             }
 
@@ -152,23 +346,66 @@ impl TypeChecker {
 
             StmtKind::FunctionDecl { params, body, .. } => {
                 self.push_scope();
-                for (pname, pty) in params {
-                    self.declare(pname, pty.clone());
-                }
+                self.declare_params(params);
                 self.check_block(body);
                 self.pop_scope();
             }
 
-            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough | StmtKind::Include(_) => {}
+            StmtKind::Throw(e) => {
+                self.infer_expr(e);
+            }
+            StmtKind::TryCatch {
+                try_block,
+                catch_block,
+                ..
+            } => {
+                // The caught value can be of any type, so the catch name stays untyped.
+                self.check_block(try_block);
+                self.check_block(catch_block);
+            }
             StmtKind::Switch {
                 condition,
                 cases,
                 default,
             } => {
-                self.infer_expr(condition);
-                for (e, b) in cases {
-                    self.infer_expr(e);
-                    self.check_block(b);
+                let discr_ty = self.infer_expr(condition);
+                // `switch (toType(x))`: a case of type labels narrows x, unless the
+                // case before falls through into it
+                let mut discr_vars = Vec::new();
+                let discr_key = as_to_type_arg(condition).and_then(|arg| narrow_key(arg, &mut discr_vars));
+                let mut entered_by_fallthrough = false;
+                for (es, b) in cases {
+                    for e in es {
+                        let case_ty = self.infer_expr(e);
+                        if let (Some(dt), Some(ct)) = (&discr_ty, &case_ty) {
+                            if !Self::may_be_equal(dt, ct) {
+                                self.err(
+                                    e.pos(),
+                                    format!(
+                                        "Type mismatch: case label of type {} can never match a switch value of type {}",
+                                        ct, dt
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    let label_ty = es
+                        .iter()
+                        .map(|e| match &e.kind {
+                            ExprKind::TypeLiteral(ty) => Some(ty.clone()),
+                            _ => None,
+                        })
+                        .reduce(|a, b| a.zip(b).map(|(a, b)| a.union(b)))
+                        .flatten();
+                    match (&discr_key, label_ty) {
+                        (Some(key), Some(ty)) if !entered_by_fallthrough => {
+                            let fact = (key.clone(), Narrowing { ty, vars: discr_vars.clone() });
+                            self.check_narrowed_block(b, vec![fact]);
+                        }
+                        _ => self.check_block(b),
+                    }
+                    entered_by_fallthrough = b.ends_in_fallthrough();
                 }
                 if let Some(b) = default {
                     self.check_block(b);
@@ -184,7 +421,11 @@ impl TypeChecker {
             ExprKind::TypeLiteral(_) => None, // not a runtime value
             ExprKind::Template(_) => Some(VarType::String),
 
-            ExprKind::Ident(name) => self.lookup(name),
+            // `any` (e.g. untyped arrow params) is statically unknown
+            ExprKind::Ident(name) => self
+                .narrowed_type(name)
+                .or_else(|| self.lookup(name))
+                .filter(|t| *t != VarType::Any),
 
             ExprKind::ObjectLiteral { .. } => Some(VarType::Object),
 
@@ -230,9 +471,14 @@ impl TypeChecker {
                         }
                     }
                 }
+                self.forget(name, false);
+                rhs
+            }
+            ExprKind::AssignMember { object, value, .. } => {
+                let rhs = self.infer_expr(value.as_ref());
+                self.forget_paths_of(object);
                 rhs
             }
-            ExprKind::AssignMember { value, .. } => self.infer_expr(value.as_ref()),
             ExprKind::AssignIndex {
                 object,
                 index,
@@ -258,14 +504,18 @@ impl TypeChecker {
                     }
                 }
 
+                self.forget_paths_of(object);
                 rhs_ty
             }
 
-            // Reads
-            ExprKind::Member { .. } => None,
+            // Reads; a guarded path has its narrowed type
+            ExprKind::Member { .. } => narrow_key(e, &mut Vec::new()).and_then(|key| self.narrowed_type(&key)),
             ExprKind::Index { object, index } => {
                 let obj_ty = self.infer_expr(object.as_ref());
                 let idx_ty = self.infer_expr(index.as_ref());
+                if let Some(ty) = narrow_key(e, &mut Vec::new()).and_then(|key| self.narrowed_type(&key)) {
+                    return Some(ty);
+                }
                 match (obj_ty, idx_ty) {
                     (Some(VarType::Array(inner)), Some(VarType::Number)) => Some(*inner), // arr[num] : inner
                     (Some(VarType::Object), Some(VarType::String)) => None, // unknown field type
@@ -277,13 +527,32 @@ impl TypeChecker {
             // but we can infer Number for known ".length()" etc. via method meta.
             ExprKind::Call { callee, args } => {
                 // visit args regardless
-                for a in args {
-                    self.infer_expr(a);
-                }
+                let arg_tys: Vec<Option<VarType>> =
+                    args.iter().map(|a| self.infer_expr(a)).collect();
 
                 // If it's a method call, try lightweight inference via meta (e.g., length -> Number)
                 if let Some((recv_expr, method)) = receiver_and_method_from_callee(callee) {
-                    if let Some(recv_ty) = self.infer_expr(recv_expr) {
+                    let recv_ty = self.infer_expr(recv_expr);
+                    // e.g. `xs.removeAt(0)` moves the elements, `o.delete("k")` drops a key
+                    if is_mutating_method_any(method) {
+                        self.forget_paths_of(recv_expr);
+                    }
+                    if let Some(recv_ty) = recv_ty {
+                        // arr.push(value): if arr is Array(T), enforce value : T
+                        if let (VarType::Array(inner), "push", [Some(vt)]) =
+                            (&recv_ty, method, arg_tys.as_slice())
+                        {
+                            if **inner != VarType::Any && !Self::is_assignable(inner.as_ref(), vt) {
+                                self.err(
+                                    e.pos(),
+                                    format!(
+                                        "Type mismatch: cannot push element of type {} into array of {}",
+                                        vt,
+                                        inner.as_ref()
+                                    ),
+                                );
+                            }
+                        }
                         if let Some(meta) = method_meta_for_vartype(&recv_ty, method) {
                             if meta.returns_number {
                                 return Some(VarType::Number);
@@ -292,6 +561,13 @@ impl TypeChecker {
                     }
                 }
 
+                // Builtins such as `abs(x)` or `keys(o)`
+                if let Some(b) = ident_name_from_callee(callee).and_then(builtin_from_name) {
+                    if let Some(ty) = b.return_type() {
+                        return Some(ty);
+                    }
+                }
+
                 None
             }
 
@@ -301,6 +577,44 @@ impl TypeChecker {
                 let rt = self.infer_expr(right.as_ref());
                 self.infer_binop(*op, lt, rt, e)
             }
+
+            // Function expressions: check the body like a declared function
+            ExprKind::FunctionExpr { params, body, .. } => {
+                self.push_scope();
+                self.declare_params(params);
+                self.check_block(body);
+                self.pop_scope();
+                Some(VarType::Function)
+            }
+        }
+    }
+
+    /// Drops the narrowings of elements and fields of the variable `object` is rooted at.
+    fn forget_paths_of(&mut self, object: &Expr) {
+        let mut vars = Vec::new();
+        if narrow_key(object, &mut vars).is_some() {
+            self.forget(&vars[0], true);
+        }
+    }
+
+    /// Element type of `left + right` on two arrays: the wider of the two element
+    /// types, or the other side's when one operand is an empty `[]` literal.
+    fn infer_concat(&mut self, a: VarType, b: VarType, at: &Expr) -> Option<VarType> {
+        let is_empty_literal = |e: &Expr| matches!(&e.kind, ExprKind::Array(items) if items.is_empty());
+        let (left_empty, right_empty) = match &at.kind {
+            ExprKind::BinaryOp { left, right, .. } => (is_empty_literal(left), is_empty_literal(right)),
+            _ => (false, false),
+        };
+        let (a, b) = (VarType::Array(Box::new(a)), VarType::Array(Box::new(b)));
+        if left_empty {
+            Some(b)
+        } else if right_empty || Self::is_assignable(&a, &b) {
+            Some(a)
+        } else if Self::is_assignable(&b, &a) {
+            Some(b)
+        } else {
+            self.err(at.pos(), format!("Cannot concatenate {} and {}", a, b));
+            None
         }
     }
 
@@ -317,6 +631,7 @@ impl TypeChecker {
             Add => match (lt, rt) {
                 (Some(Number), Some(Number)) => Some(Number),
                 (Some(String), Some(String)) => Some(String),
+                (Some(Array(a)), Some(Array(b))) => self.infer_concat(*a, *b, at),
                 (Some(a), Some(b)) => {
                     self.err(
                         at.pos(),
@@ -339,11 +654,11 @@ impl TypeChecker {
             },
             Eq | Ne => Some(Bool),
             Lt | Le | Gt | Ge => match (lt, rt) {
-                (Some(Number), Some(Number)) => Some(Bool),
+                (Some(Number), Some(Number)) | (Some(String), Some(String)) => Some(Bool),
                 (Some(a), Some(b)) => {
                     self.err(
                         at.pos(),
-                        format!("Order comparison requires numbers, got {} and {}", a, b),
+                        format!("Order comparison requires two numbers or two strings, got {} and {}", a, b),
                     );
                     None
                 }
@@ -365,3 +680,70 @@ impl TypeChecker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::rjscript::testing::{eval_script_str_with_world, TestWorld};
+
+    fn run(src: &str) -> Result<(u16, String), String> {
+        eval_script_str_with_world(src, Value::Null, &TestWorld::new())
+            .map(|(status, v)| (status, v.to_string()))
+    }
+
+    /// A script declaring `x` with `decl`, then switching on it with a single `case label:`.
+    fn switch_on(decl: &str, label: &str) -> Result<(u16, String), String> {
+        run(&format!(
+            "{}\nswitch (x) {{\n    case {}:\n        return 200, \"hit\";\n    default:\n        return 200, \"miss\";\n}}",
+            decl, label
+        ))
+    }
+
+    #[test]
+    fn case_labels_of_a_matching_type() {
+        assert_eq!(switch_on("let x: num = 2;", "1, 2"), Ok((200, "hit".into())));
+        assert_eq!(switch_on("let x: str = \"a\";", "\"a\""), Ok((200, "hit".into())));
+        assert_eq!(switch_on("let x: str|num = 1;", "\"a\", 1"), Ok((200, "hit".into())));
+        assert_eq!(switch_on("let x: vec<num> = [1];", "[1]"), Ok((200, "hit".into())));
+        // a variable holds undefined until it is set, whatever its type
+        assert_eq!(switch_on("let x: num = 1;", "undefined"), Ok((200, "miss".into())));
+    }
+
+    #[test]
+    fn case_labels_that_can_never_match() {
+        for (decl, label, label_ty, discr_ty) in [
+            ("let x: num = 1;", "\"x\"", "str", "num"),
+            ("let x: str = \"1\";", "1", "num", "str"),
+            ("let x: bool = true;", "\"true\"", "str", "bool"),
+            ("let x: str|num = 1;", "false", "bool", "str|num"),
+        ] {
+            let err = switch_on(decl, label).unwrap_err();
+            let want = format!("3:10 Type mismatch: case label of type {} can never match a switch value of type {}", label_ty, discr_ty);
+            assert!(err.contains(&want), "{} / {}: {}", decl, label, err);
+        }
+        // every label of a case is checked
+        let err = switch_on("let x: num = 1;", "1, \"1\"").unwrap_err();
+        assert!(err.contains("3:13 Type mismatch: case label of type str"), "{}", err);
+    }
+
+    #[test]
+    fn arrays_concatenate_with_plus() {
+        assert_eq!(run("return 200, [1, 2] + [3];"), Ok((200, "[1,2,3]".into())));
+        assert_eq!(run("return 200, [] + [\"a\"] + [];"), Ok((200, "[\"a\"]".into())));
+        let src = "let xs: vec<num> = [1];\nlet ys: vec<num> = xs + [];\nlet zs: vec<num> = [] + xs + [2];\nreturn 200, [xs, ys, zs];";
+        assert_eq!(run(src), Ok((200, "[[1],[1],[1,2]]".into())));
+        // the operands are left as they were
+        let src = "let xs: vec<num> = [1];\nlet ys: vec<num> = xs + xs;\nys.push(3);\nreturn 200, [xs, ys];";
+        assert_eq!(run(src), Ok((200, "[[1],[1,1,3]]".into())));
+    }
+
+    #[test]
+    fn arrays_of_unrelated_types_do_not_concatenate() {
+        let src = "let xs: vec<num> = [1];\nlet ys: vec<str> = [\"a\"];\nreturn 200, xs + ys;";
+        let err = run(src).unwrap_err();
+        assert!(err.contains("3:18 Cannot concatenate vec<num> and vec<str>"), "{}", err);
+        let err = run("let xs: vec<num> = [1];\nreturn 200, xs + 1;").unwrap_err();
+        assert!(err.contains("2:"), "{}", err);
+    }
+}
@@ -3,10 +3,11 @@ use std::collections::HashSet;
 
 use crate::rjscript::{ast::{
     block::Block,
+    param::Param,
     expr::{Expr, ExprKind},
-    stmt::StmtKind,
-    visitor::{walk_block, walk_expr, Visit},
-}, semantics::methods::builtin_names_set};
+    stmt::{Stmt, StmtKind},
+    visitor::{walk_block, walk_expr, walk_stmt, Visit},
+}, semantics::{methods::builtin_names_set, types::VarType}};
 use crate::rjscript::preprocess::lints::error::LintError;
 use crate::rjscript::preprocess::lints::util::{
     ident_name_from_callee, known_method_names_any,
@@ -24,6 +25,8 @@ struct UnknownCalls {
     builtins: HashSet<&'static str>,
     known_methods: HashSet<&'static str>,
     user_funcs: HashSet<String>,
+    /// Variables and params of type `fn`, which can be called like functions
+    fn_vars: HashSet<String>,
 }
 
 impl UnknownCalls {
@@ -31,7 +34,15 @@ impl UnknownCalls {
         let builtins = builtin_names_set();
         let known_methods = known_method_names_any();
         let user_funcs = collect_function_decls(block).into_keys().collect();
-        Self { errors: Vec::new(), builtins, known_methods, user_funcs }
+        Self { errors: Vec::new(), builtins, known_methods, user_funcs, fn_vars: HashSet::new() }
+    }
+
+    fn note_fn_params(&mut self, params: &[Param]) {
+        for param in params {
+            if param.ty == VarType::Function {
+                self.fn_vars.insert(param.name.clone());
+            }
+        }
     }
 
     fn err_unknown_func(&mut self, pos: crate::rjscript::ast::position::Position, name: &str) {
@@ -43,7 +54,7 @@ impl UnknownCalls {
     }
 }
 
-impl Visit for UnknownCalls {
+impl Visit<'_> for UnknownCalls {
     fn visit_block(&mut self, b: &Block) {
         // Also collect nested function declarations
         for s in &b.stmts {
@@ -54,10 +65,27 @@ impl Visit for UnknownCalls {
         walk_block(self, b);
     }
 
+    fn visit_stmt(&mut self, s: &Stmt) {
+        match &s.kind {
+            StmtKind::Let { name, ty: VarType::Function, .. } => {
+                self.fn_vars.insert(name.clone());
+            }
+            StmtKind::FunctionDecl { params, .. } => self.note_fn_params(params),
+            _ => {}
+        }
+        walk_stmt(self, s);
+    }
+
     fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::FunctionExpr { params, .. } = &e.kind {
+            self.note_fn_params(params);
+        }
         if let ExprKind::Call { callee, .. } = &e.kind {
             if let Some(name) = ident_name_from_callee(callee) {
-                if !self.builtins.contains(name) && !self.user_funcs.contains(name) {
+                if !self.builtins.contains(name)
+                    && !self.user_funcs.contains(name)
+                    && !self.fn_vars.contains(name)
+                {
                     self.err_unknown_func(e.pos, name);
                 }
             } else if let Some((_recv, method)) = receiver_and_method_from_callee(callee) {
@@ -10,11 +10,12 @@ use crate::rjscript::{
         expr::{Expr, ExprKind},
         position::Position,
         stmt::StmtKind,
+        visitor::{walk_expr, Visit},
     },
     semantics::{
         methods::{
-            builtin_names_set, receiver_from_vartype, MethodMeta, Receiver, ARRAY_METHODS_META,
-            STRING_METHODS_META,
+            builtin_names_set, receiver_from_vartype, MethodMeta, Receiver, ARRAY_METHODS_META, BUILTINS_TBL,
+            OBJECT_METHODS_META, STRING_METHODS_META,
         },
         types::VarType,
     },
@@ -111,6 +112,7 @@ pub fn known_method_names_any() -> HashSet<&'static str> {
         .iter()
         .map(|(_, m)| m.name)
         .chain(STRING_METHODS_META.iter().map(|(_, m)| m.name))
+        .chain(OBJECT_METHODS_META.iter().map(|(_, m)| m.name))
         .collect()
 }
 
@@ -126,6 +128,10 @@ pub fn method_meta_for_receiver(receiver: Receiver, name: &str) -> Option<&'stat
             .iter()
             .find(|(_, m)| m.name == name)
             .map(|(_, m)| m),
+        Receiver::Object => OBJECT_METHODS_META
+            .iter()
+            .find(|(_, m)| m.name == name)
+            .map(|(_, m)| m),
     }
 }
 
@@ -138,8 +144,19 @@ pub fn method_meta_for_vartype(ty: &VarType, name: &str) -> Option<&'static Meth
 /// Conservative check: if a method name is mutating for *any* known receiver, return true.
 #[inline]
 pub fn is_mutating_method_any(name: &str) -> bool {
-    method_meta_for_receiver(Receiver::Array, name).is_some_and(|m| m.is_mut)
-        || method_meta_for_receiver(Receiver::String, name).is_some_and(|m| m.is_mut)
+    [Receiver::Array, Receiver::String, Receiver::Object]
+        .into_iter()
+        .any(|rcv| method_meta_for_receiver(rcv, name).is_some_and(|m| m.is_mut))
+}
+
+/// Conservative check: true only if the method is known and pure for every receiver that has it.
+#[inline]
+pub fn is_pure_method_any(name: &str) -> bool {
+    let metas: Vec<&MethodMeta> = [Receiver::Array, Receiver::String, Receiver::Object]
+        .into_iter()
+        .filter_map(|rcv| method_meta_for_receiver(rcv, name))
+        .collect();
+    !metas.is_empty() && metas.iter().all(|m| m.is_pure)
 }
 
 /// If `callee` is a bare identifier (free function call), returns its name.
@@ -169,3 +186,24 @@ pub fn collect_function_decls(block: &Block) -> HashMap<String, Position> {
     }
     map
 }
+
+/// Every call of a DB builtin in `block`, with the position of its name.
+pub fn collect_db_calls(block: &Block) -> Vec<(&'static str, Position)> {
+    struct DbCalls(Vec<(&'static str, Position)>);
+
+    impl Visit<'_> for DbCalls {
+        fn visit_expr(&mut self, e: &Expr) {
+            if let ExprKind::Call { callee, .. } = &e.kind {
+                if let Some(name) = ident_name_from_callee(callee) {
+                    let db_builtin = BUILTINS_TBL.iter().find(|(b, n)| *n == name && b.is_db());
+                    self.0.extend(db_builtin.map(|(_, n)| (*n, callee.pos)));
+                }
+            }
+            walk_expr(self, e);
+        }
+    }
+
+    let mut v = DbCalls(Vec::new());
+    v.visit_block(block);
+    v.0
+}
@@ -13,12 +13,21 @@ pub struct PreprocessResult {
 /// Preprocess when you have a Vec<Stmt>.
 /// Returns (lint_messages, transformed_stmts).
 pub fn preprocess(stmts: Vec<Stmt>) -> PreprocessResult {
+    preprocess_with(stmts, lints::run_lints)
+}
+
+/// Preprocess the functions of an included file.
+pub fn preprocess_include(stmts: Vec<Stmt>) -> PreprocessResult {
+    preprocess_with(stmts, lints::run_include_lints)
+}
+
+fn preprocess_with(stmts: Vec<Stmt>, run_lints: fn(&Block) -> Vec<LintError>) -> PreprocessResult {
     let mut block = Block::new(stmts, Position::UNKNOWN);
 
     // 2) Transforms (mutating)
     transforms::run_transforms(&mut block);
 
-    let errors = lints::run_lints(&block);
+    let errors = run_lints(&block);
 
     PreprocessResult {
         errors,
@@ -22,7 +22,9 @@ impl VisitMut for DeadCodeStrip {
                 for (i, s) in b.stmts.iter().enumerate() {
                     let last = i == b.stmts.len() - 1;
                     match &s.kind {
-                        StmtKind::Return(_) | StmtKind::ReturnStatus { .. } => return true,
+                        StmtKind::Return(_) | StmtKind::ReturnStatus { .. } | StmtKind::Throw(_) => {
+                            return true
+                        }
                         StmtKind::IfElse {
                             then_block,
                             else_block,
@@ -54,6 +56,20 @@ impl VisitMut for DeadCodeStrip {
                                 return false;
                             }
                         }
+                        StmtKind::TryCatch {
+                            try_block,
+                            catch_block,
+                            ..
+                        } => {
+                            if block_must_return(try_block) && block_must_return(catch_block) {
+                                return true;
+                            }
+                            if !last {
+                                continue;
+                            } else {
+                                return false;
+                            }
+                        }
                         StmtKind::For { .. } => {
                             if !last {
                                 continue;
@@ -74,7 +90,7 @@ impl VisitMut for DeadCodeStrip {
             }
 
             match &s.kind {
-                StmtKind::Return(_) | StmtKind::ReturnStatus { .. } => true,
+                StmtKind::Return(_) | StmtKind::ReturnStatus { .. } | StmtKind::Throw(_) => true,
                 StmtKind::IfElse {
                     then_block,
                     else_block,
@@ -84,22 +100,34 @@ impl VisitMut for DeadCodeStrip {
                         && else_block.as_ref().map(block_must_return).unwrap_or(false)
                 }
                 StmtKind::Switch { cases, default, .. } => {
-                    let all_cases_return = cases.iter().all(|(_, blk)| block_must_return(blk));
+                    // a case ending in `fallthrough` returns if the clauses after it do,
+                    // and those are all checked here too
+                    let all_cases_return = cases
+                        .iter()
+                        .all(|(_, blk)| blk.ends_in_fallthrough() || block_must_return(blk));
                     let default_returns = default.as_ref().map(block_must_return).unwrap_or(false);
                     all_cases_return && default_returns
                 }
+                StmtKind::TryCatch {
+                    try_block,
+                    catch_block,
+                    ..
+                } => block_must_return(try_block) && block_must_return(catch_block),
                 // Break/Continue only terminate a loop body; we keep prior behavior and
                 // treat them as terminating within the current block (same as before).
-                StmtKind::Break | StmtKind::Continue => true,
+                StmtKind::Break | StmtKind::Continue | StmtKind::Fallthrough => true,
                 _ => false,
             }
         }
 
-        // drop statements after the first terminating one
+        // drop statements after the first terminating one, except function
+        // declarations: they are hoisted, so still callable from above
         let mut out = Vec::with_capacity(b.stmts.len());
         let mut live = true;
         for s in std::mem::take(&mut b.stmts) {
-            if live {
+            if !live && matches!(s.kind, StmtKind::FunctionDecl { .. }) {
+                out.push(s);
+            } else if live {
                 let terminates = stmt_terminates(&s);
                 out.push(s);
                 if terminates {
@@ -7,6 +7,7 @@ use crate::rjscript::semantics::types::VarType;
 pub enum Receiver {
     Array,
     String,
+    Object,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -14,40 +15,98 @@ pub struct MethodMeta {
     pub name: &'static str,
     pub is_mut: bool,
     pub returns_number: bool,
+    /// Takes a function argument that is called back, e.g. `map`
+    pub takes_callback: bool,
+    /// Free of side effects, and the result depends only on the receiver and the
+    /// arguments, so the call can be folded, cached or reordered
+    pub is_pure: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum ArrayMethod {
     Length,
     Push,
+    Pop,
     Remove,
     RemoveAt,
+    Join,
+    JoinHuman,
+    Enumerate,
+    Zip,
+    Map,
+    FlatMap,
+    IndexOf,
+    Reverse,
+    Sort,
+    SortBy,
+    Partition,
+    CountBy,
 }
 
 pub const ARRAY_METHODS_META: &[(ArrayMethod, MethodMeta)] = &[
-    (ArrayMethod::Length,  MethodMeta { name: "length",  is_mut: false, returns_number: true  }),
-    (ArrayMethod::Push,    MethodMeta { name: "push",    is_mut: true,  returns_number: false }),
-    (ArrayMethod::Remove,     MethodMeta { name: "remove",     is_mut: true,  returns_number: false }),
-    (ArrayMethod::RemoveAt,   MethodMeta { name: "removeAt",   is_mut: true,  returns_number: false }),
+    (ArrayMethod::Length,  MethodMeta { name: "length",  is_mut: false, returns_number: true,  takes_callback: false, is_pure: true  }),
+    (ArrayMethod::Push,    MethodMeta { name: "push",    is_mut: true,  returns_number: true,  takes_callback: false, is_pure: false }),
+    (ArrayMethod::Pop,     MethodMeta { name: "pop",     is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
+    (ArrayMethod::Remove,     MethodMeta { name: "remove",     is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
+    (ArrayMethod::RemoveAt,   MethodMeta { name: "removeAt",   is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
+    (ArrayMethod::Join,       MethodMeta { name: "join",       is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ArrayMethod::JoinHuman,  MethodMeta { name: "joinHuman",  is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ArrayMethod::Enumerate,  MethodMeta { name: "enumerate",  is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ArrayMethod::Zip,        MethodMeta { name: "zip",        is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ArrayMethod::Map,        MethodMeta { name: "map",        is_mut: false, returns_number: false, takes_callback: true,  is_pure: false }),
+    (ArrayMethod::FlatMap,    MethodMeta { name: "flatMap",    is_mut: false, returns_number: false, takes_callback: true,  is_pure: false }),
+    (ArrayMethod::IndexOf,    MethodMeta { name: "indexOf",    is_mut: false, returns_number: true,  takes_callback: false, is_pure: true  }),
+    (ArrayMethod::Reverse,    MethodMeta { name: "reverse",    is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
+    (ArrayMethod::Sort,       MethodMeta { name: "sort",       is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ArrayMethod::SortBy,     MethodMeta { name: "sortBy",     is_mut: false, returns_number: false, takes_callback: true,  is_pure: false }),
+    (ArrayMethod::Partition,  MethodMeta { name: "partition",  is_mut: false, returns_number: false, takes_callback: true,  is_pure: false }),
+    (ArrayMethod::CountBy,    MethodMeta { name: "countBy",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
 ];
 
 #[derive(Debug, Clone, Copy)]
 pub enum StringMethod {
     Length,
+    GraphemeLength,
     Contains,
     Split,
     ToChars,
     Replace,
     Substring,
+    ToUpperCase,
+    ToLowerCase,
+    IndexOf,
+    ToNumber,
+    ToBool,
 }
 
 pub const STRING_METHODS_META: &[(StringMethod, MethodMeta)] = &[
-    (StringMethod::Length,      MethodMeta { name: "length",      is_mut: false, returns_number: true  }),
-    (StringMethod::Contains,    MethodMeta { name: "contains",    is_mut: false, returns_number: false }),
-    (StringMethod::Split,    MethodMeta { name: "split",    is_mut: false, returns_number: false }),
-    (StringMethod::ToChars,    MethodMeta { name: "to_chars",    is_mut: false, returns_number: false }),
-    (StringMethod::Replace,    MethodMeta { name: "replace",    is_mut: false, returns_number: false }),
-    (StringMethod::Substring,    MethodMeta { name: "substring",    is_mut: false, returns_number: false }),
+    (StringMethod::Length,      MethodMeta { name: "length",      is_mut: false, returns_number: true,  takes_callback: false, is_pure: true  }),
+    (StringMethod::GraphemeLength, MethodMeta { name: "graphemeLength", is_mut: false, returns_number: true, takes_callback: false, is_pure: true }),
+    (StringMethod::Contains,    MethodMeta { name: "contains",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::Split,    MethodMeta { name: "split",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::ToChars,    MethodMeta { name: "to_chars",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::Replace,    MethodMeta { name: "replace",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::Substring,    MethodMeta { name: "substring",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::ToUpperCase,  MethodMeta { name: "toUpperCase",  is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::ToLowerCase,  MethodMeta { name: "toLowerCase",  is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (StringMethod::IndexOf,      MethodMeta { name: "indexOf",      is_mut: false, returns_number: true,  takes_callback: false, is_pure: true  }),
+    (StringMethod::ToNumber,     MethodMeta { name: "toNumber",     is_mut: false, returns_number: true,  takes_callback: false, is_pure: true  }),
+    (StringMethod::ToBool,       MethodMeta { name: "toBool",       is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum ObjectMethod {
+    Has,
+    Get,
+    Set,
+    Delete,
+}
+
+pub const OBJECT_METHODS_META: &[(ObjectMethod, MethodMeta)] = &[
+    (ObjectMethod::Has,    MethodMeta { name: "has",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ObjectMethod::Get,    MethodMeta { name: "get",    is_mut: false, returns_number: false, takes_callback: false, is_pure: true  }),
+    (ObjectMethod::Set,    MethodMeta { name: "set",    is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
+    (ObjectMethod::Delete, MethodMeta { name: "delete", is_mut: true,  returns_number: false, takes_callback: false, is_pure: false }),
 ];
 
 #[inline]
@@ -55,6 +114,7 @@ pub fn receiver_from_vartype(ty: &VarType) -> Option<Receiver> {
     match ty {
         VarType::Array(_) => Some(Receiver::Array),
         VarType::String   => Some(Receiver::String),
+        VarType::Object   => Some(Receiver::Object),
         _ => None,
     }
 }
@@ -62,12 +122,64 @@ pub fn receiver_from_vartype(ty: &VarType) -> Option<Receiver> {
 #[derive(Debug, Clone, Copy)]
 pub enum Builtin {      
     Print,
+    LogDebug,
+    LogInfo,
+    LogWarn,
+    LogError,
+    Error,
+    Assert,
     ToType,
     ToString,
+    JsonParse,
+    JsonStringify,
+    Keys,
+    Values,
+    Entries,
+    HasKey,
+    Merge,
+    DeepMerge,
+    DeepClone,
+    Diff,
+    ParseInt,
+    ParseFloat,
+    ToNumber,
+    BodyGet,
+    BearerToken,
+    BasicAuth,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    Sha256,
+    Md5,
+    HmacSha256,
+    RegexTest,
+    RegexMatch,
+    RegexReplace,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Pow,
+    Sqrt,
+    Uuid,
+    UuidShort,
+    Random,
+    RandomInt,
+    Now,
+    Timestamp,
+    FormatDate,
+    Env,
+    HttpFetch,
+    ReadFile,
+    ReadJson,
     Sleep,
     CacheGet,
     CacheSet,
     CacheDel,
+    CacheTtl,
     CacheClear,
     DbCreateTable,
     DbGetAllTables,
@@ -76,21 +188,77 @@ pub enum Builtin {
     DbGetAll,
     DbGetById,
     DbGetByFields,
+    DbGetAllPaged,
+    DbGetByFieldsPaged,
     DbUpdateById,
+    DbReplaceById,
     DbUpdateByFields,
     DbDeleteById,
     DbDeleteByFields,
     DbDrop,
+    DbCompact,
 }
 
 pub const BUILTINS_TBL: &[(Builtin, &'static str)] = &[
     (Builtin::Print,  "print"),
+    (Builtin::LogDebug, "logDebug"),
+    (Builtin::LogInfo, "logInfo"),
+    (Builtin::LogWarn, "logWarn"),
+    (Builtin::LogError, "logError"),
+    (Builtin::Error,  "error"),
+    (Builtin::Assert, "assert"),
     (Builtin::ToType, "toType"),
     (Builtin::ToString, "toString"),
+    (Builtin::JsonParse, "jsonParse"),
+    (Builtin::JsonStringify, "jsonStringify"),
+    (Builtin::Keys, "keys"),
+    (Builtin::Values, "values"),
+    (Builtin::Entries, "entries"),
+    (Builtin::HasKey, "hasKey"),
+    (Builtin::Merge, "merge"),
+    (Builtin::DeepMerge, "deepMerge"),
+    (Builtin::DeepClone, "deepClone"),
+    (Builtin::Diff, "diff"),
+    (Builtin::ParseInt, "parseInt"),
+    (Builtin::ParseFloat, "parseFloat"),
+    (Builtin::ToNumber, "toNumber"),
+    (Builtin::BodyGet, "bodyGet"),
+    (Builtin::BearerToken, "bearerToken"),
+    (Builtin::BasicAuth, "basicAuth"),
+    (Builtin::Base64Encode, "base64Encode"),
+    (Builtin::Base64Decode, "base64Decode"),
+    (Builtin::UrlEncode, "urlEncode"),
+    (Builtin::UrlDecode, "urlDecode"),
+    (Builtin::Sha256, "sha256"),
+    (Builtin::Md5, "md5"),
+    (Builtin::HmacSha256, "hmacSha256"),
+    (Builtin::RegexTest, "regexTest"),
+    (Builtin::RegexMatch, "regexMatch"),
+    (Builtin::RegexReplace, "regexReplace"),
+    (Builtin::Abs, "abs"),
+    (Builtin::Floor, "floor"),
+    (Builtin::Ceil, "ceil"),
+    (Builtin::Round, "round"),
+    (Builtin::Min, "min"),
+    (Builtin::Max, "max"),
+    (Builtin::Pow, "pow"),
+    (Builtin::Sqrt, "sqrt"),
+    (Builtin::Uuid, "uuid"),
+    (Builtin::UuidShort, "uuidShort"),
+    (Builtin::Random, "random"),
+    (Builtin::RandomInt, "randomInt"),
+    (Builtin::Now, "now"),
+    (Builtin::Timestamp, "timestamp"),
+    (Builtin::FormatDate, "formatDate"),
+    (Builtin::Env, "env"),
+    (Builtin::HttpFetch, "httpFetch"),
+    (Builtin::ReadFile, "readFile"),
+    (Builtin::ReadJson, "readJson"),
     (Builtin::Sleep, "sleep"),
     (Builtin::CacheGet, "cacheGet"),
     (Builtin::CacheSet, "cacheSet"),
     (Builtin::CacheDel, "cacheDel"),
+    (Builtin::CacheTtl, "cacheTtl"),
     (Builtin::CacheClear, "cacheClear"),
     (Builtin::DbCreateTable, "dbCreateTable"),
     (Builtin::DbGetAllTables, "dbGetAllTables"),
@@ -99,14 +267,215 @@ pub const BUILTINS_TBL: &[(Builtin, &'static str)] = &[
     (Builtin::DbGetAll, "dbGetAll"),
     (Builtin::DbGetById, "dbGetById"),
     (Builtin::DbGetByFields, "dbGetByFields"),
+    (Builtin::DbGetAllPaged, "dbGetAllPaged"),
+    (Builtin::DbGetByFieldsPaged, "dbGetByFieldsPaged"),
     (Builtin::DbUpdateById, "dbUpdateById"),
+    (Builtin::DbReplaceById, "dbReplaceById"),
     (Builtin::DbUpdateByFields, "dbUpdateByFields"),
     (Builtin::DbDeleteById, "dbDeleteById"),
     (Builtin::DbDeleteByFields, "dbDeleteByFields"),
     (Builtin::DbDrop, "dbDrop"),    
+    (Builtin::DbCompact, "dbCompact"),
 ];
 
+impl Builtin {
+    /// Whether the builtin is free of side effects and its result depends only on
+    /// its arguments. Cache, db, time, random, environment and output builtins are all side-effecting,
+    /// and `bodyGet`, `bearerToken` and `basicAuth` depend on the request.
+    pub fn is_pure(self) -> bool {
+        match self {
+            Builtin::ToType
+            | Builtin::ToString
+            | Builtin::JsonParse
+            | Builtin::JsonStringify
+            | Builtin::Keys
+            | Builtin::Values
+            | Builtin::Entries
+            | Builtin::HasKey
+            | Builtin::Merge
+            | Builtin::DeepMerge
+            | Builtin::DeepClone
+            | Builtin::Diff
+            | Builtin::ParseInt
+            | Builtin::ParseFloat
+            | Builtin::ToNumber
+            | Builtin::Base64Encode
+            | Builtin::Base64Decode
+            | Builtin::UrlEncode
+            | Builtin::UrlDecode
+            | Builtin::Sha256
+            | Builtin::Md5
+            | Builtin::HmacSha256
+            | Builtin::RegexTest
+            | Builtin::RegexMatch
+            | Builtin::RegexReplace
+            | Builtin::FormatDate
+            | Builtin::Abs
+            | Builtin::Floor
+            | Builtin::Ceil
+            | Builtin::Round
+            | Builtin::Min
+            | Builtin::Max
+            | Builtin::Pow
+            | Builtin::Sqrt => true,
+            Builtin::Print
+            | Builtin::LogDebug
+            | Builtin::LogInfo
+            | Builtin::LogWarn
+            | Builtin::LogError
+            | Builtin::Error
+            | Builtin::Assert
+            | Builtin::BodyGet
+            | Builtin::BearerToken
+            | Builtin::BasicAuth
+            | Builtin::Uuid
+            | Builtin::UuidShort
+            | Builtin::Random
+            | Builtin::RandomInt
+            | Builtin::Now
+            | Builtin::Timestamp
+            | Builtin::Env
+            | Builtin::HttpFetch
+            | Builtin::ReadFile
+            | Builtin::ReadJson
+            | Builtin::Sleep
+            | Builtin::CacheGet
+            | Builtin::CacheSet
+            | Builtin::CacheDel
+            | Builtin::CacheTtl
+            | Builtin::CacheClear
+            | Builtin::DbCreateTable
+            | Builtin::DbGetAllTables
+            | Builtin::DbDropTable
+            | Builtin::DbCreateEntry
+            | Builtin::DbGetAll
+            | Builtin::DbGetById
+            | Builtin::DbGetByFields
+            | Builtin::DbGetAllPaged
+            | Builtin::DbGetByFieldsPaged
+            | Builtin::DbUpdateById
+            | Builtin::DbReplaceById
+            | Builtin::DbUpdateByFields
+            | Builtin::DbDeleteById
+            | Builtin::DbDeleteByFields
+            | Builtin::DbDrop
+            | Builtin::DbCompact => false,
+        }
+    }
+
+    /// Whether the builtin needs the persistent DB.
+    pub fn is_db(self) -> bool {
+        matches!(
+            self,
+            Builtin::DbCreateTable
+                | Builtin::DbGetAllTables
+                | Builtin::DbDropTable
+                | Builtin::DbCreateEntry
+                | Builtin::DbGetAll
+                | Builtin::DbGetById
+                | Builtin::DbGetByFields
+                | Builtin::DbGetAllPaged
+                | Builtin::DbGetByFieldsPaged
+                | Builtin::DbUpdateById
+                | Builtin::DbReplaceById
+                | Builtin::DbUpdateByFields
+                | Builtin::DbDeleteById
+                | Builtin::DbDeleteByFields
+                | Builtin::DbDrop
+                | Builtin::DbCompact
+        )
+    }
+
+    /// The type every successful call returns, when there is one.
+    pub fn return_type(self) -> Option<VarType> {
+        match self {
+            Builtin::Abs
+            | Builtin::Floor
+            | Builtin::Ceil
+            | Builtin::Round
+            | Builtin::Min
+            | Builtin::Max
+            | Builtin::Pow
+            | Builtin::Sqrt
+            | Builtin::Random
+            | Builtin::RandomInt
+            | Builtin::Now => Some(VarType::Number),
+            Builtin::Timestamp
+            | Builtin::FormatDate
+            | Builtin::Uuid
+            | Builtin::UuidShort
+            | Builtin::UrlEncode
+            | Builtin::Sha256
+            | Builtin::Md5
+            | Builtin::HmacSha256
+            | Builtin::RegexReplace
+            | Builtin::JsonStringify
+            | Builtin::ReadFile => Some(VarType::String),
+            Builtin::RegexTest => Some(VarType::Bool),
+            Builtin::Keys => Some(VarType::Array(Box::new(VarType::String))),
+            Builtin::Values => Some(VarType::Array(Box::new(VarType::Any))),
+            Builtin::Entries => Some(VarType::Array(Box::new(VarType::Object))),
+            Builtin::HasKey => Some(VarType::Bool),
+            Builtin::Merge | Builtin::DeepMerge | Builtin::Diff | Builtin::HttpFetch => Some(VarType::Object),
+            _ => None,
+        }
+    }
+}
+
+#[inline]
+pub fn builtin_from_name(name: &str) -> Option<Builtin> {
+    BUILTINS_TBL.iter().find(|(_, n)| *n == name).map(|(b, _)| *b)
+}
+
+/// `true` for known pure builtins; unknown names are treated as side-effecting.
+#[inline]
+pub fn is_pure_builtin(name: &str) -> bool {
+    builtin_from_name(name).is_some_and(Builtin::is_pure)
+}
+
 #[inline]
 pub fn builtin_names_set() -> HashSet<&'static str> {
     BUILTINS_TBL.iter().map(|(_, n)| *n).collect()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rjscript::preprocess::lints::util::is_pure_method_any;
+
+    #[test]
+    fn side_effecting_builtins_are_not_pure() {
+        for name in [
+            "now", "timestamp", "random", "randomInt", "uuid", "uuidShort", "httpFetch", "env",
+            "readFile", "readJson", "sleep", "print", "error", "bodyGet", "bearerToken", "basicAuth",
+            "cacheGet", "cacheSet", "cacheDel", "cacheTtl", "dbCreateEntry", "dbGetAll",
+        ] {
+            assert!(builtin_from_name(name).is_some(), "{} is a builtin", name);
+            assert!(!is_pure_builtin(name), "{}", name);
+        }
+        for (builtin, name) in BUILTINS_TBL {
+            assert!(!(builtin.is_db() && builtin.is_pure()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn pure_builtins_and_methods() {
+        for name in ["toType", "jsonParse", "jsonStringify", "keys", "deepClone", "base64Encode", "sha256", "sqrt", "max"] {
+            assert!(is_pure_builtin(name), "{}", name);
+        }
+        assert!(!is_pure_builtin("noSuchBuiltin"));
+
+        for name in ["length", "split", "toUpperCase", "join", "indexOf"] {
+            assert!(is_pure_method_any(name), "{}", name);
+        }
+        // mutating and callback-taking methods, and unknown ones, are not
+        for name in ["push", "pop", "reverse", "map", "sortBy", "noSuchMethod"] {
+            assert!(!is_pure_method_any(name), "{}", name);
+        }
+        let metas = ARRAY_METHODS_META.iter().map(|(_, m)| m)
+            .chain(STRING_METHODS_META.iter().map(|(_, m)| m))
+            .chain(OBJECT_METHODS_META.iter().map(|(_, m)| m));
+        for meta in metas {
+            assert!(!(meta.is_pure && (meta.is_mut || meta.takes_callback)), "{}", meta.name);
+        }
+    }
+}
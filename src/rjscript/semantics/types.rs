@@ -6,7 +6,12 @@ pub enum VarType {
     Array(Box<VarType>),
     Object,
     Any,
-    Undefined
+    Undefined,
+    Null,
+    /// Function value, e.g. `func (x: num): num { ... }`
+    Function,
+    /// Any one of the member types, e.g. `str|num`. Members are distinct and never unions.
+    Union(Vec<VarType>),
 }
 
 impl std::fmt::Display for VarType {
@@ -18,8 +23,60 @@ impl std::fmt::Display for VarType {
             String => write!(f, "str"),
             Object => write!(f, "obj"),
             Undefined => write!(f, "undefined"),
+            Null => write!(f, "null"),
             Any => write!(f, "any"),
+            Function => write!(f, "fn"),
             Array(inner) => write!(f, "vec<{}>", inner),
+            Union(members) => {
+                let names: Vec<std::string::String> = members.iter().map(|m| m.to_string()).collect();
+                write!(f, "{}", names.join("|"))
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+impl VarType {
+    /// `self|other`, flattening nested unions and dropping duplicate members.
+    pub fn union(self, other: VarType) -> VarType {
+        let mut members: Vec<VarType> = Vec::new();
+        for ty in [self, other] {
+            let parts = match ty {
+                VarType::Union(parts) => parts,
+                single => vec![single],
+            };
+            for part in parts {
+                if !members.contains(&part) {
+                    members.push(part);
+                }
+            }
+        }
+        if members.len() == 1 {
+            members.remove(0)
+        } else {
+            VarType::Union(members)
+        }
+    }
+
+    /// The members of `self` outside of `removed`: `str|num|bool` without `str` is
+    /// `num|bool`. `None` when nothing is left or `self` is not a union.
+    pub fn without(&self, removed: &VarType) -> Option<VarType> {
+        let VarType::Union(members) = self else {
+            return None;
+        };
+        members
+            .iter()
+            .filter(|m| !m.is_within(removed))
+            .cloned()
+            .reduce(VarType::union)
+    }
+
+    /// Whether every value of type `self` is also a value of type `other`, as far
+    /// as unions go: `str` is within `str|num`, and `str|num` within `num|str|bool`.
+    pub fn is_within(&self, other: &VarType) -> bool {
+        match (self, other) {
+            (VarType::Union(members), _) => members.iter().all(|m| m.is_within(other)),
+            (_, VarType::Union(members)) => members.contains(self),
+            _ => self == other,
+        }
+    }
+}
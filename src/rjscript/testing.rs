@@ -0,0 +1,192 @@
+//! Helpers to evaluate scripts in isolation, without the process-wide globals.
+//!
+//! Every `TestWorld` owns its own cache and (optionally) its own db, so evaluations
+//! in different worlds never see each other's state, even when run in parallel.
+//! It is a `ScriptWorld` like the `EvalCtx` builtins get, so a test reads back what
+//! a script did through the same accessors the builtin used.
+//!
+//! ```ignore
+//! let world = TestWorld::new();
+//! let (status, _) = eval_script_str_with_world("cacheSet(\"k\", 1); return 201, true;", json!(null), &world)?;
+//! assert_eq!(status, 201);
+//! assert!(world.cache_entries().contains_key("k"));
+//! assert_eq!(eval_expr_str("cacheGet(\"k\")", &world)?, RJSValue::Bool(true));
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use serde_json::Value;
+
+use crate::{
+    http::request::Request,
+    rjscript::{
+        ast::{block::Block, position::Position},
+        evaluator::{
+            engine::driver::eval_script_with_globals,
+            errors::EvalError,
+            runtime::{
+                clock::WallClock, files::FileSandbox, runtime_globals::RuntimeGlobals,
+                value::RJSValue, world::ScriptWorld,
+            },
+        },
+        parser::parser::parse_script,
+        preprocess::preprocess,
+    },
+    rjsdb::{db::JsonTableDb, mem::MemTableDb, DbValue, TableDb},
+};
+
+static NEXT_DB_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Isolated evaluation state: its own cache and optional db.
+pub struct TestWorld {
+    globals: Arc<RuntimeGlobals>,
+    db_dir: Option<PathBuf>,
+}
+
+impl TestWorld {
+    /// A world with an empty cache and no db.
+    pub fn new() -> Self {
+        TestWorld {
+            globals: RuntimeGlobals::isolated(None),
+            db_dir: None,
+        }
+    }
+
+    /// A world backed by the given db.
+    pub fn with_db(db: Arc<dyn TableDb>) -> Self {
+        TestWorld {
+            globals: RuntimeGlobals::isolated(Some(db)),
+            db_dir: None,
+        }
+    }
+
+    /// A world with no db whose `now()` reads `clock`, e.g. a frozen instant.
+    pub fn with_clock(clock: WallClock) -> Self {
+        TestWorld {
+            globals: RuntimeGlobals::isolated_with_clock(None, clock),
+            db_dir: None,
+        }
+    }
+
+    /// A world with no db whose `env()` may only read the given variables.
+    pub fn with_env_allowlist(names: &[&str]) -> Self {
+        let names = names.iter().map(|n| n.to_string()).collect();
+        TestWorld {
+            globals: RuntimeGlobals::isolated_with_env_allowlist(None, names),
+            db_dir: None,
+        }
+    }
+
+    /// A world with no db whose `readFile()`/`readJson()` read from `dir`.
+    pub fn with_data_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(TestWorld {
+            globals: RuntimeGlobals::isolated_with_files(None, FileSandbox::new(dir)?),
+            db_dir: None,
+        })
+    }
+
+    /// A world with a fresh on-disk db in a unique temp directory, removed on drop.
+    pub fn with_temp_db() -> io::Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "rjs-testworld-{}-{}",
+            std::process::id(),
+            NEXT_DB_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let db: Arc<dyn TableDb> = Arc::new(JsonTableDb::open(&dir)?);
+        let mut world = Self::with_db(db);
+        world.db_dir = Some(dir);
+        Ok(world)
+    }
+
+    /// A world with a fresh in-memory db, which writes nothing to disk.
+    pub fn with_mem_db() -> Self {
+        Self::with_db(Arc::new(MemTableDb::new()))
+    }
+
+    pub fn db_dir(&self) -> Option<&Path> {
+        self.db_dir.as_deref()
+    }
+
+    /// Copy of the cache contents.
+    pub fn cache_entries(&self) -> HashMap<String, RJSValue> {
+        self.globals.cache.snapshot()
+    }
+
+    /// All db tables with their entries, keyed by table then id.
+    pub fn db_dump(&self) -> io::Result<BTreeMap<String, BTreeMap<String, DbValue>>> {
+        let mut out = BTreeMap::new();
+        if let Some(db) = self.db() {
+            for table in db.get_all_tables()? {
+                let entries = db.get_all(&table)?.into_iter().collect();
+                out.insert(table, entries);
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl ScriptWorld for TestWorld {
+    fn globals(&self) -> &Arc<RuntimeGlobals> {
+        &self.globals
+    }
+}
+
+impl Default for TestWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestWorld {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.db_dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Parses, preprocesses and evaluates `src` in `world` with `body` as the request body.
+/// Parse, lint and evaluation errors are all reported as strings, except `error(status, body)`,
+/// which gives its status and body like the server does.
+pub fn eval_script_str_with_world(
+    src: &str,
+    body: Value,
+    world: &impl ScriptWorld,
+) -> Result<(u16, RJSValue), String> {
+    let req = Request::new(body, HashMap::new(), HashMap::new(), HashMap::new());
+    eval_request_str_with_world(src, &req, world)
+}
+
+/// Like `eval_script_str_with_world`, for a whole request (headers, params, query).
+pub fn eval_request_str_with_world(
+    src: &str,
+    req: &Request,
+    world: &impl ScriptWorld,
+) -> Result<(u16, RJSValue), String> {
+    let block = parse_script(src).map_err(|e| e.to_string())?;
+    let prep = preprocess(block.stmts);
+    if !prep.errors.is_empty() {
+        let msgs: Vec<String> = prep.errors.iter().map(|e| e.to_string()).collect();
+        return Err(msgs.join("\n"));
+    }
+    let block = Block::new(prep.stmts, Position::UNKNOWN);
+    match eval_script_with_globals(&block, req, Arc::clone(world.globals())) {
+        // sent as a response by the server, like a returned status
+        Err(EvalError::Http { status, body, .. }) => Ok((status, body)),
+        other => other.map_err(|e| e.to_string()),
+    }
+}
+
+/// Evaluates a single expression in `world`.
+pub fn eval_expr_str(src: &str, world: &impl ScriptWorld) -> Result<RJSValue, String> {
+    let script = format!("return {};", src);
+    eval_script_str_with_world(&script, Value::Null, world).map(|(_, v)| v)
+}
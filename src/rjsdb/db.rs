@@ -1,19 +1,56 @@
 use std::{
     collections::HashMap,
     fs::{self, File, OpenOptions},
-    io::{self, BufRead, BufReader, Write},
+    io::{self, BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU64, Ordering},
-        Mutex,
+        Arc, RwLock, Weak,
     },
-    time::{SystemTime, UNIX_EPOCH},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 
-use crate::{rjsdb::{DbValue, FieldFilter, TableDb}};
+use tracing::warn;
+
+use crate::rjsdb::{filter::FilterMatcher, merge, DbValue, FieldFilter, TableDb};
+
+/// When WAL lines reach the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Every write is in the file before the call returns
+    Immediate,
+    /// Writes are buffered and written once `max_ops` are pending, every `interval`,
+    /// on `flush()` and when the DB is dropped. A crash loses at most that window.
+    Buffered { max_ops: usize, interval: Duration },
+}
+
+impl SyncMode {
+    pub const DEFAULT_BUFFERED: SyncMode = SyncMode::Buffered {
+        max_ops: 1000,
+        interval: Duration::from_secs(1),
+    };
+
+    /// Mode picked by `RJS_DB_SYNC`: `immediate` (the default) or `buffered`.
+    pub fn from_env() -> io::Result<Self> {
+        Self::parse(std::env::var("RJS_DB_SYNC").ok().as_deref())
+    }
+
+    /// Mode named by a `RJS_DB_SYNC` value, `None` when it is unset.
+    fn parse(value: Option<&str>) -> io::Result<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("" | "immediate") => Ok(SyncMode::Immediate),
+            Some("buffered") => Ok(SyncMode::DEFAULT_BUFFERED),
+            Some(other) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("RJS_DB_SYNC must be 'immediate' or 'buffered', got '{}'", other),
+            )),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "op", rename_all = "snake_case")]
@@ -50,28 +87,97 @@ struct Snapshot {
     tables: HashMap<String, HashMap<String, Entry>>,
 }
 
-#[derive(Default)]
+const WAL_FILE: &str = "wal.jsonl";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// The WAL is compacted once it is larger than this and than the last snapshot,
+/// so a big data set is not rewritten for every few changes.
+const COMPACT_MIN_WAL_BYTES: u64 = 16 * 1024 * 1024;
+
 struct Inner {
+    dir: PathBuf,
     snap: Snapshot,
-    wal: Option<File>,
+    wal: Option<BufWriter<File>>,
+    sync: SyncMode,
+    /// WAL lines written to the buffer but not to the file yet
+    pending: usize,
+    wal_bytes: u64,
+    snapshot_bytes: u64,
+}
+
+impl Inner {
+    fn flush_wal(&mut self) -> io::Result<()> {
+        if let Some(wal) = &mut self.wal {
+            wal.flush()?;
+        }
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Writes the tables to `snapshot.json` and empties the WAL. The snapshot replaces
+    /// the old one atomically before the WAL is truncated; if that step is lost, the
+    /// old WAL is replayed over the new snapshot on open, which yields the same tables
+    /// since every WAL op sets state rather than changing it.
+    fn compact(&mut self) -> io::Result<()> {
+        self.flush_wal()?;
+        let data = json::to_vec(&self.snap)?;
+        let tmp = self.dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+        {
+            let mut f = File::create(&tmp)?;
+            f.write_all(&data)?;
+            f.sync_all()?;
+        }
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE))?;
+
+        let wal_path = self.dir.join(WAL_FILE);
+        self.wal = None;
+        File::create(&wal_path)?;
+        self.wal = Some(open_wal(&wal_path)?);
+        self.wal_bytes = 0;
+        self.snapshot_bytes = data.len() as u64;
+        Ok(())
+    }
 }
 
+/// Reads share `inner`; a write holds it exclusively until its WAL line is written,
+/// so no reader sees a change the WAL does not have yet.
 pub struct JsonTableDb {
     dir: PathBuf,
-    inner: Mutex<Inner>,
+    inner: Arc<RwLock<Inner>>,
     id_counter: AtomicU64,
 }
 
 impl JsonTableDb {
     pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        Self::open_with_sync(dir, SyncMode::Immediate)
+    }
+
+    pub fn open_with_sync<P: AsRef<Path>>(dir: P, sync: SyncMode) -> io::Result<Self> {
         let dir = dir.as_ref().to_path_buf();
         fs::create_dir_all(&dir)?;
 
-        let snap: Snapshot = Snapshot::default();
-        let mut inner = Inner { snap, wal: None };
+        let mut inner = Inner {
+            dir: dir.clone(),
+            snap: Snapshot::default(),
+            wal: None,
+            sync,
+            pending: 0,
+            wal_bytes: 0,
+            snapshot_bytes: 0,
+        };
 
-        let wal_path = dir.join("wal.jsonl");
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        if snapshot_path.exists() {
+            let data = fs::read(&snapshot_path)?;
+            inner.snap = json::from_slice(&data).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", snapshot_path.display(), e))
+            })?;
+            inner.snapshot_bytes = data.len() as u64;
+        }
+
+        let wal_path = dir.join(WAL_FILE);
         if wal_path.exists() {
+            inner.wal_bytes = fs::metadata(&wal_path)?.len();
             let f = File::open(&wal_path)?;
             for line in BufReader::new(f).lines() {
                 if let Ok(line) = line {
@@ -85,16 +191,15 @@ impl JsonTableDb {
             }
         }
 
-        inner.wal = Some(
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&wal_path)?,
-        );
+        inner.wal = Some(open_wal(&wal_path)?);
 
+        let inner = Arc::new(RwLock::new(inner));
+        if let SyncMode::Buffered { interval, .. } = sync {
+            spawn_flusher(Arc::downgrade(&inner), interval);
+        }
         Ok(Self {
             dir,
-            inner: Mutex::new(inner),
+            inner,
             id_counter: AtomicU64::new(seed_counter()),
         })
     }
@@ -104,66 +209,31 @@ impl JsonTableDb {
             let line = serde_json::to_string(op)?;
             wal.write_all(line.as_bytes())?;
             wal.write_all(b"\n")?;
-            wal.flush()?;
+            inner.pending += 1;
+            inner.wal_bytes += line.len() as u64 + 1;
+            let due = match inner.sync {
+                SyncMode::Immediate => true,
+                SyncMode::Buffered { max_ops, .. } => inner.pending >= max_ops,
+            };
+            if due {
+                inner.flush_wal()?;
+            }
+            if inner.wal_bytes > COMPACT_MIN_WAL_BYTES.max(inner.snapshot_bytes) {
+                // the change itself is logged, so a failed compaction is retried on the next one
+                if let Err(e) = inner.compact() {
+                    warn!("Failed to compact the DB write-ahead log: {}", e);
+                }
+            }
         }
         Ok(())
     }
 
-    fn new_id(&self) -> String {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as u128;
-        let ctr = self.id_counter.fetch_add(1, Ordering::Relaxed) as u128;
-        format!("{}-{}", base36_u128(nanos), base36_u128(ctr))
-    }
-
     fn ensure_table<'a>(
         tables: &'a mut HashMap<String, HashMap<String, Entry>>,
         t: &str,
     ) -> &'a mut HashMap<String, Entry> {
         tables.entry(t.to_string()).or_default()
     }
-
-    fn to_json(v: &DbValue) -> serde_json::Value {
-        match v {
-            DbValue::Number(n) => json::Value::from(*n),
-            DbValue::Bool(b) => json::Value::from(*b),
-            DbValue::String(s) => json::Value::from(s.clone()),
-            DbValue::Null => json::Value::Null,
-            DbValue::Json(j) => j.clone(),
-        }
-    }
-
-    fn match_filter(val: &DbValue, filter: &FieldFilter) -> bool {
-        if filter.is_empty() {
-            return true;
-        }
-        match val {
-            DbValue::Json(json::Value::Object(obj)) => {
-                for (k, fv) in filter {
-                    if let Some(v) = obj.get(k) {
-                        if v != fv {
-                            return false;
-                        }
-                    } else {
-                        return false;
-                    }
-                }
-                true
-            }
-            _ => {
-                if filter.len() != 1 {
-                    return false;
-                }
-                if let Some(fv) = filter.get("$value") {
-                    &Self::to_json(val) == fv
-                } else {
-                    false
-                }
-            }
-        }
-    }
 }
 
 fn apply_wal(snap: &mut Snapshot, op: WalOp) {
@@ -191,14 +261,25 @@ fn apply_wal(snap: &mut Snapshot, op: WalOp) {
     }
 }
 
-fn seed_counter() -> u64 {
+/// A new entry id: the creation time in nanoseconds and a per-DB counter, in base36.
+pub(crate) fn new_id(counter: &AtomicU64) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let ctr = counter.fetch_add(1, Ordering::Relaxed) as u128;
+    format!("{}-{}", base36_u128(nanos), base36_u128(ctr))
+}
+
+pub(crate) fn seed_counter() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
 
-fn base36_u128(mut x: u128) -> String {
+/// Lowercase base36 digits of `x`, without leading zeros.
+pub fn base36_u128(mut x: u128) -> String {
     const ALPH: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
     if x == 0 {
         return "0".into();
@@ -214,7 +295,7 @@ fn base36_u128(mut x: u128) -> String {
 
 impl TableDb for JsonTableDb {
     fn create_table(&self, table: &str) -> io::Result<()> {
-        let mut g = self.inner.lock().unwrap();
+        let mut g = self.inner.write().unwrap();
         g.snap.tables.entry(table.to_string()).or_default();
         JsonTableDb::append(
             &mut g,
@@ -225,12 +306,12 @@ impl TableDb for JsonTableDb {
     }
 
     fn get_all_tables(&self) -> io::Result<Vec<String>> {
-        let g = self.inner.lock().unwrap();
+        let g = self.inner.read().unwrap();
         Ok(g.snap.tables.keys().cloned().collect())
     }
 
     fn drop_table(&self, table: &str) -> io::Result<()> {
-        let mut g = self.inner.lock().unwrap();
+        let mut g = self.inner.write().unwrap();
         g.snap.tables.remove(table);
         JsonTableDb::append(
             &mut g,
@@ -241,8 +322,8 @@ impl TableDb for JsonTableDb {
     }
 
     fn create_entry(&self, table: &str, value: DbValue) -> io::Result<String> {
-        let mut g = self.inner.lock().unwrap();
-        let id = self.new_id();
+        let mut g = self.inner.write().unwrap();
+        let id = new_id(&self.id_counter);
         let t = JsonTableDb::ensure_table(&mut g.snap.tables, table);
 
         t.insert(
@@ -263,7 +344,7 @@ impl TableDb for JsonTableDb {
     }
 
     fn get_all(&self, table: &str) -> io::Result<Vec<(String, DbValue)>> {
-        let g = self.inner.lock().unwrap();
+        let g = self.inner.read().unwrap();
         let mut out = Vec::new();
         if let Some(t) = g.snap.tables.get(table) {
             for (id, e) in t {
@@ -274,7 +355,7 @@ impl TableDb for JsonTableDb {
     }
 
     fn get_by_id(&self, table: &str, id: &str) -> io::Result<Option<(String, DbValue)>> {
-        let g = self.inner.lock().unwrap();
+        let g = self.inner.read().unwrap();
         Ok(g.snap
             .tables
             .get(table)
@@ -287,11 +368,12 @@ impl TableDb for JsonTableDb {
         table: &str,
         filter: &FieldFilter,
     ) -> io::Result<Vec<(String, DbValue)>> {
-        let g = self.inner.lock().unwrap();
+        let matcher = FilterMatcher::new(filter)?;
+        let g = self.inner.read().unwrap();
         let mut out = Vec::new();
         if let Some(t) = g.snap.tables.get(table) {
             for (id, e) in t {
-                if JsonTableDb::match_filter(&e.value, filter) {
+                if matcher.matches(&e.value) {
                     out.push((id.clone(), e.value.clone()));
                 }
             }
@@ -300,7 +382,7 @@ impl TableDb for JsonTableDb {
     }
 
     fn update_by_id(&self, table: &str, id: &str, patch: DbValue) -> io::Result<bool> {
-        let mut g = self.inner.lock().unwrap();
+        let mut g = self.inner.write().unwrap();
         if let Some(t) = g.snap.tables.get_mut(table) {
             if let Some(ent) = t.get_mut(id) {
                 ent.value = merge(ent.value.clone(), patch.clone());
@@ -319,20 +401,40 @@ impl TableDb for JsonTableDb {
         Ok(false)
     }
 
+    fn replace_by_id(&self, table: &str, id: &str, value: DbValue) -> io::Result<bool> {
+        let mut g = self.inner.write().unwrap();
+        if let Some(t) = g.snap.tables.get_mut(table) {
+            if let Some(ent) = t.get_mut(id) {
+                ent.value = value.clone();
+                JsonTableDb::append(
+                    &mut g,
+                    &WalOp::UpdateEntry {
+                        table: table.to_string(),
+                        id: id.to_string(),
+                        value,
+                    },
+                )?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     fn update_by_fields(
         &self,
         table: &str,
         filter: &FieldFilter,
         patch: DbValue,
     ) -> io::Result<usize> {
-        let mut g = self.inner.lock().unwrap();
+        let matcher = FilterMatcher::new(filter)?;
+        let mut g = self.inner.write().unwrap();
         let mut updated = 0usize;
         let mut changes: Vec<(String, DbValue)> = Vec::new();
 
         if let Some(t) = g.snap.tables.get_mut(table) {
             let ids: Vec<String> = t
                 .iter()
-                .filter(|(_, e)| JsonTableDb::match_filter(&e.value, filter))
+                .filter(|(_, e)| matcher.matches(&e.value))
                 .map(|(id, _)| id.clone())
                 .collect();
 
@@ -360,7 +462,7 @@ impl TableDb for JsonTableDb {
     }
 
     fn delete_by_id(&self, table: &str, id: &str) -> io::Result<bool> {
-        let mut g = self.inner.lock().unwrap();
+        let mut g = self.inner.write().unwrap();
         if let Some(t) = g.snap.tables.get_mut(table) {
             if t.remove(id).is_some() {
                 JsonTableDb::append(
@@ -377,11 +479,12 @@ impl TableDb for JsonTableDb {
     }
 
     fn delete_by_fields(&self, table: &str, filter: &FieldFilter) -> io::Result<usize> {
-        let mut g = self.inner.lock().unwrap();
+        let matcher = FilterMatcher::new(filter)?;
+        let mut g = self.inner.write().unwrap();
 
         let ids: Vec<String> = if let Some(t) = g.snap.tables.get(table) {
             t.iter()
-                .filter(|(_, e)| JsonTableDb::match_filter(&e.value, filter))
+                .filter(|(_, e)| matcher.matches(&e.value))
                 .map(|(id, _)| id.clone())
                 .collect()
         } else {
@@ -413,30 +516,394 @@ impl TableDb for JsonTableDb {
     }
 
     fn drop_db(&self) -> io::Result<()> {
-        let mut g = self.inner.lock().unwrap();
+        let mut g = self.inner.write().unwrap();
         g.snap.tables.clear();
-        let _ = fs::remove_file(self.dir.join("wal.jsonl"));
-        // fresh WAL
-        let wal_path = self.dir.join("wal.jsonl");
-        g.wal = Some(
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&wal_path)?,
-        );
+        let _ = fs::remove_file(self.dir.join(WAL_FILE));
+        let _ = fs::remove_file(self.dir.join(SNAPSHOT_FILE));
+        // fresh WAL; whatever was still buffered belonged to the dropped data
+        let wal_path = self.dir.join(WAL_FILE);
+        g.wal = Some(open_wal(&wal_path)?);
+        g.pending = 0;
+        g.wal_bytes = 0;
+        g.snapshot_bytes = 0;
         Ok(())
     }
+
+    fn compact(&self) -> io::Result<()> {
+        self.inner.write().unwrap().compact()
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.write().unwrap().flush_wal()
+    }
+}
+
+impl Drop for JsonTableDb {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("Failed to flush the DB write-ahead log: {}", e);
+        }
+    }
+}
+
+fn open_wal(path: &Path) -> io::Result<BufWriter<File>> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Writes buffered WAL lines every `interval` until the DB is dropped, so a quiet
+/// server does not hold on to its last writes.
+fn spawn_flusher(inner: Weak<RwLock<Inner>>, interval: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let mut g = inner.write().unwrap();
+        if g.pending > 0 {
+            if let Err(e) = g.flush_wal() {
+                warn!("Failed to flush the DB write-ahead log: {}", e);
+            }
+        }
+    });
 }
 
-fn merge(orig: DbValue, patch: DbValue) -> DbValue {
-    use serde_json::Value::Object;
-    match (orig, patch) {
-        (DbValue::Json(Object(mut base)), DbValue::Json(Object(p))) => {
-            for (k, v) in p {
-                base.insert(k, v);
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+
+    /// A unique directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rjs-db-test-{}-{}",
+                std::process::id(),
+                NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+
+        fn open(&self) -> JsonTableDb {
+            JsonTableDb::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn obj(v: Value) -> DbValue {
+        DbValue::Json(v)
+    }
+
+    /// Every row of `table` as JSON, keyed by id.
+    fn rows(db: &JsonTableDb, table: &str) -> std::collections::BTreeMap<String, Value> {
+        db.get_all(table)
+            .unwrap()
+            .into_iter()
+            .map(|(id, v)| (id, v.to_json()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_share_the_lock() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let id = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+
+        // a second reader gets in while the first still holds the lock
+        let _reader = db.inner.read().unwrap();
+        assert_eq!(db.get_by_id("t", &id).unwrap().unwrap().1.to_json(), json!({ "n": 1 }));
+        assert_eq!(db.get_all_tables().unwrap(), ["t"]);
+    }
+
+    #[test]
+    fn concurrent_reads_and_writes() {
+        const WRITERS: usize = 4;
+        const PER_WRITER: usize = 50;
+        let dir = TempDir::new();
+        let db = Arc::new(dir.open());
+
+        thread::scope(|s| {
+            for w in 0..WRITERS {
+                let db = &db;
+                s.spawn(move || {
+                    for i in 0..PER_WRITER {
+                        db.create_entry("t", obj(json!({ "w": w, "i": i }))).unwrap();
+                    }
+                });
+            }
+            for _ in 0..WRITERS {
+                let db = &db;
+                s.spawn(move || {
+                    let mut seen = 0;
+                    while seen < WRITERS * PER_WRITER {
+                        let now = db.get_all("t").unwrap().len();
+                        // rows only ever get added
+                        assert!(now >= seen);
+                        seen = now;
+                        thread::yield_now();
+                    }
+                });
+            }
+        });
+
+        let written = rows(&db, "t");
+        assert_eq!(written.len(), WRITERS * PER_WRITER);
+        drop(db);
+        // every row a reader saw made it into the WAL
+        assert_eq!(rows(&dir.open(), "t"), written);
+    }
+
+    fn buffered(max_ops: usize) -> SyncMode {
+        // long enough that the background flush never runs during a test
+        SyncMode::Buffered { max_ops, interval: Duration::from_secs(3600) }
+    }
+
+    fn wal_lines(dir: &TempDir) -> usize {
+        fs::read_to_string(dir.0.join(WAL_FILE)).unwrap().lines().count()
+    }
+
+    #[test]
+    fn sync_mode_values() {
+        for v in [None, Some(""), Some("immediate"), Some(" Immediate\n")] {
+            assert_eq!(SyncMode::parse(v).unwrap(), SyncMode::Immediate, "{:?}", v);
+        }
+        assert_eq!(SyncMode::parse(Some("BUFFERED")).unwrap(), SyncMode::DEFAULT_BUFFERED);
+
+        let err = SyncMode::parse(Some("fsync")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "RJS_DB_SYNC must be 'immediate' or 'buffered', got 'fsync'");
+    }
+
+    #[test]
+    fn immediate_writes_reach_the_file() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        db.create_entry("t", obj(json!(1))).unwrap();
+        assert_eq!(wal_lines(&dir), 1);
+    }
+
+    #[test]
+    fn buffered_writes_wait_for_flush() {
+        let dir = TempDir::new();
+        let db = JsonTableDb::open_with_sync(&dir.0, buffered(100)).unwrap();
+        let id = db.create_entry("t", obj(json!(1))).unwrap();
+        db.update_by_id("t", &id, obj(json!(2))).unwrap();
+
+        // readers see the change before the file does
+        assert_eq!(db.get_by_id("t", &id).unwrap().unwrap().1.to_json(), json!(2));
+        assert_eq!(wal_lines(&dir), 0);
+        db.flush().unwrap();
+        assert_eq!(wal_lines(&dir), 2);
+        assert_eq!(db.inner.read().unwrap().pending, 0);
+    }
+
+    #[test]
+    fn buffered_writes_flush_at_max_ops() {
+        let dir = TempDir::new();
+        let db = JsonTableDb::open_with_sync(&dir.0, buffered(3)).unwrap();
+        for n in 0..2 {
+            db.create_entry("t", obj(json!(n))).unwrap();
+        }
+        assert_eq!(wal_lines(&dir), 0);
+        db.create_entry("t", obj(json!(2))).unwrap();
+        assert_eq!(wal_lines(&dir), 3);
+        db.create_entry("t", obj(json!(3))).unwrap();
+        assert_eq!(wal_lines(&dir), 3);
+    }
+
+    #[test]
+    fn buffered_writes_survive_drop() {
+        let dir = TempDir::new();
+        let db = JsonTableDb::open_with_sync(&dir.0, buffered(100)).unwrap();
+        let id = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+        let written = rows(&db, "t");
+        drop(db);
+
+        let db = dir.open();
+        assert_eq!(rows(&db, "t"), written);
+        assert!(db.get_by_id("t", &id).unwrap().is_some());
+    }
+
+    #[test]
+    fn drop_db_discards_buffered_writes() {
+        let dir = TempDir::new();
+        let db = JsonTableDb::open_with_sync(&dir.0, buffered(100)).unwrap();
+        db.create_entry("t", obj(json!(1))).unwrap();
+        db.drop_db().unwrap();
+        db.flush().unwrap();
+        drop(db);
+        assert!(dir.open().get_all_tables().unwrap().is_empty());
+    }
+
+    fn wal_len(dir: &TempDir) -> u64 {
+        fs::metadata(dir.0.join(WAL_FILE)).unwrap().len()
+    }
+
+    /// Inserts `rows` rows with `sync`, returning how many inserts grew the WAL file,
+    /// then checks they all come back after a reopen.
+    fn seed(sync: SyncMode, rows: usize) -> usize {
+        let dir = TempDir::new();
+        let db = JsonTableDb::open_with_sync(&dir.0, sync).unwrap();
+        let mut writes = 0;
+        let mut len = wal_len(&dir);
+        for i in 0..rows {
+            db.create_entry("t", obj(json!({ "i": i }))).unwrap();
+            let now = wal_len(&dir);
+            if now != len {
+                writes += 1;
+                len = now;
             }
-            DbValue::Json(json::Value::Object(base))
         }
-        (_, p) => p,
+        drop(db);
+
+        let db = dir.open();
+        let mut seen: Vec<u64> = db
+            .get_all("t")
+            .unwrap()
+            .into_iter()
+            .map(|(_, v)| v.to_json()["i"].as_u64().unwrap())
+            .collect();
+        seen.sort_unstable();
+        assert!(seen.iter().copied().eq(0..rows as u64), "{:?}", sync);
+        writes
+    }
+
+    /// Seeding 10k rows: immediate mode writes the WAL file once per row; buffered mode
+    /// leaves nearly all of those writes out (the file then grows only when the buffer
+    /// fills or `max_ops` is reached), and still recovers every row after reopening.
+    #[test]
+    fn buffered_seeding_writes_the_file_far_less() {
+        const ROWS: usize = 10_000;
+        assert_eq!(seed(SyncMode::Immediate, ROWS), ROWS);
+        let buffered = seed(SyncMode::DEFAULT_BUFFERED, ROWS);
+        assert!(buffered * 50 < ROWS, "{} writes", buffered);
+    }
+
+    #[test]
+    fn compact_moves_the_wal_into_the_snapshot() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let id = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+        db.update_by_id("t", &id, obj(json!({ "m": 2 }))).unwrap();
+        db.create_table("empty").unwrap();
+        let written = rows(&db, "t");
+
+        db.compact().unwrap();
+        assert_eq!(wal_len(&dir), 0);
+        assert!(dir.0.join(SNAPSHOT_FILE).exists());
+        assert!(!dir.0.join(format!("{}.tmp", SNAPSHOT_FILE)).exists());
+        drop(db);
+
+        let db = dir.open();
+        assert_eq!(rows(&db, "t"), written);
+        let mut tables = db.get_all_tables().unwrap();
+        tables.sort();
+        assert_eq!(tables, ["empty", "t"]);
+    }
+
+    #[test]
+    fn wal_after_the_snapshot_is_replayed_over_it() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let keep = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+        let gone = db.create_entry("t", obj(json!({ "n": 2 }))).unwrap();
+        db.create_entry("dropped", obj(json!(0))).unwrap();
+        db.compact().unwrap();
+
+        db.update_by_id("t", &keep, obj(json!({ "n": 10 }))).unwrap();
+        db.delete_by_id("t", &gone).unwrap();
+        let added = db.create_entry("t", obj(json!({ "n": 3 }))).unwrap();
+        db.drop_table("dropped").unwrap();
+        drop(db);
+
+        let db = dir.open();
+        let t = rows(&db, "t");
+        assert_eq!(t.len(), 2);
+        assert_eq!(t[&keep], json!({ "n": 10 }));
+        assert_eq!(t[&added], json!({ "n": 3 }));
+        assert_eq!(db.get_all_tables().unwrap(), ["t"]);
+    }
+
+    #[test]
+    fn stale_wal_over_a_new_snapshot_changes_nothing() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let id = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+        db.update_by_id("t", &id, obj(json!({ "n": 2 }))).unwrap();
+        let other = db.create_entry("t", obj(json!({ "n": 3 }))).unwrap();
+        db.delete_by_id("t", &other).unwrap();
+        let wal = fs::read(dir.0.join(WAL_FILE)).unwrap();
+        let written = rows(&db, "t");
+        db.compact().unwrap();
+        drop(db);
+
+        // as if the process died after the snapshot was renamed but before the truncate
+        fs::write(dir.0.join(WAL_FILE), wal).unwrap();
+        assert_eq!(rows(&dir.open(), "t"), written);
+    }
+
+    #[test]
+    fn compact_of_an_empty_db() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        db.compact().unwrap();
+        db.compact().unwrap();
+        drop(db);
+        assert!(dir.open().get_all_tables().unwrap().is_empty());
+    }
+
+    #[test]
+    fn torn_wal_line_is_skipped() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let id = db.create_entry("t", obj(json!({ "n": 1 }))).unwrap();
+        drop(db);
+        let mut f = OpenOptions::new().append(true).open(dir.0.join(WAL_FILE)).unwrap();
+        f.write_all(br#"{"op":"create_entry","table":"t","id":"x","val"#).unwrap();
+        drop(f);
+
+        let db = dir.open();
+        assert_eq!(rows(&db, "t").keys().collect::<Vec<_>>(), [&id]);
+    }
+
+    #[test]
+    fn corrupt_snapshot_fails_to_open() {
+        let dir = TempDir::new();
+        dir.open().compact().unwrap();
+        fs::write(dir.0.join(SNAPSHOT_FILE), b"{\"tables\":").unwrap();
+
+        let err = JsonTableDb::open(&dir.0).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(SNAPSHOT_FILE), "{}", err);
+    }
+
+    #[test]
+    fn large_wal_compacts_itself() {
+        let dir = TempDir::new();
+        let db = dir.open();
+        let small = db.create_entry("t", obj(json!(1))).unwrap();
+        assert!(!dir.0.join(SNAPSHOT_FILE).exists());
+
+        // one line past the threshold
+        let big = "x".repeat(COMPACT_MIN_WAL_BYTES as usize);
+        let id = db.create_entry("t", DbValue::String(big.clone())).unwrap();
+        assert_eq!(wal_len(&dir), 0);
+        assert!(dir.0.join(SNAPSHOT_FILE).exists());
+        drop(db);
+
+        let db = dir.open();
+        assert_eq!(db.get_by_id("t", &id).unwrap().unwrap().1.to_json(), json!(big));
+        assert!(db.get_by_id("t", &small).unwrap().is_some());
     }
 }
@@ -0,0 +1,265 @@
+//! `FieldFilter` matching, shared by the DB backends.
+
+use std::{cmp::Ordering, io};
+
+use regex::Regex;
+use serde_json as json;
+
+use crate::rjsdb::{DbValue, FieldFilter};
+
+/// Operators a filter field may use instead of a plain value.
+const FILTER_OPERATORS: &[&str] = &["$contains", "$regex", "$gt", "$gte", "$lt", "$lte", "$ne", "$in"];
+
+/// How a filter field is compared with the entry field of the same name.
+enum Condition {
+    Equals(json::Value),
+    /// `{ "$ne": value }`: a field with any other value
+    NotEquals(json::Value),
+    /// `{ "$in": [a, b] }`: a field equal to one of the values
+    In(Vec<json::Value>),
+    /// `{ "$gt": value }`, `$gte`, `$lt`, `$lte`: a field ordered against `value`,
+    /// numbers with numbers and strings with strings
+    Compare(CompareOp, json::Value),
+    /// `{ "$contains": "text" }`: a string field containing `text`
+    Contains(String),
+    /// `{ "$regex": "pattern" }`: a string field matching `pattern`
+    Regex(Regex),
+    /// Several operators on one field, e.g. `{ "$gte": 18, "$lt": 65 }`
+    All(Vec<Condition>),
+}
+
+#[derive(Clone, Copy)]
+enum CompareOp {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CompareOp {
+    fn accepts(self, ord: Ordering) -> bool {
+        match self {
+            CompareOp::Gt => ord == Ordering::Greater,
+            CompareOp::Gte => ord != Ordering::Less,
+            CompareOp::Lt => ord == Ordering::Less,
+            CompareOp::Lte => ord != Ordering::Greater,
+        }
+    }
+}
+
+/// Orders two numbers by value, so `17` and `17.0` are equal whichever way they were written.
+fn number_cmp(a: &json::Number, b: &json::Number) -> Option<Ordering> {
+    a.as_f64().zip(b.as_f64()).and_then(|(a, b)| a.partial_cmp(&b))
+}
+
+/// JSON equality with numbers compared by value at every depth.
+fn values_equal(a: &json::Value, b: &json::Value) -> bool {
+    match (a, b) {
+        (json::Value::Number(a), json::Value::Number(b)) => number_cmp(a, b) == Some(Ordering::Equal),
+        (json::Value::Array(a), json::Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (json::Value::Object(a), json::Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, a)| b.get(k).is_some_and(|b| values_equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+fn invalid_filter(key: &str, msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("filter field '{}': {}", key, msg))
+}
+
+impl Condition {
+    /// An object whose keys are all operators is a condition; any other value,
+    /// objects included, is compared for equality.
+    fn parse(key: &str, fv: &json::Value) -> io::Result<Self> {
+        let ops = match fv.as_object() {
+            Some(o) if !o.is_empty() && o.keys().all(|op| FILTER_OPERATORS.contains(&op.as_str())) => o,
+            _ => return Ok(Condition::Equals(fv.clone())),
+        };
+        let mut conditions = ops
+            .iter()
+            .map(|(op, operand)| Condition::parse_operator(key, op, operand))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(if conditions.len() == 1 {
+            conditions.pop().unwrap()
+        } else {
+            Condition::All(conditions)
+        })
+    }
+
+    fn parse_operator(key: &str, op: &str, operand: &json::Value) -> io::Result<Self> {
+        let string = || match operand {
+            json::Value::String(s) => Ok(s.clone()),
+            _ => Err(invalid_filter(key, format!("{} expects a string", op))),
+        };
+        let compare = |cmp: CompareOp| match operand {
+            json::Value::Number(_) | json::Value::String(_) => Ok(Condition::Compare(cmp, operand.clone())),
+            _ => Err(invalid_filter(key, format!("{} expects a number or a string", op))),
+        };
+        match op {
+            "$contains" => Ok(Condition::Contains(string()?)),
+            "$regex" => {
+                let re = Regex::new(&string()?)
+                    .map_err(|e| invalid_filter(key, format!("invalid $regex: {}", e)))?;
+                Ok(Condition::Regex(re))
+            }
+            "$gt" => compare(CompareOp::Gt),
+            "$gte" => compare(CompareOp::Gte),
+            "$lt" => compare(CompareOp::Lt),
+            "$lte" => compare(CompareOp::Lte),
+            "$ne" => Ok(Condition::NotEquals(operand.clone())),
+            "$in" => match operand {
+                json::Value::Array(values) => Ok(Condition::In(values.clone())),
+                _ => Err(invalid_filter(key, "$in expects an array".to_string())),
+            },
+            _ => unreachable!("not in FILTER_OPERATORS: {}", op),
+        }
+    }
+
+    fn matches(&self, v: &json::Value) -> bool {
+        match (self, v) {
+            (Condition::Equals(fv), v) => values_equal(v, fv),
+            (Condition::NotEquals(fv), v) => !values_equal(v, fv),
+            (Condition::In(values), v) => values.iter().any(|fv| values_equal(v, fv)),
+            (Condition::Compare(op, bound), v) => {
+                let ord = match (v, bound) {
+                    (json::Value::Number(a), json::Value::Number(b)) => number_cmp(a, b),
+                    (json::Value::String(a), json::Value::String(b)) => Some(a.cmp(b)),
+                    // numbers and strings are never ordered against each other
+                    _ => None,
+                };
+                ord.is_some_and(|ord| op.accepts(ord))
+            }
+            (Condition::Contains(needle), json::Value::String(s)) => s.contains(needle.as_str()),
+            (Condition::Regex(re), json::Value::String(s)) => re.is_match(s),
+            (Condition::All(conditions), v) => conditions.iter().all(|c| c.matches(v)),
+            // text operators never match non-string fields
+            _ => false,
+        }
+    }
+}
+
+/// A `FieldFilter` with its operators parsed, so a query checks (and compiles
+/// regexes) once rather than per entry.
+pub(crate) struct FilterMatcher {
+    conditions: Vec<(String, Condition)>,
+}
+
+impl FilterMatcher {
+    pub(crate) fn new(filter: &FieldFilter) -> io::Result<Self> {
+        let conditions = filter
+            .iter()
+            .map(|(k, fv)| Ok((k.clone(), Condition::parse(k, fv)?)))
+            .collect::<io::Result<_>>()?;
+        Ok(FilterMatcher { conditions })
+    }
+
+    pub(crate) fn matches(&self, val: &DbValue) -> bool {
+        if self.conditions.is_empty() {
+            return true;
+        }
+        match val {
+            DbValue::Json(json::Value::Object(obj)) => self
+                .conditions
+                .iter()
+                .all(|(k, c)| obj.get(k).is_some_and(|v| c.matches(v))),
+            _ => match self.conditions.as_slice() {
+                [(k, c)] if k == "$value" => c.matches(&val.to_json()),
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn matcher(filter: json::Value) -> io::Result<FilterMatcher> {
+        let filter: FieldFilter = json::from_value(filter).unwrap();
+        FilterMatcher::new(&filter)
+    }
+
+    fn matches(filter: json::Value, entry: json::Value) -> bool {
+        matcher(filter).unwrap().matches(&DbValue::Json(entry))
+    }
+
+    #[test]
+    fn numbers_compare_by_value_in_every_operator() {
+        let entry = json!({ "n": 17, "xs": [1, { "v": 2 }] });
+        assert!(matches(json!({ "n": 17.0 }), entry.clone()));
+        assert!(!matches(json!({ "n": { "$ne": 17.0 } }), entry.clone()));
+        assert!(matches(json!({ "n": { "$in": [3, 17.0] } }), entry.clone()));
+        assert!(matches(json!({ "n": { "$gte": 17.0, "$lte": 17 } }), entry.clone()));
+        assert!(matches(json!({ "xs": [1.0, { "v": 2.0 }] }), entry.clone()));
+        assert!(!matches(json!({ "xs": [1.0, { "v": 2.5 }] }), entry));
+
+        let stored = json!({ "n": 17.0 });
+        assert!(matches(json!({ "n": 17 }), stored.clone()));
+        assert!(matches(json!({ "n": { "$in": [17] } }), stored));
+    }
+
+    #[test]
+    fn equality_does_not_convert_types() {
+        assert!(!matches(json!({ "n": "17" }), json!({ "n": 17 })));
+        assert!(!matches(json!({ "b": 1 }), json!({ "b": true })));
+        assert!(matches(json!({ "n": { "$ne": "17" } }), json!({ "n": 17 })));
+        assert!(!matches(json!({ "o": { "a": 1 } }), json!({ "o": { "a": 1, "b": 2 } })));
+    }
+
+    #[test]
+    fn compare_orders_strings_and_never_mixes_types() {
+        assert!(matches(json!({ "s": { "$gt": "apple" } }), json!({ "s": "banana" })));
+        assert!(!matches(json!({ "s": { "$gt": "banana" } }), json!({ "s": "banana" })));
+        assert!(!matches(json!({ "n": { "$lt": "9" } }), json!({ "n": 1 })));
+        assert!(!matches(json!({ "s": { "$gte": 0 } }), json!({ "s": "1" })));
+    }
+
+    #[test]
+    fn text_operators_only_match_strings() {
+        assert!(matches(json!({ "s": { "$contains": "ell" } }), json!({ "s": "hello" })));
+        assert!(matches(json!({ "s": { "$regex": "^h.*o$" } }), json!({ "s": "hello" })));
+        assert!(!matches(json!({ "s": { "$regex": "^H" } }), json!({ "s": "hello" })));
+        assert!(!matches(json!({ "s": { "$contains": "1" } }), json!({ "s": 1 })));
+    }
+
+    #[test]
+    fn missing_fields_never_match() {
+        assert!(!matches(json!({ "n": { "$ne": 1 } }), json!({ "m": 1 })));
+        assert!(!matches(json!({ "n": null }), json!({})));
+        assert!(matches(json!({}), json!({})));
+    }
+
+    #[test]
+    fn objects_with_other_keys_are_compared_for_equality() {
+        let filter = json!({ "o": { "$gt": 1, "plain": 2 } });
+        assert!(matches(filter.clone(), json!({ "o": { "$gt": 1, "plain": 2 } })));
+        assert!(!matches(filter, json!({ "o": 5 })));
+    }
+
+    #[test]
+    fn scalar_entries_match_on_value() {
+        let m = matcher(json!({ "$value": { "$gt": 1 } })).unwrap();
+        assert!(m.matches(&DbValue::Number(2.0)));
+        assert!(!m.matches(&DbValue::Number(1.0)));
+        assert!(!matcher(json!({ "n": 1 })).unwrap().matches(&DbValue::Number(1.0)));
+    }
+
+    #[test]
+    fn bad_operands_are_errors() {
+        for filter in [
+            json!({ "n": { "$in": 1 } }),
+            json!({ "n": { "$gt": true } }),
+            json!({ "n": { "$contains": 1 } }),
+            json!({ "n": { "$regex": "(" } }),
+        ] {
+            let err = matcher(filter.clone()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", filter);
+            assert!(err.to_string().starts_with("filter field 'n'"), "{}", err);
+        }
+    }
+}
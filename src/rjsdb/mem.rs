@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{atomic::AtomicU64, RwLock},
+};
+
+use crate::rjsdb::{
+    db::{new_id, seed_counter},
+    filter::FilterMatcher,
+    merge, DbValue, FieldFilter, TableDb,
+};
+
+/// A `TableDb` that lives only in memory: nothing is read or written on disk, and
+/// the data is gone when the server stops. For ephemeral test runs.
+pub struct MemTableDb {
+    tables: RwLock<HashMap<String, HashMap<String, DbValue>>>,
+    id_counter: AtomicU64,
+}
+
+impl MemTableDb {
+    pub fn new() -> Self {
+        Self {
+            tables: RwLock::new(HashMap::new()),
+            id_counter: AtomicU64::new(seed_counter()),
+        }
+    }
+}
+
+impl Default for MemTableDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TableDb for MemTableDb {
+    fn create_table(&self, table: &str) -> io::Result<()> {
+        self.tables.write().unwrap().entry(table.to_string()).or_default();
+        Ok(())
+    }
+
+    fn get_all_tables(&self) -> io::Result<Vec<String>> {
+        Ok(self.tables.read().unwrap().keys().cloned().collect())
+    }
+
+    fn drop_table(&self, table: &str) -> io::Result<()> {
+        self.tables.write().unwrap().remove(table);
+        Ok(())
+    }
+
+    fn create_entry(&self, table: &str, value: DbValue) -> io::Result<String> {
+        let id = new_id(&self.id_counter);
+        self.tables
+            .write()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default()
+            .insert(id.clone(), value);
+        Ok(id)
+    }
+
+    fn get_all(&self, table: &str) -> io::Result<Vec<(String, DbValue)>> {
+        let g = self.tables.read().unwrap();
+        Ok(g.get(table)
+            .map(|t| t.iter().map(|(id, v)| (id.clone(), v.clone())).collect())
+            .unwrap_or_default())
+    }
+
+    fn get_by_id(&self, table: &str, id: &str) -> io::Result<Option<(String, DbValue)>> {
+        let g = self.tables.read().unwrap();
+        Ok(g.get(table)
+            .and_then(|t| t.get(id))
+            .map(|v| (id.to_string(), v.clone())))
+    }
+
+    fn get_by_fields(
+        &self,
+        table: &str,
+        filter: &FieldFilter,
+    ) -> io::Result<Vec<(String, DbValue)>> {
+        let matcher = FilterMatcher::new(filter)?;
+        let g = self.tables.read().unwrap();
+        Ok(g.get(table)
+            .map(|t| {
+                t.iter()
+                    .filter(|(_, v)| matcher.matches(v))
+                    .map(|(id, v)| (id.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn update_by_id(&self, table: &str, id: &str, patch: DbValue) -> io::Result<bool> {
+        let mut g = self.tables.write().unwrap();
+        match g.get_mut(table).and_then(|t| t.get_mut(id)) {
+            Some(v) => {
+                *v = merge(v.clone(), patch);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn replace_by_id(&self, table: &str, id: &str, value: DbValue) -> io::Result<bool> {
+        let mut g = self.tables.write().unwrap();
+        match g.get_mut(table).and_then(|t| t.get_mut(id)) {
+            Some(v) => {
+                *v = value;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn update_by_fields(
+        &self,
+        table: &str,
+        filter: &FieldFilter,
+        patch: DbValue,
+    ) -> io::Result<usize> {
+        let matcher = FilterMatcher::new(filter)?;
+        let mut g = self.tables.write().unwrap();
+        let Some(t) = g.get_mut(table) else {
+            return Ok(0);
+        };
+        let mut updated = 0usize;
+        for v in t.values_mut().filter(|v| matcher.matches(v)) {
+            *v = merge(v.clone(), patch.clone());
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    fn delete_by_id(&self, table: &str, id: &str) -> io::Result<bool> {
+        let mut g = self.tables.write().unwrap();
+        Ok(g.get_mut(table).is_some_and(|t| t.remove(id).is_some()))
+    }
+
+    fn delete_by_fields(&self, table: &str, filter: &FieldFilter) -> io::Result<usize> {
+        let matcher = FilterMatcher::new(filter)?;
+        let mut g = self.tables.write().unwrap();
+        let Some(t) = g.get_mut(table) else {
+            return Ok(0);
+        };
+        let before = t.len();
+        t.retain(|_, v| !matcher.matches(v));
+        Ok(before - t.len())
+    }
+
+    fn drop_db(&self) -> io::Result<()> {
+        self.tables.write().unwrap().clear();
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 pub mod db;
+mod filter;
+pub mod mem;
 
-use std::io;
+use std::{cmp::Ordering, io};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -35,13 +37,124 @@ impl DbValue {
                 DbValue::Json(Value::Object(json_obj))
             }
 
-            RJSValue::Type(_) | RJSValue::Undefined => DbValue::Null,
+            RJSValue::Type(_) | RJSValue::Undefined | RJSValue::Null | RJSValue::Function(_) => {
+                DbValue::Null
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            DbValue::Number(n) => Value::from(*n),
+            DbValue::Bool(b) => Value::from(*b),
+            DbValue::String(s) => Value::from(s.clone()),
+            DbValue::Null => Value::Null,
+            DbValue::Json(j) => j.clone(),
         }
     }
 }
 
 pub type FieldFilter = std::collections::BTreeMap<String, serde_json::Value>;
 
+/// Which rows of a query to return, see `TableDb::get_page`.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    /// At most this many rows, all of them when `None`
+    pub limit: Option<usize>,
+    /// Rows to skip after sorting
+    pub offset: usize,
+    /// Field to sort by; without one rows are in id order
+    pub sort: Option<SortKey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    /// A field of the stored objects, or `id`
+    pub field: String,
+    pub descending: bool,
+}
+
+impl Page {
+    /// Sorts `rows` and cuts the page out of them. Rows without the sort field come
+    /// last in either direction, and ties are in id order, so a page is the same on
+    /// every run.
+    pub fn apply(&self, mut rows: Vec<(String, DbValue)>) -> Vec<(String, DbValue)> {
+        match &self.sort {
+            None => rows.sort_by(|a, b| a.0.cmp(&b.0)),
+            Some(key) => rows.sort_by(|a, b| {
+                let by_field = match (sort_value(a, &key.field), sort_value(b, &key.field)) {
+                    (Some(x), Some(y)) if key.descending => compare_json(&y, &x),
+                    (Some(x), Some(y)) => compare_json(&x, &y),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+                by_field.then_with(|| a.0.cmp(&b.0))
+            }),
+        }
+        rows.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+fn sort_value(row: &(String, DbValue), field: &str) -> Option<Value> {
+    if field == "id" {
+        // the id the entry is returned with, whatever the stored object holds
+        return Some(Value::String(row.0.clone()));
+    }
+    match &row.1 {
+        DbValue::Json(Value::Object(obj)) => obj.get(field).cloned(),
+        _ => None,
+    }
+}
+
+/// Numbers before strings before booleans before everything else; numbers by value,
+/// strings by character code, `false` before `true`. Other values are all equal.
+fn compare_json(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Number(_) => 0,
+            Value::String(_) => 1,
+            Value::Bool(_) => 2,
+            _ => 3,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.total_cmp(&y)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Patch key listing the fields to remove, e.g. `{ "$unset": ["oldField"] }`.
+const UNSET_KEY: &str = "$unset";
+
+/// Object patches set (and `$unset`) fields of an object value; any other patch
+/// replaces the value.
+pub(crate) fn merge(orig: DbValue, patch: DbValue) -> DbValue {
+    use Value::Object;
+    match (orig, patch) {
+        (DbValue::Json(Object(mut base)), DbValue::Json(Object(mut p))) => {
+            if let Some(Value::Array(keys)) = p.remove(UNSET_KEY) {
+                for key in keys.iter().filter_map(Value::as_str) {
+                    base.shift_remove(key);
+                }
+            }
+            for (k, v) in p {
+                base.insert(k, v);
+            }
+            DbValue::Json(Value::Object(base))
+        }
+        (_, p) => p,
+    }
+}
+
 pub trait TableDb: Send + Sync {
     fn create_table(&self, table: &str) -> io::Result<()>;
     fn get_all_tables(&self) -> io::Result<Vec<String>>;
@@ -56,8 +169,19 @@ pub trait TableDb: Send + Sync {
         table: &str,
         filter: &FieldFilter,
     ) -> io::Result<Vec<(String, DbValue)>>;
+    /// Rows matching `filter` (all of them for an empty one), sorted and sliced by `page`.
+    fn get_page(
+        &self,
+        table: &str,
+        filter: &FieldFilter,
+        page: &Page,
+    ) -> io::Result<Vec<(String, DbValue)>> {
+        Ok(page.apply(self.get_by_fields(table, filter)?))
+    }
 
     fn update_by_id(&self, table: &str, id: &str, patch: DbValue) -> io::Result<bool>;
+    /// Overwrites the stored value instead of merging into it.
+    fn replace_by_id(&self, table: &str, id: &str, value: DbValue) -> io::Result<bool>;
     fn update_by_fields(
         &self,
         table: &str,
@@ -69,4 +193,210 @@ pub trait TableDb: Send + Sync {
     fn delete_by_fields(&self, table: &str, filter: &FieldFilter) -> io::Result<usize>;
 
     fn drop_db(&self) -> io::Result<()>;
+
+    /// Rewrites whatever log a backend replays on open into its shortest form.
+    fn compact(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Writes out anything a backend holds back for batching; called on shutdown.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Behavior every `TableDb` must share, run against each backend.
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::rjscript::{evaluator::runtime::world::ScriptWorld, testing::TestWorld};
+
+    /// Runs each test against a fresh `JsonTableDb` in a temp dir and a fresh `MemTableDb`.
+    macro_rules! on_every_backend {
+        ($($test:ident),* $(,)?) => {
+            mod json {
+                $(
+                    #[test]
+                    fn $test() {
+                        let world = super::TestWorld::with_temp_db().unwrap();
+                        super::$test(super::ScriptWorld::db(&world).unwrap().as_ref());
+                    }
+                )*
+            }
+            mod mem {
+                $(
+                    #[test]
+                    fn $test() {
+                        super::$test(&crate::rjsdb::mem::MemTableDb::new());
+                    }
+                )*
+            }
+        };
+    }
+
+    on_every_backend!(
+        crud_round_trip,
+        ids_are_unique,
+        missing_tables_and_ids,
+        tables_and_drop,
+        filters_select_rows,
+        update_and_delete_by_fields,
+        unset_removes_fields,
+        pages,
+    );
+
+    fn obj(v: Value) -> DbValue {
+        DbValue::Json(v)
+    }
+
+    fn filter(v: Value) -> FieldFilter {
+        serde_json::from_value(v).unwrap()
+    }
+
+    /// Every row of `table` as JSON, keyed by id.
+    fn rows(db: &dyn TableDb, table: &str) -> BTreeMap<String, Value> {
+        db.get_all(table)
+            .unwrap()
+            .into_iter()
+            .map(|(id, v)| (id, v.to_json()))
+            .collect()
+    }
+
+    /// Adds a `people` row for each of the given ages, returning their ids in order.
+    fn people(db: &dyn TableDb, ages: &[u32]) -> Vec<String> {
+        ages.iter()
+            .map(|age| db.create_entry("people", obj(json!({ "age": age }))).unwrap())
+            .collect()
+    }
+
+    fn crud_round_trip(db: &dyn TableDb) {
+        let id = db.create_entry("t", obj(json!({ "a": 1, "b": 2 }))).unwrap();
+        assert_eq!(db.get_by_id("t", &id).unwrap().unwrap().1.to_json(), json!({ "a": 1, "b": 2 }));
+
+        assert!(db.update_by_id("t", &id, obj(json!({ "b": 3, "c": 4 }))).unwrap());
+        assert_eq!(rows(db, "t")[&id], json!({ "a": 1, "b": 3, "c": 4 }));
+
+        assert!(db.replace_by_id("t", &id, obj(json!({ "z": 0 }))).unwrap());
+        assert_eq!(rows(db, "t")[&id], json!({ "z": 0 }));
+
+        assert!(db.delete_by_id("t", &id).unwrap());
+        assert!(!db.delete_by_id("t", &id).unwrap());
+        assert!(db.get_by_id("t", &id).unwrap().is_none());
+        assert_eq!(db.get_all_tables().unwrap(), ["t"]);
+    }
+
+    fn ids_are_unique(db: &dyn TableDb) {
+        let ids = people(db, &[1; 100]);
+        let mut unique = ids.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+    }
+
+    fn missing_tables_and_ids(db: &dyn TableDb) {
+        assert!(db.get_all("nope").unwrap().is_empty());
+        assert!(db.get_by_id("nope", "x").unwrap().is_none());
+        assert!(!db.update_by_id("nope", "x", DbValue::Null).unwrap());
+        assert!(!db.replace_by_id("nope", "x", DbValue::Null).unwrap());
+        assert!(!db.delete_by_id("nope", "x").unwrap());
+        assert_eq!(db.update_by_fields("nope", &FieldFilter::new(), DbValue::Null).unwrap(), 0);
+        assert_eq!(db.delete_by_fields("nope", &FieldFilter::new()).unwrap(), 0);
+        db.drop_table("nope").unwrap();
+
+        db.create_table("t").unwrap();
+        assert!(!db.update_by_id("t", "x", DbValue::Null).unwrap());
+        assert_eq!(db.get_all_tables().unwrap(), ["t"]);
+    }
+
+    fn tables_and_drop(db: &dyn TableDb) {
+        db.create_table("a").unwrap();
+        let id = db.create_entry("a", obj(json!(1))).unwrap();
+        db.create_table("a").unwrap();
+        assert!(db.get_by_id("a", &id).unwrap().is_some(), "create_table keeps rows");
+
+        db.create_entry("b", obj(json!(2))).unwrap();
+        db.drop_table("a").unwrap();
+        assert_eq!(db.get_all_tables().unwrap(), ["b"]);
+        db.drop_db().unwrap();
+        assert!(db.get_all_tables().unwrap().is_empty());
+    }
+
+    fn filters_select_rows(db: &dyn TableDb) {
+        let ids = people(db, &[17, 30, 65]);
+        let found = |f: Value| -> Vec<String> {
+            let mut ids: Vec<String> =
+                db.get_by_fields("people", &filter(f)).unwrap().into_iter().map(|(id, _)| id).collect();
+            ids.sort();
+            ids
+        };
+        let mut all = ids.clone();
+        all.sort();
+        assert_eq!(found(json!({ "age": 30 })), [ids[1].clone()]);
+        assert_eq!(found(json!({ "age": { "$gte": 18, "$lt": 65 } })), [ids[1].clone()]);
+        let mut young_and_old = vec![ids[0].clone(), ids[2].clone()];
+        young_and_old.sort();
+        assert_eq!(found(json!({ "age": { "$in": [17, 65] } })), young_and_old);
+        assert_eq!(found(json!({})), all);
+        assert!(found(json!({ "name": "x" })).is_empty());
+
+        let err = db.get_by_fields("people", &filter(json!({ "age": { "$in": 17 } }))).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn update_and_delete_by_fields(db: &dyn TableDb) {
+        let ids = people(db, &[17, 30, 65]);
+        let adults = filter(json!({ "age": { "$gte": 18 } }));
+        assert_eq!(db.update_by_fields("people", &adults, obj(json!({ "adult": true }))).unwrap(), 2);
+        assert_eq!(rows(db, "people")[&ids[2]], json!({ "age": 65, "adult": true }));
+        assert_eq!(rows(db, "people")[&ids[0]], json!({ "age": 17 }));
+
+        assert_eq!(db.delete_by_fields("people", &adults).unwrap(), 2);
+        assert_eq!(rows(db, "people").into_keys().collect::<Vec<_>>(), [ids[0].clone()]);
+
+        let bad = filter(json!({ "age": { "$regex": "(" } }));
+        assert!(db.update_by_fields("people", &bad, DbValue::Null).is_err());
+        assert!(db.delete_by_fields("people", &bad).is_err());
+        assert_eq!(rows(db, "people").len(), 1);
+    }
+
+    fn unset_removes_fields(db: &dyn TableDb) {
+        let id = db.create_entry("t", obj(json!({ "a": 1, "b": 2 }))).unwrap();
+        let patch = obj(json!({ "$unset": ["a", "missing"], "c": 3 }));
+        assert!(db.update_by_id("t", &id, patch).unwrap());
+        assert_eq!(rows(db, "t")[&id], json!({ "b": 2, "c": 3 }));
+
+        // a non-object patch replaces the value
+        assert!(db.update_by_id("t", &id, obj(json!([1]))).unwrap());
+        assert_eq!(rows(db, "t")[&id], json!([1]));
+    }
+
+    fn pages(db: &dyn TableDb) {
+        let ids = people(db, &[40, 20, 30, 20]);
+        let page = |limit, offset, descending| -> Vec<Value> {
+            let page = Page {
+                limit,
+                offset,
+                sort: Some(SortKey { field: "age".into(), descending }),
+            };
+            db.get_page("people", &FieldFilter::new(), &page)
+                .unwrap()
+                .into_iter()
+                .map(|(_, v)| v.to_json()["age"].clone())
+                .collect()
+        };
+        assert_eq!(page(None, 0, false), [json!(20), json!(20), json!(30), json!(40)]);
+        assert_eq!(page(Some(2), 1, true), [json!(30), json!(20)]);
+        assert_eq!(page(Some(0), 0, false), Vec::<Value>::new());
+        assert_eq!(page(Some(10), 4, false), Vec::<Value>::new());
+
+        // without a sort key, rows come in id order
+        let all = db.get_page("people", &FieldFilter::new(), &Page::default()).unwrap();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(all.into_iter().map(|(id, _)| id).collect::<Vec<_>>(), sorted);
+    }
 }